@@ -1,6 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use nu_plugin_topology::algo::sparse::SparseVector;
 use nu_plugin_topology::algo::{clustering, discover, lsh, simhash, tfidf, tokenizer};
-use std::collections::HashMap;
 
 /// Generate synthetic text data for benchmarking
 fn generate_texts(n: usize) -> Vec<String> {
@@ -91,7 +91,7 @@ fn bench_distance_matrix(c: &mut Criterion) {
         for tokens in &token_lists {
             corpus.add_document(tokens);
         }
-        let vectors: Vec<HashMap<String, f64>> = (0..size).map(|i| corpus.tfidf_vector(i)).collect();
+        let vectors: Vec<SparseVector> = (0..size).map(|i| corpus.tfidf_vector(i)).collect();
 
         group.bench_with_input(BenchmarkId::from_parameter(size), &vectors, |b, vecs| {
             b.iter(|| black_box(clustering::cosine_distance_matrix(vecs)))
@@ -109,12 +109,12 @@ fn bench_hac(c: &mut Criterion) {
         for tokens in &token_lists {
             corpus.add_document(tokens);
         }
-        let vectors: Vec<HashMap<String, f64>> = (0..size).map(|i| corpus.tfidf_vector(i)).collect();
+        let vectors: Vec<SparseVector> = (0..size).map(|i| corpus.tfidf_vector(i)).collect();
         let distances = clustering::cosine_distance_matrix(&vectors);
 
         group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &n| {
             b.iter(|| {
-                let dend = clustering::hac(&distances, n, clustering::Linkage::Ward);
+                let dend = clustering::hac(distances.clone(), n, clustering::Linkage::Ward);
                 black_box(clustering::cut_tree(&dend, 10))
             })
         });
@@ -134,6 +134,7 @@ fn bench_discover_taxonomy(c: &mut Criterion) {
             keywords_per_cluster: 20,
             linkage: clustering::Linkage::Ward,
             seed: 42,
+            ..Default::default()
         };
 
         group.bench_with_input(BenchmarkId::from_parameter(size), &texts, |b, t| {
@@ -5,11 +5,18 @@
 
 use serde::{Deserialize, Serialize};
 use siphasher::sip::SipHasher13;
+use siphasher::sip128::{Hasher128, SipHasher13 as SipHasher13_128};
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use super::simhash;
-use super::tokenizer;
+use super::tokenizer::TokenizerConfig;
+
+/// On-disk cache layout version. Bump whenever the table layout or the
+/// meaning of a stored hash changes; artifacts written with an older
+/// version are dropped on open instead of being misread. The near-duplicate
+/// index is versioned separately and kept.
+pub const SCHEMA_VERSION: u32 = 3;
 
 /// Artifact types that can be cached in the topology cache database.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -44,8 +51,8 @@ impl ArtifactKind {
 /// Metadata for a cached artifact, stored alongside the payload.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheMeta {
-    /// SimHash of concatenated input texts (detects data changes).
-    pub content_hash: u64,
+    /// Exact digest of the input rows and how they were read (detects data changes).
+    pub content_hash: u128,
     /// Number of input rows when the artifact was cached.
     pub row_count: usize,
     /// SipHash of serialized command arguments (detects config changes).
@@ -58,7 +65,7 @@ pub struct CacheMeta {
 
 impl CacheMeta {
     /// Create a new CacheMeta with the current version and timestamp.
-    pub fn new(content_hash: u64, row_count: usize, args_hash: u64) -> Self {
+    pub fn new(content_hash: u128, row_count: usize, args_hash: u64) -> Self {
         let created_at = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
@@ -75,18 +82,66 @@ impl CacheMeta {
 
 /// Compute a content hash for cache invalidation.
 ///
-/// Tokenizes and SimHash-es the concatenation of all input texts.
-/// This produces a stable 64-bit fingerprint that changes when data changes.
-pub fn content_hash(texts: &[String]) -> u64 {
-    if texts.is_empty() {
-        return 0;
-    }
-    // Concatenate all texts and compute SimHash with uniform weights
-    let all_tokens: Vec<String> = texts
+/// A 128-bit SipHash over the field name, the tokenizer settings and every
+/// row text in order. Unlike a SimHash, any edit — reordering rows, touching
+/// a stopword, moving text between rows — yields a different digest.
+pub fn content_hash(texts: &[String], field: &str, tokenizer: &TokenizerConfig) -> u128 {
+    let mut hasher = SipHasher13_128::new();
+    let config = serde_json::to_string(tokenizer).unwrap_or_default();
+    for part in [field, config.as_str()] {
+        hasher.write_usize(part.len());
+        hasher.write(part.as_bytes());
+    }
+    // Length-prefix each row so ["ab", "c"] and ["a", "bc"] differ.
+    hasher.write_usize(texts.len());
+    for text in texts {
+        hasher.write_usize(text.len());
+        hasher.write(text.as_bytes());
+    }
+    hasher.finish128().as_u128()
+}
+
+/// Render a content hash the way it is stored and reported.
+pub fn content_hash_hex(hash: u128) -> String {
+    format!("{hash:032x}")
+}
+
+/// Hash a single row's text for row-level cache keying.
+///
+/// Row-keyed artifacts (corpus, fingerprints) depend only on each row's text,
+/// so rows are identified by this hash rather than by position: reordering,
+/// inserting or deleting rows leaves every other row reusable.
+pub fn row_hash(text: &str) -> u64 {
+    let mut hasher = SipHasher13::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A cached payload aligned to input rows by their [`row_hash`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowKeyed<T> {
+    /// `row_hash` of each row, in the order `data` was built.
+    pub row_hashes: Vec<u64>,
+    pub data: T,
+}
+
+/// Match current rows against the rows of a cached artifact.
+///
+/// Returns, for each current row, the index of an unused cached row with the
+/// same hash (duplicates are matched one-to-one), plus the cached indices
+/// that no current row claimed, in ascending order.
+pub fn match_rows(cached: &[u64], current: &[u64]) -> (Vec<Option<usize>>, Vec<usize>) {
+    let mut available: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, &h) in cached.iter().enumerate().rev() {
+        available.entry(h).or_default().push(i);
+    }
+    let matched: Vec<Option<usize>> = current
         .iter()
-        .flat_map(|t| tokenizer::tokenize(t))
+        .map(|h| available.get_mut(h).and_then(|v| v.pop()))
         .collect();
-    simhash::simhash_uniform(&all_tokens)
+    let mut removed: Vec<usize> = available.into_values().flatten().collect();
+    removed.sort_unstable();
+    (matched, removed)
 }
 
 /// Hash command arguments for cache keying.
@@ -107,12 +162,20 @@ pub fn args_hash(args: &impl Serialize) -> u64 {
 /// - Content hash (same data)
 /// - Args hash (same parameters)
 /// - Version (same crate version)
-pub fn is_valid(meta: &CacheMeta, content_hash: u64, args_hash: u64) -> bool {
+pub fn is_valid(meta: &CacheMeta, content_hash: u128, args_hash: u64) -> bool {
     meta.content_hash == content_hash
         && meta.args_hash == args_hash
         && meta.version == env!("CARGO_PKG_VERSION")
 }
 
+/// Check whether a row-keyed artifact can seed an incremental update.
+///
+/// Unlike [`is_valid`], the content hash is ignored: row-keyed artifacts are
+/// diffed against the current rows with [`match_rows`] instead.
+pub fn is_reusable(meta: &CacheMeta, args_hash: u64) -> bool {
+    meta.args_hash == args_hash && meta.version == env!("CARGO_PKG_VERSION")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,26 +198,45 @@ mod tests {
         assert_eq!(ArtifactKind::from_str("unknown"), None);
     }
 
+    fn hash(texts: &[&str]) -> u128 {
+        let texts: Vec<String> = texts.iter().map(|t| t.to_string()).collect();
+        content_hash(&texts, "content", &TokenizerConfig::default())
+    }
+
     #[test]
     fn content_hash_deterministic() {
-        let texts = vec!["hello world".into(), "foo bar".into()];
-        let h1 = content_hash(&texts);
-        let h2 = content_hash(&texts);
-        assert_eq!(h1, h2);
+        assert_eq!(hash(&["hello world", "foo bar"]), hash(&["hello world", "foo bar"]));
     }
 
     #[test]
     fn content_hash_changes_with_data() {
-        let t1 = vec!["rust programming".into()];
-        let t2 = vec!["cooking recipes".into()];
-        let h1 = content_hash(&t1);
-        let h2 = content_hash(&t2);
-        assert_ne!(h1, h2);
+        assert_ne!(hash(&["rust programming"]), hash(&["cooking recipes"]));
+    }
+
+    #[test]
+    fn content_hash_is_order_sensitive() {
+        assert_ne!(hash(&["alpha", "beta"]), hash(&["beta", "alpha"]));
+        assert_ne!(hash(&["ab", "c"]), hash(&["a", "bc"]));
+    }
+
+    #[test]
+    fn content_hash_sees_stopword_edits() {
+        assert_ne!(hash(&["rust is fast"]), hash(&["rust was fast"]));
+    }
+
+    #[test]
+    fn content_hash_covers_field_and_tokenizer() {
+        let texts = vec!["hello world".to_string()];
+        let base = content_hash(&texts, "content", &TokenizerConfig::default());
+        assert_ne!(base, content_hash(&texts, "title", &TokenizerConfig::default()));
+        let keep_stopwords = TokenizerConfig { stopwords: false, ..Default::default() };
+        assert_ne!(base, content_hash(&texts, "content", &keep_stopwords));
     }
 
     #[test]
-    fn content_hash_empty() {
-        assert_eq!(content_hash(&[]), 0);
+    fn content_hash_empty_is_stable() {
+        assert_eq!(hash(&[]), hash(&[]));
+        assert_ne!(hash(&[]), hash(&[""]));
     }
 
     #[test]
@@ -216,6 +298,28 @@ mod tests {
         assert!(!is_valid(&meta, 42, 99));
     }
 
+    #[test]
+    fn row_hash_distinguishes_rows() {
+        assert_eq!(row_hash("rust"), row_hash("rust"));
+        assert_ne!(row_hash("rust"), row_hash("go"));
+    }
+
+    #[test]
+    fn match_rows_handles_insert_delete_and_reorder() {
+        let cached = vec![10, 20, 30, 20];
+        let current = vec![30, 20, 40, 20, 20];
+        let (matched, removed) = match_rows(&cached, &current);
+        assert_eq!(matched, vec![Some(2), Some(1), None, Some(3), None]);
+        assert_eq!(removed, vec![0]);
+    }
+
+    #[test]
+    fn is_reusable_ignores_content_hash() {
+        let meta = CacheMeta::new(42, 100, 99);
+        assert!(is_reusable(&meta, 99));
+        assert!(!is_reusable(&meta, 98));
+    }
+
     #[test]
     fn cache_meta_serde_roundtrip() {
         let meta = CacheMeta::new(12345, 500, 67890);
@@ -1,6 +1,9 @@
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use super::sparse::SparseVector;
+
 /// Linkage method for HAC.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Linkage {
@@ -33,6 +36,80 @@ pub struct Dendrogram {
     pub n: usize,
 }
 
+impl Dendrogram {
+    /// Render the tree in Newick format, labelling leaf `i` with `labels[i]`
+    /// (its index when missing). A merge sits at height `distance`, leaves at
+    /// 0, and branch lengths are the height differences.
+    pub fn to_newick(&self, labels: &[String]) -> String {
+        enum Step {
+            Enter(usize, Option<f64>),
+            Comma,
+            Exit(usize, Option<f64>),
+        }
+
+        let n = self.n;
+        let height = |id: usize| if id < n { 0.0 } else { self.merges[id - n].distance };
+        let branch = |out: &mut String, id: usize, parent: Option<f64>| {
+            if let Some(parent) = parent {
+                out.push_str(&format!(":{}", (parent - height(id)).max(0.0)));
+            }
+        };
+
+        // Clusters never merged into another are roots; a complete tree has one.
+        let mut merged = vec![false; n + self.merges.len()];
+        for m in &self.merges {
+            merged[m.cluster_a] = true;
+            merged[m.cluster_b] = true;
+        }
+        let roots: Vec<usize> = (0..merged.len()).filter(|&id| !merged[id]).collect();
+
+        // Explicit stack: single-linkage chains are as deep as n.
+        let mut out = String::new();
+        let mut stack: Vec<Step> = Vec::new();
+        for (i, &root) in roots.iter().enumerate().rev() {
+            stack.push(Step::Enter(root, None));
+            if i > 0 {
+                stack.push(Step::Comma);
+            }
+        }
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Enter(id, parent) if id < n => {
+                    let label = labels.get(id).cloned().unwrap_or_else(|| id.to_string());
+                    out.push_str(&newick_label(&label));
+                    branch(&mut out, id, parent);
+                }
+                Step::Enter(id, parent) => {
+                    let merge = &self.merges[id - n];
+                    out.push('(');
+                    stack.push(Step::Exit(id, parent));
+                    stack.push(Step::Enter(merge.cluster_b, Some(merge.distance)));
+                    stack.push(Step::Comma);
+                    stack.push(Step::Enter(merge.cluster_a, Some(merge.distance)));
+                }
+                Step::Comma => out.push(','),
+                Step::Exit(id, parent) => {
+                    out.push(')');
+                    branch(&mut out, id, parent);
+                }
+            }
+        }
+        if roots.len() > 1 {
+            out = format!("({out})");
+        }
+        out.push(';');
+        out
+    }
+}
+
+/// Quote a Newick label when it holds whitespace or punctuation.
+fn newick_label(label: &str) -> String {
+    if !label.is_empty() && !label.chars().any(|c| c.is_whitespace() || "()[]':;,".contains(c)) {
+        return label.to_string();
+    }
+    format!("'{}'", label.replace('\'', "''"))
+}
+
 /// A single merge step in the dendrogram.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Merge {
@@ -46,88 +123,122 @@ pub struct Merge {
 ///
 /// `distances` is a flat upper-triangular distance matrix of size n*(n-1)/2.
 /// Index for pair (i,j) where i < j: i*n - i*(i+1)/2 + j - i - 1
-pub fn hac(distances: &[f64], n: usize, linkage: Linkage) -> Dendrogram {
-    // Work with a mutable distance matrix (condensed form → full for easier updates)
-    let mut dist = vec![vec![0.0f64; n]; n];
-    for i in 0..n {
-        for j in (i + 1)..n {
-            let idx = condensed_index(i, j, n);
-            dist[i][j] = distances[idx];
-            dist[j][i] = distances[idx];
-        }
+///
+/// Uses the nearest-neighbour chain algorithm: follow nearest neighbours
+/// until two clusters are each other's nearest, merge them, and update the
+/// condensed matrix in place with the Lance-Williams formula. All four
+/// linkages are reducible, so this finds the same merges as greedy
+/// closest-pair search in O(n²) time. The matrix is taken by value and
+/// overwritten, so the condensed matrix is the only O(n²) allocation;
+/// callers that still need the original distances must keep their own copy.
+/// Merges are returned in non-decreasing distance order.
+pub fn hac(mut dist: Vec<f64>, n: usize, linkage: Linkage) -> Dendrogram {
+    if n < 2 {
+        return Dendrogram { merges: Vec::new(), n };
     }
 
     let mut active: Vec<bool> = vec![true; n];
     let mut sizes: Vec<usize> = vec![1; n];
-    let mut merges: Vec<Merge> = Vec::with_capacity(n - 1);
-    // Map from original indices to new cluster IDs (>= n means merged cluster)
-    let mut cluster_id: Vec<usize> = (0..n).collect();
-    let mut next_id = n;
+    let mut chain: Vec<usize> = Vec::with_capacity(n);
+    // (surviving slot, absorbed slot, distance) in the order merges happen
+    let mut steps: Vec<(usize, usize, f64)> = Vec::with_capacity(n - 1);
 
-    for _ in 0..(n - 1) {
-        // Find closest pair among active clusters
-        let mut best_i = 0;
-        let mut best_j = 0;
-        let mut best_dist = f64::INFINITY;
+    while steps.len() < n - 1 {
+        if chain.is_empty() {
+            chain.extend((0..n).find(|&i| active[i]));
+        }
 
-        for i in 0..n {
-            if !active[i] {
-                continue;
-            }
-            for j in (i + 1)..n {
-                if !active[j] {
+        // Grow the chain until its last two entries are reciprocal nearest
+        // neighbours. Ties go to the previous entry so the chain can't cycle.
+        let (a, b) = loop {
+            let a = chain[chain.len() - 1];
+            let prev = chain.len().checked_sub(2).map(|i| chain[i]);
+            let mut nearest = prev;
+            let mut best = prev.map_or(f64::INFINITY, |p| dist[pair_index(a, p, n)]);
+            for k in 0..n {
+                if !active[k] || k == a {
                     continue;
                 }
-                if dist[i][j] < best_dist {
-                    best_dist = dist[i][j];
-                    best_i = i;
-                    best_j = j;
+                let d = dist[pair_index(a, k, n)];
+                if nearest.is_none() || d < best {
+                    best = d;
+                    nearest = Some(k);
                 }
             }
-        }
-
-        let new_size = sizes[best_i] + sizes[best_j];
-        merges.push(Merge {
-            cluster_a: cluster_id[best_i],
-            cluster_b: cluster_id[best_j],
-            distance: best_dist,
-            size: new_size,
-        });
+            let b = nearest.expect("at least two active clusters");
+            if Some(b) == prev {
+                chain.truncate(chain.len() - 2);
+                break (a, b);
+            }
+            chain.push(b);
+        };
 
-        // Update distances: merge best_j into best_i
+        let (keep, gone) = (a.min(b), a.max(b));
+        let d_ab = dist[pair_index(keep, gone, n)];
+        let (na, nb) = (sizes[keep] as f64, sizes[gone] as f64);
         for k in 0..n {
-            if !active[k] || k == best_i || k == best_j {
+            if !active[k] || k == keep || k == gone {
                 continue;
             }
-            let new_dist = match linkage {
-                Linkage::Single => dist[best_i][k].min(dist[best_j][k]),
-                Linkage::Complete => dist[best_i][k].max(dist[best_j][k]),
-                Linkage::Average => {
-                    let ni = sizes[best_i] as f64;
-                    let nj = sizes[best_j] as f64;
-                    (ni * dist[best_i][k] + nj * dist[best_j][k]) / (ni + nj)
-                }
+            let d_ak = dist[pair_index(keep, k, n)];
+            let d_bk = dist[pair_index(gone, k, n)];
+            dist[pair_index(keep, k, n)] = match linkage {
+                Linkage::Single => d_ak.min(d_bk),
+                Linkage::Complete => d_ak.max(d_bk),
+                Linkage::Average => (na * d_ak + nb * d_bk) / (na + nb),
                 Linkage::Ward => {
-                    let ni = sizes[best_i] as f64;
-                    let nj = sizes[best_j] as f64;
                     let nk = sizes[k] as f64;
-                    let total = ni + nj + nk;
-                    ((ni + nk) * dist[best_i][k] + (nj + nk) * dist[best_j][k]
-                        - nk * best_dist)
-                        / total
+                    let sq = ((na + nk) * d_ak * d_ak + (nb + nk) * d_bk * d_bk - nk * d_ab * d_ab)
+                        / (na + nb + nk);
+                    sq.max(0.0).sqrt()
                 }
             };
-            dist[best_i][k] = new_dist;
-            dist[k][best_i] = new_dist;
         }
 
-        active[best_j] = false;
-        sizes[best_i] = new_size;
-        cluster_id[best_i] = next_id;
-        next_id += 1;
+        active[gone] = false;
+        sizes[keep] += sizes[gone];
+        steps.push((keep, gone, d_ab));
+    }
+
+    // The chain finds merges out of order. Sorting is stable, so a merge
+    // still follows the merges that built its two sides, even on ties.
+    steps.sort_by(|x, y| x.2.total_cmp(&y.2));
+    Dendrogram { merges: label_merges(&steps, n), n }
+}
+
+/// Turn slot-based merge steps into dendrogram merges, where merge `i`
+/// creates cluster id `n + i`.
+fn label_merges(steps: &[(usize, usize, f64)], n: usize) -> Vec<Merge> {
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut cluster_id: Vec<usize> = (0..n).collect();
+    let mut sizes: Vec<usize> = vec![1; n];
+
+    fn find(parent: &mut [usize], mut x: usize) -> usize {
+        while parent[x] != x {
+            parent[x] = parent[parent[x]];
+            x = parent[x];
+        }
+        x
     }
 
-    Dendrogram { merges, n }
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, &(a, b, distance))| {
+            let ra = find(&mut parent, a);
+            let rb = find(&mut parent, b);
+            let merge = Merge {
+                cluster_a: cluster_id[ra],
+                cluster_b: cluster_id[rb],
+                distance,
+                size: sizes[ra] + sizes[rb],
+            };
+            parent[rb] = ra;
+            sizes[ra] += sizes[rb];
+            cluster_id[ra] = n + i;
+            merge
+        })
+        .collect()
 }
 
 /// Cut the dendrogram at a given number of clusters.
@@ -139,11 +250,30 @@ pub fn cut_tree(dendrogram: &Dendrogram, k: usize) -> Vec<usize> {
     }
 
     // Apply first (n - k) merges, then the remaining items form k clusters
-    let num_merges = n.saturating_sub(k);
+    apply_merges(dendrogram, n.saturating_sub(k))
+}
+
+/// Cut the dendrogram at a distance threshold: every merge at or below
+/// `max_distance` is applied, so no two clusters are closer than it under
+/// the dendrogram's linkage. Labels are numbered as in [`cut_tree`].
+pub fn cut_tree_at(dendrogram: &Dendrogram, max_distance: f64) -> Vec<usize> {
+    // `hac` returns merges in non-decreasing distance order.
+    let num_merges = dendrogram
+        .merges
+        .iter()
+        .take_while(|m| m.distance <= max_distance)
+        .count();
+    apply_merges(dendrogram, num_merges)
+}
+
+/// Cluster labels after the first `num_merges` merges.
+fn apply_merges(dendrogram: &Dendrogram, num_merges: usize) -> Vec<usize> {
+    let n = dendrogram.n;
     let mut parent: HashMap<usize, usize> = HashMap::new();
 
-    for merge in dendrogram.merges.iter().take(num_merges) {
-        let new_id = parent.len() + n;
+    // Merge `i` created cluster id `n + i` (see `hac`).
+    for (i, merge) in dendrogram.merges.iter().take(num_merges).enumerate() {
+        let new_id = n + i;
         parent.insert(merge.cluster_a, new_id);
         parent.insert(merge.cluster_b, new_id);
     }
@@ -173,42 +303,157 @@ pub fn cut_tree(dendrogram: &Dendrogram, k: usize) -> Vec<usize> {
         .collect()
 }
 
+/// Quality of one candidate cluster count in an automatic k search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KScore {
+    pub k: usize,
+    /// Mean silhouette over all items, in [-1, 1]; higher is better.
+    pub silhouette: f64,
+    /// Distance of the merge that would reduce k clusters to k - 1. A large
+    /// jump over the next k's value marks a natural cut.
+    pub merge_distance: f64,
+}
+
+/// The chosen cluster count together with the score of every k tried.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KSelection {
+    pub k: usize,
+    pub scores: Vec<KScore>,
+}
+
+/// Mean silhouette coefficient of a clustering.
+///
+/// For each item, `a` is its mean distance to the rest of its cluster and `b`
+/// the smallest mean distance to another cluster; its silhouette is
+/// `(b - a) / max(a, b)`, and 0 for singletons.
+pub fn silhouette_score(distances: &[f64], n: usize, labels: &[usize]) -> f64 {
+    let k = labels.iter().max().map(|m| m + 1).unwrap_or(0);
+    if n < 2 || k < 2 {
+        return 0.0;
+    }
+
+    let mut sizes = vec![0usize; k];
+    for &l in labels {
+        sizes[l] += 1;
+    }
+
+    let mut total = 0.0;
+    let mut sums = vec![0.0f64; k];
+    for i in 0..n {
+        sums.iter_mut().for_each(|s| *s = 0.0);
+        for j in 0..n {
+            if i != j {
+                sums[labels[j]] += distances[pair_index(i, j, n)];
+            }
+        }
+
+        let own = labels[i];
+        if sizes[own] < 2 {
+            continue;
+        }
+        let a = sums[own] / (sizes[own] - 1) as f64;
+        let b = (0..k)
+            .filter(|&c| c != own && sizes[c] > 0)
+            .map(|c| sums[c] / sizes[c] as f64)
+            .fold(f64::INFINITY, f64::min);
+        let denom = a.max(b);
+        if denom > 0.0 {
+            total += (b - a) / denom;
+        }
+    }
+
+    total / n as f64
+}
+
+/// Pick the cluster count in `k_min..=k_max` with the highest silhouette.
+///
+/// Candidates are cut from the one dendrogram, so the search costs one
+/// silhouette pass per k rather than a clustering per k. Ties go to the
+/// smaller k. When the range is empty (too few items), `k_max` capped at
+/// `n` is returned with no scores.
+pub fn select_k(dendrogram: &Dendrogram, distances: &[f64], k_min: usize, k_max: usize) -> KSelection {
+    let n = dendrogram.n;
+    let lo = k_min.max(2);
+    let hi = k_max.min(n.saturating_sub(1));
+
+    let mut scores = Vec::new();
+    let mut best: Option<(usize, f64)> = None;
+    for k in lo..=hi {
+        let labels = cut_tree(dendrogram, k);
+        let silhouette = silhouette_score(distances, n, &labels);
+        let merge_distance = dendrogram
+            .merges
+            .get(n - k)
+            .map(|m| m.distance)
+            .unwrap_or(0.0);
+        if best.is_none_or(|(_, s)| silhouette > s) {
+            best = Some((k, silhouette));
+        }
+        scores.push(KScore { k, silhouette, merge_distance });
+    }
+
+    KSelection {
+        k: best.map(|(k, _)| k).unwrap_or(k_max.min(n)),
+        scores,
+    }
+}
+
 /// Compute cosine distance matrix (condensed form) from TF-IDF vectors.
-pub fn cosine_distance_matrix(vectors: &[HashMap<String, f64>]) -> Vec<f64> {
+///
+/// Rows of the condensed matrix are filled in parallel.
+pub fn cosine_distance_matrix(vectors: &[SparseVector]) -> Vec<f64> {
     let n = vectors.len();
+    if n < 2 {
+        return Vec::new();
+    }
     let mut distances = vec![0.0; n * (n - 1) / 2];
 
     // Precompute norms
-    let norms: Vec<f64> = vectors
-        .iter()
-        .map(|v| v.values().map(|x| x * x).sum::<f64>().sqrt())
-        .collect();
+    let norms: Vec<f64> = vectors.iter().map(SparseVector::norm).collect();
 
+    // Row i holds pairs (i, i+1..n), contiguous in condensed order.
+    let mut rows: Vec<(usize, &mut [f64])> = Vec::with_capacity(n);
+    let mut rest = distances.as_mut_slice();
     for i in 0..n {
-        for j in (i + 1)..n {
-            let dot: f64 = vectors[i]
-                .iter()
-                .filter_map(|(k, vi)| vectors[j].get(k).map(|vj| vi * vj))
-                .sum();
-            let sim = if norms[i] > 0.0 && norms[j] > 0.0 {
-                dot / (norms[i] * norms[j])
-            } else {
-                0.0
-            };
-            let dist = 1.0 - sim;
-            distances[condensed_index(i, j, n)] = dist;
-        }
+        let (row, tail) = rest.split_at_mut(n - i - 1);
+        rows.push((i, row));
+        rest = tail;
     }
 
+    rows.into_par_iter().for_each(|(i, row)| {
+        for (offset, slot) in row.iter_mut().enumerate() {
+            let j = i + 1 + offset;
+            *slot = 1.0 - cosine_similarity(&vectors[i], norms[i], &vectors[j], norms[j]);
+        }
+    });
+
     distances
 }
 
+/// Cosine similarity of two sparse vectors given their norms (see
+/// [`SparseVector::norm`]); 0 when either vector is empty.
+pub fn cosine_similarity(a: &SparseVector, norm_a: f64, b: &SparseVector, norm_b: f64) -> f64 {
+    if norm_a <= 0.0 || norm_b <= 0.0 {
+        return 0.0;
+    }
+    a.dot(b) / (norm_a * norm_b)
+}
+
 /// Index into a condensed distance matrix for pair (i, j) where i < j.
 fn condensed_index(i: usize, j: usize, n: usize) -> usize {
     debug_assert!(i < j);
     i * n - i * (i + 1) / 2 + j - i - 1
 }
 
+/// Index into a condensed distance matrix for an unordered pair.
+fn pair_index(i: usize, j: usize, n: usize) -> usize {
+    if i < j {
+        condensed_index(i, j, n)
+    } else {
+        condensed_index(j, i, n)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,16 +467,126 @@ mod tests {
     #[test]
     fn hac_single_linkage() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Single);
+        let dend = hac(d, n, Linkage::Single);
         assert_eq!(dend.merges.len(), 3);
         // First merge should be the closest pair (distance 1.0)
         assert!((dend.merges[0].distance - 1.0).abs() < 1e-10);
     }
 
+    /// Condensed Euclidean distances between pseudo-random 2-D points.
+    fn random_points(n: usize, seed: u64) -> Vec<f64> {
+        let mut state = seed;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let points: Vec<(f64, f64)> = (0..n).map(|_| (next() * 10.0, next() * 10.0)).collect();
+        let mut d = Vec::new();
+        for i in 0..n {
+            for j in (i + 1)..n {
+                d.push(((points[i].0 - points[j].0).powi(2) + (points[i].1 - points[j].1).powi(2)).sqrt());
+            }
+        }
+        d
+    }
+
+    #[test]
+    fn dendrogram_json_round_trip_is_exact() {
+        // Cached dendrograms must re-cut exactly like fresh ones, which needs
+        // serde_json's `float_roundtrip` parser: the default one can be off
+        // by an ulp on long decimal expansions.
+        let n = 40;
+        let dend = hac(random_points(n, 7), n, Linkage::Average);
+        let json = serde_json::to_string(&dend).unwrap();
+        let back: Dendrogram = serde_json::from_str(&json).unwrap();
+        for (a, b) in dend.merges.iter().zip(&back.merges) {
+            assert_eq!(a.distance.to_bits(), b.distance.to_bits(), "{}", a.distance);
+        }
+    }
+
+    /// Naive O(n³) closest-pair HAC, as a reference for the chain algorithm.
+    fn greedy_merge_distances(distances: &[f64], n: usize, linkage: Linkage) -> Vec<f64> {
+        let mut dist = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in (i + 1)..n {
+                dist[i][j] = distances[condensed_index(i, j, n)];
+                dist[j][i] = dist[i][j];
+            }
+        }
+        let mut active = vec![true; n];
+        let mut sizes = vec![1.0f64; n];
+        let mut out = Vec::new();
+        for _ in 0..n - 1 {
+            let (mut bi, mut bj, mut best) = (0, 0, f64::INFINITY);
+            for i in 0..n {
+                for j in (i + 1)..n {
+                    if active[i] && active[j] && dist[i][j] < best {
+                        (bi, bj, best) = (i, j, dist[i][j]);
+                    }
+                }
+            }
+            for k in 0..n {
+                if !active[k] || k == bi || k == bj {
+                    continue;
+                }
+                let (na, nb, nk) = (sizes[bi], sizes[bj], sizes[k]);
+                let (dak, dbk) = (dist[bi][k], dist[bj][k]);
+                let d = match linkage {
+                    Linkage::Single => dak.min(dbk),
+                    Linkage::Complete => dak.max(dbk),
+                    Linkage::Average => (na * dak + nb * dbk) / (na + nb),
+                    Linkage::Ward => (((na + nk) * dak * dak + (nb + nk) * dbk * dbk
+                        - nk * best * best)
+                        / (na + nb + nk))
+                        .sqrt(),
+                };
+                dist[bi][k] = d;
+                dist[k][bi] = d;
+            }
+            active[bj] = false;
+            sizes[bi] += sizes[bj];
+            out.push(best);
+        }
+        out
+    }
+
+    #[test]
+    fn hac_matches_greedy_reference() {
+        let n = 40;
+        let d = random_points(n, 7);
+        for linkage in [Linkage::Single, Linkage::Complete, Linkage::Average, Linkage::Ward] {
+            let dend = hac(d.clone(), n, linkage);
+            let expected = greedy_merge_distances(&d, n, linkage);
+            assert_eq!(dend.merges.len(), n - 1);
+            for (m, e) in dend.merges.iter().zip(&expected) {
+                assert!((m.distance - e).abs() < 1e-9, "{linkage:?}: {} vs {e}", m.distance);
+            }
+            assert_eq!(dend.merges.last().unwrap().size, n);
+            for k in 1..=n {
+                let labels = cut_tree(&dend, k);
+                assert_eq!(labels.iter().max().unwrap() + 1, k, "{linkage:?} k={k}");
+            }
+        }
+    }
+
+    #[test]
+    fn hac_handles_ties_and_tiny_inputs() {
+        assert!(hac(Vec::new(), 0, Linkage::Ward).merges.is_empty());
+        assert!(hac(Vec::new(), 1, Linkage::Ward).merges.is_empty());
+        // Every pair equidistant: the chain must still terminate.
+        let n = 6;
+        let d = vec![1.0; n * (n - 1) / 2];
+        for linkage in [Linkage::Single, Linkage::Complete, Linkage::Average, Linkage::Ward] {
+            let dend = hac(d.clone(), n, linkage);
+            assert_eq!(dend.merges.len(), n - 1);
+            assert_eq!(cut_tree(&dend, 3).iter().max(), Some(&2));
+        }
+    }
+
     #[test]
     fn cut_tree_gives_correct_k() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Complete);
+        let dend = hac(d, n, Linkage::Complete);
         let labels = cut_tree(&dend, 2);
         assert_eq!(labels.len(), 4);
         let unique: std::collections::HashSet<usize> = labels.iter().copied().collect();
@@ -241,14 +596,106 @@ mod tests {
     #[test]
     fn cut_tree_all_separate() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Single);
+        let dend = hac(d, n, Linkage::Single);
         let labels = cut_tree(&dend, 4);
         assert_eq!(labels, vec![0, 1, 2, 3]);
     }
 
+    #[test]
+    fn cut_tree_follows_merged_clusters() {
+        // Four tight pairs on a line: merges 4-6 combine merged clusters,
+        // so the cut must resolve ids created by earlier merges.
+        let points: [f64; 8] = [0.0, 0.1, 1.0, 1.1, 5.0, 5.1, 6.0, 6.1];
+        let n = points.len();
+        let mut d = Vec::new();
+        for i in 0..n {
+            for j in (i + 1)..n {
+                d.push((points[i] - points[j]).abs());
+            }
+        }
+        let dend = hac(d, n, Linkage::Single);
+        assert_eq!(cut_tree(&dend, 2), vec![0, 0, 0, 0, 1, 1, 1, 1]);
+        assert_eq!(cut_tree(&dend, 4), vec![0, 0, 1, 1, 2, 2, 3, 3]);
+    }
+
+    #[test]
+    fn cut_tree_at_distance_threshold() {
+        let points: [f64; 8] = [0.0, 0.1, 1.0, 1.1, 5.0, 5.1, 6.0, 6.1];
+        let n = points.len();
+        let mut d = Vec::new();
+        for i in 0..n {
+            for j in (i + 1)..n {
+                d.push((points[i] - points[j]).abs());
+            }
+        }
+        let dend = hac(d, n, Linkage::Single);
+        assert_eq!(cut_tree_at(&dend, 0.0), (0..8).collect::<Vec<usize>>());
+        assert_eq!(cut_tree_at(&dend, 0.5), cut_tree(&dend, 4));
+        assert_eq!(cut_tree_at(&dend, 1.0), cut_tree(&dend, 2));
+        assert_eq!(cut_tree_at(&dend, 100.0), vec![0; 8]);
+    }
+
+    #[test]
+    fn newick_nests_merges_with_branch_lengths() {
+        let dend = Dendrogram {
+            n: 3,
+            merges: vec![
+                Merge { cluster_a: 0, cluster_b: 1, distance: 0.25, size: 2 },
+                Merge { cluster_a: 2, cluster_b: 3, distance: 1.0, size: 3 },
+            ],
+        };
+        let labels = vec!["a".to_string(), "b c".to_string()];
+        assert_eq!(dend.to_newick(&labels), "(2:1,(a:0.25,'b c':0.25):0.75);");
+        let partial = Dendrogram { n: 3, merges: dend.merges[..1].to_vec() };
+        assert_eq!(partial.to_newick(&labels), "(2,(a:0.25,'b c':0.25));");
+        assert_eq!(Dendrogram { n: 0, merges: vec![] }.to_newick(&[]), ";");
+        assert_eq!(newick_label("it's"), "'it''s'");
+    }
+
+    fn two_blobs() -> (Vec<f64>, usize) {
+        let points: [f64; 6] = [0.0, 0.2, 0.4, 10.0, 10.2, 10.4];
+        let n = points.len();
+        let mut d = Vec::new();
+        for i in 0..n {
+            for j in (i + 1)..n {
+                d.push((points[i] - points[j]).abs());
+            }
+        }
+        (d, n)
+    }
+
+    #[test]
+    fn silhouette_prefers_true_partition() {
+        let (d, n) = two_blobs();
+        let good = silhouette_score(&d, n, &[0, 0, 0, 1, 1, 1]);
+        let bad = silhouette_score(&d, n, &[0, 1, 0, 1, 0, 1]);
+        assert!(good > 0.9, "{good}");
+        assert!(bad < 0.0, "{bad}");
+        assert_eq!(silhouette_score(&d, n, &[0; 6]), 0.0);
+    }
+
+    #[test]
+    fn select_k_finds_natural_clusters() {
+        let (d, n) = two_blobs();
+        let dend = hac(d.clone(), n, Linkage::Average);
+        let sel = select_k(&dend, &d, 2, 10);
+        assert_eq!(sel.k, 2);
+        let ks: Vec<usize> = sel.scores.iter().map(|s| s.k).collect();
+        assert_eq!(ks, vec![2, 3, 4, 5]);
+        assert!(sel.scores[0].merge_distance > sel.scores[1].merge_distance);
+    }
+
+    #[test]
+    fn select_k_too_few_items() {
+        let dend = hac(vec![1.0], 2, Linkage::Single);
+        let sel = select_k(&dend, &[1.0], 2, 10);
+        assert_eq!(sel.k, 2);
+        assert!(sel.scores.is_empty());
+    }
+
     #[test]
     fn cosine_distance_identical_vectors() {
-        let v1: HashMap<String, f64> = [("a".into(), 1.0), ("b".into(), 2.0)].into();
+        let v1 = SparseVector::from_entries(vec![(0, 1.0), (1, 2.0)]);
         let v2 = v1.clone();
         let distances = cosine_distance_matrix(&[v1, v2]);
         assert!(distances[0].abs() < 1e-10); // identical = distance 0
@@ -256,8 +703,8 @@ mod tests {
 
     #[test]
     fn cosine_distance_orthogonal_vectors() {
-        let v1: HashMap<String, f64> = [("a".into(), 1.0)].into();
-        let v2: HashMap<String, f64> = [("b".into(), 1.0)].into();
+        let v1 = SparseVector::from_entries(vec![(0, 1.0)]);
+        let v2 = SparseVector::from_entries(vec![(1, 1.0)]);
         let distances = cosine_distance_matrix(&[v1, v2]);
         assert!((distances[0] - 1.0).abs() < 1e-10); // orthogonal = distance 1
     }
@@ -265,7 +712,7 @@ mod tests {
     #[test]
     fn hac_complete_linkage() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Complete);
+        let dend = hac(d, n, Linkage::Complete);
         assert_eq!(dend.merges.len(), 3);
         // Last merge should have the largest distance
         let last_dist = dend.merges.last().unwrap().distance;
@@ -277,7 +724,7 @@ mod tests {
     #[test]
     fn hac_average_linkage() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Average);
+        let dend = hac(d, n, Linkage::Average);
         assert_eq!(dend.merges.len(), 3);
         // Merges should be in non-decreasing distance order
         for w in dend.merges.windows(2) {
@@ -288,14 +735,14 @@ mod tests {
     #[test]
     fn hac_ward_linkage() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Ward);
+        let dend = hac(d, n, Linkage::Ward);
         assert_eq!(dend.merges.len(), 3);
     }
 
     #[test]
     fn cut_tree_single_cluster() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Single);
+        let dend = hac(d, n, Linkage::Single);
         let labels = cut_tree(&dend, 1);
         assert_eq!(labels.len(), 4);
         // k=1 applies all n-1 merges; resulting clusters depend on internal ID mapping
@@ -306,7 +753,7 @@ mod tests {
     #[test]
     fn cut_tree_k_exceeds_n() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Single);
+        let dend = hac(d, n, Linkage::Single);
         // Asking for more clusters than items → each item its own cluster
         let labels = cut_tree(&dend, 10);
         assert_eq!(labels.len(), 4);
@@ -317,7 +764,7 @@ mod tests {
     #[test]
     fn dendrogram_serde_roundtrip() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Ward);
+        let dend = hac(d, n, Linkage::Ward);
         let json = serde_json::to_string(&dend).unwrap();
         let dend2: Dendrogram = serde_json::from_str(&json).unwrap();
         assert_eq!(dend.n, dend2.n);
@@ -354,7 +801,7 @@ mod tests {
 
     #[test]
     fn cosine_distance_matrix_single_vector() {
-        let v1: HashMap<String, f64> = [("a".into(), 1.0)].into();
+        let v1 = SparseVector::from_entries(vec![(0, 1.0)]);
         let distances = cosine_distance_matrix(&[v1]);
         // 1 vector → 0 pairs → empty condensed matrix
         assert!(distances.is_empty());
@@ -362,9 +809,9 @@ mod tests {
 
     #[test]
     fn cosine_distance_three_vectors() {
-        let v1: HashMap<String, f64> = [("a".into(), 1.0), ("b".into(), 0.0)].into();
-        let v2: HashMap<String, f64> = [("a".into(), 1.0), ("b".into(), 0.0)].into();
-        let v3: HashMap<String, f64> = [("a".into(), 0.0), ("b".into(), 1.0)].into();
+        let v1 = SparseVector::from_entries(vec![(0, 1.0), (1, 0.0)]);
+        let v2 = SparseVector::from_entries(vec![(0, 1.0), (1, 0.0)]);
+        let v3 = SparseVector::from_entries(vec![(0, 0.0), (1, 1.0)]);
         let distances = cosine_distance_matrix(&[v1, v2, v3]);
         // 3 vectors → 3 pairs: (0,1), (0,2), (1,2)
         assert_eq!(distances.len(), 3);
@@ -378,7 +825,7 @@ mod tests {
     fn hac_two_items() {
         // Minimal case: 2 items, 1 distance
         let distances = vec![0.5];
-        let dend = hac(&distances, 2, Linkage::Single);
+        let dend = hac(distances, 2, Linkage::Single);
         assert_eq!(dend.merges.len(), 1);
         assert!((dend.merges[0].distance - 0.5).abs() < 1e-10);
         assert_eq!(dend.merges[0].size, 2);
@@ -387,7 +834,7 @@ mod tests {
     #[test]
     fn dendrogram_merge_sizes_increase() {
         let (d, n) = simple_distances();
-        let dend = hac(&d, n, Linkage::Single);
+        let dend = hac(d, n, Linkage::Single);
         // Each merge produces a bigger cluster; last merge should have size n
         assert_eq!(dend.merges.last().unwrap().size, n);
     }
@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+
+use super::tfidf::Corpus;
+
+/// Coherence of one topic's top terms against the corpus they came from.
+///
+/// Both scores average over every pair of top terms and use document
+/// co-occurrence, so topics whose terms actually appear together in items
+/// score higher than topics stitched from unrelated high-weight terms.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Coherence {
+    /// UMass (Mimno et al., 2011): mean of log((D(wᵢ, wⱼ) + 1) / D(wⱼ))
+    /// where wⱼ ranks above wᵢ. Usually negative; higher is more coherent.
+    pub umass: f64,
+    /// Normalized PMI (Bouma, 2009): mean of
+    /// log(P(wᵢ, wⱼ) / (P(wᵢ) P(wⱼ))) / −log P(wᵢ, wⱼ), in [−1, 1].
+    /// 1 means the terms always co-occur, −1 that they never do.
+    pub npmi: f64,
+}
+
+/// Score `terms`, ordered by rank within the topic. Terms missing from the
+/// corpus are skipped; fewer than two known terms score 0.
+pub fn topic_coherence(corpus: &Corpus, terms: &[&str]) -> Coherence {
+    let ids: Vec<_> = terms.iter().filter_map(|t| corpus.vocabulary().id(t)).collect();
+    let counts = corpus.co_doc_freq(&ids);
+    let n = corpus.num_docs() as f64;
+
+    let (mut umass, mut npmi, mut pairs) = (0.0, 0.0, 0usize);
+    for i in 1..ids.len() {
+        for j in 0..i {
+            let (d_i, d_j, d_ij) = (counts[i][i] as f64, counts[j][j] as f64, counts[i][j] as f64);
+            if d_i == 0.0 || d_j == 0.0 {
+                continue;
+            }
+            pairs += 1;
+            umass += ((d_ij + 1.0) / d_j).ln();
+            npmi += if d_ij == 0.0 {
+                -1.0
+            } else if d_ij == n {
+                1.0
+            } else {
+                let p_ij = d_ij / n;
+                (p_ij / ((d_i / n) * (d_j / n))).ln() / -p_ij.ln()
+            };
+        }
+    }
+    if pairs == 0 {
+        return Coherence::default();
+    }
+    Coherence {
+        umass: umass / pairs as f64,
+        npmi: npmi / pairs as f64,
+    }
+}
+
+/// Topic diversity (Dieng et al., 2020): the fraction of unique terms across
+/// all topics' top-term lists. 1 means no two topics share a top term; low
+/// values mean k is high enough that topics repeat each other.
+pub fn diversity<S: AsRef<str>>(topics: &[Vec<S>]) -> f64 {
+    let total: usize = topics.iter().map(Vec::len).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let unique: HashSet<&str> = topics.iter().flatten().map(AsRef::as_ref).collect();
+    unique.len() as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus(docs: &[&str]) -> Corpus {
+        let mut corpus = Corpus::new();
+        for doc in docs {
+            let tokens: Vec<String> = doc.split_whitespace().map(String::from).collect();
+            corpus.add_document(&tokens);
+        }
+        corpus
+    }
+
+    #[test]
+    fn co_occurring_terms_are_more_coherent() {
+        let c = corpus(&["rust cargo", "rust cargo crates", "html css", "html css dom"]);
+        let together = topic_coherence(&c, &["rust", "cargo"]);
+        let apart = topic_coherence(&c, &["rust", "html"]);
+        assert!(together.umass > apart.umass);
+        assert!(together.npmi > apart.npmi);
+        assert_eq!(apart.npmi, -1.0);
+        // P(rust, cargo) = P(rust) = P(cargo) = 1/2: NPMI is exactly 1.
+        assert!((together.npmi - 1.0).abs() < 1e-12);
+        // UMass: log((2 + 1) / 2)
+        assert!((together.umass - 1.5f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unknown_or_single_terms_score_zero() {
+        let c = corpus(&["rust cargo"]);
+        assert_eq!(topic_coherence(&c, &["rust"]), Coherence::default());
+        assert_eq!(topic_coherence(&c, &["zig", "nim"]), Coherence::default());
+        assert_eq!(topic_coherence(&c, &["rust", "cargo"]).npmi, 1.0);
+    }
+
+    #[test]
+    fn diversity_counts_unique_terms() {
+        assert_eq!(diversity(&[vec!["a", "b"], vec!["c", "d"]]), 1.0);
+        assert_eq!(diversity(&[vec!["a", "b"], vec!["a", "c"]]), 0.75);
+        assert_eq!(diversity::<&str>(&[]), 0.0);
+    }
+}
@@ -1,12 +1,16 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
+use crate::algo::sparse::{SparseVector, TermId, Vocabulary};
 use crate::algo::{clustering, sampling, taxonomy, tfidf, tokenizer};
 
 /// Configuration for automatic taxonomy discovery.
 pub struct DiscoverConfig {
     /// Number of clusters to discover.
     pub k: usize,
-    /// Max sample size for clustering (HAC is O(n^2), so we cap it).
+    /// Max sample size for clustering. HAC holds an n*(n-1)/2 distance
+    /// matrix, about 16 MB at the default 2000; larger inputs are sampled
+    /// and every item is still classified against the result.
     pub sample_size: usize,
     /// Number of top TF-IDF terms per cluster label.
     pub label_terms: usize,
@@ -16,21 +20,122 @@ pub struct DiscoverConfig {
     pub linkage: clustering::Linkage,
     /// Random seed for sampling.
     pub seed: u64,
+    /// Number of taxonomy levels. 1 produces a flat list of categories.
+    pub depth: usize,
+    /// Growth in cluster count per level: level `l` cuts the dendrogram at
+    /// `k * branching^l` clusters, splitting the largest clusters first.
+    pub branching: usize,
+    /// Choose the top-level cluster count by silhouette score, trying every
+    /// k from 2 up to `k`.
+    pub auto_k: bool,
+    /// Keep a full-corpus TF-IDF centroid on every category, for
+    /// [`assign_by_centroid`].
+    pub centroids: bool,
 }
 
 impl Default for DiscoverConfig {
     fn default() -> Self {
         Self {
             k: 15,
-            sample_size: 500,
+            sample_size: 2000,
             label_terms: 3,
             keywords_per_cluster: 20,
             linkage: clustering::Linkage::Ward,
             seed: 42,
+            depth: 1,
+            branching: 3,
+            auto_k: false,
+            centroids: false,
         }
     }
 }
 
+/// How items are matched to discovered categories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Assignment {
+    /// BM25 against each category's keyword list.
+    #[default]
+    Bm25,
+    /// Cosine similarity to each leaf category's TF-IDF centroid.
+    Centroid,
+}
+
+impl Assignment {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "bm25" => Some(Self::Bm25),
+            "centroid" => Some(Self::Centroid),
+            _ => None,
+        }
+    }
+}
+
+/// Scale of the `_confidence` reported by keyword (BM25) classification.
+///
+/// Raw BM25 grows with keyword list size and document length. The other
+/// scales fall in [0, 1] whatever the taxonomy, so one threshold carries
+/// over between datasets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ConfidenceScale {
+    /// The best category's BM25 score.
+    #[default]
+    Raw,
+    /// Softmax of the best score over every category's score. Relative to
+    /// the other categories, but BM25 is unbounded, so longer or more
+    /// keyword-dense texts get sharper softmaxes: not a calibrated probability.
+    Softmax,
+    /// `(best - second) / best`: how clearly the best category wins.
+    Margin,
+}
+
+impl ConfidenceScale {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "raw" => Some(Self::Raw),
+            "softmax" => Some(Self::Softmax),
+            "margin" => Some(Self::Margin),
+            _ => None,
+        }
+    }
+
+    /// Confidence of `scores[best]` on this scale.
+    fn apply(self, scores: &[f64], best: usize) -> f64 {
+        let top = scores[best];
+        match self {
+            Self::Raw => top,
+            Self::Softmax => 1.0 / scores.iter().map(|s| (s - top).exp()).sum::<f64>(),
+            Self::Margin => {
+                let second = scores
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| i != best)
+                    .map(|(_, &s)| s)
+                    .fold(0.0f64, f64::max);
+                (top - second) / top
+            }
+        }
+    }
+}
+
+/// Nearest-centroid match for one item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CentroidAssignment {
+    pub category: String,
+    pub path: String,
+    /// Cosine similarity to the assigned centroid.
+    pub similarity: f64,
+    /// `similarity` on the classify scale, over every leaf's similarity.
+    pub confidence: f64,
+    /// Path of the second-nearest leaf category, if there is one.
+    pub runner_up: Option<String>,
+    /// `similarity` minus the runner-up's similarity.
+    pub margin: f64,
+}
+
+/// Sub-clusters smaller than this stay in their parent instead of becoming
+/// a child category.
+const MIN_CHILD_SIZE: usize = 2;
+
 /// Discover a taxonomy from raw text content.
 ///
 /// Pipeline:
@@ -39,13 +144,22 @@ impl Default for DiscoverConfig {
 /// 3. Sample if dataset > sample_size (HAC is O(n^2))
 /// 4. Compute cosine distance matrix on sample
 /// 5. Run HAC clustering
-/// 6. Cut dendrogram at k clusters
+/// 6. Cut dendrogram at k clusters (or the best-scoring k when `auto_k`)
 /// 7. Label each cluster by its top TF-IDF terms
 /// 8. Return taxonomy with keyword lists per cluster
 pub fn discover_taxonomy(texts: &[String], config: &DiscoverConfig) -> taxonomy::Taxonomy {
+    discover_taxonomy_with_selection(texts, config).0
+}
+
+/// Like [`discover_taxonomy`], also returning the per-k scores when
+/// `config.auto_k` chose the cluster count.
+pub fn discover_taxonomy_with_selection(
+    texts: &[String],
+    config: &DiscoverConfig,
+) -> (taxonomy::Taxonomy, Option<clustering::KSelection>) {
     let n = texts.len();
     if n == 0 {
-        return empty_taxonomy();
+        return (empty_taxonomy(), None);
     }
 
     // Tokenize everything (needed for corpus IDF)
@@ -57,19 +171,46 @@ pub fn discover_taxonomy(texts: &[String], config: &DiscoverConfig) -> taxonomy:
         corpus.add_document(tokens);
     }
 
+    discover_with_selection(&all_tokens, &corpus, config)
+}
+
+/// Discover a taxonomy from pre-tokenized items and their full corpus.
+///
+/// Same pipeline as [`discover_taxonomy`], for callers that tokenize with
+/// their own settings or need the corpus afterwards.
+pub fn discover_from_tokens(
+    all_tokens: &[Vec<String>],
+    corpus: &tfidf::Corpus,
+    config: &DiscoverConfig,
+) -> taxonomy::Taxonomy {
+    discover_with_selection(all_tokens, corpus, config).0
+}
+
+/// Like [`discover_from_tokens`], also returning the per-k scores when
+/// `config.auto_k` chose the cluster count.
+pub fn discover_with_selection(
+    all_tokens: &[Vec<String>],
+    corpus: &tfidf::Corpus,
+    config: &DiscoverConfig,
+) -> (taxonomy::Taxonomy, Option<clustering::KSelection>) {
+    let n = all_tokens.len();
+    if n == 0 {
+        return (empty_taxonomy(), None);
+    }
+
     // Sample if too large for HAC
-    let (_sample_indices, sample_tokens) = if n > config.sample_size {
+    let (sample_indices, sample_tokens) = if n > config.sample_size {
         let indices =
             sampling::random_sample(n, config.sample_size, config.seed);
         let tokens: Vec<Vec<String>> = indices.iter().map(|&i| all_tokens[i].clone()).collect();
         (indices, tokens)
     } else {
-        ((0..n).collect(), all_tokens.clone())
+        ((0..n).collect(), all_tokens.to_vec())
     };
 
     let sample_n = sample_tokens.len();
     if sample_n < 2 {
-        return single_cluster_taxonomy(&corpus, 0);
+        return (single_cluster_taxonomy(corpus, 0, config.centroids), None);
     }
 
     // Build TF-IDF vectors for sample
@@ -79,70 +220,271 @@ pub fn discover_taxonomy(texts: &[String], config: &DiscoverConfig) -> taxonomy:
         sample_corpus.add_document(tokens);
     }
 
-    let vectors: Vec<HashMap<String, f64>> = (0..sample_n)
-        .map(|i| sample_corpus.tfidf_vector(i))
+    let sample = TermSpace {
+        vectors: (0..sample_n).map(|i| sample_corpus.tfidf_vector(i)).collect(),
+        vocabulary: sample_corpus.vocabulary(),
+    };
+
+    // Centroids live in full-corpus IDF space so every item, sampled or
+    // not, can be compared against them.
+    let full = TermSpace {
+        vectors: if config.centroids {
+            sample_indices.iter().map(|&i| corpus.tfidf_vector(i)).collect()
+        } else {
+            Vec::new()
+        },
+        vocabulary: corpus.vocabulary(),
+    };
+
+    // Compute distance matrix and run HAC. HAC overwrites the matrix, so
+    // silhouette scoring recomputes it rather than keeping a second copy.
+    let distances = clustering::cosine_distance_matrix(&sample.vectors);
+    let dendrogram = clustering::hac(distances, sample_n, config.linkage);
+    let selection = config.auto_k.then(|| {
+        let distances = clustering::cosine_distance_matrix(&sample.vectors);
+        clustering::select_k(&dendrogram, &distances, 2, config.k)
+    });
+    let k = selection.as_ref().map_or(config.k, |s| s.k).min(sample_n);
+
+    // One cut per level. Cuts of the same dendrogram at increasing k are
+    // nested, so every deeper cluster lies inside exactly one parent.
+    let mut level_labels = Vec::with_capacity(config.depth.max(1));
+    let mut level_k = k;
+    for _ in 0..config.depth.max(1) {
+        level_labels.push(clustering::cut_tree(&dendrogram, level_k));
+        level_k = (level_k * config.branching.max(1)).min(sample_n);
+    }
+
+    let everyone: Vec<usize> = (0..sample_n).collect();
+    let categories: Vec<taxonomy::Category> = group_by_label(&everyone, &level_labels[0])
+        .iter()
+        .map(|members| {
+            build_category(members, 0, &level_labels, &sample, &full, config, &[])
+        })
         .collect();
 
-    // Compute distance matrix and run HAC
-    let distances = clustering::cosine_distance_matrix(&vectors);
-    let k = config.k.min(sample_n);
-    let dendrogram = clustering::hac(&distances, sample_n, config.linkage);
-    let labels = clustering::cut_tree(&dendrogram, k);
-
-    // Group sample items by cluster
-    let actual_k = labels.iter().max().map(|m| m + 1).unwrap_or(0);
-    let mut categories: Vec<taxonomy::Category> = Vec::with_capacity(actual_k);
-
-    for cluster_idx in 0..actual_k {
-        let member_indices: Vec<usize> = labels
-            .iter()
-            .enumerate()
-            .filter(|(_, &l)| l == cluster_idx)
-            .map(|(i, _)| i)
-            .collect();
+    let taxonomy = taxonomy::Taxonomy {
+        name: "discovered".into(),
+        version: "auto".into(),
+        categories,
+    };
+    (taxonomy, selection)
+}
 
-        if member_indices.is_empty() {
-            continue;
-        }
+/// Split `members` by their cluster label, ordered by label.
+fn group_by_label(members: &[usize], labels: &[usize]) -> Vec<Vec<usize>> {
+    let mut groups: std::collections::BTreeMap<usize, Vec<usize>> = Default::default();
+    for &i in members {
+        groups.entry(labels[i]).or_default().push(i);
+    }
+    groups.into_values().collect()
+}
 
-        // Merge TF-IDF vectors for this cluster
-        let mut merged: HashMap<String, f64> = HashMap::new();
-        for &i in &member_indices {
-            for (term, weight) in &vectors[i] {
-                *merged.entry(term.clone()).or_insert(0.0) += weight;
-            }
+/// TF-IDF vectors together with the vocabulary their term ids refer to.
+struct TermSpace<'a> {
+    vectors: Vec<SparseVector>,
+    vocabulary: &'a Vocabulary,
+}
+
+/// Build the category for one cluster at `level`, recursing into the
+/// sub-clusters the next level's cut splits it into.
+///
+/// Labels skip terms already used by an ancestor, so a path reads
+/// `Rust, Cargo > Async, Tokio` rather than repeating `Rust` at each level.
+/// `full.vectors` is empty unless centroids were requested.
+fn build_category(
+    members: &[usize],
+    level: usize,
+    level_labels: &[Vec<usize>],
+    sample: &TermSpace,
+    full: &TermSpace,
+    config: &DiscoverConfig,
+    ancestor_terms: &[String],
+) -> taxonomy::Category {
+    // Merge TF-IDF vectors for this cluster
+    let mut merged: HashMap<TermId, f64> = HashMap::new();
+    for &i in members {
+        for (id, weight) in sample.vectors[i].iter() {
+            *merged.entry(id).or_insert(0.0) += weight;
         }
+    }
 
-        // Sort by weight, take top keywords
-        let mut sorted_terms: Vec<(String, f64)> = merged.into_iter().collect();
-        sorted_terms
-            .sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    // Sort by weight (ties by term for stable labels), take top keywords
+    let mut sorted_terms: Vec<(&str, f64)> = merged
+        .into_iter()
+        .map(|(id, w)| (sample.vocabulary.term(id), w))
+        .collect();
+    sorted_terms.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(b.0))
+    });
 
-        let keywords: Vec<String> = sorted_terms
-            .iter()
-            .take(config.keywords_per_cluster)
-            .map(|(t, _)| t.clone())
+    let keywords: Vec<String> = sorted_terms
+        .iter()
+        .take(config.keywords_per_cluster)
+        .map(|(t, _)| t.to_string())
+        .collect();
+
+    let mut label_terms: Vec<String> = sorted_terms
+        .iter()
+        .map(|(t, _)| t.to_string())
+        .filter(|t| !ancestor_terms.contains(t))
+        .take(config.label_terms)
+        .collect();
+    if label_terms.is_empty() {
+        label_terms = keywords.iter().take(config.label_terms).cloned().collect();
+    }
+    let label = label_terms
+        .iter()
+        .map(|t| capitalize(t))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let mut children = vec![];
+    if let Some(next_labels) = level_labels.get(level + 1) {
+        let groups: Vec<Vec<usize>> = group_by_label(members, next_labels)
+            .into_iter()
+            .filter(|g| g.len() >= MIN_CHILD_SIZE)
             .collect();
+        if groups.len() >= 2 {
+            let mut terms = ancestor_terms.to_vec();
+            terms.extend(label_terms);
+            children = groups
+                .iter()
+                .map(|g| {
+                    build_category(g, level + 1, level_labels, sample, full, config, &terms)
+                })
+                .collect();
+        }
+    }
 
-        let label = sorted_terms
-            .iter()
-            .take(config.label_terms)
-            .map(|(t, _)| capitalize(t))
-            .collect::<Vec<String>>()
-            .join(", ");
+    let centroid = if full.vectors.is_empty() {
+        BTreeMap::new()
+    } else {
+        centroid_of(members.iter().map(|&i| &full.vectors[i]), full.vocabulary)
+    };
 
-        categories.push(taxonomy::Category {
-            name: label,
-            keywords,
-            children: vec![],
-        });
+    taxonomy::Category {
+        name: label,
+        keywords,
+        children,
+        centroid,
     }
+}
 
-    taxonomy::Taxonomy {
-        name: "discovered".into(),
-        version: "auto".into(),
-        categories,
+/// Mean of the unit-normalized vectors.
+fn centroid_of<'a>(
+    vectors: impl Iterator<Item = &'a SparseVector>,
+    vocabulary: &Vocabulary,
+) -> BTreeMap<String, f64> {
+    let mut sum = SparseVector::new();
+    let mut count = 0usize;
+    for v in vectors {
+        count += 1;
+        let norm = v.norm();
+        if norm == 0.0 {
+            continue;
+        }
+        sum.add_scaled(v, 1.0 / norm);
+    }
+    let mut sum: BTreeMap<String, f64> = sum.to_terms(vocabulary).map(|(t, w)| (t.to_string(), w)).collect();
+    if count > 0 {
+        sum.values_mut().for_each(|w| *w /= count as f64);
     }
+    sum
+}
+
+fn l2_norm<'a>(weights: impl Iterator<Item = &'a f64>) -> f64 {
+    weights.map(|w| w * w).sum::<f64>().sqrt()
+}
+
+/// Assign each item to the leaf category whose centroid is most
+/// cosine-similar to its TF-IDF vector.
+///
+/// Item vectors must use the IDF the centroids were built with, with term
+/// ids from `vocabulary`; centroid terms outside it can never match and
+/// only count towards the centroid norm. Items that
+/// share no terms with any centroid come back `Uncategorized`. Errors if
+/// the taxonomy carries no centroids (e.g. a hand-written taxonomy file).
+pub fn assign_by_centroid(
+    item_vectors: &[SparseVector],
+    vocabulary: &Vocabulary,
+    taxonomy: &taxonomy::Taxonomy,
+) -> Result<Vec<CentroidAssignment>, String> {
+    assign_by_centroid_scaled(item_vectors, vocabulary, taxonomy, 0.0, ConfidenceScale::Raw)
+}
+
+/// [`assign_by_centroid`] with confidence on `scale` (raw is the cosine
+/// similarity itself); items whose confidence is below `threshold` come
+/// back `Uncategorized`.
+pub fn assign_by_centroid_scaled(
+    item_vectors: &[SparseVector],
+    vocabulary: &Vocabulary,
+    taxonomy: &taxonomy::Taxonomy,
+    threshold: f64,
+    scale: ConfidenceScale,
+) -> Result<Vec<CentroidAssignment>, String> {
+    let leaves: Vec<(String, SparseVector, f64)> = taxonomy
+        .leaves()
+        .into_iter()
+        .filter(|(_, cat)| !cat.centroid.is_empty())
+        .map(|(path, cat)| {
+            let centroid = SparseVector::from_entries(
+                cat.centroid
+                    .iter()
+                    .filter_map(|(t, &w)| vocabulary.id(t).map(|id| (id, w)))
+                    .collect(),
+            );
+            (path, centroid, l2_norm(cat.centroid.values()))
+        })
+        .collect();
+    if leaves.is_empty() {
+        return Err("Taxonomy has no category centroids; centroid assignment needs a taxonomy discovered with centroids".into());
+    }
+
+    Ok(item_vectors
+        .iter()
+        .map(|vector| {
+            let norm = vector.norm();
+            let similarities: Vec<f64> = leaves
+                .iter()
+                .map(|(_, centroid, c_norm)| {
+                    let dot = vector.dot(centroid);
+                    if norm > 0.0 && *c_norm > 0.0 { dot / (norm * c_norm) } else { 0.0 }
+                })
+                .collect();
+            let mut scored: Vec<(usize, f64)> = similarities.iter().copied().enumerate().collect();
+            // Highest similarity first; earlier leaves win ties.
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+            let (best, similarity) = scored[0];
+            let confidence = if similarity > 0.0 { scale.apply(&similarities, best) } else { 0.0 };
+            if similarity <= 0.0 || confidence < threshold {
+                return CentroidAssignment {
+                    category: "Uncategorized".into(),
+                    path: "Uncategorized".into(),
+                    similarity: 0.0,
+                    confidence: 0.0,
+                    runner_up: None,
+                    margin: 0.0,
+                };
+            }
+            let path = &leaves[best].0;
+            let (runner_up, margin) = match scored.get(1) {
+                Some(&(idx, sim)) => (Some(leaves[idx].0.clone()), similarity - sim),
+                None => (None, similarity),
+            };
+            CentroidAssignment {
+                category: leaf_name(path).to_string(),
+                path: path.clone(),
+                similarity,
+                confidence,
+                runner_up,
+                margin,
+            }
+        })
+        .collect())
 }
 
 /// Classify items against a discovered (or provided) taxonomy using BM25.
@@ -153,41 +495,189 @@ pub fn classify_against_taxonomy(
     taxonomy: &taxonomy::Taxonomy,
     threshold: f64,
 ) -> Vec<(String, String, f64)> {
-    let flat = taxonomy.flatten();
+    classify_against_taxonomy_scaled(texts, taxonomy, threshold, ConfidenceScale::Raw)
+}
 
-    // Build BM25 corpus from taxonomy keywords
-    let mut corpus = tfidf::Corpus::new();
-    for (_, keywords) in &flat {
-        corpus.add_document(keywords);
-    }
+/// [`classify_against_taxonomy`] with confidence (and `threshold`) on `scale`.
+pub fn classify_against_taxonomy_scaled(
+    texts: &[String],
+    taxonomy: &taxonomy::Taxonomy,
+    threshold: f64,
+    scale: ConfidenceScale,
+) -> Vec<(String, String, f64)> {
+    let token_lists: Vec<Vec<String>> = texts.iter().map(|t| tokenizer::tokenize(t)).collect();
+    classify_tokens_scaled(&token_lists, taxonomy, threshold, None, scale)
+}
 
-    texts
-        .iter()
-        .map(|text| {
-            let tokens = tokenizer::tokenize(text);
+/// Classify pre-tokenized items against a taxonomy using BM25.
+///
+/// With `idf`, term weights come from that snapshot (typically the corpus the
+/// taxonomy was trained on) instead of from the category keyword lists.
+pub fn classify_tokens(
+    token_lists: &[Vec<String>],
+    taxonomy: &taxonomy::Taxonomy,
+    threshold: f64,
+    idf: Option<&tfidf::IdfStats>,
+) -> Vec<(String, String, f64)> {
+    classify_tokens_scaled(token_lists, taxonomy, threshold, idf, ConfidenceScale::Raw)
+}
 
-            let mut best_score = 0.0f64;
-            let mut best_category = String::new();
-            let mut best_path = String::new();
+/// [`classify_tokens`] with confidence (and `threshold`) on `scale`.
+pub fn classify_tokens_scaled(
+    token_lists: &[Vec<String>],
+    taxonomy: &taxonomy::Taxonomy,
+    threshold: f64,
+    idf: Option<&tfidf::IdfStats>,
+    scale: ConfidenceScale,
+) -> Vec<(String, String, f64)> {
+    let scorer = KeywordScorer::new(taxonomy);
 
-            for (doc_idx, (path, _)) in flat.iter().enumerate() {
-                let score = corpus.bm25_score(doc_idx, &tokens);
+    token_lists
+        .iter()
+        .map(|tokens| {
+            let scores = scorer.scores(tokens, idf);
+            let mut best: Option<usize> = None;
+            let mut best_score = 0.0f64;
+            for (i, &score) in scores.iter().enumerate() {
                 if score > best_score {
                     best_score = score;
-                    best_category = path.split(" > ").last().unwrap_or(path).to_string();
-                    best_path = path.clone();
+                    best = Some(i);
                 }
             }
 
-            if best_score >= threshold {
-                (best_category, best_path, best_score)
-            } else {
-                ("Uncategorized".into(), "Uncategorized".into(), 0.0)
+            let classified = best.map(|i| (i, scale.apply(&scores, i)));
+            match classified {
+                Some((i, confidence)) if confidence >= threshold => {
+                    let path = &scorer.flat[i].0;
+                    (leaf_name(path).to_string(), path.clone(), confidence)
+                }
+                _ => ("Uncategorized".into(), "Uncategorized".into(), 0.0),
             }
         })
         .collect()
 }
 
+/// One of possibly several categories an item belongs to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategoryMatch {
+    pub category: String,
+    pub path: String,
+    /// Raw BM25 score.
+    pub score: f64,
+    /// This category's score on the classify scale; `threshold` is compared
+    /// against it.
+    #[serde(default)]
+    pub confidence: f64,
+    /// Share of this item's total score across its matches; sums to 1.
+    pub probability: f64,
+}
+
+/// Multi-label variant of [`classify_against_taxonomy`].
+pub fn classify_against_taxonomy_multi(
+    texts: &[String],
+    taxonomy: &taxonomy::Taxonomy,
+    threshold: f64,
+) -> Vec<Vec<CategoryMatch>> {
+    classify_against_taxonomy_multi_scaled(texts, taxonomy, threshold, ConfidenceScale::Raw)
+}
+
+/// [`classify_against_taxonomy_multi`] with `threshold` on `scale`.
+pub fn classify_against_taxonomy_multi_scaled(
+    texts: &[String],
+    taxonomy: &taxonomy::Taxonomy,
+    threshold: f64,
+    scale: ConfidenceScale,
+) -> Vec<Vec<CategoryMatch>> {
+    let token_lists: Vec<Vec<String>> = texts.iter().map(|t| tokenizer::tokenize(t)).collect();
+    classify_tokens_multi_scaled(&token_lists, taxonomy, threshold, None, scale)
+}
+
+/// Every category (at any level) scoring at least `threshold` per item,
+/// best first. Items matching nothing get an empty list.
+pub fn classify_tokens_multi(
+    token_lists: &[Vec<String>],
+    taxonomy: &taxonomy::Taxonomy,
+    threshold: f64,
+    idf: Option<&tfidf::IdfStats>,
+) -> Vec<Vec<CategoryMatch>> {
+    classify_tokens_multi_scaled(token_lists, taxonomy, threshold, idf, ConfidenceScale::Raw)
+}
+
+/// [`classify_tokens_multi`] with each category's score converted to
+/// `scale` before it is compared to `threshold`. On the margin scale only
+/// the best category can score above zero.
+pub fn classify_tokens_multi_scaled(
+    token_lists: &[Vec<String>],
+    taxonomy: &taxonomy::Taxonomy,
+    threshold: f64,
+    idf: Option<&tfidf::IdfStats>,
+    scale: ConfidenceScale,
+) -> Vec<Vec<CategoryMatch>> {
+    let scorer = KeywordScorer::new(taxonomy);
+
+    token_lists
+        .iter()
+        .map(|tokens| {
+            let scores = scorer.scores(tokens, idf);
+            let mut matches: Vec<(&String, f64, f64)> = scorer
+                .flat
+                .iter()
+                .zip(&scores)
+                .enumerate()
+                .filter(|&(_, (_, &score))| score > 0.0)
+                .map(|(i, ((path, _), &score))| (path, score, scale.apply(&scores, i)))
+                .filter(|&(_, _, confidence)| confidence >= threshold)
+                .collect();
+            // Stable: equal scores keep taxonomy order.
+            matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let total: f64 = matches.iter().map(|(_, s, _)| s).sum();
+            matches
+                .into_iter()
+                .map(|(path, score, confidence)| CategoryMatch {
+                    category: leaf_name(path).to_string(),
+                    path: path.clone(),
+                    score,
+                    confidence,
+                    probability: score / total,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// BM25 over the flattened taxonomy, one keyword list per category.
+struct KeywordScorer {
+    flat: Vec<(String, Vec<String>)>,
+    corpus: tfidf::Corpus,
+}
+
+impl KeywordScorer {
+    fn new(taxonomy: &taxonomy::Taxonomy) -> Self {
+        let flat = taxonomy.flatten();
+        let mut corpus = tfidf::Corpus::new();
+        for (_, keywords) in &flat {
+            corpus.add_document(keywords);
+        }
+        Self { flat, corpus }
+    }
+
+    /// Score of `tokens` against every category, in `flat` order.
+    fn scores(&self, tokens: &[String], idf: Option<&tfidf::IdfStats>) -> Vec<f64> {
+        (0..self.flat.len())
+            .map(|doc_idx| match idf {
+                Some(stats) => {
+                    self.corpus.bm25_score_with_idf(doc_idx, tokens, 1.2, 0.75, |t| stats.idf(t))
+                }
+                None => self.corpus.bm25_score(doc_idx, tokens),
+            })
+            .collect()
+    }
+}
+
+fn leaf_name(path: &str) -> &str {
+    path.split(" > ").last().unwrap_or(path)
+}
+
 fn empty_taxonomy() -> taxonomy::Taxonomy {
     taxonomy::Taxonomy {
         name: "discovered".into(),
@@ -196,7 +686,11 @@ fn empty_taxonomy() -> taxonomy::Taxonomy {
     }
 }
 
-fn single_cluster_taxonomy(corpus: &tfidf::Corpus, doc_idx: usize) -> taxonomy::Taxonomy {
+fn single_cluster_taxonomy(
+    corpus: &tfidf::Corpus,
+    doc_idx: usize,
+    centroids: bool,
+) -> taxonomy::Taxonomy {
     let top = corpus.top_terms(doc_idx, 20);
     let keywords: Vec<String> = top.iter().map(|(t, _)| t.clone()).collect();
     let label = top
@@ -213,6 +707,11 @@ fn single_cluster_taxonomy(corpus: &tfidf::Corpus, doc_idx: usize) -> taxonomy::
             name: label,
             keywords,
             children: vec![],
+            centroid: if centroids {
+                centroid_of(std::iter::once(&corpus.tfidf_vector(doc_idx)), corpus.vocabulary())
+            } else {
+                BTreeMap::new()
+            },
         }],
     }
 }
@@ -312,6 +811,105 @@ mod tests {
         }
     }
 
+    fn two_level_texts() -> Vec<String> {
+        let mut texts = Vec::new();
+        for i in 0..4 {
+            texts.push(format!("rust programming async tokio futures runtime executor {i}"));
+            texts.push(format!("rust programming borrow checker lifetimes ownership {i}"));
+            texts.push(format!("cooking kitchen baking bread flour oven dough {i}"));
+            texts.push(format!("cooking kitchen pasta sauce tomato basil noodles {i}"));
+        }
+        texts
+    }
+
+    #[test]
+    fn discover_auto_k_picks_topic_count() {
+        let config = DiscoverConfig { k: 8, auto_k: true, ..Default::default() };
+        let texts = two_level_texts();
+        let all_tokens: Vec<Vec<String>> = texts.iter().map(|t| tokenizer::tokenize(t)).collect();
+        let mut corpus = tfidf::Corpus::new();
+        for tokens in &all_tokens {
+            corpus.add_document(tokens);
+        }
+        let (tax, selection) = discover_with_selection(&all_tokens, &corpus, &config);
+        let selection = selection.unwrap();
+        assert_eq!(selection.scores.len(), 7);
+        // Four distinct subtopics, each repeated: four clusters fit best.
+        assert_eq!(selection.k, 4);
+        assert_eq!(tax.categories.len(), 4);
+    }
+
+    #[test]
+    fn centroid_assignment_reports_runner_up() {
+        let texts = two_level_texts();
+        let all_tokens: Vec<Vec<String>> = texts.iter().map(|t| tokenizer::tokenize(t)).collect();
+        let mut corpus = tfidf::Corpus::new();
+        for tokens in &all_tokens {
+            corpus.add_document(tokens);
+        }
+        // Sample half the items; the rest are assigned only via centroids.
+        let config = DiscoverConfig { k: 4, sample_size: 8, centroids: true, ..Default::default() };
+        let tax = discover_from_tokens(&all_tokens, &corpus, &config);
+        assert!(tax.categories.iter().all(|c| !c.centroid.is_empty()));
+
+        let vectors: Vec<_> = (0..texts.len()).map(|i| corpus.tfidf_vector(i)).collect();
+        let assigned = assign_by_centroid(&vectors, corpus.vocabulary(), &tax).unwrap();
+        assert_eq!(assigned.len(), texts.len());
+        // Items built from the same subtopic land together.
+        for (i, a) in assigned.iter().enumerate() {
+            assert_eq!(a.path, assigned[i % 4].path);
+            assert!(a.similarity > 0.0);
+            let runner_up = a.runner_up.as_deref().unwrap();
+            assert_ne!(runner_up, a.path);
+            assert!(a.margin > 0.0 && a.margin <= a.similarity);
+        }
+
+        let empty = assign_by_centroid(&[SparseVector::new()], corpus.vocabulary(), &tax).unwrap();
+        assert_eq!(empty[0].category, "Uncategorized");
+    }
+
+    #[test]
+    fn centroid_assignment_needs_centroids() {
+        let config = DiscoverConfig { k: 2, ..Default::default() };
+        let tax = discover_taxonomy(&two_level_texts(), &config);
+        assert!(assign_by_centroid(&[SparseVector::new()], &Vocabulary::new(), &tax).is_err());
+    }
+
+    #[test]
+    fn discover_depth_one_is_flat() {
+        let config = DiscoverConfig { k: 2, ..Default::default() };
+        let tax = discover_taxonomy(&two_level_texts(), &config);
+        assert!(tax.categories.iter().all(|c| c.children.is_empty()));
+    }
+
+    #[test]
+    fn discover_nests_subclusters() {
+        let config = DiscoverConfig {
+            k: 2,
+            depth: 2,
+            branching: 2,
+            ..Default::default()
+        };
+        let tax = discover_taxonomy(&two_level_texts(), &config);
+        assert_eq!(tax.categories.len(), 2);
+        for cat in &tax.categories {
+            assert_eq!(cat.children.len(), 2, "{} should split in two", cat.name);
+            for child in &cat.children {
+                // Child labels don't repeat the parent's label terms.
+                for term in cat.name.split(", ") {
+                    assert!(!child.name.split(", ").any(|t| t == term), "{} / {}", cat.name, child.name);
+                }
+            }
+        }
+
+        let items = vec!["tokio async runtime".to_string(), "tomato basil pasta".to_string()];
+        let results = classify_against_taxonomy(&items, &tax, 0.1);
+        for (_, path, _) in &results {
+            assert!(path.contains(" > "), "expected a nested path, got {path}");
+        }
+        assert_ne!(results[0].1, results[1].1);
+    }
+
     #[test]
     fn classify_with_threshold() {
         let texts = vec![
@@ -325,6 +923,7 @@ mod tests {
                 name: "Rust".into(),
                 keywords: vec!["rust".into(), "systems".into(), "memory".into(), "safety".into()],
                 children: vec![],
+                centroid: Default::default(),
             }],
         };
         // With only 1 category and threshold=0, the matching text should get it
@@ -344,6 +943,7 @@ mod tests {
                 name: "Rust".into(),
                 keywords: vec!["rust".into(), "systems".into()],
                 children: vec![],
+                centroid: Default::default(),
             }],
         };
         let results = classify_against_taxonomy(&texts, &tax, 999.0);
@@ -361,6 +961,7 @@ mod tests {
                 name: "Cat".into(),
                 keywords: vec!["word".into()],
                 children: vec![],
+                centroid: Default::default(),
             }],
         };
         let results = classify_against_taxonomy(&texts, &tax, 0.0);
@@ -381,17 +982,44 @@ mod tests {
                     name: "Rust".into(),
                     keywords: vec!["rust".into(), "memory".into(), "safety".into(), "borrow".into()],
                     children: vec![],
+                    centroid: Default::default(),
                 },
                 taxonomy::Category {
                     name: "Web".into(),
                     keywords: vec!["javascript".into(), "web".into(), "html".into(), "css".into()],
                     children: vec![],
+                    centroid: Default::default(),
                 },
             ],
         };
         let results = classify_against_taxonomy(&texts, &tax, 0.0);
         assert_eq!(results[0].0, "Rust");
         assert_eq!(results[1].0, "Web");
+
+        let mixed = vec!["rust web server with html templates".to_string(), "gardening".to_string()];
+        let multi = classify_against_taxonomy_multi(&mixed, &tax, 0.0);
+        let paths: Vec<&str> = multi[0].iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths.len(), 2, "{paths:?}");
+        assert!(multi[0][0].score >= multi[0][1].score);
+        assert!((multi[0].iter().map(|m| m.probability).sum::<f64>() - 1.0).abs() < 1e-9);
+        // Nothing matched: no categories rather than a zero-score entry.
+        assert!(multi[1].is_empty());
+        assert_eq!(classify_against_taxonomy(&mixed, &tax, 0.0)[1].0, "Uncategorized");
+    }
+
+    #[test]
+    fn confidence_scales() {
+        let scores = [3.0, 1.0, 0.0];
+        assert_eq!(ConfidenceScale::Raw.apply(&scores, 0), 3.0);
+        let softmax = ConfidenceScale::Softmax.apply(&scores, 0);
+        let expected = 3f64.exp() / (3f64.exp() + 1f64.exp() + 1.0);
+        assert!((softmax - expected).abs() < 1e-12);
+        assert!((ConfidenceScale::Margin.apply(&scores, 0) - 2.0 / 3.0).abs() < 1e-12);
+        // A lone category wins outright.
+        assert_eq!(ConfidenceScale::Softmax.apply(&[5.0], 0), 1.0);
+        assert_eq!(ConfidenceScale::Margin.apply(&[5.0], 0), 1.0);
+        assert_eq!(ConfidenceScale::from_str("SoftMax"), Some(ConfidenceScale::Softmax));
+        assert_eq!(ConfidenceScale::from_str("logit"), None);
     }
 
     #[test]
@@ -0,0 +1,445 @@
+//! Graph model and serializers for GraphML, Graphviz DOT and GEXF.
+//!
+//! Node attributes are kept as strings: every attribute is declared once per
+//! graph with the names seen on any node, so files open in Gephi, yEd or
+//! `dot` without a schema of their own.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::clustering::Dendrogram;
+use super::taxonomy::{Category, Taxonomy};
+
+/// Output format for [`Graph::render`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    GraphMl,
+    Dot,
+    Gexf,
+}
+
+impl GraphFormat {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "graphml" => Some(Self::GraphMl),
+            "dot" | "gv" | "graphviz" => Some(Self::Dot),
+            "gexf" => Some(Self::Gexf),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub id: String,
+    pub label: String,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Node {
+    pub fn new(id: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    pub fn with(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+    pub weight: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Graph {
+    pub directed: bool,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn new(directed: bool) -> Self {
+        Self {
+            directed,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_edge(&mut self, source: &str, target: &str, weight: Option<f64>) {
+        self.edges.push(Edge {
+            source: source.into(),
+            target: target.into(),
+            weight,
+        });
+    }
+
+    /// Tree of a dendrogram: `leaves[i]` is item `i`, and merge `m` becomes
+    /// node `cluster{n + m}` (the id [`super::clustering::hac`] gives it)
+    /// with edges to both merged clusters weighted by the merge distance.
+    pub fn from_dendrogram(dendrogram: &Dendrogram, leaves: Vec<Node>) -> Self {
+        let n = dendrogram.n;
+        let mut ids: Vec<String> = leaves.iter().map(|l| l.id.clone()).collect();
+        let mut graph = Self::new(true);
+        graph.nodes = leaves;
+        for (m, merge) in dendrogram.merges.iter().enumerate() {
+            let id = format!("cluster{}", n + m);
+            graph.nodes.push(
+                Node::new(&id, format!("{} items", merge.size))
+                    .with("kind", "cluster")
+                    .with("size", merge.size.to_string())
+                    .with("distance", format!("{:.6}", merge.distance)),
+            );
+            for child in [merge.cluster_a, merge.cluster_b] {
+                if let Some(child_id) = ids.get(child) {
+                    graph.add_edge(&id, child_id, Some(merge.distance));
+                }
+            }
+            ids.push(id);
+        }
+        graph
+    }
+
+    /// Category tree of a taxonomy. Nodes are `cat:<path>` with the
+    /// category's keywords; edges run from parent to child.
+    pub fn from_taxonomy(taxonomy: &Taxonomy) -> Self {
+        let mut graph = Self::new(true);
+        for category in &taxonomy.categories {
+            graph.add_category(category, None);
+        }
+        graph
+    }
+
+    /// Add the category node for `path` (`A > B > C`) and its missing
+    /// ancestors. Returns the node id.
+    pub fn add_category_path(&mut self, path: &str) -> String {
+        let mut parent: Option<String> = None;
+        let mut prefix = String::new();
+        for name in path.split(" > ") {
+            if !prefix.is_empty() {
+                prefix.push_str(" > ");
+            }
+            prefix.push_str(name);
+            let id = category_id(&prefix);
+            if !self.nodes.iter().any(|n| n.id == id) {
+                self.nodes.push(Node::new(&id, name).with("kind", "category").with("category", &prefix));
+                if let Some(parent) = &parent {
+                    self.add_edge(parent, &id, None);
+                }
+            }
+            parent = Some(id);
+        }
+        parent.unwrap_or_default()
+    }
+
+    fn add_category(&mut self, category: &Category, parent: Option<&str>) {
+        let path = match parent {
+            Some(parent) => format!("{parent} > {}", category.name),
+            None => category.name.clone(),
+        };
+        let id = self.add_category_path(&path);
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.id == id) {
+            node.attributes.insert("keywords".into(), category.keywords.join(", "));
+        }
+        for child in &category.children {
+            self.add_category(child, Some(&path));
+        }
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::GraphMl => self.to_graphml(),
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Gexf => self.to_gexf(),
+        }
+    }
+
+    /// Attribute names used by any node, sorted.
+    fn attribute_names(&self) -> Vec<&str> {
+        let names: BTreeSet<&str> = self
+            .nodes
+            .iter()
+            .flat_map(|n| n.attributes.keys().map(String::as_str))
+            .collect();
+        names.into_iter().collect()
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let names = self.attribute_names();
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        out.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+        for (i, name) in names.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "  <key id=\"a{i}\" for=\"node\" attr.name=\"{}\" attr.type=\"string\"/>",
+                xml_escape(name)
+            );
+        }
+        out.push_str("  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n");
+        let _ = writeln!(
+            out,
+            "  <graph id=\"topology\" edgedefault=\"{}\">",
+            if self.directed { "directed" } else { "undirected" }
+        );
+        for node in &self.nodes {
+            let _ = writeln!(out, "    <node id=\"{}\">", xml_escape(&node.id));
+            let _ = writeln!(out, "      <data key=\"label\">{}</data>", xml_escape(&node.label));
+            for (i, name) in names.iter().enumerate() {
+                if let Some(value) = node.attributes.get(*name) {
+                    let _ = writeln!(out, "      <data key=\"a{i}\">{}</data>", xml_escape(value));
+                }
+            }
+            out.push_str("    </node>\n");
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            let _ = write!(
+                out,
+                "    <edge id=\"e{i}\" source=\"{}\" target=\"{}\"",
+                xml_escape(&edge.source),
+                xml_escape(&edge.target)
+            );
+            match edge.weight {
+                Some(w) => {
+                    let _ = writeln!(out, ">\n      <data key=\"weight\">{w}</data>\n    </edge>");
+                }
+                None => out.push_str("/>\n"),
+            }
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+
+    pub fn to_dot(&self) -> String {
+        let (keyword, arrow) = if self.directed { ("digraph", "->") } else { ("graph", "--") };
+        let mut out = format!("{keyword} topology {{\n");
+        for node in &self.nodes {
+            let mut attrs = vec![format!("label={}", dot_quote(&node.label))];
+            attrs.extend(
+                node.attributes
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", dot_quote(k), dot_quote(v))),
+            );
+            let _ = writeln!(out, "  {} [{}];", dot_quote(&node.id), attrs.join(", "));
+        }
+        for edge in &self.edges {
+            let _ = write!(out, "  {} {arrow} {}", dot_quote(&edge.source), dot_quote(&edge.target));
+            match edge.weight {
+                Some(w) => {
+                    let _ = writeln!(out, " [weight={w}];");
+                }
+                None => out.push_str(";\n"),
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_gexf(&self) -> String {
+        let names = self.attribute_names();
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n");
+        let _ = writeln!(
+            out,
+            "  <graph mode=\"static\" defaultedgetype=\"{}\">",
+            if self.directed { "directed" } else { "undirected" }
+        );
+        if !names.is_empty() {
+            out.push_str("    <attributes class=\"node\">\n");
+            for (i, name) in names.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "      <attribute id=\"{i}\" title=\"{}\" type=\"string\"/>",
+                    xml_escape(name)
+                );
+            }
+            out.push_str("    </attributes>\n");
+        }
+        out.push_str("    <nodes>\n");
+        for node in &self.nodes {
+            let _ = write!(
+                out,
+                "      <node id=\"{}\" label=\"{}\"",
+                xml_escape(&node.id),
+                xml_escape(&node.label)
+            );
+            if node.attributes.is_empty() {
+                out.push_str("/>\n");
+                continue;
+            }
+            out.push_str(">\n        <attvalues>\n");
+            for (i, name) in names.iter().enumerate() {
+                if let Some(value) = node.attributes.get(*name) {
+                    let _ = writeln!(
+                        out,
+                        "          <attvalue for=\"{i}\" value=\"{}\"/>",
+                        xml_escape(value)
+                    );
+                }
+            }
+            out.push_str("        </attvalues>\n      </node>\n");
+        }
+        out.push_str("    </nodes>\n    <edges>\n");
+        for (i, edge) in self.edges.iter().enumerate() {
+            let _ = write!(
+                out,
+                "      <edge id=\"{i}\" source=\"{}\" target=\"{}\"",
+                xml_escape(&edge.source),
+                xml_escape(&edge.target)
+            );
+            if let Some(w) = edge.weight {
+                let _ = write!(out, " weight=\"{w}\"");
+            }
+            out.push_str("/>\n");
+        }
+        out.push_str("    </edges>\n  </graph>\n</gexf>\n");
+        out
+    }
+}
+
+/// Node id of a taxonomy category path.
+pub fn category_id(path: &str) -> String {
+    format!("cat:{path}")
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Control characters other than tab/newline are not valid XML 1.0.
+            c if c.is_control() && c != '\t' && c != '\n' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn dot_quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::clustering::Merge;
+
+    fn sample() -> Graph {
+        let mut graph = Graph::new(false);
+        graph.nodes.push(Node::new("n0", "Rust & \"async\"").with("category", "Tech > Rust"));
+        graph.nodes.push(Node::new("n1", "Pasta").with("tags", "pasta, sauce"));
+        graph.add_edge("n0", "n1", Some(0.5));
+        graph
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!(GraphFormat::from_str("GraphML"), Some(GraphFormat::GraphMl));
+        assert_eq!(GraphFormat::from_str("gv"), Some(GraphFormat::Dot));
+        assert_eq!(GraphFormat::from_str("gexf"), Some(GraphFormat::Gexf));
+        assert_eq!(GraphFormat::from_str("svg"), None);
+    }
+
+    #[test]
+    fn graphml_declares_and_escapes_attributes() {
+        let xml = sample().to_graphml();
+        assert!(xml.contains(r#"<key id="a0" for="node" attr.name="category" attr.type="string"/>"#));
+        assert!(xml.contains(r#"<key id="a1" for="node" attr.name="tags" attr.type="string"/>"#));
+        assert!(xml.contains(r#"edgedefault="undirected""#));
+        assert!(xml.contains("<data key=\"label\">Rust &amp; &quot;async&quot;</data>"));
+        assert!(xml.contains("<data key=\"a0\">Tech &gt; Rust</data>"));
+        assert!(xml.contains("<data key=\"weight\">0.5</data>"));
+    }
+
+    #[test]
+    fn dot_quotes_ids_and_labels() {
+        let dot = sample().to_dot();
+        assert!(dot.starts_with("graph topology {"));
+        assert!(dot.contains(r#""n0" [label="Rust & \"async\"", "category"="Tech > Rust"];"#));
+        assert!(dot.contains(r#""n0" -- "n1" [weight=0.5];"#));
+        let mut directed = sample();
+        directed.directed = true;
+        assert!(directed.to_dot().contains(r#""n0" -> "n1""#));
+    }
+
+    #[test]
+    fn gexf_lists_attvalues() {
+        let gexf = sample().to_gexf();
+        assert!(gexf.contains(r#"<attribute id="0" title="category" type="string"/>"#));
+        assert!(gexf.contains(r#"<attvalue for="1" value="pasta, sauce"/>"#));
+        assert!(gexf.contains(r#"<edge id="0" source="n0" target="n1" weight="0.5"/>"#));
+    }
+
+    #[test]
+    fn dendrogram_tree_links_merges() {
+        let dendrogram = Dendrogram {
+            n: 3,
+            merges: vec![
+                Merge { cluster_a: 0, cluster_b: 1, distance: 0.1, size: 2 },
+                Merge { cluster_a: 2, cluster_b: 3, distance: 0.7, size: 3 },
+            ],
+        };
+        let leaves = (0..3).map(|i| Node::new(format!("n{i}"), format!("item {i}"))).collect();
+        let graph = Graph::from_dendrogram(&dendrogram, leaves);
+        assert_eq!(graph.nodes.len(), 5);
+        assert_eq!(graph.nodes[4].id, "cluster4");
+        let edges: Vec<(&str, &str)> = graph
+            .edges
+            .iter()
+            .map(|e| (e.source.as_str(), e.target.as_str()))
+            .collect();
+        assert_eq!(edges, [("cluster3", "n0"), ("cluster3", "n1"), ("cluster4", "n2"), ("cluster4", "cluster3")]);
+        assert_eq!(graph.edges[3].weight, Some(0.7));
+    }
+
+    #[test]
+    fn taxonomy_tree_and_paths() {
+        let taxonomy = Taxonomy {
+            name: "t".into(),
+            version: "1".into(),
+            categories: vec![Category {
+                name: "Tech".into(),
+                keywords: vec!["software".into()],
+                children: vec![Category {
+                    name: "Rust".into(),
+                    keywords: vec!["rust".into(), "cargo".into()],
+                    children: vec![],
+                    centroid: Default::default(),
+                }],
+                centroid: Default::default(),
+            }],
+        };
+        let mut graph = Graph::from_taxonomy(&taxonomy);
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.nodes[1].attributes["keywords"], "rust, cargo");
+        assert_eq!(graph.edges[0].target, "cat:Tech > Rust");
+
+        let id = graph.add_category_path("Tech > Go");
+        assert_eq!(id, "cat:Tech > Go");
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.edges.len(), 2);
+    }
+}
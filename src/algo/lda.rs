@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use super::sampling::LcgRng;
+use super::sparse::{TermId, Vocabulary};
+
+/// Latent Dirichlet Allocation for topic modeling.
+///
+/// Each document is a mixture of k topics and each topic a distribution
+/// over terms. Fitted with collapsed Gibbs sampling (Griffiths & Steyvers,
+/// 2004) on raw term counts, which suits short texts such as titles better
+/// than NMF on TF-IDF: a two-word title still gets a proper topic mixture
+/// instead of a near-zero row.
+pub struct LdaResult {
+    /// Document-topic distribution θ (n_docs × k). Each row sums to 1.
+    pub doc_topics: Vec<Vec<f64>>,
+    /// Topic-term distribution φ (k × n_terms). Each row sums to 1.
+    pub topic_terms: Vec<Vec<f64>>,
+    /// Vocabulary mapping index → term.
+    pub vocabulary: Vec<String>,
+    /// Number of topics.
+    pub k: usize,
+    /// Number of Gibbs sweeps run.
+    pub iterations: usize,
+    /// exp(−log-likelihood / tokens) of the corpus under θ and φ; lower is
+    /// a better fit. 0 when there are no tokens.
+    pub perplexity: f64,
+}
+
+impl LdaResult {
+    /// Get top N terms for topic t.
+    pub fn top_terms(&self, topic: usize, n: usize) -> Vec<(String, f64)> {
+        if topic >= self.k {
+            return vec![];
+        }
+        let row = &self.topic_terms[topic];
+        let mut indexed: Vec<(usize, f64)> = row.iter().copied().enumerate().collect();
+        indexed.sort_by(|a, b| b.1.total_cmp(&a.1));
+        indexed
+            .into_iter()
+            .take(n)
+            .map(|(i, w)| (self.vocabulary[i].clone(), w))
+            .collect()
+    }
+
+    /// Get dominant topic for each document; the lowest topic wins ties.
+    pub fn dominant_topics(&self) -> Vec<usize> {
+        self.doc_topics
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .fold((0, f64::NEG_INFINITY), |best, (i, &p)| if p > best.1 { (i, p) } else { best })
+                    .0
+            })
+            .collect()
+    }
+}
+
+/// Configuration for [`lda`].
+#[derive(Debug, Clone)]
+pub struct LdaConfig {
+    /// Gibbs sweeps over every token.
+    pub iterations: usize,
+    /// Symmetric Dirichlet prior on document-topic mixtures. Lower values
+    /// push each document towards fewer topics.
+    pub alpha: f64,
+    /// Symmetric Dirichlet prior on topic-term distributions. Lower values
+    /// give topics fewer, more distinctive terms.
+    pub beta: f64,
+    /// Seed for topic initialisation and sampling; equal seeds give equal runs.
+    pub seed: u64,
+    /// Maximum vocabulary size (top terms by doc frequency).
+    pub vocab_limit: usize,
+}
+
+impl Default for LdaConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 200,
+            alpha: 0.1,
+            beta: 0.01,
+            seed: 42,
+            vocab_limit: 5000,
+        }
+    }
+}
+
+/// Run LDA on per-document term counts.
+///
+/// * `term_counts` - One `(term id, count)` list per document, ids from
+///   `vocabulary` (see [`super::tfidf::Corpus::term_counts`]).
+/// * `k` - Number of topics.
+///
+/// Each sweep costs O(tokens·k); memory is O(tokens + (n_docs + n_terms)·k).
+pub fn lda(
+    term_counts: &[&[(TermId, u32)]],
+    vocabulary: &Vocabulary,
+    k: usize,
+    config: &LdaConfig,
+) -> LdaResult {
+    let n_docs = term_counts.len();
+
+    // Build vocabulary (sorted by doc frequency, ties by term, limited)
+    let mut doc_freq: HashMap<TermId, usize> = HashMap::new();
+    for doc in term_counts {
+        for &(id, _) in doc.iter() {
+            *doc_freq.entry(id).or_insert(0) += 1;
+        }
+    }
+    let mut vocab: Vec<(TermId, usize)> = doc_freq.into_iter().collect();
+    vocab.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| vocabulary.term(a.0).cmp(vocabulary.term(b.0))));
+    vocab.truncate(config.vocab_limit);
+    let column: HashMap<TermId, usize> = vocab.iter().enumerate().map(|(col, &(id, _))| (id, col)).collect();
+    let vocabulary: Vec<String> = vocab.iter().map(|&(id, _)| vocabulary.term(id).to_string()).collect();
+    let n_terms = vocabulary.len();
+
+    // One entry per token occurrence: (document, term column)
+    let tokens: Vec<(usize, usize)> = term_counts
+        .iter()
+        .enumerate()
+        .flat_map(|(d, doc)| {
+            doc.iter()
+                .filter_map(|(id, count)| column.get(id).map(|&col| (col, *count)))
+                .flat_map(move |(col, count)| std::iter::repeat_n((d, col), count as usize))
+        })
+        .collect();
+
+    if k == 0 {
+        return LdaResult {
+            doc_topics: vec![vec![]; n_docs],
+            topic_terms: vec![],
+            vocabulary,
+            k,
+            iterations: 0,
+            perplexity: 0.0,
+        };
+    }
+
+    let mut doc_topic = vec![vec![0u32; k]; n_docs];
+    let mut topic_term = vec![vec![0u32; n_terms]; k];
+    let mut topic_total = vec![0u32; k];
+    let mut rng = LcgRng::new(config.seed);
+
+    let mut assignment: Vec<usize> = Vec::with_capacity(tokens.len());
+    for &(d, w) in &tokens {
+        let z = (rng.next_f64() * k as f64) as usize % k;
+        assignment.push(z);
+        doc_topic[d][z] += 1;
+        topic_term[z][w] += 1;
+        topic_total[z] += 1;
+    }
+
+    let beta_sum = config.beta * n_terms as f64;
+    let mut weights = vec![0.0f64; k];
+    let iterations = if tokens.is_empty() { 0 } else { config.iterations };
+
+    for _ in 0..iterations {
+        for (i, &(d, w)) in tokens.iter().enumerate() {
+            let old = assignment[i];
+            doc_topic[d][old] -= 1;
+            topic_term[old][w] -= 1;
+            topic_total[old] -= 1;
+
+            // p(z = t | rest) ∝ (n_dt + α) · (n_tw + β) / (n_t + Vβ)
+            let mut total = 0.0;
+            for t in 0..k {
+                total += (doc_topic[d][t] as f64 + config.alpha) * (topic_term[t][w] as f64 + config.beta)
+                    / (topic_total[t] as f64 + beta_sum);
+                weights[t] = total;
+            }
+            let target = rng.next_f64() * total;
+            let new = weights.iter().position(|&c| c > target).unwrap_or(k - 1);
+
+            assignment[i] = new;
+            doc_topic[d][new] += 1;
+            topic_term[new][w] += 1;
+            topic_total[new] += 1;
+        }
+    }
+
+    let alpha_sum = config.alpha * k as f64;
+    let doc_topics: Vec<Vec<f64>> = doc_topic
+        .iter()
+        .map(|counts| {
+            let len: u32 = counts.iter().sum();
+            counts
+                .iter()
+                .map(|&c| (c as f64 + config.alpha) / (len as f64 + alpha_sum))
+                .collect()
+        })
+        .collect();
+    let topic_terms: Vec<Vec<f64>> = topic_term
+        .iter()
+        .zip(&topic_total)
+        .map(|(counts, &total)| {
+            counts
+                .iter()
+                .map(|&c| (c as f64 + config.beta) / (total as f64 + beta_sum))
+                .collect()
+        })
+        .collect();
+
+    let log_likelihood: f64 = tokens
+        .iter()
+        .map(|&(d, w)| (0..k).map(|t| doc_topics[d][t] * topic_terms[t][w]).sum::<f64>().ln())
+        .sum();
+    let perplexity = if tokens.is_empty() { 0.0 } else { (-log_likelihood / tokens.len() as f64).exp() };
+
+    LdaResult {
+        doc_topics,
+        topic_terms,
+        vocabulary,
+        k,
+        iterations,
+        perplexity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::tfidf::Corpus;
+
+    fn corpus(docs: &[&str]) -> Corpus {
+        let mut corpus = Corpus::new();
+        for doc in docs {
+            let tokens: Vec<String> = doc.split_whitespace().map(String::from).collect();
+            corpus.add_document(&tokens);
+        }
+        corpus
+    }
+
+    fn run(corpus: &Corpus, k: usize, config: &LdaConfig) -> LdaResult {
+        let counts: Vec<&[(TermId, u32)]> = (0..corpus.num_docs() as usize).map(|i| corpus.term_counts(i)).collect();
+        lda(&counts, corpus.vocabulary(), k, config)
+    }
+
+    fn two_themes() -> Corpus {
+        corpus(&[
+            "rust cargo borrow",
+            "rust borrow lifetimes",
+            "cargo rust crates",
+            "css html layout",
+            "html css browser",
+            "browser html dom",
+        ])
+    }
+
+    #[test]
+    fn lda_separates_themes() {
+        let result = run(&two_themes(), 2, &LdaConfig::default());
+        let topics = result.dominant_topics();
+        assert!(topics[..3].iter().all(|&t| t == topics[0]));
+        assert!(topics[3..].iter().all(|&t| t == topics[3]));
+        assert_ne!(topics[0], topics[3]);
+
+        let rust_topic = &result.top_terms(topics[0], 2);
+        assert_eq!(rust_topic[0].0, "rust");
+    }
+
+    #[test]
+    fn lda_distributions_are_normalized() {
+        let result = run(&two_themes(), 3, &LdaConfig::default());
+        for row in result.doc_topics.iter().chain(&result.topic_terms) {
+            assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            assert!(row.iter().all(|&p| p > 0.0));
+        }
+        assert!(result.perplexity > 1.0);
+        assert_eq!(result.iterations, 200);
+    }
+
+    #[test]
+    fn lda_is_reproducible_per_seed() {
+        let corpus = two_themes();
+        let a = run(&corpus, 2, &LdaConfig { iterations: 20, ..Default::default() });
+        let b = run(&corpus, 2, &LdaConfig { iterations: 20, ..Default::default() });
+        assert_eq!(a.doc_topics, b.doc_topics);
+        assert_eq!(a.topic_terms, b.topic_terms);
+
+        let c = run(&corpus, 2, &LdaConfig { iterations: 20, seed: 7, ..Default::default() });
+        assert_ne!(a.topic_terms, c.topic_terms);
+    }
+
+    #[test]
+    fn lda_empty_and_vocab_limit() {
+        let empty = run(&corpus(&[]), 2, &LdaConfig::default());
+        assert!(empty.doc_topics.is_empty());
+        assert_eq!(empty.perplexity, 0.0);
+
+        let blank = run(&corpus(&["", "rust"]), 2, &LdaConfig::default());
+        assert_eq!(blank.doc_topics[0], vec![0.5, 0.5]);
+
+        let limited = run(&two_themes(), 2, &LdaConfig { vocab_limit: 3, ..Default::default() });
+        assert_eq!(limited.vocabulary.len(), 3);
+        assert!(limited.top_terms(5, 3).is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use rayon::prelude::*;
 use siphasher::sip::SipHasher13;
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
@@ -46,10 +47,7 @@ impl LshIndex {
             self.bands * self.rows
         );
 
-        for band_idx in 0..self.bands {
-            let start = band_idx * self.rows;
-            let band_slice = &signature[start..start + self.rows];
-            let band_hash = hash_band(band_idx, band_slice);
+        for (band_idx, band_hash) in self.band_keys(signature).into_iter().enumerate() {
             self.buckets[band_idx]
                 .entry(band_hash)
                 .or_default()
@@ -62,11 +60,7 @@ impl LshIndex {
     pub fn query(&self, signature: &[u64]) -> HashSet<usize> {
         let mut candidates = HashSet::new();
 
-        for band_idx in 0..self.bands {
-            let start = band_idx * self.rows;
-            let band_slice = &signature[start..start + self.rows];
-            let band_hash = hash_band(band_idx, band_slice);
-
+        for (band_idx, band_hash) in self.band_keys(signature).into_iter().enumerate() {
             if let Some(items) = self.buckets[band_idx].get(&band_hash) {
                 for &id in items {
                     candidates.insert(id);
@@ -77,6 +71,17 @@ impl LshIndex {
         candidates
     }
 
+    /// Bucket key of each band of `signature`, in band order. Persistent
+    /// indexes store these keys to find the same candidates as [`Self::query`].
+    pub fn band_keys(&self, signature: &[u64]) -> Vec<u64> {
+        (0..self.bands)
+            .map(|band_idx| {
+                let start = band_idx * self.rows;
+                hash_band(band_idx, &signature[start..start + self.rows])
+            })
+            .collect()
+    }
+
     /// Find all candidate pairs (items that share at least one band).
     /// Returns deduplicated pairs (i, j) where i < j.
     pub fn candidate_pairs(&self) -> Vec<(usize, usize)> {
@@ -102,6 +107,16 @@ impl LshIndex {
         result
     }
 
+    /// For every inserted item, up to `limit` other items sharing a band
+    /// with it, nearest first by `distance`; see [`nearest_candidates`].
+    pub fn top_candidates<D: Ord>(
+        &self,
+        limit: usize,
+        distance: impl Fn(usize, usize) -> D + Sync,
+    ) -> Vec<(usize, Vec<usize>)> {
+        nearest_candidates(&self.buckets, limit, distance)
+    }
+
     pub fn bands(&self) -> usize {
         self.bands
     }
@@ -148,8 +163,7 @@ impl SimHashLshIndex {
 
     /// Insert a SimHash fingerprint.
     pub fn insert(&mut self, item_id: usize, fingerprint: u64) {
-        for band_idx in 0..self.bands {
-            let band_val = extract_band(fingerprint, band_idx, self.bits_per_band);
+        for (band_idx, band_val) in self.band_keys(fingerprint).into_iter().enumerate() {
             self.buckets[band_idx]
                 .entry(band_val)
                 .or_default()
@@ -160,8 +174,7 @@ impl SimHashLshIndex {
     /// Query for candidate near-neighbors.
     pub fn query(&self, fingerprint: u64) -> HashSet<usize> {
         let mut candidates = HashSet::new();
-        for band_idx in 0..self.bands {
-            let band_val = extract_band(fingerprint, band_idx, self.bits_per_band);
+        for (band_idx, band_val) in self.band_keys(fingerprint).into_iter().enumerate() {
             if let Some(items) = self.buckets[band_idx].get(&band_val) {
                 for &id in items {
                     candidates.insert(id);
@@ -171,6 +184,13 @@ impl SimHashLshIndex {
         candidates
     }
 
+    /// Value of each bit band of `fingerprint`, in band order.
+    pub fn band_keys(&self, fingerprint: u64) -> Vec<u64> {
+        (0..self.bands)
+            .map(|band_idx| extract_band(fingerprint, band_idx, self.bits_per_band))
+            .collect()
+    }
+
     /// Find all candidate pairs.
     pub fn candidate_pairs(&self) -> Vec<(usize, usize)> {
         let mut pairs: HashSet<(usize, usize)> = HashSet::new();
@@ -194,12 +214,191 @@ impl SimHashLshIndex {
     }
 }
 
+impl SimHashLshIndex {
+    /// For every inserted item, up to `limit` other items sharing a band
+    /// with it, nearest first by `distance`; see [`nearest_candidates`].
+    pub fn top_candidates<D: Ord>(
+        &self,
+        limit: usize,
+        distance: impl Fn(usize, usize) -> D + Sync,
+    ) -> Vec<(usize, Vec<usize>)> {
+        nearest_candidates(&self.buckets, limit, distance)
+    }
+}
+
+/// Per-item candidate lists bounded to `limit`, ordered by item.
+///
+/// Unlike `candidate_pairs`, which keeps every pair sharing any band, each
+/// item keeps only the `limit` items sharing a band with it that are nearest
+/// by `distance` (ties by lower id). `distance` should be a cheap estimate
+/// such as a fingerprint Hamming distance; the output holds at most
+/// n·`limit` pairs even when loose bands put most of the collection in each
+/// bucket.
+fn nearest_candidates<D: Ord>(
+    buckets: &[HashMap<u64, Vec<usize>>],
+    limit: usize,
+    distance: impl Fn(usize, usize) -> D + Sync,
+) -> Vec<(usize, Vec<usize>)> {
+    let mut memberships: HashMap<usize, Vec<&Vec<usize>>> = HashMap::new();
+    for band in buckets {
+        for items in band.values() {
+            for &id in items {
+                memberships.entry(id).or_default().push(items);
+            }
+        }
+    }
+    let n = memberships.keys().max().map_or(0, |&id| id + 1);
+    let mut result: Vec<(usize, Vec<usize>)> = memberships
+        .into_par_iter()
+        .map_init(
+            || vec![false; n],
+            |seen, (id, shared)| {
+                let mut ranked: Vec<(D, usize)> = Vec::new();
+                for items in shared {
+                    for &other in items {
+                        if other != id && !seen[other] {
+                            seen[other] = true;
+                            ranked.push((distance(id, other), other));
+                        }
+                    }
+                }
+                for (_, other) in &ranked {
+                    seen[*other] = false;
+                }
+                if ranked.len() > limit && limit > 0 {
+                    ranked.select_nth_unstable(limit - 1);
+                }
+                ranked.truncate(limit);
+                ranked.sort_unstable();
+                (id, ranked.into_iter().map(|(_, other)| other).collect())
+            },
+        )
+        .collect();
+    result.sort_unstable_by_key(|(id, _)| *id);
+    result
+}
+
 fn extract_band(fingerprint: u64, band_idx: usize, bits_per_band: usize) -> u64 {
     let shift = band_idx * bits_per_band;
-    let mask = (1u64 << bits_per_band) - 1;
+    let mask = if bits_per_band >= 64 { u64::MAX } else { (1u64 << bits_per_band) - 1 };
     (fingerprint >> shift) & mask
 }
 
+/// Fewest MinHash values per band. With a single value per band, almost
+/// every pair sharing any shingle lands in a common bucket.
+pub const MIN_MINHASH_ROWS: usize = 2;
+
+/// Band layout chosen for a similarity threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Banding {
+    pub bands: usize,
+    /// Rows (MinHash values or SimHash bits) per band.
+    pub rows: usize,
+    /// Probability that a pair exactly at the threshold shares no band and
+    /// is never compared. Closer pairs are missed less often.
+    pub false_negative_rate: f64,
+}
+
+impl Banding {
+    /// SimHash banding with the fewest (widest, most selective) bands whose
+    /// false-negative rate at Hamming distance `threshold` is at most
+    /// `1 - recall`. With more bands than `threshold`, some band is always
+    /// untouched and nothing is missed.
+    ///
+    /// `recall` can only add bands: the search starts at
+    /// `min(threshold + 1, 16)`. Up to a threshold of 15 that is the exact
+    /// layout, which misses nothing; wider thresholds start from the 16 bands
+    /// of [`SimHashLshIndex::default_64`], so no pair within the threshold
+    /// that the default layout would find is dropped.
+    pub fn simhash(threshold: u32, recall: f64) -> Self {
+        let min_bands = (threshold as usize + 1).min(16);
+        (min_bands..=64)
+            .map(|bands| Self::simhash_with_bands(threshold, bands))
+            .find(|b| b.false_negative_rate <= 1.0 - recall)
+            .unwrap_or_else(|| Self::simhash_with_bands(threshold, 64))
+    }
+
+    /// SimHash banding with a fixed number of bands of `64 / bands` bits.
+    pub fn simhash_with_bands(threshold: u32, bands: usize) -> Self {
+        let bands = bands.clamp(1, 64);
+        let rows = 64 / bands;
+        Self {
+            bands,
+            rows,
+            false_negative_rate: simhash_false_negative_rate(threshold, bands, rows),
+        }
+    }
+
+    /// MinHash banding over `num_perm` values with the most rows per band
+    /// whose false-negative rate at Jaccard `threshold` is at most
+    /// `1 - recall`. Bands keep at least [`MIN_MINHASH_ROWS`] rows, so a
+    /// target no layout can reach (such as a recall of 1.0) settles on the
+    /// narrowest allowed bands instead of one value per band.
+    pub fn minhash(threshold: f64, recall: f64, num_perm: usize) -> Self {
+        let num_perm = num_perm.max(1);
+        let min_rows = MIN_MINHASH_ROWS.min(num_perm);
+        (min_rows..=num_perm)
+            .rev()
+            .map(|rows| Self::minhash_with_bands(threshold, num_perm / rows, num_perm))
+            .find(|b| b.false_negative_rate <= 1.0 - recall)
+            .unwrap_or_else(|| Self::minhash_with_bands(threshold, num_perm / min_rows, num_perm))
+    }
+
+    /// MinHash banding with a fixed number of bands of `num_perm / bands`
+    /// rows, capped so each band keeps at least [`MIN_MINHASH_ROWS`] rows.
+    pub fn minhash_with_bands(threshold: f64, bands: usize, num_perm: usize) -> Self {
+        let num_perm = num_perm.max(1);
+        let bands = bands.clamp(1, (num_perm / MIN_MINHASH_ROWS).max(1));
+        let rows = num_perm / bands;
+        Self {
+            bands,
+            rows,
+            false_negative_rate: minhash_false_negative_rate(threshold, bands, rows),
+        }
+    }
+
+    pub fn simhash_index(&self) -> SimHashLshIndex {
+        SimHashLshIndex::new(self.bands, self.rows)
+    }
+
+    pub fn minhash_index(&self) -> LshIndex {
+        LshIndex::new(self.bands, self.rows)
+    }
+}
+
+/// Probability that two 64-bit fingerprints `distance` bits apart (bits
+/// chosen uniformly) differ in every one of `bands` bands of `bits_per_band`.
+///
+/// Inclusion-exclusion over the bands left untouched: `k` given bands are
+/// all untouched with probability C(64 - k·w, d) / C(64, d).
+pub fn simhash_false_negative_rate(distance: u32, bands: usize, bits_per_band: usize) -> f64 {
+    let d = distance as usize;
+    if d < bands {
+        return 0.0;
+    }
+    let untouched = |k: usize| -> f64 {
+        let free = 64usize.saturating_sub(k * bits_per_band);
+        if free < d {
+            return 0.0;
+        }
+        (0..d).map(|i| (free - i) as f64 / (64 - i) as f64).product()
+    };
+    let mut binomial = 1.0;
+    let mut any_untouched = 0.0;
+    for k in 1..=bands {
+        binomial *= (bands - k + 1) as f64 / k as f64;
+        let sign = if k % 2 == 1 { 1.0 } else { -1.0 };
+        any_untouched += sign * binomial * untouched(k);
+    }
+    (1.0 - any_untouched).clamp(0.0, 1.0)
+}
+
+/// Probability that two MinHash signatures with Jaccard similarity
+/// `jaccard` share none of `bands` bands of `rows` rows: (1 - s^r)^b.
+pub fn minhash_false_negative_rate(jaccard: f64, bands: usize, rows: usize) -> f64 {
+    (1.0 - jaccard.clamp(0.0, 1.0).powi(rows as i32)).powi(bands as i32)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -323,6 +522,133 @@ mod tests {
         assert!(results.contains(&0));
     }
 
+    #[test]
+    fn band_keys_match_buckets() {
+        let idx = LshIndex::new(4, 2);
+        let sig = vec![1u64, 2, 3, 4, 5, 6, 7, 8];
+        let mut other = sig.clone();
+        other[7] = 99;
+        let (a, b) = (idx.band_keys(&sig), idx.band_keys(&other));
+        assert_eq!(a.len(), 4);
+        assert_eq!(a[..3], b[..3]);
+        assert_ne!(a[3], b[3]);
+
+        let simhash = SimHashLshIndex::default_64();
+        let keys = simhash.band_keys(0xABCD);
+        assert_eq!(keys.len(), 16);
+        assert_eq!(keys[..4], [0xD, 0xC, 0xB, 0xA]);
+        assert!(keys[4..].iter().all(|&k| k == 0));
+    }
+
+    #[test]
+    fn simhash_banding_guarantees_recall_below_band_count() {
+        let banding = Banding::simhash(3, 0.95);
+        assert_eq!((banding.bands, banding.rows), (4, 16));
+        assert_eq!(banding.false_negative_rate, 0.0);
+        assert_eq!(Banding::simhash(3, 1.0).bands, 4);
+
+        // Past 16 bits the default 16 x 4 layout starts missing pairs.
+        assert!(simhash_false_negative_rate(20, 16, 4) > 0.0);
+        assert!(Banding::simhash(20, 0.95).false_negative_rate <= 0.05);
+        let exact = Banding::simhash(20, 1.0);
+        assert_eq!((exact.bands, exact.rows, exact.false_negative_rate), (21, 3, 0.0));
+    }
+
+    #[test]
+    fn simhash_banding_never_misses_pairs_the_default_layout_finds() {
+        for threshold in 0..=15 {
+            let banding = Banding::simhash(threshold, 0.95);
+            assert_eq!(banding.false_negative_rate, 0.0, "threshold {threshold}");
+            assert!(banding.bands <= 16);
+        }
+        for threshold in 16..=40 {
+            let banding = Banding::simhash(threshold, 0.5);
+            assert!(banding.bands >= 16);
+            assert!(banding.false_negative_rate <= simhash_false_negative_rate(threshold, 16, 4));
+        }
+    }
+
+    #[test]
+    fn top_candidates_stay_below_quadratic_with_loose_bands() {
+        // Cosine 0.2 is a Hamming radius of 28: most rows share some band.
+        let banding = Banding::simhash(28, 0.95);
+        let mut idx = banding.simhash_index();
+        let n = 3000;
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let fingerprints: Vec<u64> = (0..n)
+            .map(|_| {
+                state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                let mut z = state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                z ^ (z >> 31)
+            })
+            .collect();
+        for (i, &fp) in fingerprints.iter().enumerate() {
+            idx.insert(i, fp);
+        }
+        let k = 5;
+        let lists = idx.top_candidates(k, |a, b| (fingerprints[a] ^ fingerprints[b]).count_ones());
+        assert_eq!(lists.len(), n);
+        assert!(lists.iter().all(|(id, others)| others.len() == k && !others.contains(id)));
+        let total: usize = lists.iter().map(|(_, others)| others.len()).sum();
+        assert!(total <= n * k);
+        assert!(total < n * (n - 1) / 2 / 100);
+    }
+
+    #[test]
+    fn top_candidates_keep_the_nearest_items() {
+        let mut idx = SimHashLshIndex::new(4, 16);
+        let fingerprints = [0u64, 0xFFFF_FFFF, 0x1, 0xFFFF_0000_0000_0000];
+        for (i, &fp) in fingerprints.iter().enumerate() {
+            idx.insert(i, fp);
+        }
+        let lists = idx.top_candidates(1, |a, b| (fingerprints[a] ^ fingerprints[b]).count_ones());
+        assert_eq!(lists, vec![(0, vec![2]), (1, vec![2]), (2, vec![0]), (3, vec![0])]);
+    }
+
+    #[test]
+    fn simhash_false_negative_rate_matches_enumeration() {
+        // 2 bits in 2 bands of 32: missed only when they fall in different bands.
+        let expected = 2.0 * 32.0 * 32.0 / (64.0 * 63.0);
+        assert!((simhash_false_negative_rate(2, 2, 32) - expected).abs() < 1e-12);
+        assert_eq!(simhash_false_negative_rate(64, 4, 16), 1.0);
+    }
+
+    #[test]
+    fn simhash_banding_finds_pairs_at_threshold() {
+        let banding = Banding::simhash(10, 1.0);
+        let mut idx = banding.simhash_index();
+        let fp = 0xDEADBEEF12345678u64;
+        idx.insert(0, fp);
+        idx.insert(1, fp ^ 0x8421_0842_1084_2108); // 13 bits spread across bands
+        idx.insert(2, fp ^ 0x0000_0000_0000_03FF); // 10 low bits
+        assert!(idx.query(fp).contains(&2));
+    }
+
+    #[test]
+    fn minhash_banding_meets_target_recall() {
+        let banding = Banding::minhash(0.5, 0.95, 128);
+        assert_eq!((banding.bands, banding.rows), (42, 3));
+        assert!(banding.false_negative_rate <= 0.05);
+        let stricter = Banding::minhash(0.8, 0.95, 128);
+        assert!(stricter.rows > banding.rows);
+        assert!(stricter.false_negative_rate <= 0.05);
+
+        let fixed = Banding::minhash_with_bands(0.5, 32, 128);
+        assert_eq!(fixed.rows, 4);
+        assert!((fixed.false_negative_rate - 0.9375f64.powi(32)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn minhash_banding_never_uses_single_row_bands() {
+        let exact = Banding::minhash(0.5, 1.0, 128);
+        assert_eq!((exact.bands, exact.rows), (64, 2));
+        assert!(exact.false_negative_rate < 1e-7);
+        assert_eq!(Banding::minhash(0.01, 0.95, 128).rows, 2);
+        assert_eq!(Banding::minhash_with_bands(0.5, 128, 128).bands, 64);
+    }
+
     #[test]
     fn simhash_lsh_far_apart() {
         let mut idx = SimHashLshIndex::default_64();
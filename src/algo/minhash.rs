@@ -85,6 +85,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_range_loop)]
     fn overlapping_sets_high_jaccard() {
         let mh = MinHasher::new(256);
         let a: Vec<String> = (0..100).map(|i| format!("token_{i}")).collect();
@@ -160,7 +161,7 @@ mod tests {
         let sig_a = mh.signature(&a);
         let sig_b = mh.signature(&b);
         let j = mh.jaccard(&sig_a, &sig_b);
-        assert!(j >= 0.0 && j <= 1.0, "Jaccard must be in [0,1], got {j}");
+        assert!((0.0..=1.0).contains(&j), "Jaccard must be in [0,1], got {j}");
     }
 
     #[test]
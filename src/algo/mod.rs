@@ -1,13 +1,18 @@
 pub mod cache;
 pub mod clustering;
+pub mod coherence;
 #[cfg(feature = "cache")]
 pub mod storage;
 pub mod discover;
+pub mod graph;
+pub mod lda;
 pub mod lsh;
 pub mod minhash;
+pub mod model;
 pub mod nmf;
 pub mod sampling;
 pub mod simhash;
+pub mod sparse;
 pub mod string_distance;
 pub mod taxonomy;
 pub mod tfidf;
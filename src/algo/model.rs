@@ -0,0 +1,219 @@
+//! Persisted classifier model: a taxonomy frozen together with the corpus
+//! statistics and tokenizer settings it was trained with.
+//!
+//! Training once and classifying against the saved model keeps categories
+//! and scores stable as new items arrive, instead of rediscovering a taxonomy
+//! from whatever input set happens to be at hand.
+
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::clustering::KSelection;
+use super::discover::{self, CategoryMatch, ConfidenceScale, DiscoverConfig};
+use super::taxonomy::Taxonomy;
+use super::tfidf::{Corpus, IdfStats};
+use super::tokenizer::TokenizerConfig;
+
+/// Identifies a file as a topology classifier model.
+pub const MODEL_FORMAT: &str = "topology-classifier";
+
+/// Model file layout version. Bump on any incompatible change; older readers
+/// refuse newer files rather than misclassifying with them.
+pub const MODEL_VERSION: u32 = 1;
+
+/// A trained classifier, serializable as a self-describing JSON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassifierModel {
+    pub format: String,
+    pub format_version: u32,
+    /// Crate version that wrote the model (informational).
+    pub crate_version: String,
+    /// Unix timestamp when the model was trained.
+    pub created_at: u64,
+    /// Number of items the model was trained on.
+    pub trained_rows: usize,
+    pub tokenizer: TokenizerConfig,
+    /// Document frequencies over the full training corpus.
+    pub idf: IdfStats,
+    pub taxonomy: Taxonomy,
+    /// Per-k scores when the cluster count was chosen automatically.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k_selection: Option<KSelection>,
+}
+
+impl ClassifierModel {
+    /// Discover a taxonomy from `texts` and freeze it with the corpus IDF.
+    pub fn train(texts: &[String], config: &DiscoverConfig, tokenizer: TokenizerConfig) -> Self {
+        let token_lists: Vec<Vec<String>> = texts.iter().map(|t| tokenizer.tokenize(t)).collect();
+        let mut corpus = Corpus::new();
+        for tokens in &token_lists {
+            corpus.add_document(tokens);
+        }
+        let (taxonomy, k_selection) =
+            discover::discover_with_selection(&token_lists, &corpus, config);
+        Self {
+            k_selection,
+            ..Self::from_parts(taxonomy, &corpus, texts.len(), tokenizer)
+        }
+    }
+
+    /// Package an existing taxonomy with the statistics of `corpus`.
+    pub fn from_parts(
+        taxonomy: Taxonomy,
+        corpus: &Corpus,
+        trained_rows: usize,
+        tokenizer: TokenizerConfig,
+    ) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Self {
+            format: MODEL_FORMAT.into(),
+            format_version: MODEL_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").into(),
+            created_at,
+            trained_rows,
+            tokenizer,
+            idf: corpus.idf_stats(),
+            taxonomy,
+            k_selection: None,
+        }
+    }
+
+    /// Classify items with the model's tokenizer and training IDF.
+    ///
+    /// Returns (category_name, hierarchy_path, confidence_score) per item,
+    /// like [`discover::classify_against_taxonomy`].
+    pub fn classify(&self, texts: &[String], threshold: f64) -> Vec<(String, String, f64)> {
+        self.classify_scaled(texts, threshold, ConfidenceScale::Raw)
+    }
+
+    /// [`Self::classify`] with confidence (and `threshold`) on `scale`.
+    pub fn classify_scaled(
+        &self,
+        texts: &[String],
+        threshold: f64,
+        scale: ConfidenceScale,
+    ) -> Vec<(String, String, f64)> {
+        let token_lists: Vec<Vec<String>> =
+            texts.iter().map(|t| self.tokenizer.tokenize(t)).collect();
+        discover::classify_tokens_scaled(&token_lists, &self.taxonomy, threshold, Some(&self.idf), scale)
+    }
+
+    /// Every category scoring at least `threshold` per item, best first.
+    pub fn classify_multi(&self, texts: &[String], threshold: f64) -> Vec<Vec<CategoryMatch>> {
+        self.classify_multi_scaled(texts, threshold, ConfidenceScale::Raw)
+    }
+
+    /// [`Self::classify_multi`] with `threshold` on `scale`.
+    pub fn classify_multi_scaled(
+        &self,
+        texts: &[String],
+        threshold: f64,
+        scale: ConfidenceScale,
+    ) -> Vec<Vec<CategoryMatch>> {
+        let token_lists: Vec<Vec<String>> =
+            texts.iter().map(|t| self.tokenizer.tokenize(t)).collect();
+        discover::classify_tokens_multi_scaled(&token_lists, &self.taxonomy, threshold, Some(&self.idf), scale)
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize model: {e}"))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_json()?)
+            .map_err(|e| format!("Failed to write model '{path}': {e}"))
+    }
+}
+
+/// Parse a model from JSON, checking its format and version.
+pub fn parse_model(json: &str) -> Result<ClassifierModel, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse model: {e}"))?;
+    check_header(&value)?;
+    serde_json::from_value(value).map_err(|e| format!("Failed to parse model: {e}"))
+}
+
+/// Load a model from a file path.
+pub fn load_model(path: &str) -> Result<ClassifierModel, String> {
+    let json =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{path}': {e}"))?;
+    parse_model(&json)
+}
+
+fn check_header(value: &serde_json::Value) -> Result<(), String> {
+    if value.get("format").and_then(|v| v.as_str()) != Some(MODEL_FORMAT) {
+        return Err(format!("Not a topology model (expected format '{MODEL_FORMAT}')"));
+    }
+    let version = value
+        .get("format_version")
+        .and_then(|v| v.as_u64())
+        .ok_or("Model is missing 'format_version'")?;
+    if version > MODEL_VERSION as u64 {
+        return Err(format!(
+            "Model format version {version} is newer than supported version {MODEL_VERSION}; upgrade topology"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn training_texts() -> Vec<String> {
+        vec![
+            "rust systems memory safety ownership borrow checker compiler".into(),
+            "rust performance zero cost abstractions concurrent safe compile".into(),
+            "cooking recipe pasta italian sauce ingredients kitchen chef".into(),
+            "cooking baking bread flour dessert restaurant dinner menu".into(),
+        ]
+    }
+
+    fn train() -> ClassifierModel {
+        let config = DiscoverConfig { k: 2, ..Default::default() };
+        ClassifierModel::train(&training_texts(), &config, TokenizerConfig::default())
+    }
+
+    #[test]
+    fn train_records_corpus_stats() {
+        let model = train();
+        assert_eq!(model.format, MODEL_FORMAT);
+        assert_eq!(model.format_version, MODEL_VERSION);
+        assert_eq!(model.trained_rows, 4);
+        assert_eq!(model.idf.num_docs, 4);
+        assert_eq!(model.idf.doc_freq.get("rust"), Some(&2));
+        assert!(!model.taxonomy.categories.is_empty());
+    }
+
+    #[test]
+    fn roundtrip_classifies_identically() {
+        let model = train();
+        let parsed = parse_model(&model.to_json().unwrap()).unwrap();
+        let items = vec!["borrow checker ownership".to_string(), "pasta sauce".to_string()];
+        assert_eq!(model.classify(&items, 0.1), parsed.classify(&items, 0.1));
+        assert_ne!(model.classify(&items, 0.1)[0].0, model.classify(&items, 0.1)[1].0);
+    }
+
+    #[test]
+    fn classification_does_not_depend_on_batch() {
+        let model = train();
+        let alone = model.classify(&["rust compiler".to_string()], 0.1);
+        let batch = model.classify(
+            &["bread flour".to_string(), "rust compiler".to_string()],
+            0.1,
+        );
+        assert_eq!(alone[0], batch[1]);
+    }
+
+    #[test]
+    fn rejects_foreign_and_newer_files() {
+        assert!(parse_model(r#"{"name": "x", "version": "1", "categories": []}"#).is_err());
+        let mut value = serde_json::to_value(train()).unwrap();
+        value["format_version"] = (MODEL_VERSION + 1).into();
+        let err = parse_model(&value.to_string()).unwrap_err();
+        assert!(err.contains("newer"), "{err}");
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use super::sparse::{SparseVector, TermId, Vocabulary};
+
 /// Non-negative Matrix Factorization for topic modeling.
 ///
 /// Given a term-document matrix V (n_docs × n_terms), decompose into:
@@ -17,8 +19,15 @@ pub struct NmfResult {
     pub vocabulary: Vec<String>,
     /// Number of topics.
     pub k: usize,
+    /// Frobenius norm ‖V − WH‖ after the last iteration.
+    pub reconstruction_error: f64,
+    /// `reconstruction_error` divided by ‖V‖; 0 is a perfect fit, 1 is no better than zero.
+    pub relative_error: f64,
+    /// Number of update iterations run.
+    pub iterations: usize,
+    /// Whether the relative objective change dropped below `tol` before `max_iter`.
+    pub converged: bool,
 }
-
 impl NmfResult {
     /// Get top N terms for topic t.
     pub fn top_terms(&self, topic: usize, n: usize) -> Vec<(String, f64)> {
@@ -35,6 +44,22 @@ impl NmfResult {
             .collect()
     }
 
+    /// Each document's topic weights scaled to sum to 1; documents with no
+    /// weight on any topic stay all-zero.
+    pub fn topic_mixtures(&self) -> Vec<Vec<f64>> {
+        self.doc_topics
+            .iter()
+            .map(|row| {
+                let total: f64 = row.iter().sum();
+                if total > 0.0 {
+                    row.iter().map(|w| w / total).collect()
+                } else {
+                    row.clone()
+                }
+            })
+            .collect()
+    }
+
     /// Get dominant topic for each document.
     pub fn dominant_topics(&self) -> Vec<usize> {
         self.doc_topics
@@ -50,51 +75,96 @@ impl NmfResult {
     }
 }
 
+/// How W and H are seeded before the multiplicative updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NmfInit {
+    /// NNDSVDa (Boutsidis & Gallopoulos, 2008): non-negative parts of the
+    /// leading singular vectors, zeros filled with the mean of V. Topics
+    /// start from distinct directions in the data.
+    #[default]
+    Nndsvd,
+    /// Near-constant deterministic values; every topic starts alike.
+    Uniform,
+}
+
+impl NmfInit {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "nndsvd" | "nndsvda" => Some(Self::Nndsvd),
+            "uniform" => Some(Self::Uniform),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration for [`nmf`].
+#[derive(Debug, Clone)]
+pub struct NmfConfig {
+    /// Maximum update iterations.
+    pub max_iter: usize,
+    /// Stop once the relative drop in the objective (half the squared
+    /// reconstruction error plus penalties) between two iterations falls
+    /// below this.
+    pub tol: f64,
+    /// Maximum vocabulary size (top terms by doc frequency).
+    pub vocab_limit: usize,
+    pub init: NmfInit,
+    /// L1 penalty on W and H; pushes weights to exactly zero for sparser topics.
+    pub l1: f64,
+    /// L2 penalty on W and H; shrinks weights and spreads them more evenly.
+    pub l2: f64,
+}
+
+impl Default for NmfConfig {
+    fn default() -> Self {
+        Self {
+            max_iter: 200,
+            tol: 1e-4,
+            vocab_limit: 5000,
+            init: NmfInit::Nndsvd,
+            l1: 0.0,
+            l2: 0.0,
+        }
+    }
+}
+
 /// Run NMF on TF-IDF vectors.
 ///
-/// * `tfidf_vectors` - One HashMap per document, term → weight.
+/// * `tfidf_vectors` - One sparse vector per document, ids from `vocabulary`.
 /// * `k` - Number of topics.
-/// * `max_iter` - Maximum iterations (default: 200).
-/// * `vocab_limit` - Maximum vocabulary size (top terms by doc frequency).
+///
+/// The term-document matrix stays sparse: each update costs
+/// O(nnz·k + (n_docs + n_terms)·k²) and memory is O(nnz + (n_docs + n_terms)·k).
+/// The reconstruction error is tracked from the same products, so early
+/// stopping adds only O(n_docs·k²) per iteration.
 pub fn nmf(
-    tfidf_vectors: &[HashMap<String, f64>],
+    tfidf_vectors: &[SparseVector],
+    vocabulary: &Vocabulary,
     k: usize,
-    max_iter: usize,
-    vocab_limit: usize,
+    config: &NmfConfig,
 ) -> NmfResult {
     let n_docs = tfidf_vectors.len();
 
-    // Build vocabulary (sorted by doc frequency, limited)
-    let mut doc_freq: HashMap<&str, usize> = HashMap::new();
+    // Build vocabulary (sorted by doc frequency, ties by term, limited)
+    let mut doc_freq: HashMap<TermId, usize> = HashMap::new();
     for vec in tfidf_vectors {
-        for term in vec.keys() {
-            *doc_freq.entry(term.as_str()).or_insert(0) += 1;
+        for (id, _) in vec.iter() {
+            *doc_freq.entry(id).or_insert(0) += 1;
         }
     }
-    let mut vocab: Vec<(String, usize)> = doc_freq
-        .into_iter()
-        .map(|(t, c)| (t.to_string(), c))
-        .collect();
-    vocab.sort_by(|a, b| b.1.cmp(&a.1));
-    vocab.truncate(vocab_limit);
-    let vocabulary: Vec<String> = vocab.into_iter().map(|(t, _)| t).collect();
+    let mut vocab: Vec<(TermId, usize)> = doc_freq.into_iter().collect();
+    vocab.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| vocabulary.term(a.0).cmp(vocabulary.term(b.0))));
+    vocab.truncate(config.vocab_limit);
+    let column: HashMap<TermId, usize> = vocab.iter().enumerate().map(|(col, &(id, _))| (id, col)).collect();
+    let vocabulary: Vec<String> = vocab.iter().map(|&(id, _)| vocabulary.term(id).to_string()).collect();
     let n_terms = vocabulary.len();
 
-    let term_idx: HashMap<&str, usize> = vocabulary
+    // Sparse rows of V (n_docs × n_terms) as (column, weight)
+    let v: Vec<Vec<(usize, f64)>> = tfidf_vectors
         .iter()
-        .enumerate()
-        .map(|(i, t)| (t.as_str(), i))
+        .map(|vec| vec.iter().filter_map(|(id, w)| column.get(&id).map(|&col| (col, w))).collect())
         .collect();
-
-    // Build V matrix (n_docs × n_terms)
-    let mut v = vec![vec![0.0f64; n_terms]; n_docs];
-    for (d, vec) in tfidf_vectors.iter().enumerate() {
-        for (term, &weight) in vec {
-            if let Some(&idx) = term_idx.get(term.as_str()) {
-                v[d][idx] = weight;
-            }
-        }
-    }
+    let v_norm_sq: f64 = v.iter().flatten().map(|&(_, w)| w * w).sum();
 
     if n_docs == 0 || n_terms == 0 || k == 0 {
         return NmfResult {
@@ -102,57 +172,254 @@ pub fn nmf(
             topic_terms: vec![vec![0.0; n_terms]; k],
             vocabulary,
             k,
+            reconstruction_error: v_norm_sq.sqrt(),
+            relative_error: if v_norm_sq > 0.0 { 1.0 } else { 0.0 },
+            iterations: 0,
+            converged: true,
         };
     }
 
-    // Initialize W and H with small positive values (deterministic)
-    let mut w = vec![vec![0.0f64; k]; n_docs];
-    let mut h = vec![vec![0.0f64; n_terms]; k];
-
-    for i in 0..n_docs {
-        for j in 0..k {
-            w[i][j] = 0.1 + 0.01 * ((i * k + j) % 100) as f64 / 100.0;
-        }
-    }
-    for i in 0..k {
-        for j in 0..n_terms {
-            h[i][j] = 0.1 + 0.01 * ((i * n_terms + j) % 100) as f64 / 100.0;
-        }
-    }
+    let (mut w, mut h) = match config.init {
+        NmfInit::Nndsvd => nndsvd(&v, n_terms, k),
+        NmfInit::Uniform => uniform_init(n_docs, n_terms, k),
+    };
 
     let eps = 1e-10;
+    let mut wtw = mat_mul_transpose_a(&w, &w, n_docs, k, k);
+    let mut error = f64::INFINITY;
+    let mut objective = f64::INFINITY;
+    let mut iterations = 0;
+    let mut converged = false;
 
     // Multiplicative update rules
-    for _ in 0..max_iter {
-        // Update H: H = H * (W^T V) / (W^T W H)
-        let wt_v = mat_mul_transpose_a(&w, &v, n_docs, k, n_terms);
-        let wtw = mat_mul_transpose_a(&w, &w, n_docs, k, k);
+    while iterations < config.max_iter {
+        iterations += 1;
+
+        // Update H: H = H * (W^T V) / (W^T W H + l1 + l2 H)
+        let wt_v = sparse_wt_v(&w, &v, k, n_terms);
         let wtw_h = mat_mul(&wtw, &h, k, k, n_terms);
 
         for i in 0..k {
             for j in 0..n_terms {
-                h[i][j] *= wt_v[i][j] / (wtw_h[i][j] + eps);
+                h[i][j] *= wt_v[i][j] / (wtw_h[i][j] + config.l1 + config.l2 * h[i][j] + eps);
             }
         }
 
-        // Update W: W = W * (V H^T) / (W H H^T)
-        let v_ht = mat_mul_transpose_b(&v, &h, n_docs, n_terms, k);
-        let wh = mat_mul(&w, &h, n_docs, k, n_terms);
-        let wh_ht = mat_mul_transpose_b(&wh, &h, n_docs, n_terms, k);
+        // Update W: W = W * (V H^T) / (W (H H^T) + l1 + l2 W)
+        let v_ht = sparse_v_ht(&v, &h, k);
+        let hht = mat_mul_transpose_b(&h, &h, k, n_terms, k);
+        let w_hht = mat_mul(&w, &hht, n_docs, k, k);
 
         for i in 0..n_docs {
             for j in 0..k {
-                w[i][j] *= v_ht[i][j] / (wh_ht[i][j] + eps);
+                w[i][j] *= v_ht[i][j] / (w_hht[i][j] + config.l1 + config.l2 * w[i][j] + eps);
             }
         }
+
+        // ‖V − WH‖² = ‖V‖² − 2·⟨W, VHᵀ⟩ + ⟨WᵀW, HHᵀ⟩
+        wtw = mat_mul_transpose_a(&w, &w, n_docs, k, k);
+        let cross: f64 = w.iter().zip(&v_ht).map(|(a, b)| dot(a, b)).sum();
+        let gram: f64 = wtw.iter().zip(&hht).map(|(a, b)| dot(a, b)).sum();
+        let error_sq = (v_norm_sq - 2.0 * cross + gram).max(0.0);
+        error = error_sq.sqrt();
+
+        let previous = objective;
+        objective = 0.5 * error_sq + penalty(&w, config) + penalty(&h, config);
+        if previous.is_finite() && (previous - objective) / previous.max(eps) < config.tol {
+            converged = true;
+            break;
+        }
     }
 
+    if !error.is_finite() {
+        error = v_norm_sq.sqrt();
+    }
     NmfResult {
         doc_topics: w,
         topic_terms: h,
         vocabulary,
         k,
+        reconstruction_error: error,
+        relative_error: if v_norm_sq > 0.0 { error / v_norm_sq.sqrt() } else { 0.0 },
+        iterations,
+        converged,
+    }
+}
+
+/// `l1·Σx + l2/2·Σx²` over one factor, matching the update denominators.
+fn penalty(factor: &[Vec<f64>], config: &NmfConfig) -> f64 {
+    if config.l1 == 0.0 && config.l2 == 0.0 {
+        return 0.0;
+    }
+    factor.iter().flatten().map(|&x| config.l1 * x + 0.5 * config.l2 * x * x).sum()
+}
+
+/// Small positive values (deterministic).
+#[allow(clippy::needless_range_loop)]
+fn uniform_init(n_docs: usize, n_terms: usize, k: usize) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    let mut w = vec![vec![0.0f64; k]; n_docs];
+    let mut h = vec![vec![0.0f64; n_terms]; k];
+
+    for i in 0..n_docs {
+        for j in 0..k {
+            w[i][j] = 0.1 + 0.01 * ((i * k + j) % 100) as f64 / 100.0;
+        }
+    }
+    for i in 0..k {
+        for j in 0..n_terms {
+            h[i][j] = 0.1 + 0.01 * ((i * n_terms + j) % 100) as f64 / 100.0;
+        }
     }
+    (w, h)
+}
+
+/// NNDSVDa initialisation from the top-k singular triplets of V.
+///
+/// Singular vectors come from power iteration with deflation against the
+/// ones already found, which only needs sparse V·y and Vᵀ·x products.
+/// Components beyond the rank of V stay zero and, like every other zero,
+/// are filled with the mean of V so the multiplicative updates can move them.
+fn nndsvd(v: &[Vec<(usize, f64)>], n_terms: usize, k: usize) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    const POWER_ITERS: usize = 100;
+    let n_docs = v.len();
+    let mut w = vec![vec![0.0f64; k]; n_docs];
+    let mut h = vec![vec![0.0f64; n_terms]; k];
+    let mut lefts: Vec<Vec<f64>> = Vec::with_capacity(k);
+    let mut rights: Vec<Vec<f64>> = Vec::with_capacity(k);
+
+    for j in 0..k {
+        // Deterministic, non-constant start so components can differ.
+        let mut y: Vec<f64> = (0..n_terms)
+            .map(|t| 1.0 + ((t * 31 + j * 17) % 97) as f64 / 97.0)
+            .collect();
+        orthogonalize(&mut y, &rights);
+        if normalize(&mut y) == 0.0 {
+            break;
+        }
+        let mut x = vec![0.0; n_docs];
+        let mut sigma = 0.0;
+        for _ in 0..POWER_ITERS {
+            x = sparse_mul(v, &y);
+            orthogonalize(&mut x, &lefts);
+            sigma = normalize(&mut x);
+            if sigma < 1e-12 {
+                break;
+            }
+            let mut next = sparse_mul_transpose(v, &x, n_terms);
+            orthogonalize(&mut next, &rights);
+            if normalize(&mut next) == 0.0 {
+                break;
+            }
+            let change = 1.0 - dot(&y, &next).abs();
+            y = next;
+            if change < 1e-10 {
+                break;
+            }
+        }
+        if sigma < 1e-12 {
+            break;
+        }
+
+        // The leading pair of a non-negative matrix is one-signed; later
+        // pairs keep whichever sign half carries more mass.
+        let (pos_x, neg_x) = split_signs(&x);
+        let (pos_y, neg_y) = split_signs(&y);
+        let (nx_p, ny_p, nx_n, ny_n) = (l2(&pos_x), l2(&pos_y), l2(&neg_x), l2(&neg_y));
+        let (u, vt, mass, nu, nv) = if nx_p * ny_p >= nx_n * ny_n {
+            (pos_x, pos_y, nx_p * ny_p, nx_p, ny_p)
+        } else {
+            (neg_x, neg_y, nx_n * ny_n, nx_n, ny_n)
+        };
+        if mass > 0.0 {
+            let scale = (sigma * mass).sqrt();
+            for d in 0..n_docs {
+                w[d][j] = scale * u[d] / nu;
+            }
+            for t in 0..n_terms {
+                h[j][t] = scale * vt[t] / nv;
+            }
+        }
+        lefts.push(x);
+        rights.push(y);
+    }
+
+    let total: f64 = v.iter().flatten().map(|&(_, w)| w).sum();
+    let mean = total / (n_docs * n_terms) as f64;
+    for x in w.iter_mut().flatten().chain(h.iter_mut().flatten()) {
+        if *x == 0.0 {
+            *x = mean;
+        }
+    }
+    (w, h)
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn l2(a: &[f64]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// Scale `a` to unit length, returning its previous norm (0 leaves it untouched).
+fn normalize(a: &mut [f64]) -> f64 {
+    let norm = l2(a);
+    if norm > 0.0 {
+        a.iter_mut().for_each(|x| *x /= norm);
+    }
+    norm
+}
+
+/// Remove the components of `a` along each of the orthonormal `basis` vectors.
+fn orthogonalize(a: &mut [f64], basis: &[Vec<f64>]) {
+    for b in basis {
+        let proj = dot(a, b);
+        a.iter_mut().zip(b).for_each(|(x, y)| *x -= proj * y);
+    }
+}
+
+fn split_signs(a: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    (a.iter().map(|x| x.max(0.0)).collect(), a.iter().map(|x| (-x).max(0.0)).collect())
+}
+
+// V × y for sparse V (n_docs × n_terms), result has n_docs entries
+fn sparse_mul(v: &[Vec<(usize, f64)>], y: &[f64]) -> Vec<f64> {
+    v.iter().map(|row| row.iter().map(|&(col, w)| w * y[col]).sum()).collect()
+}
+
+// V^T × x for sparse V (n_docs × n_terms), result has n_terms entries
+fn sparse_mul_transpose(v: &[Vec<(usize, f64)>], x: &[f64], n_terms: usize) -> Vec<f64> {
+    let mut result = vec![0.0; n_terms];
+    for (row, &xd) in v.iter().zip(x) {
+        for &(col, w) in row {
+            result[col] += w * xd;
+        }
+    }
+    result
+}
+
+// W^T × V for dense W (n_docs × k) and sparse V, result is (k × n_terms)
+fn sparse_wt_v(w: &[Vec<f64>], v: &[Vec<(usize, f64)>], k: usize, n_terms: usize) -> Vec<Vec<f64>> {
+    let mut result = vec![vec![0.0; n_terms]; k];
+    for (w_row, v_row) in w.iter().zip(v) {
+        for &(col, weight) in v_row {
+            for i in 0..k {
+                result[i][col] += w_row[i] * weight;
+            }
+        }
+    }
+    result
+}
+
+// V × H^T for sparse V and dense H (k × n_terms), result is (n_docs × k)
+fn sparse_v_ht(v: &[Vec<(usize, f64)>], h: &[Vec<f64>], k: usize) -> Vec<Vec<f64>> {
+    v.iter()
+        .map(|v_row| {
+            (0..k)
+                .map(|i| v_row.iter().map(|&(col, weight)| weight * h[i][col]).sum())
+                .collect()
+        })
+        .collect()
 }
 
 // A^T × B where A is (m × n), result is (n × p)
@@ -204,6 +471,19 @@ fn mat_mul_transpose_b(a: &[Vec<f64>], b: &[Vec<f64>], m: usize, _n: usize, p: u
 mod tests {
     use super::*;
 
+    fn run(docs: &[HashMap<String, f64>], k: usize, max_iter: usize, vocab_limit: usize) -> NmfResult {
+        run_with(docs, k, &NmfConfig { max_iter, vocab_limit, ..Default::default() })
+    }
+
+    fn run_with(docs: &[HashMap<String, f64>], k: usize, config: &NmfConfig) -> NmfResult {
+        let mut vocab = Vocabulary::new();
+        let vectors: Vec<SparseVector> = docs
+            .iter()
+            .map(|d| SparseVector::from_terms(d.iter().map(|(t, &w)| (t.as_str(), w)), &mut vocab))
+            .collect();
+        nmf(&vectors, &vocab, k, config)
+    }
+
     #[test]
     fn nmf_basic() {
         let docs: Vec<HashMap<String, f64>> = vec![
@@ -212,7 +492,7 @@ mod tests {
             [("web".into(), 3.0), ("javascript".into(), 2.0), ("html".into(), 1.0)].into(),
             [("web".into(), 2.0), ("css".into(), 3.0), ("design".into(), 1.0)].into(),
         ];
-        let result = nmf(&docs, 2, 100, 1000);
+        let result = run(&docs, 2, 100, 1000);
         assert_eq!(result.k, 2);
         assert_eq!(result.doc_topics.len(), 4);
         assert_eq!(result.topic_terms.len(), 2);
@@ -230,7 +510,7 @@ mod tests {
             [("alpha".into(), 5.0), ("beta".into(), 1.0)].into(),
             [("gamma".into(), 5.0), ("beta".into(), 1.0)].into(),
         ];
-        let result = nmf(&docs, 2, 50, 100);
+        let result = run(&docs, 2, 50, 100);
         let top = result.top_terms(0, 2);
         assert_eq!(top.len(), 2);
         // Top term should have highest weight
@@ -240,7 +520,7 @@ mod tests {
     #[test]
     fn nmf_empty() {
         let docs: Vec<HashMap<String, f64>> = vec![];
-        let result = nmf(&docs, 3, 10, 100);
+        let result = run(&docs, 3, 10, 100);
         assert_eq!(result.doc_topics.len(), 0);
     }
 
@@ -249,7 +529,7 @@ mod tests {
         let docs: Vec<HashMap<String, f64>> = vec![
             [("rust".into(), 3.0), ("fast".into(), 1.0)].into(),
         ];
-        let result = nmf(&docs, 1, 50, 100);
+        let result = run(&docs, 1, 50, 100);
         assert_eq!(result.doc_topics.len(), 1);
         assert_eq!(result.k, 1);
         let topics = result.dominant_topics();
@@ -263,7 +543,7 @@ mod tests {
             [("a".into(), 1.0)].into(),
             [("b".into(), 1.0)].into(),
         ];
-        let result = nmf(&docs, 5, 20, 100);
+        let result = run(&docs, 5, 20, 100);
         assert_eq!(result.k, 5);
         assert_eq!(result.doc_topics.len(), 2);
         assert_eq!(result.topic_terms.len(), 5);
@@ -276,7 +556,7 @@ mod tests {
             [("web".into(), 3.0), ("javascript".into(), 2.0)].into(),
             [("data".into(), 3.0), ("science".into(), 2.0)].into(),
         ];
-        let result = nmf(&docs, 2, 50, 100);
+        let result = run(&docs, 2, 50, 100);
         let topics = result.dominant_topics();
         assert_eq!(topics.len(), 3);
         for &t in &topics {
@@ -289,7 +569,7 @@ mod tests {
         let docs: Vec<HashMap<String, f64>> = vec![
             [("a".into(), 1.0)].into(),
         ];
-        let result = nmf(&docs, 1, 10, 100);
+        let result = run(&docs, 1, 10, 100);
         // Asking for a topic that doesn't exist
         let top = result.top_terms(99, 5);
         assert!(top.is_empty());
@@ -301,7 +581,7 @@ mod tests {
             [("rust".into(), 3.0), ("fast".into(), 1.0)].into(),
             [("web".into(), 3.0), ("html".into(), 1.0)].into(),
         ];
-        let result = nmf(&docs, 2, 100, 100);
+        let result = run(&docs, 2, 100, 100);
         // NMF guarantees non-negative factors
         for row in &result.doc_topics {
             for &v in row {
@@ -321,7 +601,83 @@ mod tests {
             [("a".into(), 1.0), ("b".into(), 1.0), ("c".into(), 1.0),
              ("d".into(), 1.0), ("e".into(), 1.0)].into(),
         ];
-        let result = nmf(&docs, 1, 10, 3); // limit vocab to 3
+        let result = run(&docs, 1, 10, 3); // limit vocab to 3
         assert!(result.vocabulary.len() <= 3, "Vocab should be limited to 3");
     }
+
+    fn two_theme_docs() -> Vec<HashMap<String, f64>> {
+        vec![
+            [("rust".into(), 3.0), ("systems".into(), 2.0), ("fast".into(), 1.0)].into(),
+            [("rust".into(), 2.0), ("memory".into(), 3.0), ("safe".into(), 1.0)].into(),
+            [("web".into(), 3.0), ("javascript".into(), 2.0), ("html".into(), 1.0)].into(),
+            [("web".into(), 2.0), ("css".into(), 3.0), ("design".into(), 1.0)].into(),
+        ]
+    }
+
+    #[test]
+    fn nmf_stops_early_and_reports_error() {
+        let config = NmfConfig { max_iter: 5000, ..Default::default() };
+        let result = run_with(&two_theme_docs(), 2, &config);
+        assert!(result.converged);
+        assert!(result.iterations > 0 && result.iterations < 5000);
+        assert!(result.relative_error >= 0.0 && result.relative_error < 1.0);
+
+        // The tracked error matches a dense recomputation of ‖V − WH‖.
+        let docs = two_theme_docs();
+        let mut residual = 0.0;
+        for (d, doc) in docs.iter().enumerate() {
+            for (t, term) in result.vocabulary.iter().enumerate() {
+                let approx: f64 = (0..2).map(|i| result.doc_topics[d][i] * result.topic_terms[i][t]).sum();
+                residual += (doc.get(term).copied().unwrap_or(0.0) - approx).powi(2);
+            }
+        }
+        assert!((residual.sqrt() - result.reconstruction_error).abs() < 1e-6);
+
+        let capped = run_with(&two_theme_docs(), 2, &NmfConfig { max_iter: 3, tol: 0.0, ..Default::default() });
+        assert_eq!(capped.iterations, 3);
+        assert!(!capped.converged);
+    }
+
+    #[test]
+    fn nndsvd_separates_topics_before_any_update() {
+        let config = NmfConfig { max_iter: 0, ..Default::default() };
+        let topics = run_with(&two_theme_docs(), 2, &config).dominant_topics();
+        assert_eq!(topics[0], topics[1]);
+        assert_eq!(topics[2], topics[3]);
+        assert_ne!(topics[0], topics[2]);
+
+        let uniform = NmfConfig { max_iter: 0, init: NmfInit::Uniform, ..Default::default() };
+        let result = run_with(&two_theme_docs(), 2, &uniform);
+        assert!((result.topic_terms[0][0] - result.topic_terms[1][0]).abs() < 0.02);
+    }
+
+    #[test]
+    fn l1_penalty_sparsifies_factors() {
+        let zeros = |r: &NmfResult| r.topic_terms.iter().flatten().filter(|&&w| w < 1e-6).count();
+        let plain = run_with(&two_theme_docs(), 3, &NmfConfig { tol: 0.0, ..Default::default() });
+        let sparse = run_with(&two_theme_docs(), 3, &NmfConfig { tol: 0.0, l1: 0.5, ..Default::default() });
+        assert!(zeros(&sparse) > zeros(&plain), "{} vs {}", zeros(&sparse), zeros(&plain));
+        assert!(sparse.reconstruction_error >= plain.reconstruction_error);
+    }
+
+    #[test]
+    fn topic_mixtures_are_normalized() {
+        let docs = two_theme_docs();
+        let mut with_empty = docs.clone();
+        with_empty.push(HashMap::new());
+        let result = run(&with_empty, 2, 100, 100);
+        let mixtures = result.topic_mixtures();
+        for row in &mixtures[..4] {
+            assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+        assert_eq!(mixtures[4], vec![0.0, 0.0]);
+        assert_eq!(result.dominant_topics()[0], if mixtures[0][0] > mixtures[0][1] { 0 } else { 1 });
+    }
+
+    #[test]
+    fn init_parses() {
+        assert_eq!(NmfInit::from_str("NNDSVD"), Some(NmfInit::Nndsvd));
+        assert_eq!(NmfInit::from_str("uniform"), Some(NmfInit::Uniform));
+        assert_eq!(NmfInit::from_str("random"), None);
+    }
 }
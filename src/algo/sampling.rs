@@ -124,20 +124,25 @@ pub fn reservoir_sample(total: usize, size: usize, seed: u64) -> Vec<usize> {
 }
 
 /// Simple Linear Congruential Generator for deterministic sampling.
-struct LcgRng {
+pub(crate) struct LcgRng {
     state: u64,
 }
 
 impl LcgRng {
-    fn new(seed: u64) -> Self {
+    pub(crate) fn new(seed: u64) -> Self {
         Self { state: seed.wrapping_add(1) }
     }
 
-    fn next(&mut self) -> u64 {
+    pub(crate) fn next(&mut self) -> u64 {
         // LCG constants from Numerical Recipes
         self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
         self.state
     }
+
+    /// Uniform in [0, 1) from the high 53 bits (the low bits of an LCG are weak).
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
 }
 
 #[cfg(test)]
@@ -180,8 +185,8 @@ mod tests {
         // Should get roughly 7 from "a" and 3 from "b"
         let a_count = s.iter().filter(|&&i| i < 70).count();
         let b_count = s.iter().filter(|&&i| i >= 70).count();
-        assert!(a_count >= 5 && a_count <= 9, "a_count={a_count}");
-        assert!(b_count >= 1 && b_count <= 5, "b_count={b_count}");
+        assert!((5..=9).contains(&a_count), "a_count={a_count}");
+        assert!((1..=5).contains(&b_count), "b_count={b_count}");
     }
 
     #[test]
@@ -218,7 +223,7 @@ mod tests {
         // Elements should be roughly evenly spaced (step ~10)
         for w in s.windows(2) {
             let gap = w[1] - w[0];
-            assert!(gap >= 5 && gap <= 15, "Gap should be near 10, got {gap}");
+            assert!((5..=15).contains(&gap), "Gap should be near 10, got {gap}");
         }
     }
 
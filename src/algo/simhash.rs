@@ -9,6 +9,7 @@ const BITS: usize = 64;
 /// Each token is hashed with SipHash-1-3 to produce a 64-bit value.
 /// Bit positions are accumulated with +weight (bit=1) or -weight (bit=0).
 /// Final fingerprint: bit i = 1 if accumulator[i] > 0.
+#[allow(clippy::needless_range_loop)]
 pub fn simhash(tokens: &[String], weights: &HashMap<String, f64>) -> u64 {
     let mut v = [0.0f64; BITS];
 
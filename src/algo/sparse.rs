@@ -0,0 +1,213 @@
+//! Interned vocabulary and sorted sparse vectors.
+//!
+//! Terms are interned once into a [`Vocabulary`] and referred to by
+//! [`TermId`] everywhere after that, so per-document vectors hold integers
+//! instead of cloned strings, and dot products are a merge of two sorted
+//! id lists instead of a hash lookup per term.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Index of a term in a [`Vocabulary`].
+pub type TermId = u32;
+
+/// Bidirectional term ↔ id map. Ids are assigned in first-seen order and
+/// never reused. Serializes as the plain term list.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct Vocabulary {
+    terms: Vec<String>,
+    ids: HashMap<String, TermId>,
+}
+
+impl Vocabulary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Id of `term`, adding it if unseen.
+    pub fn intern(&mut self, term: &str) -> TermId {
+        if let Some(&id) = self.ids.get(term) {
+            return id;
+        }
+        let id = self.terms.len() as TermId;
+        self.terms.push(term.to_string());
+        self.ids.insert(term.to_string(), id);
+        id
+    }
+
+    pub fn id(&self, term: &str) -> Option<TermId> {
+        self.ids.get(term).copied()
+    }
+
+    pub fn term(&self, id: TermId) -> &str {
+        &self.terms[id as usize]
+    }
+
+    pub fn terms(&self) -> &[String] {
+        &self.terms
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+impl From<Vec<String>> for Vocabulary {
+    fn from(terms: Vec<String>) -> Self {
+        let mut vocab = Self::new();
+        for term in &terms {
+            vocab.intern(term);
+        }
+        vocab
+    }
+}
+
+impl From<Vocabulary> for Vec<String> {
+    fn from(vocab: Vocabulary) -> Self {
+        vocab.terms
+    }
+}
+
+/// Sparse vector of `(term id, weight)` entries, sorted by id with no
+/// duplicates.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SparseVector {
+    entries: Vec<(TermId, f64)>,
+}
+
+impl SparseVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build from entries in any order; weights of repeated ids are summed.
+    pub fn from_entries(mut entries: Vec<(TermId, f64)>) -> Self {
+        entries.sort_unstable_by_key(|&(id, _)| id);
+        entries.dedup_by(|next, kept| {
+            if next.0 == kept.0 {
+                kept.1 += next.1;
+                true
+            } else {
+                false
+            }
+        });
+        Self { entries }
+    }
+
+    /// Build from string-keyed weights, interning the terms.
+    pub fn from_terms<'a>(
+        weights: impl IntoIterator<Item = (&'a str, f64)>,
+        vocabulary: &mut Vocabulary,
+    ) -> Self {
+        Self::from_entries(weights.into_iter().map(|(t, w)| (vocabulary.intern(t), w)).collect())
+    }
+
+    pub fn entries(&self) -> &[(TermId, f64)] {
+        &self.entries
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TermId, f64)> + '_ {
+        self.entries.iter().copied()
+    }
+
+    /// Weight of `id`, 0 when absent.
+    pub fn get(&self, id: TermId) -> f64 {
+        self.entries
+            .binary_search_by_key(&id, |&(i, _)| i)
+            .map_or(0.0, |pos| self.entries[pos].1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.entries.iter().map(|(_, w)| w * w).sum::<f64>().sqrt()
+    }
+
+    /// Dot product by merging the two sorted id lists.
+    pub fn dot(&self, other: &SparseVector) -> f64 {
+        let (a, b) = (&self.entries, &other.entries);
+        let (mut i, mut j, mut sum) = (0, 0, 0.0);
+        while i < a.len() && j < b.len() {
+            match a[i].0.cmp(&b[j].0) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    sum += a[i].1 * b[j].1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        sum
+    }
+
+    /// Add `scale × other` into this vector.
+    pub fn add_scaled(&mut self, other: &SparseVector, scale: f64) {
+        let mut entries = std::mem::take(&mut self.entries);
+        entries.extend(other.iter().map(|(id, w)| (id, w * scale)));
+        *self = Self::from_entries(entries);
+    }
+
+    /// Resolve ids back to terms.
+    pub fn to_terms<'a>(&'a self, vocabulary: &'a Vocabulary) -> impl Iterator<Item = (&'a str, f64)> + 'a {
+        self.entries.iter().map(|&(id, w)| (vocabulary.term(id), w))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vocabulary_interns_once_and_roundtrips() {
+        let mut vocab = Vocabulary::new();
+        assert_eq!(vocab.intern("rust"), 0);
+        assert_eq!(vocab.intern("go"), 1);
+        assert_eq!(vocab.intern("rust"), 0);
+        assert_eq!(vocab.term(1), "go");
+        assert_eq!(vocab.id("zig"), None);
+
+        let json = serde_json::to_string(&vocab).unwrap();
+        assert_eq!(json, r#"["rust","go"]"#);
+        let parsed: Vocabulary = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.id("go"), Some(1));
+    }
+
+    #[test]
+    fn sparse_vector_sorts_and_merges() {
+        let v = SparseVector::from_entries(vec![(5, 1.0), (2, 2.0), (5, 0.5)]);
+        assert_eq!(v.entries(), &[(2, 2.0), (5, 1.5)]);
+        assert_eq!(v.get(5), 1.5);
+        assert_eq!(v.get(3), 0.0);
+    }
+
+    #[test]
+    fn dot_and_norm() {
+        let a = SparseVector::from_entries(vec![(0, 1.0), (2, 2.0), (4, 3.0)]);
+        let b = SparseVector::from_entries(vec![(1, 5.0), (2, 1.0), (4, 2.0)]);
+        assert_eq!(a.dot(&b), 8.0);
+        assert_eq!(a.dot(&SparseVector::new()), 0.0);
+        assert!((a.norm() - 14f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn add_scaled_and_terms() {
+        let mut vocab = Vocabulary::new();
+        let mut a = SparseVector::from_terms([("rust", 1.0), ("cargo", 1.0)], &mut vocab);
+        let b = SparseVector::from_terms([("cargo", 2.0), ("tokio", 4.0)], &mut vocab);
+        a.add_scaled(&b, 0.5);
+        let terms: Vec<(&str, f64)> = a.to_terms(&vocab).collect();
+        assert_eq!(terms, [("rust", 1.0), ("cargo", 2.0), ("tokio", 2.0)]);
+    }
+}
@@ -2,10 +2,38 @@
 //!
 //! Each artifact is keyed by `(kind, content_hash, args_hash)` and stored as
 //! a compressed JSON blob. The storage layer is opt-in via the `cache` feature.
+//!
+//! The layout is versioned with `PRAGMA user_version`. A database written by
+//! an older layout is dropped and recreated on open: cached artifacts are
+//! always recomputable, so discarding them is safer than reinterpreting them.
+//!
+//! The same database also holds a persistent near-duplicate index: records
+//! with their SimHash / MinHash fingerprints and the LSH band keys they fall
+//! into, so a lookup touches only the matching buckets instead of rebuilding
+//! an in-memory index every run. Unlike artifacts, index entries cannot be
+//! recomputed, so a schema change never drops them. Their band keys are
+//! derived data with their own layout version (see [`CacheDb::index_rekey`])
+//! and are rebuilt from the stored fingerprints when that layout changes.
 
 use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeSet;
+
+use super::cache::{content_hash_hex, ArtifactKind, CacheMeta, SCHEMA_VERSION};
+
+/// An [`IndexEntry`] from a `item_id, url_key, simhash, signature, record` row.
+fn read_index_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<IndexEntry> {
+    Ok(IndexEntry {
+        id: row.get(0)?,
+        url_key: row.get(1)?,
+        simhash: row.get::<_, Option<i64>>(2)?.map(|fp| fp as u64),
+        signature: row.get::<_, Option<Vec<u8>>>(3)?.map(|b| decode_signature(&b)),
+        record: row.get(4)?,
+    })
+}
 
-use super::cache::{ArtifactKind, CacheMeta};
+fn parse_content_hash(hex: &str) -> u128 {
+    u128::from_str_radix(hex, 16).unwrap_or_default()
+}
 
 /// SQLite-backed cache database for topology artifacts.
 pub struct CacheDb {
@@ -24,10 +52,18 @@ impl CacheDb {
         )
         .map_err(|e| format!("Failed to set PRAGMA: {e}"))?;
 
+        let user_version: u32 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read cache schema version: {e}"))?;
+        if user_version != SCHEMA_VERSION {
+            conn.execute_batch("DROP TABLE IF EXISTS cache_artifacts;")
+                .map_err(|e| format!("Failed to reset outdated cache schema: {e}"))?;
+        }
+
         conn.execute_batch(
             "CREATE TABLE IF NOT EXISTS cache_artifacts (
                 kind          TEXT NOT NULL,
-                content_hash  INTEGER NOT NULL,
+                content_hash  TEXT NOT NULL,
                 args_hash     INTEGER NOT NULL,
                 row_count     INTEGER NOT NULL,
                 version       TEXT NOT NULL,
@@ -36,10 +72,32 @@ impl CacheDb {
                 UNIQUE(kind, content_hash, args_hash)
             );
             CREATE INDEX IF NOT EXISTS idx_cache_lookup
-                ON cache_artifacts(kind, content_hash, args_hash);",
+                ON cache_artifacts(kind, content_hash, args_hash);
+            CREATE TABLE IF NOT EXISTS lsh_items (
+                item_id    TEXT PRIMARY KEY,
+                url_key    TEXT,
+                simhash    INTEGER,
+                signature  BLOB,
+                record     TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_lsh_items_url ON lsh_items(url_key);
+            CREATE TABLE IF NOT EXISTS lsh_bands (
+                band     INTEGER NOT NULL,
+                bucket   INTEGER NOT NULL,
+                item_id  TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_lsh_bands_bucket ON lsh_bands(band, bucket);
+            CREATE INDEX IF NOT EXISTS idx_lsh_bands_item ON lsh_bands(item_id);
+            CREATE TABLE IF NOT EXISTS lsh_meta (
+                key    TEXT PRIMARY KEY,
+                value  INTEGER NOT NULL
+            );",
         )
         .map_err(|e| format!("Failed to create cache schema: {e}"))?;
 
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)
+            .map_err(|e| format!("Failed to set cache schema version: {e}"))?;
+
         Ok(Self { conn })
     }
 
@@ -47,7 +105,7 @@ impl CacheDb {
     pub fn get(
         &self,
         kind: ArtifactKind,
-        content_hash: u64,
+        content_hash: u128,
         args_hash: u64,
     ) -> Result<Option<(CacheMeta, Vec<u8>)>, String> {
         let mut stmt = self
@@ -61,7 +119,7 @@ impl CacheDb {
 
         let result = stmt
             .query_row(
-                params![kind.as_str(), content_hash as i64, args_hash as i64],
+                params![kind.as_str(), content_hash_hex(content_hash), args_hash as i64],
                 |row| {
                     let row_count: i64 = row.get(0)?;
                     let version: String = row.get(1)?;
@@ -85,6 +143,66 @@ impl CacheDb {
         }))
     }
 
+    /// Retrieve the most recently stored artifact of `kind` for `args_hash`,
+    /// whatever its content hash. Seeds incremental row-level updates.
+    pub fn get_latest(
+        &self,
+        kind: ArtifactKind,
+        args_hash: u64,
+    ) -> Result<Option<(CacheMeta, Vec<u8>)>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT content_hash, row_count, version, created_at, payload
+                 FROM cache_artifacts
+                 WHERE kind = ?1 AND args_hash = ?2
+                 ORDER BY created_at DESC, rowid DESC
+                 LIMIT 1",
+            )
+            .map_err(|e| format!("Failed to prepare SELECT: {e}"))?;
+
+        let result = stmt
+            .query_row(params![kind.as_str(), args_hash as i64], |row| {
+                let content_hash: String = row.get(0)?;
+                let row_count: i64 = row.get(1)?;
+                let version: String = row.get(2)?;
+                let created_at: i64 = row.get(3)?;
+                let payload: Vec<u8> = row.get(4)?;
+                Ok((content_hash, row_count, version, created_at, payload))
+            })
+            .optional()
+            .map_err(|e| format!("Failed to query cache: {e}"))?;
+
+        Ok(result.map(|(content_hash, row_count, version, created_at, payload)| {
+            let meta = CacheMeta {
+                content_hash: parse_content_hash(&content_hash),
+                row_count: row_count as usize,
+                args_hash,
+                version,
+                created_at: created_at as u64,
+            };
+            (meta, payload)
+        }))
+    }
+
+    /// Delete artifacts of `kind` for `args_hash` except the one stored under
+    /// `keep_content_hash`. Keeps row-keyed artifacts from piling up a full
+    /// copy per run once a newer version has been written.
+    pub fn prune(
+        &self,
+        kind: ArtifactKind,
+        args_hash: u64,
+        keep_content_hash: u128,
+    ) -> Result<usize, String> {
+        self.conn
+            .execute(
+                "DELETE FROM cache_artifacts
+                 WHERE kind = ?1 AND args_hash = ?2 AND content_hash != ?3",
+                params![kind.as_str(), args_hash as i64, content_hash_hex(keep_content_hash)],
+            )
+            .map_err(|e| format!("Failed to prune cache: {e}"))
+    }
+
     /// Store (upsert) an artifact in the cache.
     pub fn put(
         &self,
@@ -103,7 +221,7 @@ impl CacheDb {
                                payload = excluded.payload",
                 params![
                     kind.as_str(),
-                    meta.content_hash as i64,
+                    content_hash_hex(meta.content_hash),
                     meta.args_hash as i64,
                     meta.row_count as i64,
                     meta.version,
@@ -153,7 +271,7 @@ impl CacheDb {
             .query_map([], |row| {
                 Ok(ArtifactInfo {
                     kind: row.get::<_, String>(0)?,
-                    content_hash: row.get::<_, i64>(1)? as u64,
+                    content_hash: parse_content_hash(&row.get::<_, String>(1)?),
                     args_hash: row.get::<_, i64>(2)? as u64,
                     row_count: row.get::<_, i64>(3)? as usize,
                     version: row.get(4)?,
@@ -170,6 +288,185 @@ impl CacheDb {
         Ok(result)
     }
 
+    /// Insert (or replace) an entry in the near-duplicate index under the
+    /// given `(band, bucket)` keys.
+    pub fn index_insert(&self, entry: &IndexEntry, band_keys: &[(u32, u64)]) -> Result<(), String> {
+        self.index_insert_all([(entry, band_keys)])
+    }
+
+    /// Insert (or replace) several index entries in one transaction: either
+    /// all of them are written or, on error, none are.
+    pub fn index_insert_all<'a>(
+        &self,
+        entries: impl IntoIterator<Item = (&'a IndexEntry, &'a [(u32, u64)])>,
+    ) -> Result<(), String> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start index transaction: {e}"))?;
+        {
+            let mut delete_bands = tx
+                .prepare("DELETE FROM lsh_bands WHERE item_id = ?1")
+                .map_err(|e| format!("Failed to prepare DELETE: {e}"))?;
+            let mut insert_item = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO lsh_items (item_id, url_key, simhash, signature, record)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )
+                .map_err(|e| format!("Failed to prepare INSERT: {e}"))?;
+            let mut insert_band = tx
+                .prepare("INSERT INTO lsh_bands (band, bucket, item_id) VALUES (?1, ?2, ?3)")
+                .map_err(|e| format!("Failed to prepare INSERT: {e}"))?;
+            for (entry, band_keys) in entries {
+                delete_bands
+                    .execute(params![entry.id])
+                    .map_err(|e| format!("Failed to replace index entry: {e}"))?;
+                insert_item
+                    .execute(params![
+                        entry.id,
+                        entry.url_key,
+                        entry.simhash.map(|fp| fp as i64),
+                        entry.signature.as_deref().map(encode_signature),
+                        entry.record,
+                    ])
+                    .map_err(|e| format!("Failed to insert index entry: {e}"))?;
+                for &(band, bucket) in band_keys {
+                    insert_band
+                        .execute(params![band, bucket as i64, entry.id])
+                        .map_err(|e| format!("Failed to insert index band: {e}"))?;
+                }
+            }
+        }
+        tx.commit().map_err(|e| format!("Failed to commit index entries: {e}"))
+    }
+
+    /// Remove an entry from the near-duplicate index. Returns whether it existed.
+    pub fn index_remove(&self, id: &str) -> Result<bool, String> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start index transaction: {e}"))?;
+        tx.execute("DELETE FROM lsh_bands WHERE item_id = ?1", params![id])
+            .map_err(|e| format!("Failed to remove index entry: {e}"))?;
+        let deleted = tx
+            .execute("DELETE FROM lsh_items WHERE item_id = ?1", params![id])
+            .map_err(|e| format!("Failed to remove index entry: {e}"))?;
+        tx.commit().map_err(|e| format!("Failed to commit index removal: {e}"))?;
+        Ok(deleted > 0)
+    }
+
+    /// Band layout the index keys were written with, `None` for an index
+    /// that predates layout versions.
+    pub fn index_layout(&self) -> Result<Option<u32>, String> {
+        self.conn
+            .query_row("SELECT value FROM lsh_meta WHERE key = 'layout'", [], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to read index layout: {e}"))
+    }
+
+    /// Replace every entry's band keys with `band_keys(entry)` and record
+    /// `layout`, in one transaction. Entries and their fingerprints are kept.
+    pub fn index_rekey(
+        &self,
+        layout: u32,
+        band_keys: impl Fn(&IndexEntry) -> Vec<(u32, u64)>,
+    ) -> Result<(), String> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start index transaction: {e}"))?;
+        let entries = {
+            let mut stmt = tx
+                .prepare("SELECT item_id, url_key, simhash, signature, record FROM lsh_items")
+                .map_err(|e| format!("Failed to prepare SELECT: {e}"))?;
+            let rows = stmt
+                .query_map([], read_index_entry)
+                .map_err(|e| format!("Failed to query index: {e}"))?;
+            rows.collect::<Result<Vec<IndexEntry>, _>>()
+                .map_err(|e| format!("Failed to read row: {e}"))?
+        };
+        tx.execute("DELETE FROM lsh_bands", [])
+            .map_err(|e| format!("Failed to clear index bands: {e}"))?;
+        {
+            let mut insert_band = tx
+                .prepare("INSERT INTO lsh_bands (band, bucket, item_id) VALUES (?1, ?2, ?3)")
+                .map_err(|e| format!("Failed to prepare INSERT: {e}"))?;
+            for entry in &entries {
+                for (band, bucket) in band_keys(entry) {
+                    insert_band
+                        .execute(params![band, bucket as i64, entry.id])
+                        .map_err(|e| format!("Failed to insert index band: {e}"))?;
+                }
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO lsh_meta (key, value) VALUES ('layout', ?1)",
+            params![layout],
+        )
+        .map_err(|e| format!("Failed to record index layout: {e}"))?;
+        tx.commit().map_err(|e| format!("Failed to commit index rekey: {e}"))
+    }
+
+    /// Entries sharing at least one `(band, bucket)` key or the URL key,
+    /// ordered by id. Candidates still need their fingerprints compared.
+    pub fn index_candidates(
+        &self,
+        band_keys: &[(u32, u64)],
+        url_key: Option<&str>,
+    ) -> Result<Vec<IndexEntry>, String> {
+        let mut ids: BTreeSet<String> = BTreeSet::new();
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT item_id FROM lsh_bands WHERE band = ?1 AND bucket = ?2")
+            .map_err(|e| format!("Failed to prepare SELECT: {e}"))?;
+        for &(band, bucket) in band_keys {
+            let rows = stmt
+                .query_map(params![band, bucket as i64], |row| row.get::<_, String>(0))
+                .map_err(|e| format!("Failed to query index: {e}"))?;
+            for row in rows {
+                ids.insert(row.map_err(|e| format!("Failed to read row: {e}"))?);
+            }
+        }
+        if let Some(key) = url_key {
+            let mut stmt = self
+                .conn
+                .prepare_cached("SELECT item_id FROM lsh_items WHERE url_key = ?1")
+                .map_err(|e| format!("Failed to prepare SELECT: {e}"))?;
+            let rows = stmt
+                .query_map(params![key], |row| row.get::<_, String>(0))
+                .map_err(|e| format!("Failed to query index: {e}"))?;
+            for row in rows {
+                ids.insert(row.map_err(|e| format!("Failed to read row: {e}"))?);
+            }
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT item_id, url_key, simhash, signature, record
+                 FROM lsh_items WHERE item_id = ?1",
+            )
+            .map_err(|e| format!("Failed to prepare SELECT: {e}"))?;
+        let mut entries = Vec::with_capacity(ids.len());
+        for id in &ids {
+            let entry = stmt
+                .query_row(params![id], read_index_entry)
+                .optional()
+                .map_err(|e| format!("Failed to query index: {e}"))?;
+            entries.extend(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Number of entries in the near-duplicate index.
+    pub fn index_len(&self) -> Result<usize, String> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM lsh_items", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to count index entries: {e}"))?;
+        Ok(count as usize)
+    }
+
     /// Total size of the database file in bytes (approximate).
     pub fn db_size_bytes(&self) -> Result<u64, String> {
         let page_count: i64 = self
@@ -184,11 +481,36 @@ impl CacheDb {
     }
 }
 
+/// A record in the persistent near-duplicate index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub id: String,
+    /// Canonical URL key, for exact URL matches.
+    pub url_key: Option<String>,
+    /// SimHash fingerprint of the record's text.
+    pub simhash: Option<u64>,
+    /// MinHash signature of the record's text.
+    pub signature: Option<Vec<u64>>,
+    /// The indexed record as JSON, returned with lookup results.
+    pub record: String,
+}
+
+fn encode_signature(signature: &[u64]) -> Vec<u8> {
+    signature.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_signature(bytes: &[u8]) -> Vec<u64> {
+    bytes
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap_or_default()))
+        .collect()
+}
+
 /// Info record for a cached artifact (returned by `CacheDb::info()`).
 #[derive(Debug, Clone)]
 pub struct ArtifactInfo {
     pub kind: String,
-    pub content_hash: u64,
+    pub content_hash: u128,
     pub args_hash: u64,
     pub row_count: usize,
     pub version: String,
@@ -335,6 +657,164 @@ mod tests {
         assert!(size > 0);
     }
 
+    #[test]
+    fn get_latest_ignores_content_hash() {
+        let db = test_db();
+        let mut old = CacheMeta::new(111, 50, 222);
+        old.created_at = 1;
+        db.put(ArtifactKind::Corpus, &old, b"old").unwrap();
+        let mut new = CacheMeta::new(333, 60, 222);
+        new.created_at = 2;
+        db.put(ArtifactKind::Corpus, &new, b"new").unwrap();
+        db.put(ArtifactKind::Corpus, &CacheMeta::new(444, 10, 999), b"other args").unwrap();
+
+        let (meta, payload) = db.get_latest(ArtifactKind::Corpus, 222).unwrap().unwrap();
+        assert_eq!(meta.content_hash, 333);
+        assert_eq!(payload, b"new");
+        assert!(db.get_latest(ArtifactKind::Taxonomy, 222).unwrap().is_none());
+    }
+
+    #[test]
+    fn prune_keeps_only_requested_content() {
+        let db = test_db();
+        db.put(ArtifactKind::Corpus, &CacheMeta::new(111, 50, 222), b"a").unwrap();
+        db.put(ArtifactKind::Corpus, &CacheMeta::new(333, 50, 222), b"b").unwrap();
+        db.put(ArtifactKind::Corpus, &CacheMeta::new(555, 50, 999), b"c").unwrap();
+
+        let deleted = db.prune(ArtifactKind::Corpus, 222, 333).unwrap();
+        assert_eq!(deleted, 1);
+        assert!(db.get(ArtifactKind::Corpus, 111, 222).unwrap().is_none());
+        assert!(db.get(ArtifactKind::Corpus, 333, 222).unwrap().is_some());
+        assert!(db.get(ArtifactKind::Corpus, 555, 999).unwrap().is_some());
+    }
+
+    #[test]
+    fn content_hash_roundtrips_full_width() {
+        let db = test_db();
+        let wide = u128::MAX - 7;
+        db.put(ArtifactKind::Taxonomy, &CacheMeta::new(wide, 5, 1), b"t").unwrap();
+        let (meta, _) = db.get(ArtifactKind::Taxonomy, wide, 1).unwrap().unwrap();
+        assert_eq!(meta.content_hash, wide);
+        assert_eq!(db.info().unwrap()[0].content_hash, wide);
+        assert!(db.get(ArtifactKind::Taxonomy, wide as u64 as u128, 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn outdated_schema_is_reset_on_open() {
+        let dir = std::env::temp_dir().join(format!("topology-schema-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("old.db");
+        let path = path.to_str().unwrap();
+        {
+            // Layout from before content hashes were 128-bit digests.
+            let conn = Connection::open(path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE cache_artifacts (
+                    kind TEXT NOT NULL, content_hash INTEGER NOT NULL,
+                    args_hash INTEGER NOT NULL, row_count INTEGER NOT NULL,
+                    version TEXT NOT NULL, created_at INTEGER NOT NULL,
+                    payload BLOB NOT NULL,
+                    UNIQUE(kind, content_hash, args_hash));
+                 INSERT INTO cache_artifacts VALUES ('taxonomy', 42, 1, 5, '0', 0, x'00');",
+            )
+            .unwrap();
+        }
+
+        let db = CacheDb::open_or_create(path).unwrap();
+        assert!(db.info().unwrap().is_empty());
+        db.put(ArtifactKind::Taxonomy, &CacheMeta::new(42, 5, 1), b"t").unwrap();
+        drop(db);
+
+        // Reopening at the current version keeps the data.
+        let db = CacheDb::open_or_create(path).unwrap();
+        assert_eq!(db.info().unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn entry(id: &str, simhash: u64) -> IndexEntry {
+        IndexEntry {
+            id: id.into(),
+            url_key: Some(format!("example.com/{id}")),
+            simhash: Some(simhash),
+            signature: Some(vec![u64::MAX, 1, 2]),
+            record: format!(r#"{{"id":"{id}"}}"#),
+        }
+    }
+
+    #[test]
+    fn index_insert_query_and_remove() {
+        let db = test_db();
+        db.index_insert(&entry("a", u64::MAX), &[(0, 1), (1, 2)]).unwrap();
+        db.index_insert(&entry("b", 7), &[(0, 1), (1, 3)]).unwrap();
+        assert_eq!(db.index_len().unwrap(), 2);
+
+        let found = db.index_candidates(&[(1, 2)], None).unwrap();
+        assert_eq!(found, vec![entry("a", u64::MAX)]);
+        let found = db.index_candidates(&[(0, 1)], None).unwrap();
+        assert_eq!(found.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        let found = db.index_candidates(&[], Some("example.com/b")).unwrap();
+        assert_eq!(found, vec![entry("b", 7)]);
+        assert!(db.index_candidates(&[(1, 1)], Some("other")).unwrap().is_empty());
+
+        assert!(db.index_remove("a").unwrap());
+        assert!(!db.index_remove("a").unwrap());
+        assert!(db.index_candidates(&[(1, 2)], None).unwrap().is_empty());
+        assert_eq!(db.index_len().unwrap(), 1);
+    }
+
+    #[test]
+    fn index_insert_replaces_bands() {
+        let db = test_db();
+        db.index_insert(&entry("a", 1), &[(0, 1)]).unwrap();
+        db.index_insert(&entry("a", 2), &[(0, 5)]).unwrap();
+        assert_eq!(db.index_len().unwrap(), 1);
+        assert!(db.index_candidates(&[(0, 1)], None).unwrap().is_empty());
+        assert_eq!(db.index_candidates(&[(0, 5)], None).unwrap()[0].simhash, Some(2));
+    }
+
+    #[test]
+    fn index_insert_all_writes_a_batch() {
+        let db = test_db();
+        let (a, b) = (entry("a", 1), entry("b", 2));
+        db.index_insert_all([(&a, &[(0, 1)][..]), (&b, &[(0, 1), (1, 4)][..])]).unwrap();
+        assert_eq!(db.index_len().unwrap(), 2);
+        assert_eq!(db.index_candidates(&[(0, 1)], None).unwrap(), vec![a, b.clone()]);
+        assert_eq!(db.index_candidates(&[(1, 4)], None).unwrap(), vec![b]);
+    }
+
+    #[test]
+    fn index_survives_cache_schema_change() {
+        let dir = std::env::temp_dir().join(format!("topology-index-schema-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("index.db");
+        let path = path.to_str().unwrap();
+        {
+            let db = CacheDb::open_or_create(path).unwrap();
+            db.put(ArtifactKind::Corpus, &CacheMeta::new(1, 1, 1), b"c").unwrap();
+            db.index_insert(&entry("a", 1), &[(0, 1)]).unwrap();
+            db.conn.pragma_update(None, "user_version", SCHEMA_VERSION - 1).unwrap();
+        }
+
+        let db = CacheDb::open_or_create(path).unwrap();
+        assert!(db.info().unwrap().is_empty());
+        assert_eq!(db.index_candidates(&[(0, 1)], None).unwrap(), vec![entry("a", 1)]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn index_rekey_replaces_band_keys() {
+        let db = test_db();
+        assert_eq!(db.index_layout().unwrap(), None);
+        db.index_insert(&entry("a", 1), &[(0, 1)]).unwrap();
+        db.index_insert(&entry("b", 2), &[(0, 1)]).unwrap();
+
+        db.index_rekey(7, |e| vec![(3, e.simhash.unwrap())]).unwrap();
+        assert_eq!(db.index_layout().unwrap(), Some(7));
+        assert!(db.index_candidates(&[(0, 1)], None).unwrap().is_empty());
+        assert_eq!(db.index_candidates(&[(3, 2)], None).unwrap(), vec![entry("b", 2)]);
+        assert_eq!(db.index_len().unwrap(), 2);
+    }
+
     #[test]
     fn version_stored_correctly() {
         let db = test_db();
@@ -151,7 +151,7 @@ mod tests {
         for (a, b) in &pairs {
             for metric in [Metric::Levenshtein, Metric::JaroWinkler, Metric::Cosine] {
                 let s = similarity(a, b, metric);
-                assert!(s >= 0.0 && s <= 1.0, "{a} vs {b} with {metric:?} = {s}");
+                assert!((0.0..=1.0).contains(&s), "{a} vs {b} with {metric:?} = {s}");
             }
         }
     }
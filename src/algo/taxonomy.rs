@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// A taxonomy category discovered from data or loaded from user file.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,6 +8,10 @@ pub struct Category {
     pub keywords: Vec<String>,
     #[serde(default)]
     pub children: Vec<Category>,
+    /// Mean unit-length TF-IDF vector of the members, for centroid
+    /// assignment. Empty unless discovered with centroids.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub centroid: BTreeMap<String, f64>,
 }
 
 /// A full taxonomy tree — always discovered from data or user-provided.
@@ -27,6 +32,15 @@ impl Taxonomy {
         result
     }
 
+    /// Leaf categories as (path, category), in the same order as [`Self::flatten`].
+    pub fn leaves(&self) -> Vec<(String, &Category)> {
+        let mut result = Vec::new();
+        for cat in &self.categories {
+            leaves_recursive(cat, "", &mut result);
+        }
+        result
+    }
+
     /// Get category names as a flat list.
     pub fn category_names(&self) -> Vec<String> {
         self.categories.iter().map(|c| c.name.clone()).collect()
@@ -45,6 +59,21 @@ fn flatten_recursive(cat: &Category, prefix: &str, out: &mut Vec<(String, Vec<St
     }
 }
 
+fn leaves_recursive<'a>(cat: &'a Category, prefix: &str, out: &mut Vec<(String, &'a Category)>) {
+    let path = if prefix.is_empty() {
+        cat.name.clone()
+    } else {
+        format!("{prefix} > {}", cat.name)
+    };
+    if cat.children.is_empty() {
+        out.push((path, cat));
+    } else {
+        for child in &cat.children {
+            leaves_recursive(child, &path, out);
+        }
+    }
+}
+
 /// Parse a taxonomy from a JSON string.
 pub fn parse_taxonomy(json: &str) -> Result<Taxonomy, String> {
     serde_json::from_str(json).map_err(|e| format!("Failed to parse taxonomy: {e}"))
@@ -71,6 +100,7 @@ mod tests {
                     name: "Alpha".into(),
                     keywords: vec!["foo".into(), "bar".into()],
                     children: vec![],
+                    centroid: Default::default(),
                 },
                 Category {
                     name: "Beta".into(),
@@ -79,7 +109,9 @@ mod tests {
                         name: "Gamma".into(),
                         keywords: vec!["qux".into()],
                         children: vec![],
+                        centroid: Default::default(),
                     }],
+                    centroid: Default::default(),
                 },
             ],
         };
@@ -112,6 +144,7 @@ mod tests {
                 name: "Solo".into(),
                 keywords: vec!["one".into()],
                 children: vec![],
+                centroid: Default::default(),
             }],
         };
         let flat = tax.flatten();
@@ -135,8 +168,11 @@ mod tests {
                         name: "L3".into(),
                         keywords: vec!["deep".into()],
                         children: vec![],
+                        centroid: Default::default(),
                     }],
+                    centroid: Default::default(),
                 }],
+                centroid: Default::default(),
             }],
         };
         let flat = tax.flatten();
@@ -144,6 +180,31 @@ mod tests {
         assert_eq!(flat[2].0, "L1 > L2 > L3");
     }
 
+    #[test]
+    fn leaves_skip_inner_nodes() {
+        let leaf = |name: &str| Category {
+            name: name.into(),
+            keywords: vec![],
+            children: vec![],
+            centroid: Default::default(),
+        };
+        let tax = Taxonomy {
+            name: "test".into(),
+            version: "1.0".into(),
+            categories: vec![
+                Category {
+                    name: "A".into(),
+                    keywords: vec![],
+                    children: vec![leaf("A1"), leaf("A2")],
+                    centroid: Default::default(),
+                },
+                leaf("B"),
+            ],
+        };
+        let paths: Vec<String> = tax.leaves().into_iter().map(|(p, _)| p).collect();
+        assert_eq!(paths, vec!["A > A1", "A > A2", "B"]);
+    }
+
     #[test]
     fn category_names_returns_top_level_only() {
         let tax = Taxonomy {
@@ -151,9 +212,9 @@ mod tests {
             version: "1.0".into(),
             categories: vec![
                 Category { name: "A".into(), keywords: vec![], children: vec![
-                    Category { name: "A1".into(), keywords: vec![], children: vec![] },
-                ]},
-                Category { name: "B".into(), keywords: vec![], children: vec![] },
+                    Category { name: "A1".into(), keywords: vec![], children: vec![], centroid: Default::default() },
+                ], centroid: Default::default() },
+                Category { name: "B".into(), keywords: vec![], children: vec![], centroid: Default::default() },
             ],
         };
         let names = tax.category_names();
@@ -187,6 +248,7 @@ mod tests {
                 name: "Cat".into(),
                 keywords: vec!["a".into(), "b".into(), "c".into()],
                 children: vec![],
+                centroid: Default::default(),
             }],
         };
         let json = serde_json::to_string(&tax).unwrap();
@@ -1,13 +1,21 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use super::sparse::{SparseVector, TermId, Vocabulary};
 
 /// A corpus of documents for TF-IDF and BM25 scoring.
+///
+/// Terms are interned into one [`Vocabulary`]; documents store sorted
+/// `(term id, count)` lists and document frequencies are indexed by id.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Corpus {
-    /// document_id -> term -> count
-    doc_terms: Vec<HashMap<String, u32>>,
-    /// term -> number of documents containing it
-    doc_freq: HashMap<String, u32>,
+    /// Every term ever added. Terms whose documents were all removed keep
+    /// their id with a document frequency of 0.
+    vocab: Vocabulary,
+    /// document_id -> (term id, count), sorted by term id
+    doc_terms: Vec<Vec<(TermId, u32)>>,
+    /// term id -> number of documents containing it
+    doc_freq: Vec<u32>,
     /// total number of documents
     num_docs: u32,
     /// average document length (in tokens)
@@ -17,8 +25,9 @@ pub struct Corpus {
 impl Corpus {
     pub fn new() -> Self {
         Self {
+            vocab: Vocabulary::new(),
             doc_terms: Vec::new(),
-            doc_freq: HashMap::new(),
+            doc_freq: Vec::new(),
             num_docs: 0,
             avg_dl: 0.0,
         }
@@ -26,39 +35,124 @@ impl Corpus {
 
     /// Add a document (as pre-tokenized terms) to the corpus.
     pub fn add_document(&mut self, tokens: &[String]) {
-        let mut term_counts: HashMap<String, u32> = HashMap::new();
-        for token in tokens {
-            *term_counts.entry(token.clone()).or_insert(0) += 1;
+        let mut ids: Vec<TermId> = tokens.iter().map(|t| self.vocab.intern(t)).collect();
+        ids.sort_unstable();
+        let mut term_counts: Vec<(TermId, u32)> = Vec::new();
+        for id in ids {
+            match term_counts.last_mut() {
+                Some((last, count)) if *last == id => *count += 1,
+                _ => term_counts.push((id, 1)),
+            }
         }
-        for term in term_counts.keys() {
-            *self.doc_freq.entry(term.clone()).or_insert(0) += 1;
+        self.doc_freq.resize(self.vocab.len(), 0);
+        for &(id, _) in &term_counts {
+            self.doc_freq[id as usize] += 1;
         }
+        let dl = tokens.len() as f64;
         self.doc_terms.push(term_counts);
         self.num_docs += 1;
 
-        // Recompute average document length
-        let total_len: usize = self.doc_terms.iter().map(|d| d.values().sum::<u32>() as usize).sum();
-        self.avg_dl = total_len as f64 / self.num_docs as f64;
+        // Update average document length incrementally
+        self.avg_dl += (dl - self.avg_dl) / self.num_docs as f64;
+    }
+
+    /// Remove the document at `doc_idx`, updating document frequencies.
+    ///
+    /// Later documents shift down by one index, as with `Vec::remove`.
+    pub fn remove_document(&mut self, doc_idx: usize) {
+        let removed = self.doc_terms.remove(doc_idx);
+        for &(id, _) in &removed {
+            self.doc_freq[id as usize] -= 1;
+        }
+        let dl = removed.iter().map(|&(_, c)| c).sum::<u32>() as f64;
+        let total = self.avg_dl * self.num_docs as f64 - dl;
+        self.num_docs -= 1;
+        self.avg_dl = if self.num_docs > 0 {
+            total.max(0.0) / self.num_docs as f64
+        } else {
+            0.0
+        };
+    }
+
+    /// Reorder documents so that new index `i` holds the document previously
+    /// at `order[i]`. `order` must be a permutation of `0..num_docs`.
+    pub fn reorder(&mut self, order: &[usize]) {
+        assert_eq!(order.len(), self.doc_terms.len(), "reorder needs a full permutation");
+        let mut old: Vec<Option<Vec<(TermId, u32)>>> =
+            std::mem::take(&mut self.doc_terms).into_iter().map(Some).collect();
+        self.doc_terms = order
+            .iter()
+            .map(|&i| old[i].take().expect("reorder index used twice"))
+            .collect();
+    }
+
+    /// The vocabulary term ids in [`Self::tfidf_vector`] refer to.
+    pub fn vocabulary(&self) -> &Vocabulary {
+        &self.vocab
     }
 
     /// Compute IDF for a term: log((N - df + 0.5) / (df + 0.5) + 1)
     pub fn idf(&self, term: &str) -> f64 {
-        let df = *self.doc_freq.get(term).unwrap_or(&0) as f64;
-        let n = self.num_docs as f64;
-        ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+        bm25_idf(self.num_docs, self.vocab.id(term).map_or(0, |id| self.doc_freq[id as usize]))
+    }
+
+    /// [`Self::idf`] by term id.
+    pub fn idf_by_id(&self, id: TermId) -> f64 {
+        bm25_idf(self.num_docs, self.doc_freq.get(id as usize).copied().unwrap_or(0))
+    }
+
+    /// Snapshot the document frequencies, detached from the documents.
+    pub fn idf_stats(&self) -> IdfStats {
+        IdfStats {
+            num_docs: self.num_docs,
+            doc_freq: self
+                .vocab
+                .terms()
+                .iter()
+                .zip(&self.doc_freq)
+                .filter(|(_, &df)| df > 0)
+                .map(|(t, &df)| (t.clone(), df))
+                .collect(),
+        }
     }
 
     /// Compute TF-IDF vector for a document by index.
-    pub fn tfidf_vector(&self, doc_idx: usize) -> HashMap<String, f64> {
+    pub fn tfidf_vector(&self, doc_idx: usize) -> SparseVector {
         let doc = &self.doc_terms[doc_idx];
-        let dl: u32 = doc.values().sum();
-        let mut result = HashMap::new();
-        for (term, &count) in doc {
-            let tf = count as f64 / dl as f64;
-            let idf = self.idf(term);
-            result.insert(term.clone(), tf * idf);
+        let dl = doc.iter().map(|&(_, c)| c).sum::<u32>() as f64;
+        SparseVector::from_entries(
+            doc.iter()
+                .map(|&(id, count)| (id, count as f64 / dl * self.idf_by_id(id)))
+                .collect(),
+        )
+    }
+
+    /// Raw `(term id, count)` pairs of a document, sorted by id.
+    pub fn term_counts(&self, doc_idx: usize) -> &[(TermId, u32)] {
+        &self.doc_terms[doc_idx]
+    }
+
+    /// Co-document frequencies of `ids`: entry `[i][j]` counts documents
+    /// containing both `ids[i]` and `ids[j]`, and the diagonal is each
+    /// term's own document frequency. One pass over the documents.
+    pub fn co_doc_freq(&self, ids: &[TermId]) -> Vec<Vec<u32>> {
+        let mut counts = vec![vec![0u32; ids.len()]; ids.len()];
+        let mut present = Vec::with_capacity(ids.len());
+        for doc in &self.doc_terms {
+            present.clear();
+            present.extend(
+                (0..ids.len()).filter(|&i| doc.binary_search_by_key(&ids[i], |&(id, _)| id).is_ok()),
+            );
+            for (a, &i) in present.iter().enumerate() {
+                for &j in &present[a..] {
+                    counts[i][j] += 1;
+                    if i != j {
+                        counts[j][i] += 1;
+                    }
+                }
+            }
         }
-        result
+        counts
     }
 
     /// BM25 score for a query (list of terms) against document at doc_idx.
@@ -74,17 +168,34 @@ impl Corpus {
         query_terms: &[String],
         k1: f64,
         b: f64,
+    ) -> f64 {
+        self.bm25_score_with_idf(doc_idx, query_terms, k1, b, |t| self.idf(t))
+    }
+
+    /// BM25 score taking term IDF from `idf` instead of this corpus, e.g. from
+    /// the [`IdfStats`] of a larger corpus the documents were derived from.
+    pub fn bm25_score_with_idf(
+        &self,
+        doc_idx: usize,
+        query_terms: &[String],
+        k1: f64,
+        b: f64,
+        idf: impl Fn(&str) -> f64,
     ) -> f64 {
         let doc = &self.doc_terms[doc_idx];
-        let dl: f64 = doc.values().sum::<u32>() as f64;
+        let dl: f64 = doc.iter().map(|&(_, c)| c).sum::<u32>() as f64;
         let mut score = 0.0;
 
         for term in query_terms {
-            let tf = *doc.get(term).unwrap_or(&0) as f64;
+            let tf = self
+                .vocab
+                .id(term)
+                .and_then(|id| doc.binary_search_by_key(&id, |&(i, _)| i).ok())
+                .map_or(0, |pos| doc[pos].1) as f64;
             if tf == 0.0 {
                 continue;
             }
-            let idf = self.idf(term);
+            let idf = idf(term);
             let numerator = tf * (k1 + 1.0);
             let denominator = tf + k1 * (1.0 - b + b * dl / self.avg_dl);
             score += idf * numerator / denominator;
@@ -94,8 +205,17 @@ impl Corpus {
 
     /// Extract top-N terms by TF-IDF weight for a document.
     pub fn top_terms(&self, doc_idx: usize, n: usize) -> Vec<(String, f64)> {
-        let mut tfidf: Vec<(String, f64)> = self.tfidf_vector(doc_idx).into_iter().collect();
-        tfidf.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        let mut tfidf: Vec<(String, f64)> = self
+            .tfidf_vector(doc_idx)
+            .to_terms(&self.vocab)
+            .map(|(t, w)| (t.to_string(), w))
+            .collect();
+        // Break score ties by term so output doesn't depend on term ids.
+        tfidf.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
         tfidf.truncate(n);
         tfidf
     }
@@ -128,6 +248,29 @@ impl Default for Corpus {
     }
 }
 
+fn bm25_idf(num_docs: u32, df: u32) -> f64 {
+    let (n, df) = (num_docs as f64, df as f64);
+    ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+}
+
+/// Document frequencies of a corpus, without the documents themselves.
+///
+/// Small enough to persist, and enough to score new text with the same IDF
+/// weights the corpus had when it was snapshotted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdfStats {
+    pub num_docs: u32,
+    /// Sorted so serialized snapshots are stable.
+    pub doc_freq: BTreeMap<String, u32>,
+}
+
+impl IdfStats {
+    /// IDF of `term`, identical to [`Corpus::idf`] on the source corpus.
+    pub fn idf(&self, term: &str) -> f64 {
+        bm25_idf(self.num_docs, *self.doc_freq.get(term).unwrap_or(&0))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,8 +336,10 @@ mod tests {
         c.add_document(&["rust".into(), "rust".into(), "fast".into()]);
         assert_eq!(c.num_docs(), 1);
         let vec = c.tfidf_vector(0);
-        assert!(vec.contains_key("rust"));
-        assert!(vec.contains_key("fast"));
+        let vocab = c.vocabulary();
+        assert!(vec.get(vocab.id("rust").unwrap()) > 0.0);
+        assert!(vec.get(vocab.id("fast").unwrap()) > 0.0);
+        assert_eq!(vec.len(), 2);
     }
 
     #[test]
@@ -259,12 +404,60 @@ mod tests {
         assert!((c.bm25_score(1, &query) - c2.bm25_score(1, &query)).abs() < 1e-10);
     }
 
+    #[test]
+    fn remove_document_matches_fresh_build() {
+        let mut c = make_corpus();
+        c.remove_document(1);
+        let mut fresh = Corpus::new();
+        fresh.add_document(&["rust".into(), "programming".into(), "language".into()]);
+        fresh.add_document(&["javascript".into(), "web".into(), "programming".into()]);
+        assert_eq!(c.num_docs(), 2);
+        assert!((c.idf("rust") - fresh.idf("rust")).abs() < 1e-10);
+        assert!((c.idf("systems") - fresh.idf("systems")).abs() < 1e-10);
+        assert_eq!(c.idf_stats().doc_freq, fresh.idf_stats().doc_freq);
+        let query = vec!["web".into(), "programming".into()];
+        assert!((c.bm25_score(1, &query) - fresh.bm25_score(1, &query)).abs() < 1e-10);
+    }
+
+    #[test]
+    fn reorder_moves_documents() {
+        let mut c = make_corpus();
+        let before = c.top_terms(2, 3);
+        c.reorder(&[2, 0, 1]);
+        assert_eq!(c.top_terms(0, 3), before);
+        assert_eq!(c.num_docs(), 3);
+    }
+
+    #[test]
+    fn idf_stats_match_corpus() {
+        let c = make_corpus();
+        let stats = c.idf_stats();
+        assert_eq!(stats.num_docs, 3);
+        for term in ["rust", "systems", "unseen"] {
+            assert!((stats.idf(term) - c.idf(term)).abs() < 1e-12);
+        }
+        let query = vec!["rust".into(), "systems".into()];
+        let with_stats = c.bm25_score_with_idf(1, &query, 1.2, 0.75, |t| stats.idf(t));
+        assert!((with_stats - c.bm25_score(1, &query)).abs() < 1e-12);
+    }
+
     #[test]
     fn tfidf_vector_sums_positive() {
         let c = make_corpus();
         let vec = c.tfidf_vector(0);
-        for &v in vec.values() {
+        for (_, v) in vec.iter() {
             assert!(v >= 0.0, "TF-IDF values should be non-negative");
         }
     }
+
+    #[test]
+    fn co_doc_freq_counts_pairs() {
+        let c = make_corpus();
+        let ids: Vec<TermId> = ["rust", "programming", "web"]
+            .iter()
+            .map(|t| c.vocabulary().id(t).unwrap())
+            .collect();
+        let counts = c.co_doc_freq(&ids);
+        assert_eq!(counts, vec![vec![2, 1, 0], vec![1, 2, 1], vec![0, 1, 1]]);
+    }
 }
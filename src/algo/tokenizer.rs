@@ -1,11 +1,42 @@
+use serde::{Deserialize, Serialize};
 use unicode_segmentation::UnicodeSegmentation;
 
+/// Settings that decide how text is split into tokens.
+///
+/// Anything derived from tokens (corpora, fingerprints, taxonomies) depends on
+/// these, so they are part of every cache key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenizerConfig {
+    /// Minimum token length in bytes.
+    pub min_len: usize,
+    /// Lowercase tokens before filtering.
+    pub lowercase: bool,
+    /// Drop English stopwords.
+    pub stopwords: bool,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        Self {
+            min_len: 2,
+            lowercase: true,
+            stopwords: true,
+        }
+    }
+}
+
+impl TokenizerConfig {
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        text.unicode_words()
+            .map(|w| if self.lowercase { w.to_lowercase() } else { w.to_string() })
+            .filter(|w| w.len() >= self.min_len && !(self.stopwords && is_stopword(w)))
+            .collect()
+    }
+}
+
 /// Tokenize text into lowercase word tokens, filtering stopwords and short tokens.
 pub fn tokenize(text: &str) -> Vec<String> {
-    text.unicode_words()
-        .map(|w| w.to_lowercase())
-        .filter(|w| w.len() >= 2 && !is_stopword(w))
-        .collect()
+    TokenizerConfig::default().tokenize(text)
 }
 
 /// Generate character n-grams (shingles) from text.
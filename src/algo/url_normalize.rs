@@ -61,12 +61,7 @@ pub fn normalize(raw: &str) -> Option<String> {
 
     // Remove trailing slash (but keep root "/" for bare domains)
     if result.ends_with('/') {
-        let path = parsed.path();
-        if path == "/" {
-            result = result.trim_end_matches('/').to_string();
-        } else {
-            result = result.trim_end_matches('/').to_string();
-        }
+        result = result.trim_end_matches('/').to_string();
     }
 
     // Strip www. prefix from host
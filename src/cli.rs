@@ -1,4 +1,5 @@
 use clap::{Parser, Subcommand};
+use nu_plugin_topology::algo::clustering::KSelection;
 use nu_plugin_topology::ops;
 use serde_json::Value;
 use std::io::{self, Read};
@@ -70,24 +71,72 @@ enum Commands {
         #[arg(short, long, default_value = "content")]
         field: String,
         /// Path to taxonomy JSON file. If omitted, categories are discovered from the data
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "model")]
         taxonomy: Option<String>,
+        /// Path to a model file from `topology train`; classifies against it instead of discovering
+        #[arg(short, long)]
+        model: Option<String>,
         /// Number of categories to discover (default: 15)
         #[arg(short, long, default_value_t = 15)]
         clusters: usize,
-        /// Max items to sample for discovery
-        #[arg(long, default_value_t = 500)]
+        /// Max items to cluster for discovery; HAC memory grows with its square (about 16 MB at 2000)
+        #[arg(long, default_value_t = 2000)]
         sample: usize,
-        /// Minimum BM25 score threshold
+        /// Minimum confidence, on the --scale scale, to assign a category
         #[arg(long, default_value_t = 0.5)]
         threshold: f64,
         /// Random seed
         #[arg(long, default_value_t = 42)]
         seed: u64,
+        /// Taxonomy levels to discover; 1 keeps categories flat
+        #[arg(short, long, default_value_t = 1)]
+        depth: usize,
+        /// Growth in cluster count per level below the top
+        #[arg(long, default_value_t = 3)]
+        branching: usize,
+        /// Choose the number of categories by silhouette score, trying 2 up to --clusters; the scores are printed to stderr
+        #[arg(long)]
+        auto_k: bool,
+        /// Match items by keyword BM25 or nearest TF-IDF centroid: bm25, centroid
+        #[arg(long, default_value = "bm25")]
+        assign: String,
+        /// Also list every category above the threshold in `_categories`
+        #[arg(long)]
+        multi_label: bool,
+        /// Scale of `_confidence` and --threshold: raw (BM25 score, or cosine with centroid), softmax, margin
+        #[arg(long, default_value = "raw")]
+        scale: String,
         /// Path to SQLite cache database for persistent artifact caching
         #[arg(long)]
         cache: Option<String>,
     },
+    /// Train a reusable classifier model (taxonomy + corpus IDF + tokenizer settings)
+    Train {
+        /// JSON field containing text to train on
+        #[arg(short, long, default_value = "content")]
+        field: String,
+        /// Number of categories to discover
+        #[arg(short, long, default_value_t = 15)]
+        clusters: usize,
+        /// Max items to cluster for discovery; HAC memory grows with its square (about 16 MB at 2000)
+        #[arg(long, default_value_t = 2000)]
+        sample: usize,
+        /// Random seed
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+        /// Taxonomy levels to discover; 1 keeps categories flat
+        #[arg(short, long, default_value_t = 1)]
+        depth: usize,
+        /// Growth in cluster count per level below the top
+        #[arg(long, default_value_t = 3)]
+        branching: usize,
+        /// Choose the number of categories by silhouette score, trying 2 up to --clusters
+        #[arg(long)]
+        auto_k: bool,
+        /// Write the model to this file (prints the model to stdout if omitted)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
     /// Extract top TF-IDF tags from content
     Tags {
         /// JSON field containing text
@@ -100,7 +149,7 @@ enum Commands {
         #[arg(long)]
         cache: Option<String>,
     },
-    /// Find duplicates using SimHash + LSH + URL normalization
+    /// Find duplicates using SimHash or MinHash + LSH + URL normalization
     Dedup {
         /// JSON field containing text
         #[arg(short, long, default_value = "content")]
@@ -108,16 +157,159 @@ enum Commands {
         /// JSON field containing URL
         #[arg(long, default_value = "url")]
         url_field: String,
-        /// Strategy: url, fuzzy, combined
+        /// Strategy: url, fuzzy, minhash, combined, combined-minhash
         #[arg(short, long, default_value = "combined")]
         strategy: String,
         /// SimHash hamming distance threshold
         #[arg(long, default_value_t = 3)]
         threshold: u32,
+        /// Minimum Jaccard similarity for minhash strategies
+        #[arg(long, default_value_t = 0.5)]
+        jaccard: f64,
+        /// Character shingle size for minhash strategies
+        #[arg(long, default_value_t = 3)]
+        shingle: usize,
+        /// Target LSH recall at the threshold; bands and rows are derived from it
+        #[arg(long, default_value_t = 0.95)]
+        recall: f64,
+        /// Fixed number of LSH bands instead of deriving them from --recall. The
+        /// layout used and its false-negative rate are printed to stderr
+        #[arg(long)]
+        bands: Option<usize>,
+        /// Keeper of each group: first, oldest[:field], newest[:field], longest, most-starred[:field], shortest-url
+        #[arg(short, long, default_value = "first")]
+        primary: String,
+        /// JSON file of existing records: report `_matches_existing` per input row instead of grouping
+        #[arg(long)]
+        against: Option<String>,
+        /// Field reported as the row id in --explain partners and groups, and as the match id with --against
+        #[arg(long, default_value = "id")]
+        id_field: String,
+        /// Output {rows, groups, banding} with per-row match reasons, a group summary and the LSH layout
+        #[arg(long)]
+        explain: bool,
         /// Path to SQLite cache database for persistent artifact caching
         #[arg(long)]
         cache: Option<String>,
     },
+    /// Collapse each duplicate group from `dedup` into one record
+    MergeDuplicates {
+        /// Merge rule per field, e.g. tags=union,date_added=min (rules: first, min, max, union, concat)
+        #[arg(short, long, default_value = "")]
+        rules: String,
+        /// JSON field holding the duplicate group
+        #[arg(long, default_value = "_dup_group")]
+        group_field: String,
+        /// JSON field listed in `_merged_from`
+        #[arg(long, default_value = "id")]
+        id_field: String,
+        /// Separator for text merged with concat
+        #[arg(long, default_value = "; ")]
+        separator: String,
+    },
+    /// Top-k most similar rows for every row as a (source, target, score) edge list
+    Neighbors {
+        /// JSON field containing text
+        #[arg(short, long, default_value = "content")]
+        field: String,
+        /// Neighbours kept per row
+        #[arg(short = 'k', long, default_value_t = 5)]
+        top_k: usize,
+        /// Score: cosine (TF-IDF) or jaccard (character shingles)
+        #[arg(short, long, default_value = "cosine")]
+        metric: String,
+        /// Minimum edge score
+        #[arg(long, default_value_t = 0.2)]
+        min_score: f64,
+        /// JSON field used as source/target; row indices when missing
+        #[arg(long, default_value = "id")]
+        id_field: String,
+        /// Character shingle size for the jaccard metric
+        #[arg(long, default_value_t = 3)]
+        shingle: usize,
+        /// Target LSH recall for pairs at --min-score
+        #[arg(long, default_value_t = 0.95)]
+        recall: f64,
+    },
+    /// Export neighbour edges, dedup groups, the taxonomy tree or a dendrogram as GraphML, DOT or GEXF
+    Graph {
+        /// What to draw: neighbors, dedup, taxonomy, dendrogram
+        #[arg(short, long, default_value = "neighbors")]
+        kind: String,
+        /// Output format: graphml, dot, gexf
+        #[arg(short = 'o', long, default_value = "graphml")]
+        format: String,
+        /// JSON field containing text
+        #[arg(short, long, default_value = "content")]
+        field: String,
+        /// JSON field used as node label; falls back to the text
+        #[arg(long, default_value = "title")]
+        label_field: String,
+        /// JSON field stored as the node's id attribute
+        #[arg(long, default_value = "id")]
+        id_field: String,
+        /// JSON field holding the duplicate group for --kind dedup
+        #[arg(long, default_value = "_dup_group")]
+        group_field: String,
+        /// Taxonomy JSON file drawn in full for --kind taxonomy
+        #[arg(long)]
+        taxonomy: Option<String>,
+        /// Linkage for --kind dendrogram: ward, complete, average, single
+        #[arg(short, long, default_value = "ward")]
+        linkage: String,
+        /// Neighbours kept per row for --kind neighbors
+        #[arg(long, default_value_t = 5)]
+        top_k: usize,
+        /// Score for --kind neighbors: cosine or jaccard
+        #[arg(short, long, default_value = "cosine")]
+        metric: String,
+        /// Minimum edge score for --kind neighbors
+        #[arg(long, default_value_t = 0.2)]
+        min_score: f64,
+    },
+    /// Add JSON records from stdin to the persistent near-duplicate index (or remove ids)
+    Index {
+        /// Path to the SQLite cache database holding the index
+        path: String,
+        /// JSON field containing text to fingerprint
+        #[arg(short, long, default_value = "content")]
+        field: String,
+        /// JSON field containing the URL
+        #[arg(long, default_value = "url")]
+        url_field: String,
+        /// JSON field identifying each record; the URL is used when missing
+        #[arg(long, default_value = "id")]
+        id_field: String,
+        /// Ids to remove from the index instead of reading records from stdin
+        #[arg(short, long, num_args = 1..)]
+        remove: Option<Vec<String>>,
+    },
+    /// Find the nearest indexed records to a text, URL, fingerprint or MinHash signature
+    Lookup {
+        /// Path to the SQLite cache database holding the index
+        path: String,
+        /// Text to look up
+        #[arg(required_unless_present_any = ["url", "fingerprint", "signature"])]
+        text: Option<String>,
+        /// URL to look up; matches records with the same canonical URL
+        #[arg(short, long)]
+        url: Option<String>,
+        /// Hex SimHash fingerprint to look up (an unweighted `fingerprint` result)
+        #[arg(long)]
+        fingerprint: Option<String>,
+        /// MinHash signature to look up: 128 hex values separated by commas
+        #[arg(long)]
+        signature: Option<String>,
+        /// SimHash hamming distance threshold (at most 15)
+        #[arg(long, default_value_t = 3)]
+        threshold: u32,
+        /// Minimum MinHash Jaccard similarity
+        #[arg(long, default_value_t = 0.5)]
+        jaccard: f64,
+        /// Maximum number of neighbours
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
     /// Compute string similarity between two strings
     Similarity {
         /// First string
@@ -145,6 +337,18 @@ enum Commands {
         /// Number of clusters / taxonomy depth
         #[arg(short = 'k', long, default_value_t = 10)]
         depth: usize,
+        /// Choose the number of clusters by silhouette score, trying 2 up to --depth
+        #[arg(long)]
+        auto_k: bool,
+        /// Cut where merges exceed this cosine distance instead of at --depth clusters
+        #[arg(short = 'd', long)]
+        cut_distance: Option<f64>,
+        /// Include the dendrogram merges and a Newick rendering as `dendrogram`
+        #[arg(long)]
+        dendrogram: bool,
+        /// JSON field labelling Newick leaves; row indices when missing
+        #[arg(long, default_value = "id")]
+        id_field: String,
         /// Linkage method: ward, complete, average, single
         #[arg(short, long, default_value = "ward")]
         linkage: String,
@@ -155,7 +359,7 @@ enum Commands {
         #[arg(long)]
         cache: Option<String>,
     },
-    /// Discover topics using NMF (Non-negative Matrix Factorization)
+    /// Discover topics using NMF (Non-negative Matrix Factorization) or LDA
     Topics {
         /// JSON field containing text
         #[arg(short, long, default_value = "content")]
@@ -166,12 +370,42 @@ enum Commands {
         /// Number of top terms per topic
         #[arg(short = 'n', long, default_value_t = 10)]
         terms: usize,
-        /// NMF iterations
+        /// Return the rows with `_topics`: each row's top N topics with normalized weights
+        #[arg(long)]
+        mixture: Option<usize>,
+        /// Fit each of these topic counts instead of --topics and compare coherence and diversity
+        #[arg(long, num_args = 1.., value_delimiter = ',')]
+        sweep: Option<Vec<usize>>,
+        /// Topic model: nmf, lda
+        #[arg(short, long, default_value = "nmf")]
+        model: String,
+        /// NMF iterations or LDA Gibbs sweeps
         #[arg(long, default_value_t = 200)]
         iterations: usize,
         /// Max vocabulary size
         #[arg(long, default_value_t = 5000)]
         vocab: usize,
+        /// Stop when the relative drop in the NMF objective falls below this
+        #[arg(long, default_value_t = 1e-4)]
+        tol: f64,
+        /// Initialisation: nndsvd, uniform
+        #[arg(long, default_value = "nndsvd")]
+        init: String,
+        /// L1 penalty on both factors, for sparser topics
+        #[arg(long, default_value_t = 0.0)]
+        l1: f64,
+        /// L2 penalty on both factors, for smoother topics
+        #[arg(long, default_value_t = 0.0)]
+        l2: f64,
+        /// LDA document-topic prior; lower means fewer topics per item
+        #[arg(long, default_value_t = 0.1)]
+        alpha: f64,
+        /// LDA topic-term prior; lower means more distinctive topics
+        #[arg(long, default_value_t = 0.01)]
+        beta: f64,
+        /// LDA random seed
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
     },
     /// Generate output paths and structure from classified items
     Organize {
@@ -260,14 +494,59 @@ fn main() {
         Commands::Classify {
             field,
             taxonomy: tax,
+            model,
             clusters,
             sample,
             threshold,
             seed,
+            depth,
+            branching,
+            auto_k,
+            assign,
+            multi_label,
+            scale,
             cache,
         } => {
             let rows = read_stdin_json();
-            match ops::op_classify_from_file_cached(&rows, &field, tax.as_deref(), clusters, sample, threshold, seed, cache.as_deref()) {
+            let assign = ops::parse_assignment(&assign).unwrap_or_else(|e| die(&e));
+            let scale = ops::parse_scale(&scale).unwrap_or_else(|e| die(&e));
+            let options = ops::ClassifyOptions {
+                depth,
+                branching,
+                auto_k,
+                assign,
+                multi_label,
+                scale,
+                ..Default::default()
+            };
+            let result = match model {
+                Some(path) => ops::op_classify_with_model(&rows, &field, &path, threshold, &options)
+                    .map(|rows| ops::ClassifyOutput { rows, k_selection: None }),
+                None => ops::op_classify_from_file_cached(&rows, &field, tax.as_deref(), clusters, sample, threshold, seed, &options, cache.as_deref()),
+            };
+            match result {
+                Ok(result) => {
+                    if let Some(selection) = &result.k_selection {
+                        report_k_selection(selection);
+                    }
+                    print_json(&result.rows)
+                }
+                Err(e) => die(&e),
+            }
+        }
+        Commands::Train {
+            field,
+            clusters,
+            sample,
+            seed,
+            depth,
+            branching,
+            auto_k,
+            output,
+        } => {
+            let rows = read_stdin_json();
+            let options = ops::ClassifyOptions { depth, branching, auto_k, ..Default::default() };
+            match ops::op_train(&rows, &field, clusters, sample, seed, &options, output.as_deref()) {
                 Ok(result) => print_json(&result),
                 Err(e) => die(&e),
             }
@@ -281,10 +560,51 @@ fn main() {
             url_field,
             strategy,
             threshold,
+            jaccard,
+            shingle,
+            recall,
+            bands,
+            primary,
+            against,
+            id_field,
+            explain,
             cache,
         } => {
             let rows = read_stdin_json();
-            print_json(&ops::op_dedup_cached(&rows, &field, &url_field, &strategy, threshold, cache.as_deref()));
+            let options = ops::DedupOptions {
+                jaccard,
+                shingle_size: shingle,
+                explain,
+                primary: ops::parse_primary(&primary).unwrap_or_else(|e| die(&e)),
+                recall,
+                bands,
+                id_field,
+            };
+            if !explain {
+                report_banding(&ops::dedup_banding(&strategy, threshold, &options));
+            }
+            match against {
+                Some(path) => {
+                    let reference = ops::load_records(&path).unwrap_or_else(|e| die(&e));
+                    print_json(&ops::op_dedup_against_cached(&rows, &reference, &field, &url_field, &strategy, threshold, &options, cache.as_deref()));
+                }
+                None => print_json(&ops::op_dedup_cached(&rows, &field, &url_field, &strategy, threshold, &options, cache.as_deref())),
+            }
+        }
+        Commands::MergeDuplicates {
+            rules,
+            group_field,
+            id_field,
+            separator,
+        } => {
+            let rows = read_stdin_json();
+            let options = ops::MergeOptions {
+                group_field,
+                id_field,
+                rules: ops::parse_merge_rules(&rules).unwrap_or_else(|e| die(&e)),
+                separator,
+            };
+            print_json(&ops::op_merge_duplicates(&rows, &options));
         }
         Commands::Similarity { a, b, metric, all } => {
             match ops::op_similarity(&a, &b, &metric, all) {
@@ -301,12 +621,17 @@ fn main() {
         Commands::Generate {
             field,
             depth,
+            auto_k,
+            cut_distance,
+            dendrogram,
+            id_field,
             linkage,
             top_terms,
             cache,
         } => {
             let rows = read_stdin_json();
-            match ops::op_generate_cached(&rows, &field, depth, &linkage, top_terms, cache.as_deref()) {
+            let options = ops::GenerateOptions { auto_k, cut_distance, dendrogram, id_field };
+            match ops::op_generate_cached(&rows, &field, depth, &linkage, top_terms, &options, cache.as_deref()) {
                 Ok(result) => print_json(&result),
                 Err(e) => die(&e),
             }
@@ -315,11 +640,36 @@ fn main() {
             field,
             topics,
             terms,
+            mixture,
+            sweep,
+            model,
             iterations,
             vocab,
+            tol,
+            init,
+            l1,
+            l2,
+            alpha,
+            beta,
+            seed,
         } => {
             let rows = read_stdin_json();
-            match ops::op_topics(&rows, &field, topics, terms, iterations, vocab) {
+            let options = ops::TopicsOptions {
+                mixture,
+                model: ops::parse_topic_model(&model).unwrap_or_else(|e| die(&e)),
+                tol,
+                init: ops::parse_nmf_init(&init).unwrap_or_else(|e| die(&e)),
+                l1,
+                l2,
+                alpha,
+                beta,
+                seed,
+            };
+            let result = match sweep {
+                Some(ks) => ops::op_topics_sweep(&rows, &field, &ks, terms, iterations, vocab, &options),
+                None => ops::op_topics_with_options(&rows, &field, topics, terms, iterations, vocab, &options),
+            };
+            match result {
                 Ok(result) => print_json(&result),
                 Err(e) => die(&e),
             }
@@ -333,6 +683,99 @@ fn main() {
             let rows = read_stdin_json();
             print_json(&ops::op_organize(&rows, &format, &output_dir, &category_field, &name_field));
         }
+        Commands::Neighbors {
+            field,
+            top_k,
+            metric,
+            min_score,
+            id_field,
+            shingle,
+            recall,
+        } => {
+            let rows = read_stdin_json();
+            let options = ops::NeighborsOptions {
+                k: top_k,
+                metric: ops::parse_neighbor_metric(&metric).unwrap_or_else(|e| die(&e)),
+                min_score,
+                id_field,
+                shingle_size: shingle,
+                recall,
+            };
+            print_json(&ops::op_neighbors(&rows, &field, &options));
+        }
+        Commands::Graph {
+            kind,
+            format,
+            field,
+            label_field,
+            id_field,
+            group_field,
+            taxonomy,
+            linkage,
+            top_k,
+            metric,
+            min_score,
+        } => {
+            let rows = read_stdin_json();
+            let options = ops::GraphOptions {
+                kind: ops::parse_graph_kind(&kind).unwrap_or_else(|e| die(&e)),
+                format: ops::parse_graph_format(&format).unwrap_or_else(|e| die(&e)),
+                label_field,
+                id_field,
+                group_field,
+                linkage: ops::parse_linkage(&linkage).unwrap_or_else(|e| die(&e)),
+                taxonomy_path: taxonomy,
+                neighbors: ops::NeighborsOptions {
+                    k: top_k,
+                    metric: ops::parse_neighbor_metric(&metric).unwrap_or_else(|e| die(&e)),
+                    min_score,
+                    ..Default::default()
+                },
+            };
+            // Raw document rather than JSON, so it pipes straight into `dot`.
+            match ops::op_graph(&rows, &field, &options) {
+                Ok(doc) => print!("{doc}"),
+                Err(e) => die(&e),
+            }
+        }
+        Commands::Index {
+            path,
+            field,
+            url_field,
+            id_field,
+            remove,
+        } => {
+            let result = match remove {
+                Some(ids) => ops::op_index_remove(&ids, &path),
+                None => ops::op_index_add(&read_stdin_json(), &field, &url_field, &id_field, &path),
+            };
+            match result {
+                Ok(r) => print_json(&r),
+                Err(e) => die(&e),
+            }
+        }
+        Commands::Lookup {
+            path,
+            text,
+            url,
+            fingerprint,
+            signature,
+            threshold,
+            jaccard,
+            limit,
+        } => {
+            let query = ops::LookupQuery {
+                text: text.unwrap_or_default(),
+                url,
+                fingerprint: fingerprint.map(|f| ops::parse_fingerprint(&f).unwrap_or_else(|e| die(&e))),
+                signature: signature.map(|s| ops::parse_signature(&s).unwrap_or_else(|e| die(&e))),
+            };
+            let options = ops::LookupOptions { threshold, jaccard, limit };
+            match ops::op_lookup(&query, &options, &path) {
+                Ok(r) => print_json(&r),
+                Err(e) => die(&e),
+            }
+        }
         Commands::Cache { path, clear, kind } => {
             let result = if clear {
                 ops::op_cache_clear(&path, kind.as_deref())
@@ -363,6 +806,30 @@ fn print_json(value: &Value) {
     println!("{}", serde_json::to_string_pretty(value).unwrap());
 }
 
+/// Print the silhouette score of every k `--auto-k` tried to stderr, so
+/// stdout stays plain JSON.
+fn report_k_selection(selection: &KSelection) {
+    eprintln!("auto-k chose {} categories", selection.k);
+    for score in &selection.scores {
+        eprintln!(
+            "  k={}: silhouette {:.4}, merge distance {:.4}",
+            score.k, score.silhouette, score.merge_distance
+        );
+    }
+}
+
+/// Print the LSH layout of a dedup run to stderr, one line per method, so
+/// stdout stays plain JSON.
+fn report_banding(banding: &Value) {
+    let Some(methods) = banding.as_object() else { return };
+    for (method, layout) in methods {
+        eprintln!(
+            "{method} banding: {} bands x {} rows, false-negative rate {:.4} at the threshold",
+            layout["bands"], layout["rows"], layout["false_negative_rate"].as_f64().unwrap_or(0.0)
+        );
+    }
+}
+
 fn die(msg: &str) -> ! {
     eprintln!("{msg}");
     std::process::exit(1);
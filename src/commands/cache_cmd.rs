@@ -1,11 +1,11 @@
 use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
-use nu_protocol::{
-    Category, Example, LabeledError, PipelineData, Record, Signature, SyntaxShape, Type, Value,
-};
+use nu_protocol::{Category, Example, LabeledError, PipelineData, Signature, SyntaxShape, Type};
 
 use crate::ops;
 use crate::TopologyPlugin;
 
+use super::util;
+
 pub struct CacheCmd;
 
 impl PluginCommand for CacheCmd {
@@ -85,38 +85,10 @@ impl PluginCommand for CacheCmd {
 
         match result {
             Ok(json_val) => {
-                let nu_val = json_to_nu(&json_val, head);
+                let nu_val = util::json_to_value(&json_val, head);
                 Ok(PipelineData::Value(nu_val, None))
             }
             Err(e) => Err(LabeledError::new(e)),
         }
     }
 }
-
-/// Convert a serde_json::Value to a nu_protocol::Value
-fn json_to_nu(val: &serde_json::Value, span: nu_protocol::Span) -> Value {
-    match val {
-        serde_json::Value::Null => Value::nothing(span),
-        serde_json::Value::Bool(b) => Value::bool(*b, span),
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Value::int(i, span)
-            } else if let Some(f) = n.as_f64() {
-                Value::float(f, span)
-            } else {
-                Value::string(n.to_string(), span)
-            }
-        }
-        serde_json::Value::String(s) => Value::string(s, span),
-        serde_json::Value::Array(arr) => {
-            Value::list(arr.iter().map(|v| json_to_nu(v, span)).collect(), span)
-        }
-        serde_json::Value::Object(map) => {
-            let mut record = Record::new();
-            for (k, v) in map {
-                record.push(k, json_to_nu(v, span));
-            }
-            Value::record(record, span)
-        }
-    }
-}
@@ -4,13 +4,22 @@ use nu_protocol::{
     Type, Value,
 };
 
-use crate::algo::{clustering, discover, taxonomy};
-#[cfg(feature = "cache")]
-use crate::algo::{cache, storage};
+use crate::algo::clustering;
+use crate::ops;
 use crate::TopologyPlugin;
 
 use super::util;
 
+/// Every column classify may add, depending on `--assign` and `--multi-label`.
+const CLASSIFY_COLUMNS: &[&str] = &[
+    "_category",
+    "_hierarchy",
+    "_confidence",
+    "_runner_up",
+    "_margin",
+    "_categories",
+];
+
 pub struct Classify;
 
 impl PluginCommand for Classify {
@@ -45,22 +54,62 @@ impl PluginCommand for Classify {
                 "Path to taxonomy JSON file. If omitted, taxonomy is discovered from the data",
                 Some('t'),
             )
+            .named(
+                "model",
+                SyntaxShape::String,
+                "Path to a model file from `topology train`; classifies against it instead of discovering",
+                Some('m'),
+            )
             .named(
                 "clusters",
                 SyntaxShape::Int,
                 "Number of categories to discover (default: 15)",
                 Some('k'),
             )
+            .switch(
+                "auto-k",
+                "Choose the number of categories by silhouette score, trying 2 up to --clusters; the scores are in the topology_k_selection metadata",
+                None,
+            )
             .named(
                 "sample",
                 SyntaxShape::Int,
-                "Max items to sample for discovery (HAC is O(n^2), default: 500)",
+                "Max items to cluster for discovery; HAC memory grows with its square, about 16 MB at the default 2000. Every item is still classified",
                 None,
             )
             .named(
                 "threshold",
                 SyntaxShape::Float,
-                "Minimum BM25 score to assign a category (default: 0.5)",
+                "Minimum confidence, on the --scale scale, to assign a category (default: 0.5)",
+                None,
+            )
+            .named(
+                "depth",
+                SyntaxShape::Int,
+                "Taxonomy levels to discover; 1 keeps categories flat (default: 1)",
+                Some('d'),
+            )
+            .named(
+                "branching",
+                SyntaxShape::Int,
+                "Growth in cluster count per level below the top (default: 3)",
+                None,
+            )
+            .named(
+                "assign",
+                SyntaxShape::String,
+                "How items are matched to discovered categories: bm25 (keywords) or centroid (nearest TF-IDF centroid, adds _runner_up and _margin). Default: bm25",
+                None,
+            )
+            .named(
+                "scale",
+                SyntaxShape::String,
+                "Scale of _confidence and --threshold: raw (BM25 score, or cosine similarity with --assign centroid), softmax (probability over categories), margin (lead over the runner-up). Default: raw",
+                None,
+            )
+            .switch(
+                "multi-label",
+                "Also list every category above the threshold in _categories, with score, path and probability",
                 None,
             )
             .named(
@@ -100,6 +149,31 @@ impl PluginCommand for Classify {
                 description: "Classify a list of strings",
                 result: None,
             },
+            Example {
+                example: r#"open articles.json | topology classify --clusters 5 --depth 2"#,
+                description: "Discover a two-level taxonomy; `_hierarchy` holds paths like `Parent > Child`",
+                result: None,
+            },
+            Example {
+                example: r#"open articles.json | topology classify --clusters 20 --auto-k"#,
+                description: "Let silhouette scores pick between 2 and 20 categories",
+                result: None,
+            },
+            Example {
+                example: r#"open bookmarks.json | topology classify --clusters 12 --assign centroid | where _margin < 0.05"#,
+                description: "Assign every item to its nearest cluster centroid and find the borderline ones",
+                result: None,
+            },
+            Example {
+                example: r#"open stars.json | topology classify --multi-label | select name _categories"#,
+                description: "List every category each item matches, e.g. both Rust and CLI tools",
+                result: None,
+            },
+            Example {
+                example: r#"open new-items.json | topology classify --model topics.model.json"#,
+                description: "Classify new items against a previously trained model",
+                result: None,
+            },
         ]
     }
 
@@ -114,13 +188,24 @@ impl PluginCommand for Classify {
             .get_flag::<String>("field")?
             .unwrap_or_else(|| "content".into());
         let taxonomy_path: Option<String> = call.get_flag("taxonomy")?;
+        let model_path: Option<String> = call.get_flag("model")?;
         let k: usize = call.get_flag::<i64>("clusters")?.unwrap_or(15) as usize;
-        let sample_size: usize = call.get_flag::<i64>("sample")?.unwrap_or(500) as usize;
+        let sample_size: usize = call.get_flag::<i64>("sample")?.unwrap_or(2000) as usize;
         let threshold: f64 = call.get_flag::<f64>("threshold")?.unwrap_or(0.5);
         let linkage_str: String = call
             .get_flag::<String>("linkage")?
             .unwrap_or_else(|| "ward".into());
         let seed: u64 = call.get_flag::<i64>("seed")?.unwrap_or(42) as u64;
+        let depth: usize = call.get_flag::<i64>("depth")?.unwrap_or(1).max(1) as usize;
+        let branching: usize = call.get_flag::<i64>("branching")?.unwrap_or(3).max(1) as usize;
+        let auto_k: bool = call.has_flag("auto-k")?;
+        let multi_label: bool = call.has_flag("multi-label")?;
+        let scale_str: String = call
+            .get_flag::<String>("scale")?
+            .unwrap_or_else(|| "raw".into());
+        let assign_str: String = call
+            .get_flag::<String>("assign")?
+            .unwrap_or_else(|| "bm25".into());
         let cache_path: Option<String> = call.get_flag("cache")?;
         let head = call.head;
 
@@ -129,95 +214,44 @@ impl PluginCommand for Classify {
                 "Unknown linkage '{linkage_str}'. Use: ward, complete, average, single"
             ))
         })?;
+        let options = ops::ClassifyOptions {
+            depth,
+            branching,
+            auto_k,
+            assign: ops::parse_assignment(&assign_str).map_err(LabeledError::new)?,
+            multi_label,
+            scale: ops::parse_scale(&scale_str).map_err(LabeledError::new)?,
+            linkage,
+        };
 
         let rows = util::normalize_input(input, head);
         if rows.is_empty() {
             return Ok(PipelineData::Value(Value::list(vec![], head), None));
         }
 
-        let texts: Vec<String> = rows
-            .iter()
-            .map(|row| {
-                row.get_data_by_key(&field)
-                    .and_then(|v| v.coerce_string().ok())
-                    .unwrap_or_default()
-            })
-            .collect();
-
-        let tax = match taxonomy_path {
-            Some(path) => taxonomy::load_taxonomy(&path)
-                .map_err(|e| LabeledError::new(e))?,
-            None => {
-                let config = discover::DiscoverConfig {
-                    k,
-                    sample_size,
-                    label_terms: 3,
-                    keywords_per_cluster: 20,
-                    linkage,
-                    seed,
-                };
-                resolve_taxonomy_cached(&texts, &config, cache_path.as_deref())
-            }
-        };
-
-        let classifications = discover::classify_against_taxonomy(&texts, &tax, threshold);
-
-        let results: Vec<Value> = rows
-            .into_iter()
-            .zip(classifications)
-            .map(|(row, (category, hierarchy, confidence))| {
-                util::append_columns(
-                    row,
-                    &[
-                        ("_category", Value::string(&category, head)),
-                        ("_hierarchy", Value::string(&hierarchy, head)),
-                        ("_confidence", Value::float(confidence, head)),
-                    ],
-                    head,
-                )
-            })
-            .collect();
-
-        Ok(ListStream::new(results.into_iter(), head, Signals::empty()).into())
-    }
-}
-
-fn resolve_taxonomy_cached(
-    texts: &[String],
-    config: &discover::DiscoverConfig,
-    #[allow(unused)] cache_path: Option<&str>,
-) -> taxonomy::Taxonomy {
-    #[cfg(feature = "cache")]
-    if let Some(cp) = cache_path {
-        if let Ok(db) = storage::CacheDb::open_or_create(cp) {
-            let c_hash = cache::content_hash(texts);
-            let args = serde_json::json!({
-                "k": config.k,
-                "sample_size": config.sample_size,
-                "seed": config.seed,
-            });
-            let a_hash = cache::args_hash(&args);
-
-            if let Ok(Some((meta, payload))) =
-                db.get(cache::ArtifactKind::Taxonomy, c_hash, a_hash)
-            {
-                if cache::is_valid(&meta, c_hash, a_hash) {
-                    if let Ok(tax) =
-                        serde_json::from_slice::<taxonomy::Taxonomy>(&payload)
-                    {
-                        return tax;
-                    }
-                }
-            }
-
-            let tax = discover::discover_taxonomy(texts, config);
-            if let Ok(payload) = serde_json::to_vec(&tax) {
-                let meta = cache::CacheMeta::new(c_hash, texts.len(), a_hash);
-                let _ = db.put(cache::ArtifactKind::Taxonomy, &meta, &payload);
-            }
-            return tax;
+        let json_rows = util::rows_to_json(&rows);
+        let output = match model_path {
+            Some(path) => ops::op_classify_with_model(&json_rows, &field, &path, threshold, &options)
+                .map(|rows| ops::ClassifyOutput { rows, k_selection: None }),
+            None => ops::op_classify_from_file_cached(
+                &json_rows,
+                &field,
+                taxonomy_path.as_deref(),
+                k,
+                sample_size,
+                threshold,
+                seed,
+                &options,
+                cache_path.as_deref(),
+            ),
         }
-    }
+        .map_err(LabeledError::new)?;
 
-    discover::discover_taxonomy(texts, config)
+        let results = util::merge_columns(rows, &output.rows, CLASSIFY_COLUMNS, head);
+        let metadata = output.k_selection.map(|selection| {
+            util::custom_metadata("topology_k_selection", util::json_to_value(&serde_json::json!(selection), head))
+        });
+        let stream = ListStream::new(results.into_iter(), head, Signals::empty());
+        Ok(PipelineData::list_stream(stream, metadata))
+    }
 }
@@ -3,14 +3,23 @@ use nu_protocol::{
     Category, Example, LabeledError, ListStream, PipelineData, Record, Signature, Signals,
     SyntaxShape, Type, Value,
 };
-use rayon::prelude::*;
-use std::collections::{HashMap, HashSet};
 
-use crate::algo::{lsh, simhash, tokenizer, url_normalize};
+use crate::ops;
 use crate::TopologyPlugin;
 
 use super::util;
 
+/// Columns added to each row by `--explain`.
+const EXPLAIN_COLUMNS: &[&str] = &[
+    "_dup_group",
+    "_is_primary",
+    "_dup_reason",
+    "_dup_partner",
+    "_dup_distance",
+    "_dup_jaccard",
+    "_canonical_key",
+];
+
 pub struct Dedup;
 
 impl PluginCommand for Dedup {
@@ -21,7 +30,7 @@ impl PluginCommand for Dedup {
     }
 
     fn description(&self) -> &str {
-        "Find duplicate and near-duplicate items using LSH + SimHash + URL normalization"
+        "Find duplicate and near-duplicate items using LSH + SimHash/MinHash + URL normalization"
     }
 
     fn signature(&self) -> Signature {
@@ -29,6 +38,7 @@ impl PluginCommand for Dedup {
             .input_output_types(vec![
                 (Type::table(), Type::table()),
                 (Type::list(Type::Any), Type::list(Type::Any)),
+                (Type::table(), Type::record()),
                 (Type::Any, Type::Any),
             ])
             .named(
@@ -46,7 +56,7 @@ impl PluginCommand for Dedup {
             .named(
                 "strategy",
                 SyntaxShape::String,
-                "Strategy: url, fuzzy, combined (default: combined)",
+                "Strategy: url, fuzzy, minhash, combined, combined-minhash (default: combined)",
                 Some('s'),
             )
             .named(
@@ -55,6 +65,53 @@ impl PluginCommand for Dedup {
                 "SimHash hamming distance threshold (default: 3)",
                 None,
             )
+            .named(
+                "jaccard",
+                SyntaxShape::Float,
+                "Minimum Jaccard similarity for minhash strategies (default: 0.5)",
+                None,
+            )
+            .named(
+                "shingle",
+                SyntaxShape::Int,
+                "Character shingle size for minhash strategies (default: 3)",
+                None,
+            )
+            .named(
+                "recall",
+                SyntaxShape::Float,
+                "Target LSH recall at the threshold; bands and rows are derived from it (default: 0.95)",
+                None,
+            )
+            .named(
+                "bands",
+                SyntaxShape::Int,
+                "Fixed number of LSH bands instead of deriving them from --recall. The layout used and its false-negative rate are in the topology_banding metadata",
+                None,
+            )
+            .named(
+                "primary",
+                SyntaxShape::String,
+                "Keeper of each group: first, oldest[:field], newest[:field] (default field: date_added), longest, most-starred[:field] (default field: stars), shortest-url (default: first)",
+                Some('p'),
+            )
+            .named(
+                "against",
+                SyntaxShape::String,
+                "Path to a JSON array of existing records: report _matches_existing per input row instead of grouping",
+                None,
+            )
+            .named(
+                "id-field",
+                SyntaxShape::String,
+                "Field reported as the row id in --explain partners and groups, and as the match id with --against (default: id)",
+                None,
+            )
+            .switch(
+                "explain",
+                "Return {rows, groups, banding}: rows gain _dup_reason, _dup_partner, _dup_distance, _dup_jaccard and _canonical_key; groups summarizes each duplicate group; banding reports the LSH layout and its expected false-negative rate",
+                None,
+            )
             .named(
                 "cache",
                 SyntaxShape::String,
@@ -66,7 +123,8 @@ impl PluginCommand for Dedup {
 
     fn search_terms(&self) -> Vec<&str> {
         vec![
-            "dedup", "duplicate", "deduplicate", "near-duplicate", "lsh", "simhash",
+            "dedup", "duplicate", "deduplicate", "near-duplicate", "lsh", "simhash", "minhash",
+            "jaccard",
         ]
     }
 
@@ -82,6 +140,31 @@ impl PluginCommand for Dedup {
                 description: "Find duplicates in a list of strings",
                 result: None,
             },
+            Example {
+                example: r#"open bookmarks.json | topology dedup --primary oldest:date_added | where _is_primary"#,
+                description: "Keep the earliest-saved bookmark of each duplicate group",
+                result: None,
+            },
+            Example {
+                example: r#"open new-stars.json | topology dedup --against curated.json | where ($it._matches_existing | is-empty)"#,
+                description: "Keep only incoming items that are not already in the curated collection",
+                result: None,
+            },
+            Example {
+                example: r#"open bookmarks.json | topology dedup --explain | get groups | sort-by size --reverse"#,
+                description: "Audit duplicate groups: size, match reasons and the largest SimHash distance",
+                result: None,
+            },
+            Example {
+                example: r#"open bookmarks.json | topology dedup | metadata | get topology_banding"#,
+                description: "Show the LSH bands, rows and expected false-negative rate the run used",
+                result: None,
+            },
+            Example {
+                example: r#"[[title]; ["Rust async book"] ["The Rust Async Book"] ["Gardening tips"]] | topology dedup --field title --strategy minhash --jaccard 0.4"#,
+                description: "Find near-duplicate titles by Jaccard similarity over character shingles",
+                result: None,
+            },
         ]
     }
 
@@ -102,7 +185,19 @@ impl PluginCommand for Dedup {
             .get_flag::<String>("strategy")?
             .unwrap_or_else(|| "combined".into());
         let threshold: u32 = call.get_flag::<i64>("threshold")?.unwrap_or(3) as u32;
-        let _cache_path: Option<String> = call.get_flag("cache")?;
+        let jaccard: f64 = call.get_flag::<f64>("jaccard")?.unwrap_or(0.5);
+        let shingle: usize = call.get_flag::<i64>("shingle")?.unwrap_or(3) as usize;
+        let recall: f64 = call.get_flag::<f64>("recall")?.unwrap_or(0.95);
+        let bands: Option<usize> = call.get_flag::<i64>("bands")?.map(|v| v.max(1) as usize);
+        let primary_str: String = call
+            .get_flag::<String>("primary")?
+            .unwrap_or_else(|| "first".into());
+        let against: Option<String> = call.get_flag("against")?;
+        let id_field: String = call
+            .get_flag::<String>("id-field")?
+            .unwrap_or_else(|| "id".into());
+        let explain: bool = call.has_flag("explain")?;
+        let cache_path: Option<String> = call.get_flag("cache")?;
         let head = call.head;
 
         let rows = util::normalize_input(input, head);
@@ -110,125 +205,54 @@ impl PluginCommand for Dedup {
             return Ok(PipelineData::Value(Value::list(vec![], head), None));
         }
 
-        let n = rows.len();
-
-        // Phase 1: URL-based exact dedup
-        let mut url_groups: HashMap<String, Vec<usize>> = HashMap::new();
-        if strategy_str == "url" || strategy_str == "combined" {
-            for (i, row) in rows.iter().enumerate() {
-                if let Some(url_val) = row.get_data_by_key(&url_field) {
-                    if let Ok(url_str) = url_val.coerce_string() {
-                        if let Some(key) = url_normalize::canonical_key(&url_str) {
-                            url_groups.entry(key).or_default().push(i);
-                        }
-                    }
-                }
-            }
-        }
-
-        // Phase 2: Content-based fuzzy dedup with SimHash + LSH
-        let mut content_pairs: HashSet<(usize, usize)> = HashSet::new();
-        if strategy_str == "fuzzy" || strategy_str == "combined" {
-            let texts: Vec<String> = rows
-                .iter()
-                .map(|row| {
-                    row.get_data_by_key(&field)
-                        .and_then(|v| v.coerce_string().ok())
-                        .unwrap_or_default()
-                })
-                .collect();
-
-            let token_lists: Vec<Vec<String>> =
-                texts.par_iter().map(|t| tokenizer::tokenize(t)).collect();
-
-            let fingerprints: Vec<u64> = token_lists
-                .par_iter()
-                .map(|tokens| simhash::simhash_uniform(tokens))
-                .collect();
-
-            let mut lsh_index = lsh::SimHashLshIndex::default_64();
-            for (i, &fp) in fingerprints.iter().enumerate() {
-                lsh_index.insert(i, fp);
-            }
-
-            for (i, j) in lsh_index.candidate_pairs() {
-                if simhash::hamming_distance(fingerprints[i], fingerprints[j]) <= threshold {
-                    content_pairs.insert((i, j));
-                }
-            }
-        }
-
-        // Union-find
-        let mut parent: Vec<usize> = (0..n).collect();
-
-        let find = |parent: &mut Vec<usize>, mut x: usize| -> usize {
-            while parent[x] != x {
-                parent[x] = parent[parent[x]];
-                x = parent[x];
-            }
-            x
+        let options = ops::DedupOptions {
+            jaccard,
+            shingle_size: shingle,
+            explain,
+            primary: ops::parse_primary(&primary_str).map_err(LabeledError::new)?,
+            recall,
+            bands,
+            id_field,
         };
-
-        let union = |parent: &mut Vec<usize>, a: usize, b: usize| {
-            let ra = {
-                let mut x = a;
-                while parent[x] != x { parent[x] = parent[parent[x]]; x = parent[x]; }
-                x
-            };
-            let rb = {
-                let mut x = b;
-                while parent[x] != x { parent[x] = parent[parent[x]]; x = parent[x]; }
-                x
-            };
-            if ra != rb { parent[rb] = ra; }
-        };
-
-        for members in url_groups.values() {
-            for i in 1..members.len() {
-                union(&mut parent, members[0], members[i]);
-            }
-        }
-        for &(i, j) in &content_pairs {
-            union(&mut parent, i, j);
+        let banding = util::json_to_value(&ops::dedup_banding(&strategy_str, threshold, &options), head);
+        let metadata = util::custom_metadata("topology_banding", banding);
+        if let Some(path) = against {
+            let reference = ops::load_records(&path).map_err(LabeledError::new)?;
+            let output = ops::op_dedup_against_cached(
+                &util::rows_to_json(&rows),
+                &reference,
+                &field,
+                &url_field,
+                &strategy_str,
+                threshold,
+                &options,
+                cache_path.as_deref(),
+            );
+            let results = util::merge_columns(rows, &output, &["_matches_existing"], head);
+            let stream = ListStream::new(results.into_iter(), head, Signals::empty());
+            return Ok(PipelineData::list_stream(stream, metadata));
         }
 
-        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
-        for i in 0..n {
-            let root = find(&mut parent, i);
-            groups.entry(root).or_default().push(i);
+        let output = ops::op_dedup_cached(
+            &util::rows_to_json(&rows),
+            &field,
+            &url_field,
+            &strategy_str,
+            threshold,
+            &options,
+            cache_path.as_deref(),
+        );
+        if explain {
+            let rows = util::merge_columns(rows, &output["rows"], EXPLAIN_COLUMNS, head);
+            let mut record = Record::new();
+            record.push("rows", Value::list(rows, head));
+            record.push("groups", util::json_to_value(&output["groups"], head));
+            record.push("banding", util::json_to_value(&output["banding"], head));
+            return Ok(PipelineData::Value(Value::record(record, head), None));
         }
+        let results = util::merge_columns(rows, &output, &["_dup_group", "_is_primary"], head);
+        let stream = ListStream::new(results.into_iter(), head, Signals::empty());
 
-        let mut group_ids = vec![0usize; n];
-        let mut is_primary = vec![true; n];
-        let mut group_counter = 0;
-
-        for members in groups.values() {
-            let gid = group_counter;
-            group_counter += 1;
-            for (idx, &member) in members.iter().enumerate() {
-                group_ids[member] = gid;
-                is_primary[member] = idx == 0;
-            }
-        }
-
-        let results: Vec<Value> = rows
-            .into_iter()
-            .enumerate()
-            .map(|(i, row)| {
-                let mut record = match row {
-                    Value::Record { val, .. } => val.into_owned(),
-                    other => {
-                        let mut r = Record::new();
-                        r.push("value", other);
-                        r
-                    }
-                };
-                record.push("_dup_group", Value::int(group_ids[i] as i64, head));
-                record.push("_is_primary", Value::bool(is_primary[i], head));
-                Value::record(record, head)
-            })
-            .collect();
-
-        Ok(ListStream::new(results.into_iter(), head, Signals::empty()).into())
+        Ok(PipelineData::list_stream(stream, metadata))
     }
 }
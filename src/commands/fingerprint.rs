@@ -3,13 +3,7 @@ use nu_protocol::{
     Category, Example, LabeledError, ListStream, PipelineData, Signature, Signals, SyntaxShape,
     Type, Value,
 };
-use rayon::prelude::*;
-
-use crate::algo::{
-    simhash::{fingerprint_to_hex, simhash, simhash_uniform},
-    tfidf::Corpus,
-    tokenizer,
-};
+use crate::ops;
 use crate::TopologyPlugin;
 
 use super::util;
@@ -99,7 +93,7 @@ impl PluginCommand for Fingerprint {
             .get_flag::<String>("field")?
             .unwrap_or_else(|| "content".into());
         let weighted: bool = call.has_flag("weighted")?;
-        let _cache_path: Option<String> = call.get_flag("cache")?;
+        let cache_path: Option<String> = call.get_flag("cache")?;
         let head = call.head;
 
         let rows = util::normalize_input(input, head);
@@ -108,45 +102,13 @@ impl PluginCommand for Fingerprint {
             return Ok(PipelineData::Value(Value::list(vec![], head), None));
         }
 
-        let texts: Vec<String> = rows
-            .iter()
-            .map(|row| {
-                row.get_data_by_key(&field)
-                    .and_then(|v| v.coerce_string().ok())
-                    .unwrap_or_default()
-            })
-            .collect();
-
-        let token_lists: Vec<Vec<String>> =
-            texts.par_iter().map(|t| tokenizer::tokenize(t)).collect();
-
-        let fingerprints: Vec<u64> = if weighted {
-            let mut corpus = Corpus::new();
-            for tokens in &token_lists {
-                corpus.add_document(tokens);
-            }
-            token_lists
-                .par_iter()
-                .map(|tokens| {
-                    let weights = corpus.token_weights(tokens);
-                    simhash(tokens, &weights)
-                })
-                .collect()
-        } else {
-            token_lists
-                .par_iter()
-                .map(|tokens| simhash_uniform(tokens))
-                .collect()
-        };
-
-        let result: Vec<Value> = rows
-            .into_iter()
-            .zip(fingerprints)
-            .map(|(row, fp)| {
-                let hex = fingerprint_to_hex(fp);
-                util::append_column(row, "_fingerprint", Value::string(hex, head), head)
-            })
-            .collect();
+        let output = ops::op_fingerprint_cached(
+            &util::rows_to_json(&rows),
+            &field,
+            weighted,
+            cache_path.as_deref(),
+        );
+        let result = util::merge_columns(rows, &output, &["_fingerprint"], head);
 
         Ok(ListStream::new(result.into_iter(), head, Signals::empty()).into())
     }
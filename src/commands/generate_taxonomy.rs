@@ -1,9 +1,7 @@
 use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
-use nu_protocol::{
-    Category, Example, LabeledError, PipelineData, Record, Signature, SyntaxShape, Type, Value,
-};
+use nu_protocol::{Category, Example, LabeledError, PipelineData, Signature, SyntaxShape, Type};
 
-use crate::algo::{clustering, tfidf, tokenizer};
+use crate::ops;
 use crate::TopologyPlugin;
 
 use super::util;
@@ -40,6 +38,28 @@ impl PluginCommand for GenerateTaxonomy {
                 "Number of clusters / taxonomy depth (default: 10)",
                 Some('k'),
             )
+            .switch(
+                "auto-k",
+                "Choose the number of clusters by silhouette score, trying 2 up to --depth",
+                None,
+            )
+            .named(
+                "cut-distance",
+                SyntaxShape::Float,
+                "Cut where merges exceed this cosine distance instead of at --depth clusters",
+                Some('d'),
+            )
+            .switch(
+                "dendrogram",
+                "Include the dendrogram merges and a Newick rendering as `dendrogram`",
+                None,
+            )
+            .named(
+                "id-field",
+                SyntaxShape::String,
+                "Field labelling Newick leaves; row indices when missing (default: id)",
+                None,
+            )
             .named(
                 "linkage",
                 SyntaxShape::String,
@@ -72,6 +92,21 @@ impl PluginCommand for GenerateTaxonomy {
                 description: "Generate 2-cluster taxonomy from a list of strings",
                 result: None,
             },
+            Example {
+                example: r#"open articles.json | topology generate --depth 20 --auto-k | get k_selection"#,
+                description: "Pick the cluster count automatically and show the silhouette per k",
+                result: None,
+            },
+            Example {
+                example: r#"open articles.json | topology generate --cut-distance 0.6 --cache topo.db"#,
+                description: "Cut the cached dendrogram at a distance; later cuts reuse it without re-clustering",
+                result: None,
+            },
+            Example {
+                example: r#"open articles.json | topology generate --dendrogram | get dendrogram.newick | save tree.nwk"#,
+                description: "Export the full merge tree in Newick format",
+                result: None,
+            },
         ]
     }
 
@@ -90,116 +125,29 @@ impl PluginCommand for GenerateTaxonomy {
             .get_flag::<String>("linkage")?
             .unwrap_or_else(|| "ward".into());
         let top_n: usize = call.get_flag::<i64>("top-terms")?.unwrap_or(5) as usize;
-        let _cache_path: Option<String> = call.get_flag("cache")?;
+        let auto_k: bool = call.has_flag("auto-k")?;
+        let cache_path: Option<String> = call.get_flag("cache")?;
         let head = call.head;
 
-        let linkage = clustering::Linkage::from_str(&linkage_str).ok_or_else(|| {
-            LabeledError::new(format!(
-                "Unknown linkage '{linkage_str}'. Use: ward, complete, average, single"
-            ))
-        })?;
-
         let rows = util::normalize_input(input, head);
-        let n = rows.len();
-
-        if n < 2 {
-            return Err(LabeledError::new(
-                "Need at least 2 items to generate a taxonomy",
-            ));
-        }
-
-        let texts: Vec<String> = rows
-            .iter()
-            .map(|row| {
-                row.get_data_by_key(&field)
-                    .and_then(|v| v.coerce_string().ok())
-                    .unwrap_or_default()
-            })
-            .collect();
-
-        let token_lists: Vec<Vec<String>> = texts.iter().map(|t| tokenizer::tokenize(t)).collect();
-
-        let mut corpus = tfidf::Corpus::new();
-        for tokens in &token_lists {
-            corpus.add_document(tokens);
-        }
-
-        let vectors: Vec<std::collections::HashMap<String, f64>> = (0..n)
-            .map(|i| corpus.tfidf_vector(i))
-            .collect();
-
-        let distances = clustering::cosine_distance_matrix(&vectors);
-        let k = k.min(n);
-        let dendrogram = clustering::hac(&distances, n, linkage);
-        let labels = clustering::cut_tree(&dendrogram, k);
-
-        let actual_k = labels.iter().max().map(|m| m + 1).unwrap_or(0);
-        let mut categories: Vec<Value> = Vec::with_capacity(actual_k);
-
-        for cluster_idx in 0..actual_k {
-            let member_indices: Vec<usize> = labels
-                .iter()
-                .enumerate()
-                .filter(|(_, &l)| l == cluster_idx)
-                .map(|(i, _)| i)
-                .collect();
-
-            if member_indices.is_empty() {
-                continue;
-            }
-
-            let mut merged: std::collections::HashMap<String, f64> =
-                std::collections::HashMap::new();
-            for &i in &member_indices {
-                for (term, weight) in &vectors[i] {
-                    *merged.entry(term.clone()).or_insert(0.0) += weight;
-                }
-            }
-
-            let mut sorted_terms: Vec<(String, f64)> = merged.into_iter().collect();
-            sorted_terms
-                .sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-            sorted_terms.truncate(top_n);
-
-            let label = sorted_terms
-                .iter()
-                .take(3)
-                .map(|(t, _)| t.as_str())
-                .collect::<Vec<&str>>()
-                .join(", ");
-
-            let keywords: Vec<Value> = sorted_terms
-                .iter()
-                .map(|(t, w)| {
-                    let mut r = Record::new();
-                    r.push("term", Value::string(t, head));
-                    r.push("weight", Value::float(*w, head));
-                    Value::record(r, head)
-                })
-                .collect();
-
-            let members: Vec<Value> = member_indices
-                .iter()
-                .map(|&i| Value::int(i as i64, head))
-                .collect();
-
-            let mut cat = Record::new();
-            cat.push("id", Value::int(cluster_idx as i64, head));
-            cat.push("label", Value::string(&label, head));
-            cat.push("size", Value::int(member_indices.len() as i64, head));
-            cat.push("keywords", Value::list(keywords, head));
-            cat.push("members", Value::list(members, head));
-
-            categories.push(Value::record(cat, head));
-        }
-
-        let mut result = Record::new();
-        result.push("name", Value::string("generated", head));
-        result.push("num_clusters", Value::int(actual_k as i64, head));
-        result.push("num_items", Value::int(n as i64, head));
-        result.push("linkage", Value::string(&linkage_str, head));
-        result.push("categories", Value::list(categories, head));
-
-        Ok(PipelineData::Value(Value::record(result, head), None))
+        let defaults = ops::GenerateOptions::default();
+        let options = ops::GenerateOptions {
+            auto_k,
+            cut_distance: call.get_flag::<f64>("cut-distance")?,
+            dendrogram: call.has_flag("dendrogram")?,
+            id_field: call.get_flag::<String>("id-field")?.unwrap_or(defaults.id_field),
+        };
+        let result = ops::op_generate_cached(
+            &util::rows_to_json(&rows),
+            &field,
+            k,
+            &linkage_str,
+            top_n,
+            &options,
+            cache_path.as_deref(),
+        )
+        .map_err(LabeledError::new)?;
+
+        Ok(PipelineData::Value(util::json_to_value(&result, head), None))
     }
 }
@@ -0,0 +1,175 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, LabeledError, PipelineData, Signature, SyntaxShape, Type, Value,
+};
+
+use crate::ops;
+use crate::TopologyPlugin;
+
+use super::util;
+
+pub struct Graph;
+
+impl PluginCommand for Graph {
+    type Plugin = TopologyPlugin;
+
+    fn name(&self) -> &str {
+        "topology graph"
+    }
+
+    fn description(&self) -> &str {
+        "Export neighbour edges, dedup groups, the taxonomy tree or a dendrogram as GraphML, Graphviz DOT or GEXF"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![
+                (Type::table(), Type::String),
+                (Type::list(Type::Any), Type::String),
+            ])
+            .named(
+                "kind",
+                SyntaxShape::String,
+                "What to draw: neighbors, dedup, taxonomy, dendrogram (default: neighbors)",
+                Some('k'),
+            )
+            .named(
+                "format",
+                SyntaxShape::String,
+                "Output format: graphml, dot, gexf (default: graphml)",
+                Some('o'),
+            )
+            .named(
+                "field",
+                SyntaxShape::String,
+                "Field containing text (default: content)",
+                Some('f'),
+            )
+            .named(
+                "label-field",
+                SyntaxShape::String,
+                "Field used as node label; falls back to the text (default: title)",
+                None,
+            )
+            .named(
+                "id-field",
+                SyntaxShape::String,
+                "Field stored as the node's id attribute (default: id)",
+                None,
+            )
+            .named(
+                "group-field",
+                SyntaxShape::String,
+                "Field holding the duplicate group for --kind dedup (default: _dup_group)",
+                None,
+            )
+            .named(
+                "taxonomy",
+                SyntaxShape::String,
+                "Taxonomy JSON file drawn in full for --kind taxonomy",
+                None,
+            )
+            .named(
+                "linkage",
+                SyntaxShape::String,
+                "Linkage for --kind dendrogram: ward, complete, average, single (default: ward)",
+                Some('l'),
+            )
+            .named(
+                "top-k",
+                SyntaxShape::Int,
+                "Neighbours kept per row for --kind neighbors (default: 5)",
+                None,
+            )
+            .named(
+                "metric",
+                SyntaxShape::String,
+                "Score for --kind neighbors: cosine or jaccard (default: cosine)",
+                Some('m'),
+            )
+            .named(
+                "min-score",
+                SyntaxShape::Float,
+                "Minimum edge score for --kind neighbors (default: 0.2)",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["graph", "graphml", "dot", "graphviz", "gexf", "gephi", "export", "network"]
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "open bookmarks.json | topology tags | topology fingerprint | topology graph --format gexf | save bookmarks.gexf",
+                description: "Similarity graph with tags and fingerprints, ready for Gephi",
+                result: None,
+            },
+            Example {
+                example: "open bookmarks.json | topology classify --depth 2 | topology graph --kind taxonomy --format dot | dot -Tsvg -o topology.svg",
+                description: "Render the category tree with its bookmarks using Graphviz",
+                result: None,
+            },
+            Example {
+                example: "open bookmarks.json | topology dedup | topology graph --kind dedup --format graphml | save dupes.graphml",
+                description: "Duplicate groups as stars around their primary bookmark",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TopologyPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let field: String = call
+            .get_flag::<String>("field")?
+            .unwrap_or_else(|| "content".into());
+        let defaults = ops::GraphOptions::default();
+        let neighbor_defaults = ops::NeighborsOptions::default();
+        let options = ops::GraphOptions {
+            kind: match call.get_flag::<String>("kind")? {
+                Some(k) => ops::parse_graph_kind(&k).map_err(LabeledError::new)?,
+                None => defaults.kind,
+            },
+            format: match call.get_flag::<String>("format")? {
+                Some(f) => ops::parse_graph_format(&f).map_err(LabeledError::new)?,
+                None => defaults.format,
+            },
+            label_field: call.get_flag::<String>("label-field")?.unwrap_or(defaults.label_field),
+            id_field: call.get_flag::<String>("id-field")?.unwrap_or(defaults.id_field),
+            group_field: call.get_flag::<String>("group-field")?.unwrap_or(defaults.group_field),
+            linkage: match call.get_flag::<String>("linkage")? {
+                Some(l) => ops::parse_linkage(&l).map_err(LabeledError::new)?,
+                None => defaults.linkage,
+            },
+            taxonomy_path: call.get_flag::<String>("taxonomy")?,
+            neighbors: ops::NeighborsOptions {
+                k: call
+                    .get_flag::<i64>("top-k")?
+                    .map(|v| v.max(0) as usize)
+                    .unwrap_or(neighbor_defaults.k),
+                metric: match call.get_flag::<String>("metric")? {
+                    Some(m) => ops::parse_neighbor_metric(&m).map_err(LabeledError::new)?,
+                    None => neighbor_defaults.metric,
+                },
+                min_score: call
+                    .get_flag::<f64>("min-score")?
+                    .unwrap_or(neighbor_defaults.min_score),
+                ..neighbor_defaults
+            },
+        };
+        let head = call.head;
+
+        let rows = util::normalize_input(input, head);
+        let output = ops::op_graph(&util::rows_to_json(&rows), &field, &options)
+            .map_err(LabeledError::new)?;
+
+        Ok(PipelineData::Value(Value::string(output, head), None))
+    }
+}
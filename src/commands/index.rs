@@ -0,0 +1,107 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{Category, Example, LabeledError, PipelineData, Signature, SyntaxShape, Type};
+
+use crate::ops;
+use crate::TopologyPlugin;
+
+use super::util;
+
+pub struct IndexCmd;
+
+impl PluginCommand for IndexCmd {
+    type Plugin = TopologyPlugin;
+
+    fn name(&self) -> &str {
+        "topology index"
+    }
+
+    fn description(&self) -> &str {
+        "Add records to (or remove them from) the persistent near-duplicate index used by `topology lookup`"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![
+                (Type::table(), Type::record()),
+                (Type::list(Type::Any), Type::record()),
+                (Type::Nothing, Type::record()),
+            ])
+            .required(
+                "path",
+                SyntaxShape::String,
+                "Path to the SQLite cache database holding the index",
+            )
+            .named(
+                "field",
+                SyntaxShape::String,
+                "Field containing text to fingerprint (default: content)",
+                Some('f'),
+            )
+            .named(
+                "url-field",
+                SyntaxShape::String,
+                "Field containing the URL (default: url)",
+                None,
+            )
+            .named(
+                "id-field",
+                SyntaxShape::String,
+                "Field identifying each record; the URL is used when missing (default: id)",
+                None,
+            )
+            .named(
+                "remove",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Ids to remove from the index instead of adding the input",
+                Some('r'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["index", "lsh", "near-duplicate", "lookup", "persistent", "sqlite"]
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "open bookmarks.json | topology index bookmarks.topology.db --field title",
+                description: "Index bookmarks for later lookups",
+                result: None,
+            },
+            Example {
+                example: "topology index bookmarks.topology.db --remove [bm-17 bm-42]",
+                description: "Remove deleted bookmarks from the index",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TopologyPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let cache_path: String = call.req(0)?;
+        let remove: Option<Vec<String>> = call.get_flag("remove")?;
+        let head = call.head;
+
+        let result = match remove {
+            Some(ids) => ops::op_index_remove(&ids, &cache_path),
+            None => {
+                let field: String = call.get_flag("field")?.unwrap_or_else(|| "content".into());
+                let url_field: String = call.get_flag("url-field")?.unwrap_or_else(|| "url".into());
+                let id_field: String = call.get_flag("id-field")?.unwrap_or_else(|| "id".into());
+                let rows = util::normalize_input(input, head);
+                ops::op_index_add(&util::rows_to_json(&rows), &field, &url_field, &id_field, &cache_path)
+            }
+        };
+
+        match result {
+            Ok(json_val) => Ok(PipelineData::Value(util::json_to_value(&json_val, head), None)),
+            Err(e) => Err(LabeledError::new(e)),
+        }
+    }
+}
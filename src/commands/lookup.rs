@@ -0,0 +1,134 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{Category, Example, LabeledError, PipelineData, Signature, SyntaxShape, Type};
+
+use crate::ops;
+use crate::TopologyPlugin;
+
+use super::util;
+
+pub struct Lookup;
+
+impl PluginCommand for Lookup {
+    type Plugin = TopologyPlugin;
+
+    fn name(&self) -> &str {
+        "topology lookup"
+    }
+
+    fn description(&self) -> &str {
+        "Find the nearest records to a text, URL, fingerprint or MinHash signature in the persistent index built by `topology index`"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .required(
+                "path",
+                SyntaxShape::String,
+                "Path to the SQLite cache database holding the index",
+            )
+            .optional("text", SyntaxShape::String, "Text to look up")
+            .named(
+                "url",
+                SyntaxShape::String,
+                "URL to look up; matches records with the same canonical URL",
+                Some('u'),
+            )
+            .named(
+                "fingerprint",
+                SyntaxShape::String,
+                "Hex SimHash fingerprint to look up (an unweighted `topology fingerprint` result)",
+                None,
+            )
+            .named(
+                "signature",
+                SyntaxShape::String,
+                "MinHash signature to look up: 128 hex values separated by commas",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Int,
+                "SimHash hamming distance threshold, at most 15 (default: 3)",
+                None,
+            )
+            .named(
+                "jaccard",
+                SyntaxShape::Float,
+                "Minimum MinHash Jaccard similarity (default: 0.5)",
+                None,
+            )
+            .named(
+                "limit",
+                SyntaxShape::Int,
+                "Maximum number of neighbours (default: 10)",
+                Some('n'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["lookup", "nearest", "neighbours", "near-duplicate", "index", "lsh"]
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: r#"topology lookup bookmarks.topology.db "Asynchronous programming in Rust""#,
+                description: "Find indexed records similar to a text",
+                result: None,
+            },
+            Example {
+                example: r#"topology lookup bookmarks.topology.db --url "https://example.com/page?utm_source=feed""#,
+                description: "Check whether a URL is already indexed",
+                result: None,
+            },
+            Example {
+                example: r#"topology lookup bookmarks.topology.db --fingerprint a3f1c2d4e5b60718"#,
+                description: "Find indexed records near a SimHash fingerprint",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TopologyPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let cache_path: String = call.req(0)?;
+        let text: Option<String> = call.opt(1)?;
+        let url: Option<String> = call.get_flag("url")?;
+        let fingerprint: Option<String> = call.get_flag("fingerprint")?;
+        let signature: Option<String> = call.get_flag("signature")?;
+        if text.is_none() && url.is_none() && fingerprint.is_none() && signature.is_none() {
+            return Err(LabeledError::new("Provide a text to look up, a --url, a --fingerprint or a --signature")
+                .with_label("nothing to look up", call.head));
+        }
+        let query = ops::LookupQuery {
+            text: text.unwrap_or_default(),
+            url,
+            fingerprint: fingerprint.as_deref().map(ops::parse_fingerprint).transpose().map_err(LabeledError::new)?,
+            signature: signature.as_deref().map(ops::parse_signature).transpose().map_err(LabeledError::new)?,
+        };
+        let defaults = ops::LookupOptions::default();
+        let options = ops::LookupOptions {
+            threshold: call
+                .get_flag::<i64>("threshold")?
+                .map(|v| v as u32)
+                .unwrap_or(defaults.threshold),
+            jaccard: call.get_flag::<f64>("jaccard")?.unwrap_or(defaults.jaccard),
+            limit: call
+                .get_flag::<i64>("limit")?
+                .map(|v| v.max(0) as usize)
+                .unwrap_or(defaults.limit),
+        };
+
+        match ops::op_lookup(&query, &options, &cache_path) {
+            Ok(json_val) => Ok(PipelineData::Value(util::json_to_value(&json_val, call.head), None)),
+            Err(e) => Err(LabeledError::new(e)),
+        }
+    }
+}
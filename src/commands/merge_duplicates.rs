@@ -0,0 +1,108 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, LabeledError, ListStream, PipelineData, Signature, Signals, SyntaxShape,
+    Type, Value,
+};
+
+use crate::ops;
+use crate::TopologyPlugin;
+
+use super::util;
+
+pub struct MergeDuplicates;
+
+impl PluginCommand for MergeDuplicates {
+    type Plugin = TopologyPlugin;
+
+    fn name(&self) -> &str {
+        "topology merge-duplicates"
+    }
+
+    fn description(&self) -> &str {
+        "Collapse each duplicate group from `topology dedup` into one record with per-field merge rules"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![
+                (Type::table(), Type::table()),
+                (Type::list(Type::Any), Type::list(Type::Any)),
+                (Type::Any, Type::Any),
+            ])
+            .named(
+                "rules",
+                SyntaxShape::String,
+                "Merge rule per field, e.g. tags=union,date_added=min,folder=concat. Rules: first, min, max, union, concat. Unlisted fields: union for lists, first non-null otherwise",
+                Some('r'),
+            )
+            .named(
+                "group-field",
+                SyntaxShape::String,
+                "Field holding the duplicate group (default: _dup_group)",
+                None,
+            )
+            .named(
+                "id-field",
+                SyntaxShape::String,
+                "Field listed in _merged_from; row indices are used when missing (default: id)",
+                None,
+            )
+            .named(
+                "separator",
+                SyntaxShape::String,
+                "Separator for text merged with concat (default: \"; \")",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["merge", "duplicates", "consolidate", "collapse", "dedup"]
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: r#"open bookmarks.json | topology dedup --primary oldest | topology merge-duplicates --rules "tags=union,date_added=min,folder=concat""#,
+                description: "Merge duplicate bookmarks, keeping every tag and folder and the earliest date",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TopologyPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let rules: Option<String> = call.get_flag("rules")?;
+        let defaults = ops::MergeOptions::default();
+        let options = ops::MergeOptions {
+            group_field: call
+                .get_flag::<String>("group-field")?
+                .unwrap_or(defaults.group_field),
+            id_field: call.get_flag::<String>("id-field")?.unwrap_or(defaults.id_field),
+            rules: match rules {
+                Some(spec) => ops::parse_merge_rules(&spec).map_err(LabeledError::new)?,
+                None => defaults.rules,
+            },
+            separator: call.get_flag::<String>("separator")?.unwrap_or(defaults.separator),
+        };
+        let head = call.head;
+
+        let rows = util::normalize_input(input, head);
+        if rows.is_empty() {
+            return Ok(PipelineData::Value(Value::list(vec![], head), None));
+        }
+
+        let output = ops::op_merge_duplicates(&util::rows_to_json(&rows), &options);
+        let results: Vec<Value> = output
+            .as_array()
+            .map(|arr| arr.iter().map(|v| util::json_to_value(v, head)).collect())
+            .unwrap_or_default();
+
+        Ok(ListStream::new(results.into_iter(), head, Signals::empty()).into())
+    }
+}
@@ -4,12 +4,18 @@ mod classify;
 mod dedup;
 mod fingerprint;
 mod generate_taxonomy;
+mod graph;
+mod index;
+mod lookup;
+mod merge_duplicates;
+mod neighbors;
 mod normalize_url;
 mod organize;
 mod sample;
 mod similarity;
 mod tags;
 mod topics;
+mod train;
 pub mod util;
 
 pub use analyze::Analyze;
@@ -18,9 +24,15 @@ pub use classify::Classify;
 pub use dedup::Dedup;
 pub use fingerprint::Fingerprint;
 pub use generate_taxonomy::GenerateTaxonomy;
+pub use graph::Graph;
+pub use index::IndexCmd;
+pub use lookup::Lookup;
+pub use merge_duplicates::MergeDuplicates;
+pub use neighbors::Neighbors;
 pub use normalize_url::NormalizeUrl;
 pub use organize::Organize;
 pub use sample::Sample;
 pub use similarity::Similarity;
 pub use tags::Tags;
 pub use topics::Topics;
+pub use train::Train;
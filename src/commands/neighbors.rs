@@ -0,0 +1,138 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, LabeledError, ListStream, PipelineData, Signature, Signals, SyntaxShape,
+    Type, Value,
+};
+
+use crate::ops;
+use crate::TopologyPlugin;
+
+use super::util;
+
+pub struct Neighbors;
+
+impl PluginCommand for Neighbors {
+    type Plugin = TopologyPlugin;
+
+    fn name(&self) -> &str {
+        "topology neighbors"
+    }
+
+    fn description(&self) -> &str {
+        "Top-k most similar rows for every row as a (source, target, score) edge list, using LSH candidates re-ranked by TF-IDF cosine or Jaccard"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![
+                (Type::table(), Type::table()),
+                (Type::list(Type::Any), Type::table()),
+            ])
+            .named(
+                "field",
+                SyntaxShape::String,
+                "Field containing text (default: content)",
+                Some('f'),
+            )
+            .named(
+                "top-k",
+                SyntaxShape::Int,
+                "Neighbours kept per row (default: 5)",
+                Some('k'),
+            )
+            .named(
+                "metric",
+                SyntaxShape::String,
+                "Score: cosine (TF-IDF) or jaccard (character shingles) (default: cosine)",
+                Some('m'),
+            )
+            .named(
+                "min-score",
+                SyntaxShape::Float,
+                "Minimum edge score (default: 0.2)",
+                None,
+            )
+            .named(
+                "id-field",
+                SyntaxShape::String,
+                "Field used as source/target; row indices when missing (default: id)",
+                None,
+            )
+            .named(
+                "shingle",
+                SyntaxShape::Int,
+                "Character shingle size for the jaccard metric (default: 3)",
+                None,
+            )
+            .named(
+                "recall",
+                SyntaxShape::Float,
+                "Target LSH recall for pairs at --min-score (default: 0.95)",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["neighbors", "neighbours", "similar", "related", "graph", "edges", "knn"]
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "open bookmarks.json | topology neighbors --field title -k 3",
+                description: "Three most similar bookmarks for each bookmark",
+                result: None,
+            },
+            Example {
+                example: "open notes.json | topology neighbors --metric jaccard --min-score 0.4 | save related.json",
+                description: "Edge list of near-identical notes for a graph visualizer",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TopologyPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let field: String = call
+            .get_flag::<String>("field")?
+            .unwrap_or_else(|| "content".into());
+        let defaults = ops::NeighborsOptions::default();
+        let options = ops::NeighborsOptions {
+            k: call
+                .get_flag::<i64>("top-k")?
+                .map(|v| v.max(0) as usize)
+                .unwrap_or(defaults.k),
+            metric: match call.get_flag::<String>("metric")? {
+                Some(m) => ops::parse_neighbor_metric(&m).map_err(LabeledError::new)?,
+                None => defaults.metric,
+            },
+            min_score: call.get_flag::<f64>("min-score")?.unwrap_or(defaults.min_score),
+            id_field: call.get_flag::<String>("id-field")?.unwrap_or(defaults.id_field),
+            shingle_size: call
+                .get_flag::<i64>("shingle")?
+                .map(|v| v.max(1) as usize)
+                .unwrap_or(defaults.shingle_size),
+            recall: call.get_flag::<f64>("recall")?.unwrap_or(defaults.recall),
+        };
+        let head = call.head;
+
+        let rows = util::normalize_input(input, head);
+        if rows.is_empty() {
+            return Ok(PipelineData::Value(Value::list(vec![], head), None));
+        }
+
+        let output = ops::op_neighbors(&util::rows_to_json(&rows), &field, &options);
+        let edges: Vec<Value> = output
+            .as_array()
+            .map(|arr| arr.iter().map(|v| util::json_to_value(v, head)).collect())
+            .unwrap_or_default();
+
+        Ok(ListStream::new(edges.into_iter(), head, Signals::empty()).into())
+    }
+}
@@ -4,7 +4,7 @@ use nu_protocol::{
     Type, Value,
 };
 
-use crate::algo::{tfidf, tokenizer};
+use crate::ops;
 use crate::TopologyPlugin;
 
 use super::util;
@@ -81,7 +81,7 @@ impl PluginCommand for Tags {
             .get_flag::<String>("field")?
             .unwrap_or_else(|| "content".into());
         let count: usize = call.get_flag::<i64>("count")?.unwrap_or(5) as usize;
-        let _cache_path: Option<String> = call.get_flag("cache")?;
+        let cache_path: Option<String> = call.get_flag("cache")?;
         let head = call.head;
 
         let rows = util::normalize_input(input, head);
@@ -89,35 +89,8 @@ impl PluginCommand for Tags {
             return Ok(PipelineData::Value(Value::list(vec![], head), None));
         }
 
-        let mut corpus = tfidf::Corpus::new();
-        let token_lists: Vec<Vec<String>> = rows
-            .iter()
-            .map(|row| {
-                let text = row
-                    .get_data_by_key(&field)
-                    .and_then(|v| v.coerce_string().ok())
-                    .unwrap_or_default();
-                tokenizer::tokenize(&text)
-            })
-            .collect();
-
-        for tokens in &token_lists {
-            corpus.add_document(tokens);
-        }
-
-        let results: Vec<Value> = rows
-            .into_iter()
-            .enumerate()
-            .map(|(i, row)| {
-                let top = corpus.top_terms(i, count);
-                let tags: Vec<Value> = top
-                    .iter()
-                    .map(|(term, _)| Value::string(term, head))
-                    .collect();
-
-                util::append_column(row, "_tags", Value::list(tags, head), head)
-            })
-            .collect();
+        let output = ops::op_tags_cached(&util::rows_to_json(&rows), &field, count, cache_path.as_deref());
+        let results = util::merge_columns(rows, &output, &["_tags"], head);
 
         Ok(ListStream::new(results.into_iter(), head, Signals::empty()).into())
     }
@@ -1,9 +1,7 @@
 use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
-use nu_protocol::{
-    Category, Example, LabeledError, PipelineData, Record, Signature, SyntaxShape, Type, Value,
-};
+use nu_protocol::{Category, Example, LabeledError, PipelineData, Signature, SyntaxShape, Type};
 
-use crate::algo::{nmf, tfidf, tokenizer};
+use crate::ops;
 use crate::TopologyPlugin;
 
 use super::util;
@@ -18,7 +16,7 @@ impl PluginCommand for Topics {
     }
 
     fn description(&self) -> &str {
-        "Discover topics using NMF (Non-negative Matrix Factorization)"
+        "Discover topics using NMF (Non-negative Matrix Factorization) or LDA (Latent Dirichlet Allocation)"
     }
 
     fn signature(&self) -> Signature {
@@ -26,7 +24,9 @@ impl PluginCommand for Topics {
             .input_output_types(vec![
                 (Type::table(), Type::record()),
                 (Type::list(Type::Any), Type::record()),
-                (Type::Any, Type::record()),
+                (Type::table(), Type::table()),
+                (Type::list(Type::Any), Type::list(Type::Any)),
+                (Type::Any, Type::Any),
             ])
             .named(
                 "field",
@@ -46,10 +46,28 @@ impl PluginCommand for Topics {
                 "Number of top terms per topic (default: 10)",
                 Some('n'),
             )
+            .named(
+                "mixture",
+                SyntaxShape::Int,
+                "Return the rows with `_topics`: each row's top N topics with normalized weights",
+                None,
+            )
+            .named(
+                "sweep",
+                SyntaxShape::List(Box::new(SyntaxShape::Int)),
+                "Fit each of these topic counts instead of --topics and compare coherence and diversity",
+                None,
+            )
+            .named(
+                "model",
+                SyntaxShape::String,
+                "Topic model: nmf, lda (default: nmf)",
+                Some('m'),
+            )
             .named(
                 "iterations",
                 SyntaxShape::Int,
-                "NMF iterations (default: 200)",
+                "NMF iterations or LDA Gibbs sweeps (default: 200)",
                 None,
             )
             .named(
@@ -58,11 +76,53 @@ impl PluginCommand for Topics {
                 "Max vocabulary size (default: 5000)",
                 None,
             )
+            .named(
+                "tol",
+                SyntaxShape::Float,
+                "Stop when the relative drop in the NMF objective falls below this (default: 0.0001)",
+                None,
+            )
+            .named(
+                "init",
+                SyntaxShape::String,
+                "Initialisation: nndsvd, uniform (default: nndsvd)",
+                None,
+            )
+            .named(
+                "l1",
+                SyntaxShape::Float,
+                "L1 penalty on both factors, for sparser topics (default: 0)",
+                None,
+            )
+            .named(
+                "l2",
+                SyntaxShape::Float,
+                "L2 penalty on both factors, for smoother topics (default: 0)",
+                None,
+            )
+            .named(
+                "alpha",
+                SyntaxShape::Float,
+                "LDA document-topic prior; lower means fewer topics per item (default: 0.1)",
+                None,
+            )
+            .named(
+                "beta",
+                SyntaxShape::Float,
+                "LDA topic-term prior; lower means more distinctive topics (default: 0.01)",
+                None,
+            )
+            .named(
+                "seed",
+                SyntaxShape::Int,
+                "LDA random seed (default: 42)",
+                None,
+            )
             .category(Category::Experimental)
     }
 
     fn search_terms(&self) -> Vec<&str> {
-        vec!["topics", "nmf", "model", "discover", "theme"]
+        vec!["topics", "nmf", "lda", "model", "discover", "theme"]
     }
 
     fn examples(&self) -> Vec<Example<'_>> {
@@ -72,6 +132,26 @@ impl PluginCommand for Topics {
                 description: "Discover 2 topics from a list of strings",
                 result: None,
             },
+            Example {
+                example: r#"open articles.json | topology topics --topics 8 | select relative_error iterations converged"#,
+                description: "Check how well 8 topics fit and whether NMF converged",
+                result: None,
+            },
+            Example {
+                example: r#"open bookmarks.json | topology topics --field title --model lda --topics 10 --seed 7"#,
+                description: "Model short titles with LDA; the same seed reproduces the same topics",
+                result: None,
+            },
+            Example {
+                example: r#"open bookmarks.json | topology topics --topics 6 --mixture 2 | select title _topics"#,
+                description: "Keep the rows and attach each one's two strongest topics with their weights",
+                result: None,
+            },
+            Example {
+                example: r#"open articles.json | topology topics --sweep [4 8 12 16] | get sweep | select k coherence.npmi diversity"#,
+                description: "Compare topic coherence and diversity across several topic counts",
+                result: None,
+            },
         ]
     }
 
@@ -91,89 +171,37 @@ impl PluginCommand for Topics {
         let vocab_limit: usize = call.get_flag::<i64>("vocab")?.unwrap_or(5000) as usize;
         let head = call.head;
 
-        let rows = util::normalize_input(input, head);
-        if rows.is_empty() {
-            return Err(LabeledError::new("Need at least 1 item for topic modeling"));
-        }
-
-        let mut corpus = tfidf::Corpus::new();
-        let token_lists: Vec<Vec<String>> = rows
-            .iter()
-            .map(|row| {
-                let text = row
-                    .get_data_by_key(&field)
-                    .and_then(|v| v.coerce_string().ok())
-                    .unwrap_or_default();
-                tokenizer::tokenize(&text)
-            })
-            .collect();
+        let defaults = ops::TopicsOptions::default();
+        let options = ops::TopicsOptions {
+            mixture: call.get_flag::<i64>("mixture")?.map(|n| n.max(0) as usize),
+            model: match call.get_flag::<String>("model")? {
+                Some(s) => ops::parse_topic_model(&s).map_err(LabeledError::new)?,
+                None => defaults.model,
+            },
+            tol: call.get_flag::<f64>("tol")?.unwrap_or(defaults.tol),
+            init: match call.get_flag::<String>("init")? {
+                Some(s) => ops::parse_nmf_init(&s).map_err(LabeledError::new)?,
+                None => defaults.init,
+            },
+            l1: call.get_flag::<f64>("l1")?.unwrap_or(defaults.l1),
+            l2: call.get_flag::<f64>("l2")?.unwrap_or(defaults.l2),
+            alpha: call.get_flag::<f64>("alpha")?.unwrap_or(defaults.alpha),
+            beta: call.get_flag::<f64>("beta")?.unwrap_or(defaults.beta),
+            seed: call.get_flag::<i64>("seed")?.map_or(defaults.seed, |s| s as u64),
+        };
 
-        for tokens in &token_lists {
-            corpus.add_document(tokens);
+        let rows = util::normalize_input(input, head);
+        let records = util::rows_to_json(&rows);
+        let sweep: Option<Vec<i64>> = call.get_flag("sweep")?;
+        let result = match sweep {
+            Some(ks) => {
+                let ks: Vec<usize> = ks.into_iter().map(|k| k.max(0) as usize).collect();
+                ops::op_topics_sweep(&records, &field, &ks, top_n, max_iter, vocab_limit, &options)
+            }
+            None => ops::op_topics_with_options(&records, &field, k, top_n, max_iter, vocab_limit, &options),
         }
+        .map_err(LabeledError::new)?;
 
-        let vectors: Vec<std::collections::HashMap<String, f64>> = (0..rows.len())
-            .map(|i| corpus.tfidf_vector(i))
-            .collect();
-
-        let result = nmf::nmf(&vectors, k, max_iter, vocab_limit);
-        let dominant = result.dominant_topics();
-
-        let topics: Vec<Value> = (0..k)
-            .map(|t| {
-                let top = result.top_terms(t, top_n);
-                let terms: Vec<Value> = top
-                    .iter()
-                    .map(|(term, weight)| {
-                        let mut r = Record::new();
-                        r.push("term", Value::string(term, head));
-                        r.push("weight", Value::float(*weight, head));
-                        Value::record(r, head)
-                    })
-                    .collect();
-
-                let members: Vec<Value> = dominant
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, &topic)| topic == t)
-                    .map(|(i, _)| Value::int(i as i64, head))
-                    .collect();
-
-                let label = top
-                    .iter()
-                    .take(3)
-                    .map(|(t, _)| t.as_str())
-                    .collect::<Vec<&str>>()
-                    .join(", ");
-
-                let mut topic_rec = Record::new();
-                topic_rec.push("id", Value::int(t as i64, head));
-                topic_rec.push("label", Value::string(&label, head));
-                topic_rec.push("size", Value::int(members.len() as i64, head));
-                topic_rec.push("terms", Value::list(terms, head));
-                topic_rec.push("members", Value::list(members, head));
-
-                Value::record(topic_rec, head)
-            })
-            .collect();
-
-        let assignments: Vec<Value> = dominant
-            .iter()
-            .enumerate()
-            .map(|(i, &topic)| {
-                let mut r = Record::new();
-                r.push("item", Value::int(i as i64, head));
-                r.push("topic", Value::int(topic as i64, head));
-                Value::record(r, head)
-            })
-            .collect();
-
-        let mut output = Record::new();
-        output.push("num_topics", Value::int(k as i64, head));
-        output.push("num_items", Value::int(rows.len() as i64, head));
-        output.push("topics", Value::list(topics, head));
-        output.push("assignments", Value::list(assignments, head));
-
-        Ok(PipelineData::Value(Value::record(output, head), None))
+        Ok(PipelineData::Value(util::json_to_value(&result, head), None))
     }
 }
@@ -0,0 +1,131 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{Category, Example, LabeledError, PipelineData, Signature, SyntaxShape, Type};
+
+use crate::ops;
+use crate::TopologyPlugin;
+
+use super::util;
+
+pub struct Train;
+
+impl PluginCommand for Train {
+    type Plugin = TopologyPlugin;
+
+    fn name(&self) -> &str {
+        "topology train"
+    }
+
+    fn description(&self) -> &str {
+        "Train a reusable classifier model: a discovered taxonomy saved with the corpus IDF and tokenizer settings"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![
+                (Type::table(), Type::record()),
+                (Type::list(Type::Any), Type::record()),
+                (Type::Any, Type::record()),
+            ])
+            .required(
+                "output",
+                SyntaxShape::String,
+                "Path to write the model file",
+            )
+            .named(
+                "field",
+                SyntaxShape::String,
+                "Field containing text to train on (default: content)",
+                Some('f'),
+            )
+            .named(
+                "clusters",
+                SyntaxShape::Int,
+                "Number of categories to discover (default: 15)",
+                Some('k'),
+            )
+            .switch(
+                "auto-k",
+                "Choose the number of categories by silhouette score, trying 2 up to --clusters",
+                None,
+            )
+            .named(
+                "sample",
+                SyntaxShape::Int,
+                "Max items to cluster for discovery; HAC memory grows with its square, about 16 MB at the default 2000. Every item is still classified",
+                None,
+            )
+            .named(
+                "depth",
+                SyntaxShape::Int,
+                "Taxonomy levels to discover; 1 keeps categories flat (default: 1)",
+                Some('d'),
+            )
+            .named(
+                "branching",
+                SyntaxShape::Int,
+                "Growth in cluster count per level below the top (default: 3)",
+                None,
+            )
+            .named(
+                "linkage",
+                SyntaxShape::String,
+                "HAC linkage: ward, complete, average, single (default: ward)",
+                None,
+            )
+            .named(
+                "seed",
+                SyntaxShape::Int,
+                "Random seed for sampling (default: 42)",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["train", "model", "classifier", "taxonomy", "save"]
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            example: r#"open articles.json | topology train topics.model.json --clusters 10"#,
+            description: "Train a 10-category model for later use with `topology classify --model`",
+            result: None,
+        }]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TopologyPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let output: String = call.req(0)?;
+        let field: String = call
+            .get_flag::<String>("field")?
+            .unwrap_or_else(|| "content".into());
+        let k: usize = call.get_flag::<i64>("clusters")?.unwrap_or(15) as usize;
+        let sample_size: usize = call.get_flag::<i64>("sample")?.unwrap_or(2000) as usize;
+        let linkage_str: String = call
+            .get_flag::<String>("linkage")?
+            .unwrap_or_else(|| "ward".into());
+        let seed: u64 = call.get_flag::<i64>("seed")?.unwrap_or(42) as u64;
+        let depth: usize = call.get_flag::<i64>("depth")?.unwrap_or(1).max(1) as usize;
+        let branching: usize = call.get_flag::<i64>("branching")?.unwrap_or(3).max(1) as usize;
+        let auto_k: bool = call.has_flag("auto-k")?;
+        let head = call.head;
+
+        let options = ops::ClassifyOptions {
+            depth,
+            branching,
+            auto_k,
+            linkage: ops::parse_linkage(&linkage_str).map_err(LabeledError::new)?,
+            ..Default::default()
+        };
+
+        let rows = util::normalize_input(input, head);
+        let summary = ops::op_train(&util::rows_to_json(&rows), &field, k, sample_size, seed, &options, Some(&output))
+            .map_err(LabeledError::new)?;
+        Ok(PipelineData::Value(util::json_to_value(&summary, head), None))
+    }
+}
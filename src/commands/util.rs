@@ -1,4 +1,4 @@
-use nu_protocol::{PipelineData, Record, Span, Value};
+use nu_protocol::{PipelineData, PipelineMetadata, Record, Span, Value};
 
 /// Normalize any PipelineData into a Vec<Value> of records.
 ///
@@ -94,3 +94,96 @@ pub fn append_columns(row: Value, cols: &[(&str, Value)], span: Span) -> Value {
         }
     }
 }
+
+/// Convert a nu value to JSON for the shared `ops` layer. Types JSON has no
+/// equivalent for (dates, file sizes, durations, ...) become their string form.
+pub fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Nothing { .. } => serde_json::Value::Null,
+        Value::Bool { val, .. } => serde_json::Value::Bool(*val),
+        Value::Int { val, .. } => serde_json::json!(val),
+        Value::Float { val, .. } => serde_json::json!(val),
+        Value::String { val, .. } => serde_json::Value::String(val.clone()),
+        Value::List { vals, .. } => serde_json::Value::Array(vals.iter().map(value_to_json).collect()),
+        Value::Record { val, .. } => serde_json::Value::Object(
+            val.iter().map(|(k, v)| (k.clone(), value_to_json(v))).collect(),
+        ),
+        other => other
+            .coerce_string()
+            .map(serde_json::Value::String)
+            .unwrap_or(serde_json::Value::Null),
+    }
+}
+
+/// Convert JSON returned by `ops` back into a nu value.
+pub fn json_to_value(val: &serde_json::Value, span: Span) -> Value {
+    match val {
+        serde_json::Value::Null => Value::nothing(span),
+        serde_json::Value::Bool(b) => Value::bool(*b, span),
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::int(i, span)
+            } else if let Some(f) = n.as_f64() {
+                Value::float(f, span)
+            } else {
+                Value::string(n.to_string(), span)
+            }
+        }
+        serde_json::Value::String(s) => Value::string(s, span),
+        serde_json::Value::Array(arr) => {
+            Value::list(arr.iter().map(|v| json_to_value(v, span)).collect(), span)
+        }
+        serde_json::Value::Object(map) => {
+            let mut record = Record::new();
+            for (k, v) in map {
+                record.push(k, json_to_value(v, span));
+            }
+            Value::record(record, span)
+        }
+    }
+}
+
+/// Rows as JSON for an `ops` call.
+pub fn rows_to_json(rows: &[Value]) -> Vec<serde_json::Value> {
+    rows.iter().map(value_to_json).collect()
+}
+
+/// Copy `columns` from the rows an `ops` call returned onto the original
+/// rows. The input columns are kept as they were, so nu types survive the
+/// round trip through JSON. Columns an output row lacks are skipped.
+pub fn merge_columns(
+    rows: Vec<Value>,
+    output: &serde_json::Value,
+    columns: &[&str],
+    span: Span,
+) -> Vec<Value> {
+    let empty = Vec::new();
+    let output = output.as_array().unwrap_or(&empty);
+    rows.into_iter()
+        .zip(output)
+        .map(|(row, out)| {
+            let mut record = match row {
+                Value::Record { val, .. } => val.into_owned(),
+                other => {
+                    let mut r = Record::new();
+                    r.push("value", other);
+                    r
+                }
+            };
+            for &col in columns {
+                if let Some(v) = out.get(col) {
+                    record.insert(col, json_to_value(v, span));
+                }
+            }
+            Value::record(record, span)
+        })
+        .collect()
+}
+
+/// Pipeline metadata carrying `value` under the custom key `key`, for run
+/// details that have no place in the rows (read them with `metadata`).
+pub fn custom_metadata(key: &str, value: Value) -> PipelineMetadata {
+    let mut metadata = PipelineMetadata::default();
+    metadata.custom.push(key, value);
+    metadata
+}
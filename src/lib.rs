@@ -28,10 +28,16 @@ impl Plugin for TopologyPlugin {
             Box::new(commands::Fingerprint),
             Box::new(commands::Analyze),
             Box::new(commands::Classify),
+            Box::new(commands::Train),
             Box::new(commands::GenerateTaxonomy),
             Box::new(commands::Tags),
             Box::new(commands::Topics),
             Box::new(commands::Dedup),
+            Box::new(commands::MergeDuplicates),
+            Box::new(commands::Neighbors),
+            Box::new(commands::Graph),
+            Box::new(commands::IndexCmd),
+            Box::new(commands::Lookup),
             Box::new(commands::Organize),
             Box::new(commands::Similarity),
             Box::new(commands::NormalizeUrl),
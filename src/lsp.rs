@@ -15,13 +15,19 @@
 //! | `topology.sample`        | Sample rows from a JSON array                  |
 //! | `topology.analyze`       | Analyze table structure and field statistics    |
 //! | `topology.classify`      | Auto-classify into categories                  |
+//! | `topology.train`         | Train a reusable classifier model               |
 //! | `topology.tags`          | Extract top TF-IDF tags                        |
-//! | `topology.dedup`         | Find duplicates via SimHash + LSH + URL        |
+//! | `topology.dedup`         | Find duplicates via SimHash/MinHash + LSH + URL |
+//! | `topology.merge_duplicates` | Collapse duplicate groups into one record   |
 //! | `topology.similarity`    | String similarity (Levenshtein/Jaro/Cosine)    |
 //! | `topology.normalize_url` | Normalize a URL for deduplication               |
 //! | `topology.generate`      | Auto-generate taxonomy via HAC clustering       |
-//! | `topology.topics`        | Discover topics via NMF                         |
+//! | `topology.topics`        | Discover topics via NMF or LDA                  |
 //! | `topology.organize`      | Generate output paths from classified items     |
+//! | `topology.neighbors`     | Top-k similar rows as an edge list              |
+//! | `topology.graph`         | Export as GraphML, DOT or GEXF                  |
+//! | `topology.index`         | Add/remove records in the persistent LSH index  |
+//! | `topology.lookup`        | Nearest indexed records to a text, URL or hash  |
 
 use serde_json::Value;
 use tower_lsp::jsonrpc::{Error as RpcError, Result as RpcResult};
@@ -36,13 +42,19 @@ const COMMAND_FINGERPRINT: &str = "topology.fingerprint";
 const COMMAND_SAMPLE: &str = "topology.sample";
 const COMMAND_ANALYZE: &str = "topology.analyze";
 const COMMAND_CLASSIFY: &str = "topology.classify";
+const COMMAND_TRAIN: &str = "topology.train";
 const COMMAND_TAGS: &str = "topology.tags";
 const COMMAND_DEDUP: &str = "topology.dedup";
+const COMMAND_MERGE_DUPLICATES: &str = "topology.merge_duplicates";
 const COMMAND_SIMILARITY: &str = "topology.similarity";
 const COMMAND_NORMALIZE_URL: &str = "topology.normalize_url";
 const COMMAND_GENERATE: &str = "topology.generate";
 const COMMAND_TOPICS: &str = "topology.topics";
 const COMMAND_ORGANIZE: &str = "topology.organize";
+const COMMAND_NEIGHBORS: &str = "topology.neighbors";
+const COMMAND_GRAPH: &str = "topology.graph";
+const COMMAND_INDEX: &str = "topology.index";
+const COMMAND_LOOKUP: &str = "topology.lookup";
 const COMMAND_CACHE_INFO: &str = "topology.cache_info";
 const COMMAND_CACHE_CLEAR: &str = "topology.cache_clear";
 
@@ -51,13 +63,19 @@ const ALL_COMMANDS: &[&str] = &[
     COMMAND_SAMPLE,
     COMMAND_ANALYZE,
     COMMAND_CLASSIFY,
+    COMMAND_TRAIN,
     COMMAND_TAGS,
     COMMAND_DEDUP,
+    COMMAND_MERGE_DUPLICATES,
     COMMAND_SIMILARITY,
     COMMAND_NORMALIZE_URL,
     COMMAND_GENERATE,
     COMMAND_TOPICS,
     COMMAND_ORGANIZE,
+    COMMAND_NEIGHBORS,
+    COMMAND_GRAPH,
+    COMMAND_INDEX,
+    COMMAND_LOOKUP,
     COMMAND_CACHE_INFO,
     COMMAND_CACHE_CLEAR,
 ];
@@ -96,7 +114,6 @@ impl LanguageServer for TopologyLsp {
                 )),
                 ..Default::default()
             },
-            ..Default::default()
         })
     }
 
@@ -131,13 +148,19 @@ impl LanguageServer for TopologyLsp {
             COMMAND_SAMPLE => exec_sample(&arg),
             COMMAND_ANALYZE => exec_analyze(&arg),
             COMMAND_CLASSIFY => exec_classify(&arg),
+            COMMAND_TRAIN => exec_train(&arg),
             COMMAND_TAGS => exec_tags(&arg),
             COMMAND_DEDUP => exec_dedup(&arg),
+            COMMAND_MERGE_DUPLICATES => exec_merge_duplicates(&arg),
             COMMAND_SIMILARITY => exec_similarity(&arg),
             COMMAND_NORMALIZE_URL => exec_normalize_url(&arg),
             COMMAND_GENERATE => exec_generate(&arg),
             COMMAND_TOPICS => exec_topics(&arg),
             COMMAND_ORGANIZE => exec_organize(&arg),
+            COMMAND_NEIGHBORS => exec_neighbors(&arg),
+            COMMAND_GRAPH => exec_graph(&arg),
+            COMMAND_INDEX => exec_index(&arg),
+            COMMAND_LOOKUP => exec_lookup(&arg),
             COMMAND_CACHE_INFO => exec_cache_info(&arg),
             COMMAND_CACHE_CLEAR => exec_cache_clear(&arg),
             _ => Err(format!("Unknown command: {cmd}")),
@@ -227,12 +250,41 @@ fn exec_classify(arg: &Value) -> Result<Value, String> {
     let rows = get_records(arg)?;
     let field = get_str(arg, "field", "content");
     let clusters = get_usize(arg, "clusters", 15);
-    let sample_size = get_usize(arg, "sample", 500);
+    let sample_size = get_usize(arg, "sample", 2000);
     let threshold = get_f64(arg, "threshold", 0.5);
     let seed = get_u64(arg, "seed", 42);
     let taxonomy = arg.get("taxonomy").filter(|v| !v.is_null());
     let cache = arg.get("cache").and_then(|v| v.as_str());
-    ops::op_classify_cached(&rows, field, taxonomy, clusters, sample_size, threshold, seed, cache)
+    let options = ops::ClassifyOptions {
+        depth: get_usize(arg, "depth", 1),
+        branching: get_usize(arg, "branching", 3),
+        auto_k: get_bool(arg, "auto_k", false),
+        assign: ops::parse_assignment(get_str(arg, "assign", "bm25"))?,
+        multi_label: get_bool(arg, "multi_label", false),
+        scale: ops::parse_scale(get_str(arg, "scale", "raw"))?,
+        ..Default::default()
+    };
+    if let Some(model) = arg.get("model").and_then(|v| v.as_str()) {
+        return ops::op_classify_with_model(&rows, field, model, threshold, &options);
+    }
+    ops::op_classify_cached(&rows, field, taxonomy, clusters, sample_size, threshold, seed, &options, cache)
+        .map(|output| output.rows)
+}
+
+fn exec_train(arg: &Value) -> Result<Value, String> {
+    let rows = get_records(arg)?;
+    let field = get_str(arg, "field", "content");
+    let clusters = get_usize(arg, "clusters", 15);
+    let sample_size = get_usize(arg, "sample", 2000);
+    let seed = get_u64(arg, "seed", 42);
+    let output = arg.get("output").and_then(|v| v.as_str());
+    let options = ops::ClassifyOptions {
+        depth: get_usize(arg, "depth", 1),
+        branching: get_usize(arg, "branching", 3),
+        auto_k: get_bool(arg, "auto_k", false),
+        ..Default::default()
+    };
+    ops::op_train(&rows, field, clusters, sample_size, seed, &options, output)
 }
 
 fn exec_tags(arg: &Value) -> Result<Value, String> {
@@ -253,8 +305,38 @@ fn exec_dedup(arg: &Value) -> Result<Value, String> {
         .and_then(|v| v.as_u64())
         .map(|v| v as u32)
         .unwrap_or(3);
+    let options = ops::DedupOptions {
+        jaccard: get_f64(arg, "jaccard", 0.5),
+        shingle_size: get_usize(arg, "shingle", 3),
+        explain: get_bool(arg, "explain", false),
+        primary: ops::parse_primary(get_str(arg, "primary", "first"))?,
+        recall: get_f64(arg, "recall", 0.95),
+        bands: arg.get("bands").and_then(|v| v.as_u64()).map(|v| v as usize),
+        id_field: get_str(arg, "id_field", "id").to_string(),
+    };
     let cache = arg.get("cache").and_then(|v| v.as_str());
-    Ok(ops::op_dedup_cached(&rows, field, url_field, strategy, threshold, cache))
+    if let Some(path) = arg.get("against").and_then(|v| v.as_str()) {
+        let reference = ops::load_records(path)?;
+        return Ok(ops::op_dedup_against_cached(&rows, &reference, field, url_field, strategy, threshold, &options, cache));
+    }
+    Ok(ops::op_dedup_cached(&rows, field, url_field, strategy, threshold, &options, cache))
+}
+
+fn exec_merge_duplicates(arg: &Value) -> Result<Value, String> {
+    let rows = get_records(arg)?;
+    let mut options = ops::MergeOptions {
+        group_field: get_str(arg, "group_field", "_dup_group").to_string(),
+        id_field: get_str(arg, "id_field", "id").to_string(),
+        separator: get_str(arg, "separator", "; ").to_string(),
+        ..Default::default()
+    };
+    if let Some(rules) = arg.get("rules").and_then(|v| v.as_object()) {
+        for (field, rule) in rules {
+            let rule = rule.as_str().ok_or_else(|| format!("Rule for '{field}' must be a string"))?;
+            options.rules.insert(field.clone(), ops::parse_merge_rule(rule)?);
+        }
+    }
+    Ok(ops::op_merge_duplicates(&rows, &options))
 }
 
 fn exec_similarity(arg: &Value) -> Result<Value, String> {
@@ -285,8 +367,14 @@ fn exec_generate(arg: &Value) -> Result<Value, String> {
     let depth = get_usize(arg, "depth", 10);
     let linkage = get_str(arg, "linkage", "ward");
     let top_terms = get_usize(arg, "top_terms", 5);
+    let options = ops::GenerateOptions {
+        auto_k: get_bool(arg, "auto_k", false),
+        cut_distance: arg.get("cut_distance").and_then(|v| v.as_f64()),
+        dendrogram: get_bool(arg, "dendrogram", false),
+        id_field: get_str(arg, "id_field", "id").to_string(),
+    };
     let cache = arg.get("cache").and_then(|v| v.as_str());
-    ops::op_generate_cached(&rows, field, depth, linkage, top_terms, cache)
+    ops::op_generate_cached(&rows, field, depth, linkage, top_terms, &options, cache)
 }
 
 fn exec_topics(arg: &Value) -> Result<Value, String> {
@@ -296,7 +384,23 @@ fn exec_topics(arg: &Value) -> Result<Value, String> {
     let top_n = get_usize(arg, "terms", 10);
     let max_iter = get_usize(arg, "iterations", 200);
     let vocab = get_usize(arg, "vocab", 5000);
-    ops::op_topics(&rows, field, k, top_n, max_iter, vocab)
+    let defaults = ops::TopicsOptions::default();
+    let options = ops::TopicsOptions {
+        mixture: arg.get("mixture").and_then(|v| v.as_u64()).map(|n| n as usize),
+        model: ops::parse_topic_model(get_str(arg, "model", "nmf"))?,
+        tol: get_f64(arg, "tol", defaults.tol),
+        init: ops::parse_nmf_init(get_str(arg, "init", "nndsvd"))?,
+        l1: get_f64(arg, "l1", defaults.l1),
+        l2: get_f64(arg, "l2", defaults.l2),
+        alpha: get_f64(arg, "alpha", defaults.alpha),
+        beta: get_f64(arg, "beta", defaults.beta),
+        seed: get_u64(arg, "seed", defaults.seed),
+    };
+    if let Some(sweep) = arg.get("sweep").and_then(|v| v.as_array()) {
+        let ks: Vec<usize> = sweep.iter().filter_map(|v| v.as_u64()).map(|k| k as usize).collect();
+        return ops::op_topics_sweep(&rows, field, &ks, top_n, max_iter, vocab, &options);
+    }
+    ops::op_topics_with_options(&rows, field, k, top_n, max_iter, vocab, &options)
 }
 
 fn exec_organize(arg: &Value) -> Result<Value, String> {
@@ -308,6 +412,79 @@ fn exec_organize(arg: &Value) -> Result<Value, String> {
     Ok(ops::op_organize(&rows, format, output_dir, category_field, name_field))
 }
 
+fn exec_neighbors(arg: &Value) -> Result<Value, String> {
+    let rows = get_records(arg)?;
+    let field = get_str(arg, "field", "content");
+    let options = ops::NeighborsOptions {
+        k: get_usize(arg, "k", 5),
+        metric: ops::parse_neighbor_metric(get_str(arg, "metric", "cosine"))?,
+        min_score: get_f64(arg, "min_score", 0.2),
+        id_field: get_str(arg, "id_field", "id").to_string(),
+        shingle_size: get_usize(arg, "shingle", 3),
+        recall: get_f64(arg, "recall", 0.95),
+    };
+    Ok(ops::op_neighbors(&rows, field, &options))
+}
+
+fn exec_graph(arg: &Value) -> Result<Value, String> {
+    let rows = get_records(arg)?;
+    let field = get_str(arg, "field", "content");
+    let options = ops::GraphOptions {
+        kind: ops::parse_graph_kind(get_str(arg, "kind", "neighbors"))?,
+        format: ops::parse_graph_format(get_str(arg, "format", "graphml"))?,
+        label_field: get_str(arg, "label_field", "title").to_string(),
+        id_field: get_str(arg, "id_field", "id").to_string(),
+        group_field: get_str(arg, "group_field", "_dup_group").to_string(),
+        linkage: ops::parse_linkage(get_str(arg, "linkage", "ward"))?,
+        taxonomy_path: arg.get("taxonomy").and_then(|v| v.as_str()).map(String::from),
+        neighbors: ops::NeighborsOptions {
+            k: get_usize(arg, "k", 5),
+            metric: ops::parse_neighbor_metric(get_str(arg, "metric", "cosine"))?,
+            min_score: get_f64(arg, "min_score", 0.2),
+            ..Default::default()
+        },
+    };
+    ops::op_graph(&rows, field, &options).map(Value::String)
+}
+
+fn exec_index(arg: &Value) -> Result<Value, String> {
+    let path = arg
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or("Missing required string field 'path'")?;
+    if let Some(remove) = arg.get("remove").and_then(|v| v.as_array()) {
+        let ids: Vec<String> = remove
+            .iter()
+            .map(|v| v.as_str().map(String::from).unwrap_or_else(|| v.to_string()))
+            .collect();
+        return ops::op_index_remove(&ids, path);
+    }
+    let rows = get_records(arg)?;
+    let field = get_str(arg, "field", "content");
+    let url_field = get_str(arg, "url_field", "url");
+    let id_field = get_str(arg, "id_field", "id");
+    ops::op_index_add(&rows, field, url_field, id_field, path)
+}
+
+fn exec_lookup(arg: &Value) -> Result<Value, String> {
+    let path = arg
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or("Missing required string field 'path'")?;
+    let query = ops::LookupQuery {
+        text: get_str(arg, "text", "").to_string(),
+        url: arg.get("url").and_then(|v| v.as_str()).map(String::from),
+        fingerprint: arg.get("fingerprint").and_then(|v| v.as_str()).map(ops::parse_fingerprint).transpose()?,
+        signature: arg.get("signature").and_then(|v| v.as_str()).map(ops::parse_signature).transpose()?,
+    };
+    let options = ops::LookupOptions {
+        threshold: get_u64(arg, "threshold", 3) as u32,
+        jaccard: get_f64(arg, "jaccard", 0.5),
+        limit: get_usize(arg, "limit", 10),
+    };
+    ops::op_lookup(&query, &options, path)
+}
+
 fn exec_cache_info(arg: &Value) -> Result<Value, String> {
     let path = arg
         .get("path")
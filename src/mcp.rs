@@ -14,6 +14,7 @@ use rmcp::{
 };
 use schemars::JsonSchema;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 
 use crate::ops;
 
@@ -71,22 +72,85 @@ pub struct ClassifyParams {
     pub field: String,
     /// Inline taxonomy JSON. If omitted, categories are auto-discovered.
     pub taxonomy: Option<serde_json::Value>,
+    /// Path to a model file written by the `train` tool. Takes precedence over
+    /// `taxonomy` and discovery.
+    pub model: Option<String>,
     /// Number of categories to discover (default: 15).
     #[serde(default = "default_clusters")]
     pub clusters: usize,
-    /// Max items to sample during discovery (default: 500).
+    /// Max items to cluster during discovery (default: 2000). HAC memory
+    /// grows with the square of this, about 16 MB at the default; every item
+    /// is still classified.
     #[serde(default = "default_sample_limit")]
     pub sample: usize,
-    /// Minimum BM25 score threshold (default: 0.5).
+    /// Minimum confidence, on the `scale` scale, to assign a category
+    /// (default: 0.5).
     #[serde(default = "default_threshold")]
     pub threshold: f64,
     /// Random seed.
     #[serde(default = "default_seed")]
     pub seed: u64,
+    /// Taxonomy levels to discover; 1 keeps categories flat (default: 1).
+    #[serde(default = "default_taxonomy_depth")]
+    pub depth: usize,
+    /// Growth in cluster count per level below the top (default: 3).
+    #[serde(default = "default_branching")]
+    pub branching: usize,
+    /// Choose the number of categories by silhouette score, trying 2 up to
+    /// `clusters`. A second content block reports `k_selection`: the chosen
+    /// k and the score of every k tried.
+    #[serde(default)]
+    pub auto_k: bool,
+    /// How items are matched to discovered categories: "bm25" (keywords) or
+    /// "centroid" (nearest TF-IDF centroid, adds `_runner_up` and `_margin`).
+    #[serde(default = "default_assign")]
+    pub assign: String,
+    /// Also return `_categories`: every category above the threshold with
+    /// its score, path and normalized probability.
+    #[serde(default)]
+    pub multi_label: bool,
+    /// Scale of `_confidence`, which `threshold` is compared on: "raw"
+    /// (default; the BM25 score, or the cosine similarity with centroid
+    /// assignment), "softmax" (probability over categories) or "margin" (lead
+    /// over the runner-up, relative to the best score).
+    #[serde(default = "default_scale")]
+    pub scale: String,
     /// Optional path to SQLite cache database for persistent artifact caching.
     pub cache: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TrainParams {
+    /// JSON array of objects to train on.
+    pub records: Vec<serde_json::Value>,
+    /// Field name containing text (default: "content").
+    #[serde(default = "default_field")]
+    pub field: String,
+    /// Number of categories to discover (default: 15).
+    #[serde(default = "default_clusters")]
+    pub clusters: usize,
+    /// Max items to cluster during discovery (default: 2000). HAC memory
+    /// grows with the square of this, about 16 MB at the default; every item
+    /// is still classified.
+    #[serde(default = "default_sample_limit")]
+    pub sample: usize,
+    /// Random seed.
+    #[serde(default = "default_seed")]
+    pub seed: u64,
+    /// Taxonomy levels to discover; 1 keeps categories flat (default: 1).
+    #[serde(default = "default_taxonomy_depth")]
+    pub depth: usize,
+    /// Growth in cluster count per level below the top (default: 3).
+    #[serde(default = "default_branching")]
+    pub branching: usize,
+    /// Choose the number of categories by silhouette score, trying 2 up to
+    /// `clusters`.
+    #[serde(default)]
+    pub auto_k: bool,
+    /// Path to write the model file. If omitted, the model JSON is returned.
+    pub output: Option<String>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct TagsParams {
     /// JSON array of objects.
@@ -111,16 +175,65 @@ pub struct DedupParams {
     /// Field containing URL (default: "url").
     #[serde(default = "default_url_field")]
     pub url_field: String,
-    /// Strategy: "url", "fuzzy", "combined" (default: "combined").
+    /// Strategy: "url", "fuzzy", "minhash", "combined", "combined-minhash" (default: "combined").
     #[serde(default = "default_dedup_strategy")]
     pub strategy: String,
     /// SimHash hamming-distance threshold (default: 3).
     #[serde(default = "default_dedup_threshold")]
     pub threshold: u32,
+    /// Minimum Jaccard similarity for minhash strategies (default: 0.5).
+    #[serde(default = "default_jaccard")]
+    pub jaccard: f64,
+    /// Character shingle size for minhash strategies (default: 3).
+    #[serde(default = "default_shingle")]
+    pub shingle: usize,
+    /// Target LSH recall at the threshold; bands and rows are derived from it (default: 0.95).
+    #[serde(default = "default_recall")]
+    pub recall: f64,
+    /// Fixed number of LSH bands instead of deriving them from `recall`.
+    pub bands: Option<usize>,
+    /// Return `{rows, groups, banding}`: rows gain `_dup_reason` ("url" or "content"),
+    /// `_dup_partner`, `_dup_distance` / `_dup_jaccard` and `_canonical_key`;
+    /// `groups` summarizes size, reasons and max distance per group;
+    /// `banding` reports the LSH layout and its expected false-negative rate.
+    #[serde(default)]
+    pub explain: bool,
+    /// Keeper of each duplicate group: "first" (default), "oldest[:field]",
+    /// "newest[:field]" (field defaults to date_added), "longest",
+    /// "most-starred[:field]" (defaults to stars) or "shortest-url".
+    #[serde(default = "default_primary")]
+    pub primary: String,
+    /// Path to a JSON array of existing records. When set, each record gets
+    /// `_matches_existing` (matching reference ids with reason and distance)
+    /// instead of being grouped; the reference set is not modified.
+    pub against: Option<String>,
+    /// Field reported as the row id in `explain` partners and groups, and
+    /// as the match id with `against` (default: "id").
+    #[serde(default = "default_name_field")]
+    pub id_field: String,
     /// Optional path to SQLite cache database for persistent artifact caching.
     pub cache: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MergeDuplicatesParams {
+    /// JSON array of objects from `dedup` (with a group field).
+    pub records: Vec<serde_json::Value>,
+    /// Merge rule per field: "first", "min", "max", "union" or "concat".
+    /// Unlisted fields use "union" for lists and "first" (non-null) otherwise.
+    #[serde(default)]
+    pub rules: BTreeMap<String, String>,
+    /// Field holding the duplicate group (default: "_dup_group").
+    #[serde(default = "default_group_field")]
+    pub group_field: String,
+    /// Field listed in `_merged_from`; row indices are used when missing (default: "id").
+    #[serde(default = "default_name_field")]
+    pub id_field: String,
+    /// Separator for text merged with "concat" (default: "; ").
+    #[serde(default = "default_separator")]
+    pub separator: String,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct SimilarityParams {
     /// First string.
@@ -151,6 +264,19 @@ pub struct GenerateParams {
     /// Number of clusters / taxonomy depth (default: 10).
     #[serde(default = "default_depth")]
     pub depth: usize,
+    /// Choose the number of clusters by silhouette score, trying 2 up to
+    /// `depth`. The per-k scores are returned as `k_selection`.
+    #[serde(default)]
+    pub auto_k: bool,
+    /// Cut where merges exceed this cosine distance instead of at `depth`
+    /// clusters; overrides `auto_k`.
+    pub cut_distance: Option<f64>,
+    /// Include the dendrogram merges and a Newick rendering as `dendrogram`.
+    #[serde(default)]
+    pub dendrogram: bool,
+    /// Field labelling Newick leaves; row indices when missing (default: "id").
+    #[serde(default = "default_name_field")]
+    pub id_field: String,
     /// Linkage method: "ward", "complete", "average", "single" (default: "ward").
     #[serde(default = "default_linkage")]
     pub linkage: String,
@@ -174,12 +300,42 @@ pub struct TopicsParams {
     /// Number of top terms per topic (default: 10).
     #[serde(default = "default_terms")]
     pub terms: usize,
-    /// NMF iterations (default: 200).
+    /// When set, return the records with `_topics`: each record's top N topics
+    /// as `{topic, label, weight}` with normalized weights, instead of the report.
+    pub mixture: Option<usize>,
+    /// Fit each of these topic counts instead of `topics` and report coherence,
+    /// diversity and fit per k, plus the best k by NPMI.
+    pub sweep: Option<Vec<usize>>,
+    /// Topic model: "nmf" or "lda" (default: "nmf").
+    #[serde(default = "default_topic_model")]
+    pub model: String,
+    /// NMF iterations or LDA Gibbs sweeps (default: 200).
     #[serde(default = "default_iterations")]
     pub iterations: usize,
     /// Max vocabulary size (default: 5000).
     #[serde(default = "default_vocab")]
     pub vocab: usize,
+    /// Stop when the relative drop in the NMF objective falls below this (default: 0.0001).
+    #[serde(default = "default_tol")]
+    pub tol: f64,
+    /// Initialisation: "nndsvd" or "uniform" (default: "nndsvd").
+    #[serde(default = "default_init")]
+    pub init: String,
+    /// L1 penalty on both factors, for sparser topics (default: 0).
+    #[serde(default)]
+    pub l1: f64,
+    /// L2 penalty on both factors, for smoother topics (default: 0).
+    #[serde(default)]
+    pub l2: f64,
+    /// LDA document-topic prior; lower means fewer topics per item (default: 0.1).
+    #[serde(default = "default_alpha")]
+    pub alpha: f64,
+    /// LDA topic-term prior; lower means more distinctive topics (default: 0.01).
+    #[serde(default = "default_beta")]
+    pub beta: f64,
+    /// LDA random seed (default: 42).
+    #[serde(default = "default_seed")]
+    pub seed: u64,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -200,6 +356,115 @@ pub struct OrganizeParams {
     pub name_field: String,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NeighborsParams {
+    /// JSON array of objects.
+    pub records: Vec<serde_json::Value>,
+    /// Field containing text (default: "content").
+    #[serde(default = "default_field")]
+    pub field: String,
+    /// Neighbours kept per row (default: 5).
+    #[serde(default = "default_top_k")]
+    pub k: usize,
+    /// Score: "cosine" (TF-IDF) or "jaccard" (character shingles) (default: "cosine").
+    #[serde(default = "default_neighbor_metric")]
+    pub metric: String,
+    /// Minimum edge score (default: 0.2).
+    #[serde(default = "default_min_score")]
+    pub min_score: f64,
+    /// Field used as source/target; row indices when missing (default: "id").
+    #[serde(default = "default_name_field")]
+    pub id_field: String,
+    /// Character shingle size for the jaccard metric (default: 3).
+    #[serde(default = "default_shingle")]
+    pub shingle: usize,
+    /// Target LSH recall for pairs at `min_score` (default: 0.95).
+    #[serde(default = "default_recall")]
+    pub recall: f64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GraphParams {
+    /// JSON array of objects.
+    pub records: Vec<serde_json::Value>,
+    /// What to draw: "neighbors", "dedup", "taxonomy" or "dendrogram" (default: "neighbors").
+    #[serde(default = "default_graph_kind")]
+    pub kind: String,
+    /// Output format: "graphml", "dot" or "gexf" (default: "graphml").
+    #[serde(default = "default_graph_format")]
+    pub format: String,
+    /// Field containing text (default: "content").
+    #[serde(default = "default_field")]
+    pub field: String,
+    /// Field used as node label; falls back to the text (default: "title").
+    #[serde(default = "default_label_field")]
+    pub label_field: String,
+    /// Field stored as the node's id attribute (default: "id").
+    #[serde(default = "default_name_field")]
+    pub id_field: String,
+    /// Field holding the duplicate group for kind "dedup" (default: "_dup_group").
+    #[serde(default = "default_group_field")]
+    pub group_field: String,
+    /// Taxonomy JSON file drawn in full for kind "taxonomy".
+    pub taxonomy: Option<String>,
+    /// Linkage for kind "dendrogram": ward, complete, average, single (default: "ward").
+    #[serde(default = "default_linkage")]
+    pub linkage: String,
+    /// Neighbours kept per row for kind "neighbors" (default: 5).
+    #[serde(default = "default_top_k")]
+    pub k: usize,
+    /// Score for kind "neighbors": "cosine" or "jaccard" (default: "cosine").
+    #[serde(default = "default_neighbor_metric")]
+    pub metric: String,
+    /// Minimum edge score for kind "neighbors" (default: 0.2).
+    #[serde(default = "default_min_score")]
+    pub min_score: f64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct IndexParams {
+    /// Path to the SQLite cache database holding the index.
+    pub path: String,
+    /// JSON array of records to add. Re-adding an id replaces its entry.
+    #[serde(default)]
+    pub records: Vec<serde_json::Value>,
+    /// Ids to remove from the index. When set, `records` is ignored.
+    pub remove: Option<Vec<String>>,
+    /// Field containing text to fingerprint (default: "content").
+    #[serde(default = "default_field")]
+    pub field: String,
+    /// Field containing the URL (default: "url").
+    #[serde(default = "default_url_field")]
+    pub url_field: String,
+    /// Field identifying each record; the URL is used when missing (default: "id").
+    #[serde(default = "default_name_field")]
+    pub id_field: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LookupParams {
+    /// Path to the SQLite cache database holding the index.
+    pub path: String,
+    /// Text to look up.
+    #[serde(default)]
+    pub text: String,
+    /// URL to look up; matches records with the same canonical URL.
+    pub url: Option<String>,
+    /// Hex SimHash fingerprint to look up (an unweighted `fingerprint` result).
+    pub fingerprint: Option<String>,
+    /// MinHash signature to look up: 128 hex values separated by commas.
+    pub signature: Option<String>,
+    /// SimHash hamming distance threshold, at most 15 (default: 3).
+    #[serde(default = "default_dedup_threshold")]
+    pub threshold: u32,
+    /// Minimum MinHash Jaccard similarity (default: 0.5).
+    #[serde(default = "default_jaccard")]
+    pub jaccard: f64,
+    /// Maximum number of neighbours (default: 10).
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct CacheInfoParams {
     /// Path to the SQLite cache database.
@@ -221,12 +486,29 @@ fn default_sample_size() -> usize { 100 }
 fn default_strategy() -> String { "random".into() }
 fn default_seed() -> u64 { 42 }
 fn default_clusters() -> usize { 15 }
-fn default_sample_limit() -> usize { 500 }
+fn default_taxonomy_depth() -> usize { 1 }
+fn default_branching() -> usize { 3 }
+fn default_assign() -> String { "bm25".into() }
+fn default_sample_limit() -> usize { 2000 }
+fn default_scale() -> String { "raw".into() }
 fn default_threshold() -> f64 { 0.5 }
 fn default_tag_count() -> usize { 5 }
 fn default_url_field() -> String { "url".into() }
 fn default_dedup_strategy() -> String { "combined".into() }
 fn default_dedup_threshold() -> u32 { 3 }
+fn default_primary() -> String { "first".into() }
+fn default_jaccard() -> f64 { 0.5 }
+fn default_shingle() -> usize { 3 }
+fn default_recall() -> f64 { 0.95 }
+fn default_group_field() -> String { "_dup_group".into() }
+fn default_separator() -> String { "; ".into() }
+fn default_limit() -> usize { 10 }
+fn default_top_k() -> usize { 5 }
+fn default_neighbor_metric() -> String { "cosine".into() }
+fn default_min_score() -> f64 { 0.2 }
+fn default_graph_kind() -> String { "neighbors".into() }
+fn default_graph_format() -> String { "graphml".into() }
+fn default_label_field() -> String { "title".into() }
 fn default_metric() -> String { "levenshtein".into() }
 fn default_depth() -> usize { 10 }
 fn default_linkage() -> String { "ward".into() }
@@ -235,6 +517,11 @@ fn default_topics() -> usize { 5 }
 fn default_terms() -> usize { 10 }
 fn default_iterations() -> usize { 200 }
 fn default_vocab() -> usize { 5000 }
+fn default_topic_model() -> String { "nmf".into() }
+fn default_tol() -> f64 { 1e-4 }
+fn default_init() -> String { "nndsvd".into() }
+fn default_alpha() -> f64 { 0.1 }
+fn default_beta() -> f64 { 0.01 }
 fn default_format() -> String { "folders".into() }
 fn default_output_dir() -> String { "./organized".into() }
 fn default_category_field() -> String { "_category".into() }
@@ -309,7 +596,7 @@ impl TopologyMcp {
 
     #[tool(
         name = "classify",
-        description = "Classify items into categories. When no taxonomy is provided, categories are auto-discovered via TF-IDF + HAC clustering + BM25 scoring. Returns records with `_category`, `_hierarchy`, and `_confidence` columns."
+        description = "Classify items into categories. When no taxonomy is provided, categories are auto-discovered via TF-IDF + HAC clustering + BM25 scoring; set `depth` > 1 for nested `Parent > Child` categories and `auto_k` to pick the category count by silhouette score. Returns records with `_category`, `_hierarchy`, and `_confidence` columns, plus a `_categories` list when `multi_label` is set. Set `scale` to \"softmax\" or \"margin\" for a 0-1 `_confidence` whose `threshold` carries over between datasets."
     )]
     async fn classify(
         &self,
@@ -317,16 +604,55 @@ impl TopologyMcp {
     ) -> Result<CallToolResult, McpError> {
         let p = params.0;
         let result = tokio::task::spawn_blocking(move || {
-            ops::op_classify_cached(
-                &p.records,
-                &p.field,
-                p.taxonomy.as_ref(),
-                p.clusters,
-                p.sample,
-                p.threshold,
-                p.seed,
-                p.cache.as_deref(),
-            )
+            let options = ops::ClassifyOptions {
+                depth: p.depth,
+                branching: p.branching,
+                auto_k: p.auto_k,
+                assign: ops::parse_assignment(&p.assign)?,
+                multi_label: p.multi_label,
+                scale: ops::parse_scale(&p.scale)?,
+                ..Default::default()
+            };
+            match p.model.as_deref() {
+                Some(model) => ops::op_classify_with_model(&p.records, &p.field, model, p.threshold, &options)
+                    .map(|rows| ops::ClassifyOutput { rows, k_selection: None }),
+                None => ops::op_classify_cached(
+                    &p.records,
+                    &p.field,
+                    p.taxonomy.as_ref(),
+                    p.clusters,
+                    p.sample,
+                    p.threshold,
+                    p.seed,
+                    &options,
+                    p.cache.as_deref(),
+                ),
+            }
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?
+        .map_err(|e| McpError::invalid_params(e, None))?;
+        let mut content = vec![Content::text(json_text(&result.rows))];
+        if let Some(selection) = result.k_selection {
+            content.push(Content::text(json_text(&serde_json::json!({ "k_selection": selection }))));
+        }
+        Ok(CallToolResult::success(content))
+    }
+
+    #[tool(
+        name = "train",
+        description = "Train a reusable classifier model: discover a taxonomy and save it with the full-corpus IDF statistics and tokenizer settings. Pass the written file as `model` to `classify` to label new items consistently without rediscovering categories."
+    )]
+    async fn train(&self, params: Parameters<TrainParams>) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let result = tokio::task::spawn_blocking(move || {
+            let options = ops::ClassifyOptions {
+                depth: p.depth,
+                branching: p.branching,
+                auto_k: p.auto_k,
+                ..Default::default()
+            };
+            ops::op_train(&p.records, &p.field, p.clusters, p.sample, p.seed, &options, p.output.as_deref())
         })
         .await
         .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?
@@ -348,15 +674,67 @@ impl TopologyMcp {
 
     #[tool(
         name = "dedup",
-        description = "Find duplicates using SimHash + LSH + URL normalization. Strategies: url (URL canonicalization only), fuzzy (SimHash content fingerprinting), combined (both). Returns records with `_dup_group` and `_is_primary` columns."
+        description = "Find duplicates using SimHash or MinHash + LSH + URL normalization. Strategies: url (URL canonicalization only), fuzzy (SimHash content fingerprinting), minhash (Jaccard over character shingles), combined (url + fuzzy), combined-minhash (url + minhash). Returns records with `_dup_group` (numbered in order of each group's first row) and `_is_primary` (chosen by `primary`) columns; set `explain` for match reasons per row and a per-group summary, or `against` to match records against an existing collection (`_matches_existing`). Without `explain`, a second content block reports the LSH `banding` used and its expected false-negative rate."
     )]
     async fn dedup(&self, params: Parameters<DedupParams>) -> Result<CallToolResult, McpError> {
         let p = params.0;
         let result = tokio::task::spawn_blocking(move || {
-            ops::op_dedup_cached(&p.records, &p.field, &p.url_field, &p.strategy, p.threshold, p.cache.as_deref())
+            let options = ops::DedupOptions {
+                jaccard: p.jaccard,
+                shingle_size: p.shingle,
+                explain: p.explain,
+                primary: ops::parse_primary(&p.primary)?,
+                recall: p.recall,
+                bands: p.bands,
+                id_field: p.id_field,
+            };
+            let banding = ops::dedup_banding(&p.strategy, p.threshold, &options);
+            let result = match p.against.as_deref() {
+                Some(path) => {
+                    let reference = ops::load_records(path)?;
+                    ops::op_dedup_against_cached(&p.records, &reference, &p.field, &p.url_field, &p.strategy, p.threshold, &options, p.cache.as_deref())
+                }
+                None => ops::op_dedup_cached(&p.records, &p.field, &p.url_field, &p.strategy, p.threshold, &options, p.cache.as_deref()),
+            };
+            Ok::<_, String>((result, (!p.explain).then_some(banding)))
         })
         .await
-        .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?;
+        .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?
+        .map_err(|e| McpError::invalid_params(e, None))?;
+        let (result, banding) = result;
+        let mut content = vec![Content::text(json_text(&result))];
+        // Explain output already embeds the layout.
+        if let Some(banding) = banding {
+            content.push(Content::text(json_text(&serde_json::json!({ "banding": banding }))));
+        }
+        Ok(CallToolResult::success(content))
+    }
+
+    #[tool(
+        name = "merge_duplicates",
+        description = "Collapse each duplicate group (from `dedup`) into one record. Fields merge by per-field `rules`: first (non-null, starting from the `_is_primary` row), min/max (numbers or ISO dates), union (distinct list items) or concat. Each merged record lists its member ids in `_merged_from`."
+    )]
+    async fn merge_duplicates(
+        &self,
+        params: Parameters<MergeDuplicatesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let result = tokio::task::spawn_blocking(move || {
+            let mut rules = BTreeMap::new();
+            for (field, rule) in &p.rules {
+                rules.insert(field.clone(), ops::parse_merge_rule(rule)?);
+            }
+            let options = ops::MergeOptions {
+                group_field: p.group_field,
+                id_field: p.id_field,
+                rules,
+                separator: p.separator,
+            };
+            Ok::<_, String>(ops::op_merge_duplicates(&p.records, &options))
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?
+        .map_err(|e| McpError::invalid_params(e, None))?;
         Ok(CallToolResult::success(vec![Content::text(json_text(&result))]))
     }
 
@@ -390,7 +768,7 @@ impl TopologyMcp {
 
     #[tool(
         name = "generate",
-        description = "Auto-generate a taxonomy from content using TF-IDF + hierarchical agglomerative clustering. Returns a record with cluster labels, keywords, and member indices. Useful for discovering natural groupings in unstructured text data."
+        description = "Auto-generate a taxonomy from content using TF-IDF + hierarchical agglomerative clustering. Returns a record with cluster labels, keywords, and member indices. Cut at `depth` clusters or at a `cut_distance`; set `dendrogram` for the merge tree and its Newick string. With `cache`, the dendrogram is stored so later cuts of the same records skip re-clustering."
     )]
     async fn generate(
         &self,
@@ -398,7 +776,13 @@ impl TopologyMcp {
     ) -> Result<CallToolResult, McpError> {
         let p = params.0;
         let result = tokio::task::spawn_blocking(move || {
-            ops::op_generate_cached(&p.records, &p.field, p.depth, &p.linkage, p.top_terms, p.cache.as_deref())
+            let options = ops::GenerateOptions {
+                auto_k: p.auto_k,
+                cut_distance: p.cut_distance,
+                dendrogram: p.dendrogram,
+                id_field: p.id_field,
+            };
+            ops::op_generate_cached(&p.records, &p.field, p.depth, &p.linkage, p.top_terms, &options, p.cache.as_deref())
         })
         .await
         .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?
@@ -408,12 +792,26 @@ impl TopologyMcp {
 
     #[tool(
         name = "topics",
-        description = "Discover topics using Non-negative Matrix Factorization (NMF) on TF-IDF vectors. Returns topic labels with top terms, member assignments, and topic sizes. Good for finding latent themes across a collection of documents."
+        description = "Discover topics using Non-negative Matrix Factorization (NMF) on TF-IDF vectors, or Latent Dirichlet Allocation (LDA, seeded Gibbs sampling) on term counts with model=\"lda\" for short texts like titles. Returns topic labels with top terms, member assignments, and topic sizes, plus fit statistics: reconstruction error, iteration count and convergence for NMF, perplexity for LDA, and per-topic UMass/NPMI coherence with overall topic diversity. Pass `sweep` with several k values to compare these metrics side by side, or `mixture` to get the records back with each one's weighted topics in `_topics`. Good for finding latent themes across a collection of documents."
     )]
     async fn topics(&self, params: Parameters<TopicsParams>) -> Result<CallToolResult, McpError> {
         let p = params.0;
         let result = tokio::task::spawn_blocking(move || {
-            ops::op_topics(&p.records, &p.field, p.topics, p.terms, p.iterations, p.vocab)
+            let options = ops::TopicsOptions {
+                mixture: p.mixture,
+                model: ops::parse_topic_model(&p.model)?,
+                tol: p.tol,
+                init: ops::parse_nmf_init(&p.init)?,
+                l1: p.l1,
+                l2: p.l2,
+                alpha: p.alpha,
+                beta: p.beta,
+                seed: p.seed,
+            };
+            match &p.sweep {
+                Some(ks) => ops::op_topics_sweep(&p.records, &p.field, ks, p.terms, p.iterations, p.vocab, &options),
+                None => ops::op_topics_with_options(&p.records, &p.field, p.topics, p.terms, p.iterations, p.vocab, &options),
+            }
         })
         .await
         .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?
@@ -438,6 +836,119 @@ impl TopologyMcp {
         Ok(CallToolResult::success(vec![Content::text(json_text(&result))]))
     }
 
+    #[tool(
+        name = "neighbors",
+        description = "Top-k most similar records for every record as an edge list of {source, target, score}. Candidates come from LSH (weighted SimHash for cosine, MinHash for jaccard) and are re-ranked by TF-IDF cosine or exact shingle Jaccard. Source/target are `id_field` values or row indices. Feed the edges to a graph visualizer or use them as related-item links."
+    )]
+    async fn neighbors(
+        &self,
+        params: Parameters<NeighborsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let result = tokio::task::spawn_blocking(move || {
+            let options = ops::NeighborsOptions {
+                k: p.k,
+                metric: ops::parse_neighbor_metric(&p.metric)?,
+                min_score: p.min_score,
+                id_field: p.id_field,
+                shingle_size: p.shingle,
+                recall: p.recall,
+            };
+            Ok::<_, String>(ops::op_neighbors(&p.records, &p.field, &options))
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?
+        .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(CallToolResult::success(vec![Content::text(json_text(&result))]))
+    }
+
+    #[tool(
+        name = "graph",
+        description = "Export records as a GraphML, Graphviz DOT or GEXF document. kind \"neighbors\" draws top-k similarity edges, \"dedup\" links each duplicate to its group's primary (run dedup first), \"taxonomy\" hangs records off their `_hierarchy` category tree, and \"dendrogram\" draws the HAC merge tree. Nodes carry id, url, category, tags and fingerprint attributes. Returns the document text, ready for Gephi or `dot`."
+    )]
+    async fn graph(
+        &self,
+        params: Parameters<GraphParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let result = tokio::task::spawn_blocking(move || {
+            let options = ops::GraphOptions {
+                kind: ops::parse_graph_kind(&p.kind)?,
+                format: ops::parse_graph_format(&p.format)?,
+                label_field: p.label_field,
+                id_field: p.id_field,
+                group_field: p.group_field,
+                linkage: ops::parse_linkage(&p.linkage)?,
+                taxonomy_path: p.taxonomy,
+                neighbors: ops::NeighborsOptions {
+                    k: p.k,
+                    metric: ops::parse_neighbor_metric(&p.metric)?,
+                    min_score: p.min_score,
+                    ..Default::default()
+                },
+            };
+            ops::op_graph(&p.records, &p.field, &options)
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?
+        .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        name = "index",
+        description = "Add records to the persistent near-duplicate index in a topology cache database, or remove them by id with `remove`. Records are keyed by `id_field` (falling back to the URL) and stored with their SimHash, MinHash signature and canonical URL for `lookup`."
+    )]
+    async fn index(
+        &self,
+        params: Parameters<IndexParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let result = tokio::task::spawn_blocking(move || match p.remove {
+            Some(ids) => ops::op_index_remove(&ids, &p.path),
+            None => ops::op_index_add(&p.records, &p.field, &p.url_field, &p.id_field, &p.path),
+        })
+        .await
+        .map_err(|e| McpError::internal_error(format!("task join error: {e}"), None))?
+        .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(CallToolResult::success(vec![Content::text(json_text(&result))]))
+    }
+
+    #[tool(
+        name = "lookup",
+        description = "Find the nearest records to a text, URL, SimHash fingerprint and/or MinHash signature in the persistent index built with `index`. Only the query's LSH buckets are read, so lookups stay fast as the index grows. Returns {id, reason, distance, jaccard, record} per neighbour, URL matches first."
+    )]
+    async fn lookup(
+        &self,
+        params: Parameters<LookupParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let p = params.0;
+        let options = ops::LookupOptions {
+            threshold: p.threshold,
+            jaccard: p.jaccard,
+            limit: p.limit,
+        };
+        let query = ops::LookupQuery {
+            text: p.text,
+            url: p.url,
+            fingerprint: p
+                .fingerprint
+                .as_deref()
+                .map(ops::parse_fingerprint)
+                .transpose()
+                .map_err(|e| McpError::invalid_params(e, None))?,
+            signature: p
+                .signature
+                .as_deref()
+                .map(ops::parse_signature)
+                .transpose()
+                .map_err(|e| McpError::invalid_params(e, None))?,
+        };
+        let result = ops::op_lookup(&query, &options, &p.path)
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(CallToolResult::success(vec![Content::text(json_text(&result))]))
+    }
+
     #[tool(
         name = "cache_info",
         description = "Show information about a topology cache database: artifact types, sizes, counts, and total database size."
@@ -467,6 +978,12 @@ impl TopologyMcp {
     }
 }
 
+impl Default for TopologyMcp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // ── ServerHandler glue ──────────────────────────────────────────────────────
 
 impl ServerHandler for TopologyMcp {
@@ -474,8 +991,8 @@ impl ServerHandler for TopologyMcp {
         ServerInfo {
             instructions: Some(
                 "Content topology engine. Tools: fingerprint, sample, analyze, classify, \
-                 tags, dedup, similarity, normalize_url, generate, topics, organize, \
-                 cache_info, cache_clear. \
+                 train, tags, dedup, merge_duplicates, similarity, normalize_url, generate, topics, organize, \
+                 neighbors, graph, index, lookup, cache_info, cache_clear. \
                  Pass JSON records for bulk operations or simple strings for \
                  similarity/normalize_url. Use the `cache` parameter on supported tools \
                  to enable persistent SQLite caching."
@@ -7,16 +7,17 @@
 //! When the `cache` feature is enabled, functions accept an optional
 //! `cache_path` parameter. On cache hit, expensive computation is skipped.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::algo::{
-    clustering, discover, lsh, nmf, sampling, simhash, string_distance, taxonomy, tfidf, tokenizer,
-    url_normalize,
+    clustering, coherence, discover, graph, lda, lsh, minhash, model, nmf, sampling, simhash, string_distance,
+    taxonomy, tfidf, tokenizer, url_normalize,
 };
+use crate::algo::sparse::{SparseVector, TermId};
 
 #[cfg(feature = "cache")]
 use crate::algo::{cache, storage};
@@ -24,11 +25,19 @@ use crate::algo::{cache, storage};
 // ── Helpers ──────────────────────────────────────────────────────────────────
 
 /// Extract a text field from a JSON object, returning "" if missing.
+/// Numbers and booleans are read in their string form.
 pub fn get_text(row: &Value, field: &str) -> String {
-    row.get(field)
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string()
+    row.get(field).and_then(scalar_text).unwrap_or_default()
+}
+
+/// String form of a scalar JSON value; `None` for null, arrays and objects.
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
 }
 
 /// Union-find with path compression (halving).
@@ -64,32 +73,42 @@ impl UnionFind {
 
 pub fn op_fingerprint(rows: &[Value], field: &str, weighted: bool) -> Value {
     let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
-    let token_lists: Vec<Vec<String>> = texts.par_iter().map(|t| tokenizer::tokenize(t)).collect();
+    attach_fingerprints(rows, &compute_fingerprints(&texts, weighted))
+}
 
-    let fingerprints: Vec<u64> = if weighted {
+/// SimHash every text, optionally weighting tokens by corpus IDF.
+fn compute_fingerprints(texts: &[String], weighted: bool) -> Vec<u64> {
+    let token_lists: Vec<Vec<String>> = texts.par_iter().map(|t| tokenizer::tokenize(t)).collect();
+    if weighted {
         let mut corpus = tfidf::Corpus::new();
         for tokens in &token_lists {
             corpus.add_document(tokens);
         }
-        token_lists
-            .par_iter()
-            .map(|tokens| {
-                let weights = corpus.token_weights(tokens);
-                simhash::simhash(tokens, &weights)
-            })
-            .collect()
+        weighted_fingerprints(&corpus, &token_lists)
     } else {
         token_lists
             .par_iter()
             .map(|tokens| simhash::simhash_uniform(tokens))
             .collect()
-    };
+    }
+}
+
+fn weighted_fingerprints(corpus: &tfidf::Corpus, token_lists: &[Vec<String>]) -> Vec<u64> {
+    token_lists
+        .par_iter()
+        .map(|tokens| {
+            let weights = corpus.token_weights(tokens);
+            simhash::simhash(tokens, &weights)
+        })
+        .collect()
+}
 
+fn attach_fingerprints(rows: &[Value], fingerprints: &[u64]) -> Value {
     let output: Vec<Value> = rows
         .iter()
         .cloned()
         .zip(fingerprints)
-        .map(|(mut row, fp)| {
+        .map(|(mut row, &fp)| {
             if let Some(obj) = row.as_object_mut() {
                 obj.insert(
                     "_fingerprint".into(),
@@ -208,7 +227,7 @@ pub fn op_analyze(rows: &[Value], field: Option<&str>) -> Value {
             *freq.entry(v.as_str()).or_insert(0) += 1;
         }
         let mut freq_vec: Vec<(&str, usize)> = freq.into_iter().collect();
-        freq_vec.sort_by(|a, b| b.1.cmp(&a.1));
+        freq_vec.sort_by_key(|f| std::cmp::Reverse(f.1));
         freq_vec.truncate(5);
 
         fields.insert(
@@ -243,6 +262,30 @@ pub fn op_classify(
     sample_size: usize,
     threshold: f64,
     seed: u64,
+) -> Result<Value, String> {
+    op_classify_with_options(
+        rows,
+        field,
+        taxonomy_json,
+        clusters,
+        sample_size,
+        threshold,
+        seed,
+        &ClassifyOptions::default(),
+    )
+}
+
+/// Classify with explicit [`ClassifyOptions`].
+#[allow(clippy::too_many_arguments)]
+pub fn op_classify_with_options(
+    rows: &[Value],
+    field: &str,
+    taxonomy_json: Option<&Value>,
+    clusters: usize,
+    sample_size: usize,
+    threshold: f64,
+    seed: u64,
+    options: &ClassifyOptions,
 ) -> Result<Value, String> {
     if rows.is_empty() {
         return Ok(Value::Array(vec![]));
@@ -257,20 +300,147 @@ pub fn op_classify(
             taxonomy::parse_taxonomy(&json_str)?
         }
         None => {
-            let config = discover::DiscoverConfig {
-                k: clusters,
-                sample_size,
-                label_terms: 3,
-                keywords_per_cluster: 20,
-                linkage: clustering::Linkage::Ward,
-                seed,
-            };
+            let config = discover_config(clusters, sample_size, seed, options);
             discover::discover_taxonomy(&texts, &config)
         }
     };
 
-    let classifications = discover::classify_against_taxonomy(&texts, &tax, threshold);
+    classify_rows(rows, field, &texts, &tax, threshold, options, None)
+}
+
+/// Extra classify tunables for taxonomy discovery.
+#[derive(Debug, Clone)]
+pub struct ClassifyOptions {
+    /// Taxonomy levels to discover; 1 keeps categories flat.
+    pub depth: usize,
+    /// Growth in cluster count per level below the top.
+    pub branching: usize,
+    /// Pick the top-level cluster count by silhouette, up to `clusters`.
+    pub auto_k: bool,
+    /// Match items by BM25 on keywords or by nearest TF-IDF centroid.
+    pub assign: discover::Assignment,
+    /// Also list every category above the threshold in `_categories`.
+    pub multi_label: bool,
+    /// Scale of `_confidence` (BM25 score, or cosine similarity with
+    /// centroid assignment); `threshold` is compared on this scale.
+    pub scale: discover::ConfidenceScale,
+    /// HAC linkage used to discover the taxonomy.
+    pub linkage: clustering::Linkage,
+}
+
+impl Default for ClassifyOptions {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            branching: 3,
+            auto_k: false,
+            assign: discover::Assignment::Bm25,
+            multi_label: false,
+            scale: discover::ConfidenceScale::Raw,
+            linkage: clustering::Linkage::Ward,
+        }
+    }
+}
+
+fn discover_config(
+    clusters: usize,
+    sample_size: usize,
+    seed: u64,
+    options: &ClassifyOptions,
+) -> discover::DiscoverConfig {
+    discover::DiscoverConfig {
+        k: clusters,
+        sample_size,
+        label_terms: 3,
+        keywords_per_cluster: 20,
+        linkage: options.linkage,
+        seed,
+        depth: options.depth,
+        branching: options.branching,
+        auto_k: options.auto_k,
+        centroids: options.assign == discover::Assignment::Centroid,
+    }
+}
+
+/// Parse an assignment method name for [`ClassifyOptions::assign`].
+pub fn parse_assignment(s: &str) -> Result<discover::Assignment, String> {
+    discover::Assignment::from_str(s)
+        .ok_or_else(|| format!("Unknown assignment '{s}'. Use: bm25, centroid"))
+}
+
+/// Parse a confidence scale name for [`ClassifyOptions::scale`].
+pub fn parse_scale(s: &str) -> Result<discover::ConfidenceScale, String> {
+    discover::ConfidenceScale::from_str(s)
+        .ok_or_else(|| format!("Unknown confidence scale '{s}'. Use: raw, softmax, margin"))
+}
+
+/// Label rows against `tax` with the assignment method in `options`.
+fn classify_rows(
+    rows: &[Value],
+    field: &str,
+    texts: &[String],
+    tax: &taxonomy::Taxonomy,
+    threshold: f64,
+    options: &ClassifyOptions,
+    cache_path: Option<&str>,
+) -> Result<Value, String> {
+    let mut output = match options.assign {
+        discover::Assignment::Bm25 => {
+            let classifications =
+                discover::classify_against_taxonomy_scaled(texts, tax, threshold, options.scale);
+            attach_classifications(rows, classifications)
+        }
+        discover::Assignment::Centroid => {
+            let corpus = resolve_corpus(field, texts, cache_path);
+            let vectors: Vec<SparseVector> =
+                (0..texts.len()).map(|i| corpus.tfidf_vector(i)).collect();
+            let assignments =
+                discover::assign_by_centroid_scaled(&vectors, corpus.vocabulary(), tax, threshold, options.scale)?;
+            attach_assignments(rows, assignments)
+        }
+    };
+    if options.multi_label {
+        let matches = discover::classify_against_taxonomy_multi_scaled(texts, tax, threshold, options.scale);
+        attach_category_lists(&mut output, matches);
+    }
+    Ok(output)
+}
+
+/// Add `_categories`: every matching category with score, confidence, path
+/// and probability. Scores are BM25 whichever assignment picked
+/// `_category`; confidence is on the classify scale.
+fn attach_category_lists(output: &mut Value, matches: Vec<Vec<discover::CategoryMatch>>) {
+    if let Some(arr) = output.as_array_mut() {
+        for (row, list) in arr.iter_mut().zip(matches) {
+            if let Some(obj) = row.as_object_mut() {
+                obj.insert("_categories".into(), serde_json::json!(list));
+            }
+        }
+    }
+}
+
+/// Like [`attach_classifications`], plus `_runner_up` and `_margin`.
+fn attach_assignments(rows: &[Value], assignments: Vec<discover::CentroidAssignment>) -> Value {
+    let output: Vec<Value> = rows
+        .iter()
+        .cloned()
+        .zip(assignments)
+        .map(|(mut row, a)| {
+            if let Some(obj) = row.as_object_mut() {
+                obj.insert("_category".into(), Value::String(a.category));
+                obj.insert("_hierarchy".into(), Value::String(a.path));
+                obj.insert("_confidence".into(), serde_json::json!(a.confidence));
+                obj.insert("_runner_up".into(), serde_json::json!(a.runner_up));
+                obj.insert("_margin".into(), serde_json::json!(a.margin));
+            }
+            row
+        })
+        .collect();
+
+    Value::Array(output)
+}
 
+fn attach_classifications(rows: &[Value], classifications: Vec<(String, String, f64)>) -> Value {
     let output: Vec<Value> = rows
         .iter()
         .cloned()
@@ -285,7 +455,69 @@ pub fn op_classify(
         })
         .collect();
 
-    Ok(Value::Array(output))
+    Value::Array(output)
+}
+
+/// Train a classifier model: discover a taxonomy and freeze it with the
+/// corpus IDF and tokenizer settings. With `output`, the model is written
+/// there and a summary is returned; otherwise the model itself is returned.
+pub fn op_train(
+    rows: &[Value],
+    field: &str,
+    clusters: usize,
+    sample_size: usize,
+    seed: u64,
+    options: &ClassifyOptions,
+    output: Option<&str>,
+) -> Result<Value, String> {
+    if rows.is_empty() {
+        return Err("Cannot train a model on empty input".into());
+    }
+
+    let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
+    let config = discover_config(clusters, sample_size, seed, options);
+    let trained =
+        model::ClassifierModel::train(&texts, &config, tokenizer::TokenizerConfig::default());
+
+    match output {
+        Some(path) => {
+            trained.save(path)?;
+            let categories: Vec<String> =
+                trained.taxonomy.flatten().into_iter().map(|(path, _)| path).collect();
+            Ok(serde_json::json!({
+                "path": path,
+                "format_version": trained.format_version,
+                "trained_rows": trained.trained_rows,
+                "vocabulary": trained.idf.doc_freq.len(),
+                "categories": categories,
+                "k_selection": trained.k_selection,
+            }))
+        }
+        None => serde_json::to_value(&trained)
+            .map_err(|e| format!("Failed to serialize model: {e}")),
+    }
+}
+
+/// Classify rows against a model file written by [`op_train`]. Of the
+/// options, only `multi_label` and `scale` apply.
+pub fn op_classify_with_model(
+    rows: &[Value],
+    field: &str,
+    model_path: &str,
+    threshold: f64,
+    options: &ClassifyOptions,
+) -> Result<Value, String> {
+    let trained = model::load_model(model_path)?;
+    if rows.is_empty() {
+        return Ok(Value::Array(vec![]));
+    }
+    let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
+    let classifications = trained.classify_scaled(&texts, threshold, options.scale);
+    let mut output = attach_classifications(rows, classifications);
+    if options.multi_label {
+        attach_category_lists(&mut output, trained.classify_multi_scaled(&texts, threshold, options.scale));
+    }
+    Ok(output)
 }
 
 /// Classify using a taxonomy loaded from a file path (for CLI usage).
@@ -307,35 +539,13 @@ pub fn op_classify_from_file(
     let tax = match taxonomy_path {
         Some(path) => taxonomy::load_taxonomy(path)?,
         None => {
-            let config = discover::DiscoverConfig {
-                k: clusters,
-                sample_size,
-                label_terms: 3,
-                keywords_per_cluster: 20,
-                linkage: clustering::Linkage::Ward,
-                seed,
-            };
+            let config = discover_config(clusters, sample_size, seed, &ClassifyOptions::default());
             discover::discover_taxonomy(&texts, &config)
         }
     };
 
     let classifications = discover::classify_against_taxonomy(&texts, &tax, threshold);
-
-    let output: Vec<Value> = rows
-        .iter()
-        .cloned()
-        .zip(classifications)
-        .map(|(mut row, (cat, hier, conf))| {
-            if let Some(obj) = row.as_object_mut() {
-                obj.insert("_category".into(), Value::String(cat));
-                obj.insert("_hierarchy".into(), Value::String(hier));
-                obj.insert("_confidence".into(), serde_json::json!(conf));
-            }
-            row
-        })
-        .collect();
-
-    Ok(Value::Array(output))
+    Ok(attach_classifications(rows, classifications))
 }
 
 pub fn op_tags(rows: &[Value], field: &str, count: usize) -> Value {
@@ -372,79 +582,418 @@ pub fn op_tags(rows: &[Value], field: &str, count: usize) -> Value {
     Value::Array(output)
 }
 
+/// Extra dedup tunables that only some strategies read.
+#[derive(Debug, Clone)]
+pub struct DedupOptions {
+    /// Minimum estimated Jaccard similarity for the MinHash strategies.
+    pub jaccard: f64,
+    /// Character shingle width used to build MinHash signatures.
+    pub shingle_size: usize,
+    /// Record why rows were grouped and summarize each group; the result
+    /// becomes `{rows, groups}` instead of a plain array.
+    pub explain: bool,
+    /// Which member of each duplicate group is marked `_is_primary`.
+    pub primary: PrimaryPolicy,
+    /// Target probability that a pair exactly at the threshold is still
+    /// compared; LSH bands and rows are derived from it.
+    pub recall: f64,
+    /// Fixed number of LSH bands, overriding the layout derived from
+    /// `recall`.
+    pub bands: Option<usize>,
+    /// Field reported as a row's id in explain output and in
+    /// `_matches_existing`; row indices are used for rows without it.
+    pub id_field: String,
+}
+
+impl Default for DedupOptions {
+    fn default() -> Self {
+        Self {
+            jaccard: 0.5,
+            shingle_size: 3,
+            explain: false,
+            primary: PrimaryPolicy::First,
+            recall: 0.95,
+            bands: None,
+            id_field: "id".into(),
+        }
+    }
+}
+
+impl DedupOptions {
+    /// SimHash banding for a Hamming `threshold`.
+    pub fn simhash_banding(&self, threshold: u32) -> lsh::Banding {
+        match self.bands {
+            Some(bands) => lsh::Banding::simhash_with_bands(threshold, bands),
+            None => lsh::Banding::simhash(threshold, self.recall),
+        }
+    }
+
+    /// MinHash banding for `jaccard` over `num_perm` signature values.
+    pub fn minhash_banding(&self, num_perm: usize) -> lsh::Banding {
+        match self.bands {
+            Some(bands) => lsh::Banding::minhash_with_bands(self.jaccard, bands, num_perm),
+            None => lsh::Banding::minhash(self.jaccard, self.recall, num_perm),
+        }
+    }
+}
+
+fn banding_json(banding: &lsh::Banding) -> Value {
+    serde_json::json!({
+        "bands": banding.bands,
+        "rows": banding.rows,
+        "false_negative_rate": banding.false_negative_rate,
+    })
+}
+
+/// LSH layout a dedup run with these settings uses, as
+/// `{simhash?, minhash?: {bands, rows, false_negative_rate}}` with an entry
+/// per content method of `strategy`. Explain output embeds it; frontends
+/// report it alongside the plain result.
+pub fn dedup_banding(strategy: &str, threshold: u32, options: &DedupOptions) -> Value {
+    let mut banding = serde_json::Map::new();
+    if strategy == "fuzzy" || strategy == "combined" {
+        banding.insert("simhash".into(), banding_json(&options.simhash_banding(threshold)));
+    }
+    if strategy == "minhash" || strategy == "combined-minhash" {
+        let num_perm = minhash::MinHasher::with_default_perm().num_perm();
+        banding.insert("minhash".into(), banding_json(&options.minhash_banding(num_perm)));
+    }
+    Value::Object(banding)
+}
+
+/// How the keeper (`_is_primary`) of a duplicate group is chosen. Rows
+/// missing the compared value never win over rows that have it; ties go to
+/// the earlier row.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum PrimaryPolicy {
+    /// The first row of the group in input order.
+    #[default]
+    First,
+    /// Earliest value of a date field (ISO 8601 text or a number).
+    Oldest(String),
+    /// Latest value of a date field.
+    Newest(String),
+    /// Longest text in the dedup field.
+    Longest,
+    /// Largest value of a numeric field, such as a star count.
+    MostStarred(String),
+    /// Shortest URL in the URL field.
+    ShortestUrl,
+}
+
+/// Parse a primary-selection policy: `first`, `oldest[:field]`,
+/// `newest[:field]` (field defaults to `date_added`), `longest`,
+/// `most-starred[:field]` (defaults to `stars`) or `shortest-url`.
+pub fn parse_primary(s: &str) -> Result<PrimaryPolicy, String> {
+    let (name, field) = match s.split_once(':') {
+        Some((name, field)) => (name, Some(field.to_string())),
+        None => (s, None),
+    };
+    let policy = match (name.to_lowercase().as_str(), field) {
+        ("first", None) => PrimaryPolicy::First,
+        ("oldest", f) => PrimaryPolicy::Oldest(f.unwrap_or_else(|| "date_added".into())),
+        ("newest", f) => PrimaryPolicy::Newest(f.unwrap_or_else(|| "date_added".into())),
+        ("longest", None) => PrimaryPolicy::Longest,
+        ("most-starred", f) => PrimaryPolicy::MostStarred(f.unwrap_or_else(|| "stars".into())),
+        ("shortest-url", None) => PrimaryPolicy::ShortestUrl,
+        _ => {
+            return Err(format!(
+                "Unknown primary policy '{s}'. Use: first, oldest[:field], newest[:field], longest, most-starred[:field], shortest-url"
+            ))
+        }
+    };
+    Ok(policy)
+}
+
+/// Sortable form of a date value: numbers (including numeric strings such
+/// as epoch timestamps) compare numerically, other text lexicographically.
+#[derive(Debug, PartialEq, PartialOrd)]
+enum DateKey {
+    Number(f64),
+    Text(String),
+}
+
+fn date_key(value: Option<&Value>) -> Option<DateKey> {
+    match value? {
+        Value::Number(n) => n.as_f64().map(DateKey::Number),
+        Value::String(s) if s.is_empty() => None,
+        Value::String(s) => Some(match s.trim().parse::<f64>() {
+            Ok(n) => DateKey::Number(n),
+            Err(_) => DateKey::Text(s.clone()),
+        }),
+        _ => None,
+    }
+}
+
+fn number_key(value: Option<&Value>) -> Option<f64> {
+    match value? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// The member whose key is best (smallest when `smaller_wins`), falling back
+/// to the first member when none has a key.
+fn best_member<K: PartialOrd>(
+    members: &[usize],
+    key: impl Fn(usize) -> Option<K>,
+    smaller_wins: bool,
+) -> usize {
+    let mut best: Option<(usize, K)> = None;
+    for &member in members {
+        let Some(k) = key(member) else { continue };
+        let better = match &best {
+            None => true,
+            Some((_, b)) if smaller_wins => k < *b,
+            Some((_, b)) => k > *b,
+        };
+        if better {
+            best = Some((member, k));
+        }
+    }
+    best.map_or(members[0], |(member, _)| member)
+}
+
+fn select_primary(
+    rows: &[Value],
+    members: &[usize],
+    field: &str,
+    url_field: &str,
+    policy: &PrimaryPolicy,
+) -> usize {
+    match policy {
+        PrimaryPolicy::First => members[0],
+        PrimaryPolicy::Oldest(f) => best_member(members, |i| date_key(rows[i].get(f)), true),
+        PrimaryPolicy::Newest(f) => best_member(members, |i| date_key(rows[i].get(f)), false),
+        PrimaryPolicy::Longest => best_member(
+            members,
+            |i| Some(get_text(&rows[i], field).chars().count()),
+            false,
+        ),
+        PrimaryPolicy::MostStarred(f) => best_member(members, |i| number_key(rows[i].get(f)), false),
+        PrimaryPolicy::ShortestUrl => best_member(
+            members,
+            |i| rows[i].get(url_field).and_then(scalar_text).map(|u| u.len()),
+            true,
+        ),
+    }
+}
+
+/// Near-duplicate pairs by MinHash over character shingles.
+///
+/// Signatures of a 128-perm MinHash are banded into an LSH index laid out by
+/// `banding` and each candidate pair is kept only if its estimated Jaccard
+/// similarity is at least `min_jaccard`. Empty texts are skipped so rows
+/// missing the field don't collapse into one group. Returns `(i, j, jaccard)`
+/// with `i < j`.
+pub fn minhash_pairs(
+    texts: &[String],
+    shingle_size: usize,
+    min_jaccard: f64,
+    banding: &lsh::Banding,
+) -> Vec<(usize, usize, f64)> {
+    let hasher = minhash::MinHasher::with_default_perm();
+    let signatures = minhash_signatures(&hasher, texts, shingle_size);
+
+    let mut index = banding.minhash_index();
+    for (i, sig) in signatures.iter().enumerate() {
+        if let Some(sig) = sig {
+            index.insert(i, sig);
+        }
+    }
+
+    index
+        .candidate_pairs()
+        .into_iter()
+        .filter_map(|(i, j)| {
+            let (a, b) = (signatures[i].as_ref()?, signatures[j].as_ref()?);
+            let score = hasher.jaccard(a, b);
+            (score >= min_jaccard).then_some((i, j, score))
+        })
+        .collect()
+}
+
+/// MinHash signature of each text's character shingles, `None` for texts
+/// that are empty after whitespace normalization.
+fn minhash_signatures(
+    hasher: &minhash::MinHasher,
+    texts: &[String],
+    shingle_size: usize,
+) -> Vec<Option<Vec<u64>>> {
+    texts
+        .par_iter()
+        .map(|t| {
+            let normalized = t.split_whitespace().collect::<Vec<&str>>().join(" ");
+            if normalized.is_empty() {
+                return None;
+            }
+            let shingles = tokenizer::shingles(&normalized, shingle_size.max(1));
+            Some(hasher.signature(&shingles))
+        })
+        .collect()
+}
+
 pub fn op_dedup(
     rows: &[Value],
     field: &str,
     url_field: &str,
     strategy: &str,
     threshold: u32,
+) -> Value {
+    op_dedup_with_options(
+        rows,
+        field,
+        url_field,
+        strategy,
+        threshold,
+        &DedupOptions::default(),
+    )
+}
+
+/// Dedup with explicit [`DedupOptions`].
+///
+/// Strategies: `url`, `fuzzy` (SimHash), `minhash` (Jaccard over shingles),
+/// `combined` (url + fuzzy) and `combined-minhash` (url + minhash). With
+/// `explain`, see [`DedupOptions::explain`] for the result shape; it also
+/// reports the LSH `banding` used and its expected false-negative rate.
+pub fn op_dedup_with_options(
+    rows: &[Value],
+    field: &str,
+    url_field: &str,
+    strategy: &str,
+    threshold: u32,
+    options: &DedupOptions,
+) -> Value {
+    dedup_rows(rows, field, url_field, strategy, threshold, options, |texts| {
+        compute_fingerprints(texts, false)
+    })
+}
+
+/// Why two rows were linked by dedup.
+#[derive(Debug, Clone, Copy)]
+enum DupEvidence {
+    /// Same canonical URL key.
+    Url,
+    /// SimHash fingerprints within the Hamming threshold.
+    SimHash(u32),
+    /// MinHash signatures at or above the Jaccard threshold.
+    MinHash(f64),
+}
+
+impl DupEvidence {
+    fn reason(self) -> &'static str {
+        match self {
+            DupEvidence::Url => "url",
+            DupEvidence::SimHash(_) | DupEvidence::MinHash(_) => "content",
+        }
+    }
+
+    /// Whether `self` is a closer match than `other`: URL matches are exact,
+    /// then lower Hamming distance or higher Jaccard wins.
+    fn closer_than(self, other: DupEvidence) -> bool {
+        match (self, other) {
+            (DupEvidence::Url, DupEvidence::Url) => false,
+            (DupEvidence::Url, _) => true,
+            (_, DupEvidence::Url) => false,
+            (DupEvidence::SimHash(a), DupEvidence::SimHash(b)) => a < b,
+            (DupEvidence::MinHash(a), DupEvidence::MinHash(b)) => a > b,
+            _ => false,
+        }
+    }
+}
+
+/// Dedup core. `fingerprints` supplies unweighted SimHashes for the texts and
+/// is only called by the SimHash strategies.
+fn dedup_rows(
+    rows: &[Value],
+    field: &str,
+    url_field: &str,
+    strategy: &str,
+    threshold: u32,
+    options: &DedupOptions,
+    fingerprints: impl FnOnce(&[String]) -> Vec<u64>,
 ) -> Value {
     if rows.is_empty() {
-        return Value::Array(vec![]);
+        return if options.explain {
+            serde_json::json!({"rows": [], "groups": [], "banding": {}})
+        } else {
+            Value::Array(vec![])
+        };
     }
     let n = rows.len();
+    let mut edges: Vec<(usize, usize, DupEvidence)> = Vec::new();
 
     // URL dedup
-    let mut url_groups: HashMap<String, Vec<usize>> = HashMap::new();
-    if strategy == "url" || strategy == "combined" {
+    let mut canonical_keys: Vec<Option<String>> = vec![None; n];
+    if matches!(strategy, "url" | "combined" | "combined-minhash") {
+        let mut url_groups: HashMap<&str, Vec<usize>> = HashMap::new();
         for (i, row) in rows.iter().enumerate() {
-            if let Some(url_str) = row.get(url_field).and_then(|v| v.as_str()) {
-                if let Some(key) = url_normalize::canonical_key(url_str) {
-                    url_groups.entry(key).or_default().push(i);
-                }
+            if let Some(url_str) = row.get(url_field).and_then(scalar_text) {
+                canonical_keys[i] = url_normalize::canonical_key(&url_str);
+            }
+        }
+        for (i, key) in canonical_keys.iter().enumerate() {
+            if let Some(key) = key {
+                url_groups.entry(key).or_default().push(i);
+            }
+        }
+        for members in url_groups.values() {
+            for &member in &members[1..] {
+                edges.push((members[0], member, DupEvidence::Url));
             }
         }
     }
 
     // Content dedup
-    let mut content_pairs: HashSet<(usize, usize)> = HashSet::new();
     if strategy == "fuzzy" || strategy == "combined" {
         let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
-        let token_lists: Vec<Vec<String>> =
-            texts.par_iter().map(|t| tokenizer::tokenize(t)).collect();
-        let fingerprints: Vec<u64> = token_lists
-            .par_iter()
-            .map(|t| simhash::simhash_uniform(t))
-            .collect();
+        let fingerprints = fingerprints(&texts);
 
-        let mut lsh_index = lsh::SimHashLshIndex::default_64();
+        let mut lsh_index = options.simhash_banding(threshold).simhash_index();
         for (i, &fp) in fingerprints.iter().enumerate() {
             lsh_index.insert(i, fp);
         }
 
         for (i, j) in lsh_index.candidate_pairs() {
-            if simhash::hamming_distance(fingerprints[i], fingerprints[j]) <= threshold {
-                content_pairs.insert((i, j));
+            let distance = simhash::hamming_distance(fingerprints[i], fingerprints[j]);
+            if distance <= threshold {
+                edges.push((i, j, DupEvidence::SimHash(distance)));
             }
         }
     }
+    if strategy == "minhash" || strategy == "combined-minhash" {
+        let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
+        let minhash_banding = options.minhash_banding(minhash::MinHasher::with_default_perm().num_perm());
+        for (i, j, score) in minhash_pairs(&texts, options.shingle_size, options.jaccard, &minhash_banding) {
+            edges.push((i, j, DupEvidence::MinHash(score)));
+        }
+    }
 
     // Union-find
     let mut uf = UnionFind::new(n);
-    for members in url_groups.values() {
-        for i in 1..members.len() {
-            uf.union(members[0], members[i]);
-        }
-    }
-    for &(i, j) in &content_pairs {
+    for &(i, j, _) in &edges {
         uf.union(i, j);
     }
 
-    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
-    for i in 0..n {
+    // Groups are numbered in order of their first row, so ids are stable
+    // across runs over the same input.
+    let mut gid_of_root: HashMap<usize, usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_ids = vec![0usize; n];
+    for (i, group_id) in group_ids.iter_mut().enumerate() {
         let root = uf.find(i);
-        groups.entry(root).or_default().push(i);
+        let gid = *gid_of_root.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[gid].push(i);
+        *group_id = gid;
     }
 
-    let mut group_ids = vec![0usize; n];
-    let mut is_primary = vec![true; n];
-    let mut gid = 0;
-    for members in groups.values() {
-        for (idx, &member) in members.iter().enumerate() {
-            group_ids[member] = gid;
-            is_primary[member] = idx == 0;
-        }
-        gid += 1;
+    let mut is_primary = vec![false; n];
+    for members in &groups {
+        is_primary[select_primary(rows, members, field, url_field, &options.primary)] = true;
     }
 
     let output: Vec<Value> = rows
@@ -460,27 +1009,868 @@ pub fn op_dedup(
         })
         .collect();
 
-    Value::Array(output)
+    if !options.explain {
+        return Value::Array(output);
+    }
+    let mut explained = explain_dedup(output, &edges, &canonical_keys, &group_ids, &options.id_field);
+    explained["banding"] = dedup_banding(strategy, threshold, options);
+    explained
 }
 
-pub fn op_similarity(
-    a: &str,
-    b: &str,
-    metric_name: &str,
-    all: bool,
-) -> Result<Value, String> {
-    if all {
-        let mut results = serde_json::Map::new();
-        results.insert("a".into(), Value::String(a.into()));
-        results.insert("b".into(), Value::String(b.into()));
-        for name in string_distance::Metric::all_names() {
-            let metric = string_distance::Metric::from_str(name).unwrap();
-            let score = string_distance::similarity(a, b, metric);
-            results.insert((*name).into(), serde_json::json!(score));
-        }
-        Ok(Value::Object(results))
-    } else {
-        let metric = string_distance::Metric::from_str(metric_name).ok_or_else(|| {
+/// Add the per-row match evidence and a per-group summary:
+/// `{rows, groups}`. Each row gets `_dup_reason` (`url`, `content` or null),
+/// `_dup_partner` (the closest linked row), `_dup_distance` (Hamming) or
+/// `_dup_jaccard`, and `_canonical_key`. Partners and group members are
+/// reported by `id_field`, or by row index for rows without it. Groups with
+/// a single member are left out of the summary.
+fn explain_dedup(
+    mut output: Vec<Value>,
+    edges: &[(usize, usize, DupEvidence)],
+    canonical_keys: &[Option<String>],
+    group_ids: &[usize],
+    id_field: &str,
+) -> Value {
+    let ids: Vec<Value> = output
+        .iter()
+        .enumerate()
+        .map(|(i, row)| match row.get(id_field) {
+            Some(id) if !id.is_null() => id.clone(),
+            _ => serde_json::json!(i),
+        })
+        .collect();
+
+    let mut best: Vec<Option<(usize, DupEvidence)>> = vec![None; output.len()];
+    for &(i, j, evidence) in edges {
+        for (row, partner) in [(i, j), (j, i)] {
+            match best[row] {
+                Some((_, current)) if !evidence.closer_than(current) => {}
+                _ => best[row] = Some((partner, evidence)),
+            }
+        }
+    }
+
+    for (i, row) in output.iter_mut().enumerate() {
+        let Some(obj) = row.as_object_mut() else { continue };
+        let (reason, partner, distance, jaccard) = match best[i] {
+            Some((partner, evidence)) => {
+                let (distance, jaccard) = match evidence {
+                    DupEvidence::Url => (None, None),
+                    DupEvidence::SimHash(d) => (Some(d), None),
+                    DupEvidence::MinHash(j) => (None, Some(j)),
+                };
+                (Some(evidence.reason()), Some(partner), distance, jaccard)
+            }
+            None => (None, None, None, None),
+        };
+        obj.insert("_dup_reason".into(), serde_json::json!(reason));
+        obj.insert("_dup_partner".into(), serde_json::json!(partner.map(|p| &ids[p])));
+        obj.insert("_dup_distance".into(), serde_json::json!(distance));
+        obj.insert("_dup_jaccard".into(), serde_json::json!(jaccard));
+        obj.insert("_canonical_key".into(), serde_json::json!(canonical_keys[i]));
+    }
+
+    #[derive(Default)]
+    struct GroupSummary {
+        members: Vec<usize>,
+        reasons: Vec<&'static str>,
+        max_distance: Option<u32>,
+        min_jaccard: Option<f64>,
+    }
+    let mut summaries: std::collections::BTreeMap<usize, GroupSummary> = Default::default();
+    for (i, &gid) in group_ids.iter().enumerate() {
+        summaries.entry(gid).or_default().members.push(i);
+    }
+    for &(i, _, evidence) in edges {
+        let summary = summaries.entry(group_ids[i]).or_default();
+        if !summary.reasons.contains(&evidence.reason()) {
+            summary.reasons.push(evidence.reason());
+        }
+        match evidence {
+            DupEvidence::Url => {}
+            DupEvidence::SimHash(d) => {
+                summary.max_distance = Some(summary.max_distance.map_or(d, |m| m.max(d)));
+            }
+            DupEvidence::MinHash(j) => {
+                summary.min_jaccard = Some(summary.min_jaccard.map_or(j, |m| m.min(j)));
+            }
+        }
+    }
+
+    let groups: Vec<Value> = summaries
+        .into_iter()
+        .filter(|(_, s)| s.members.len() > 1)
+        .map(|(gid, mut s)| {
+            s.reasons.sort_unstable();
+            serde_json::json!({
+                "group": gid,
+                "size": s.members.len(),
+                "reasons": s.reasons,
+                "max_distance": s.max_distance,
+                "min_jaccard": s.min_jaccard,
+                "members": s.members.iter().map(|&i| &ids[i]).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+
+    serde_json::json!({"rows": output, "groups": groups})
+}
+
+/// Match new `rows` against an existing `reference` collection without
+/// regrouping it.
+///
+/// The LSH index is built over the reference set only and each new row is
+/// queried against it, so duplicates within `rows` are not reported. Each
+/// row gets `_matches_existing`: the matching reference rows, closest first,
+/// as `{id, reason, distance, jaccard}` where `id` is the reference row's
+/// [`DedupOptions::id_field`] (or its index). The reference rows are never modified.
+pub fn op_dedup_against(
+    rows: &[Value],
+    reference: &[Value],
+    field: &str,
+    url_field: &str,
+    strategy: &str,
+    threshold: u32,
+    options: &DedupOptions,
+) -> Value {
+    match_reference(rows, reference, field, url_field, strategy, threshold, options, |texts| {
+        compute_fingerprints(texts, false)
+    })
+}
+
+/// [`op_dedup_against`] core. `reference_fingerprints` supplies the
+/// reference SimHashes, so they can come from the cache.
+#[allow(clippy::too_many_arguments)]
+fn match_reference(
+    rows: &[Value],
+    reference: &[Value],
+    field: &str,
+    url_field: &str,
+    strategy: &str,
+    threshold: u32,
+    options: &DedupOptions,
+    reference_fingerprints: impl FnOnce(&[String]) -> Vec<u64>,
+) -> Value {
+    let mut matches: Vec<Vec<(usize, DupEvidence)>> = vec![Vec::new(); rows.len()];
+
+    if matches!(strategy, "url" | "combined" | "combined-minhash") {
+        let mut by_key: HashMap<String, Vec<usize>> = HashMap::new();
+        for (r, row) in reference.iter().enumerate() {
+            if let Some(key) = row.get(url_field).and_then(scalar_text).and_then(|u| url_normalize::canonical_key(&u)) {
+                by_key.entry(key).or_default().push(r);
+            }
+        }
+        for (i, row) in rows.iter().enumerate() {
+            let key = row.get(url_field).and_then(scalar_text).and_then(|u| url_normalize::canonical_key(&u));
+            for &r in key.and_then(|k| by_key.get(&k)).into_iter().flatten() {
+                matches[i].push((r, DupEvidence::Url));
+            }
+        }
+    }
+
+    let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
+    let reference_texts: Vec<String> = reference.iter().map(|r| get_text(r, field)).collect();
+    if strategy == "fuzzy" || strategy == "combined" {
+        let reference_fps = reference_fingerprints(&reference_texts);
+        let mut index = options.simhash_banding(threshold).simhash_index();
+        for (r, &fp) in reference_fps.iter().enumerate() {
+            index.insert(r, fp);
+        }
+        for (i, fp) in compute_fingerprints(&texts, false).into_iter().enumerate() {
+            for r in index.query(fp) {
+                let distance = simhash::hamming_distance(fp, reference_fps[r]);
+                if distance <= threshold {
+                    matches[i].push((r, DupEvidence::SimHash(distance)));
+                }
+            }
+        }
+    }
+    if strategy == "minhash" || strategy == "combined-minhash" {
+        let hasher = minhash::MinHasher::with_default_perm();
+        let reference_sigs = minhash_signatures(&hasher, &reference_texts, options.shingle_size);
+        let mut index = options.minhash_banding(hasher.num_perm()).minhash_index();
+        for (r, sig) in reference_sigs.iter().enumerate() {
+            if let Some(sig) = sig {
+                index.insert(r, sig);
+            }
+        }
+        let sigs = minhash_signatures(&hasher, &texts, options.shingle_size);
+        for (i, sig) in sigs.iter().enumerate() {
+            let Some(sig) = sig else { continue };
+            for r in index.query(sig) {
+                let Some(reference_sig) = &reference_sigs[r] else { continue };
+                let score = hasher.jaccard(sig, reference_sig);
+                if score >= options.jaccard {
+                    matches[i].push((r, DupEvidence::MinHash(score)));
+                }
+            }
+        }
+    }
+
+    let output: Vec<Value> = rows
+        .iter()
+        .cloned()
+        .zip(matches)
+        .map(|(mut row, mut found)| {
+            // Keep the closest evidence per reference row, then order by it.
+            found.sort_by_key(|&(r, _)| r);
+            let mut closest: Vec<(usize, DupEvidence)> = Vec::new();
+            for (r, evidence) in found {
+                match closest.last_mut() {
+                    Some(last) if last.0 == r => {
+                        if evidence.closer_than(last.1) {
+                            last.1 = evidence;
+                        }
+                    }
+                    _ => closest.push((r, evidence)),
+                }
+            }
+            closest.sort_by(|a, b| {
+                if a.1.closer_than(b.1) {
+                    std::cmp::Ordering::Less
+                } else if b.1.closer_than(a.1) {
+                    std::cmp::Ordering::Greater
+                } else {
+                    a.0.cmp(&b.0)
+                }
+            });
+
+            let list: Vec<Value> = closest
+                .into_iter()
+                .map(|(r, evidence)| {
+                    let id = match reference[r].get(&options.id_field) {
+                        Some(id) if !id.is_null() => id.clone(),
+                        _ => serde_json::json!(r),
+                    };
+                    let (distance, jaccard) = match evidence {
+                        DupEvidence::Url => (None, None),
+                        DupEvidence::SimHash(d) => (Some(d), None),
+                        DupEvidence::MinHash(j) => (None, Some(j)),
+                    };
+                    serde_json::json!({
+                        "id": id,
+                        "reason": evidence.reason(),
+                        "distance": distance,
+                        "jaccard": jaccard,
+                    })
+                })
+                .collect();
+            if let Some(obj) = row.as_object_mut() {
+                obj.insert("_matches_existing".into(), Value::Array(list));
+            }
+            row
+        })
+        .collect();
+
+    Value::Array(output)
+}
+
+/// Load a JSON array of records, e.g. a reference set for
+/// [`op_dedup_against`].
+pub fn load_records(path: &str) -> Result<Vec<Value>, String> {
+    let json =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{path}': {e}"))?;
+    match serde_json::from_str(&json) {
+        Ok(Value::Array(rows)) => Ok(rows),
+        Ok(_) => Err(format!("'{path}' must contain a JSON array of records")),
+        Err(e) => Err(format!("Failed to parse '{path}': {e}")),
+    }
+}
+
+/// How [`op_merge_duplicates`] combines one field across a duplicate group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeRule {
+    /// First non-null value, starting from the group's primary row.
+    First,
+    /// Smallest value: numbers (and numeric strings) numerically, other text
+    /// lexicographically, so ISO 8601 dates compare by time.
+    Min,
+    /// Largest value, compared like [`MergeRule::Min`].
+    Max,
+    /// Distinct items of every member's list (scalars count as one item).
+    Union,
+    /// Lists appended in order; text joined with the separator.
+    Concat,
+}
+
+impl MergeRule {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "first" | "first-non-null" => Some(Self::First),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            "union" => Some(Self::Union),
+            "concat" => Some(Self::Concat),
+            _ => None,
+        }
+    }
+
+    fn merge(self, values: &[&Value], separator: &str) -> Value {
+        let Some(&first) = values.first() else {
+            return Value::Null;
+        };
+        match self {
+            MergeRule::First => first.clone(),
+            MergeRule::Min | MergeRule::Max => {
+                let indices: Vec<usize> = (0..values.len()).collect();
+                let best = best_member(&indices, |i| date_key(Some(values[i])), self == MergeRule::Min);
+                values[best].clone()
+            }
+            MergeRule::Union => {
+                let mut items: Vec<Value> = Vec::new();
+                for v in values {
+                    let list = match v {
+                        Value::Array(arr) => arr.as_slice(),
+                        other => std::slice::from_ref(*other),
+                    };
+                    for item in list {
+                        if !items.contains(item) {
+                            items.push(item.clone());
+                        }
+                    }
+                }
+                Value::Array(items)
+            }
+            MergeRule::Concat if values.iter().all(|v| v.is_array()) => Value::Array(
+                values.iter().flat_map(|v| v.as_array().unwrap().clone()).collect(),
+            ),
+            MergeRule::Concat => {
+                let mut parts: Vec<String> = Vec::new();
+                for v in values {
+                    let text = match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    if !text.is_empty() && !parts.contains(&text) {
+                        parts.push(text);
+                    }
+                }
+                Value::String(parts.join(separator))
+            }
+        }
+    }
+}
+
+/// Parse a rule list like `tags=union,date_added=min,folder=concat`.
+pub fn parse_merge_rules(spec: &str) -> Result<BTreeMap<String, MergeRule>, String> {
+    let mut rules = BTreeMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (field, rule) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("Expected field=rule, got '{entry}'"))?;
+        rules.insert(field.trim().to_string(), parse_merge_rule(rule.trim())?);
+    }
+    Ok(rules)
+}
+
+/// Parse one merge rule name.
+pub fn parse_merge_rule(s: &str) -> Result<MergeRule, String> {
+    MergeRule::from_str(s)
+        .ok_or_else(|| format!("Unknown merge rule '{s}'. Use: first, min, max, union, concat"))
+}
+
+/// Options for [`op_merge_duplicates`].
+#[derive(Debug, Clone)]
+pub struct MergeOptions {
+    /// Field holding the duplicate group, as written by dedup.
+    pub group_field: String,
+    /// Field whose values are listed in `_merged_from`; row indices are
+    /// used for rows without it.
+    pub id_field: String,
+    /// Rule per field. Fields without one use `union` for lists and
+    /// `first` for everything else.
+    pub rules: BTreeMap<String, MergeRule>,
+    /// Joins text values under the `concat` rule.
+    pub separator: String,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            group_field: "_dup_group".into(),
+            id_field: "id".into(),
+            rules: BTreeMap::new(),
+            separator: "; ".into(),
+        }
+    }
+}
+
+/// Collapse each duplicate group into one record.
+///
+/// Members are merged field by field, the `_is_primary` row first and the
+/// rest in input order, and `_merged_from` lists the member ids. Groups come
+/// out in order of their first row; rows without a group field pass through
+/// as groups of one.
+pub fn op_merge_duplicates(rows: &[Value], options: &MergeOptions) -> Value {
+    let mut group_index: HashMap<String, usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        match row.get(&options.group_field).filter(|g| !g.is_null()) {
+            Some(group) => {
+                let idx = *group_index.entry(group.to_string()).or_insert_with(|| {
+                    groups.push(Vec::new());
+                    groups.len() - 1
+                });
+                groups[idx].push(i);
+            }
+            None => groups.push(vec![i]),
+        }
+    }
+
+    let output: Vec<Value> = groups
+        .into_iter()
+        .map(|mut members| {
+            if let Some(pos) = members
+                .iter()
+                .position(|&i| rows[i].get("_is_primary") == Some(&Value::Bool(true)))
+            {
+                let primary = members.remove(pos);
+                members.insert(0, primary);
+            }
+
+            let mut fields: Vec<&String> = Vec::new();
+            for &i in &members {
+                if let Some(obj) = rows[i].as_object() {
+                    for key in obj.keys() {
+                        if !fields.contains(&key) {
+                            fields.push(key);
+                        }
+                    }
+                }
+            }
+
+            let mut merged = serde_json::Map::new();
+            for field in fields {
+                let values: Vec<&Value> = members
+                    .iter()
+                    .filter_map(|&i| rows[i].get(field))
+                    .filter(|v| !v.is_null())
+                    .collect();
+                let rule = options.rules.get(field).copied().unwrap_or(
+                    if values.first().is_some_and(|v| v.is_array()) {
+                        MergeRule::Union
+                    } else {
+                        MergeRule::First
+                    },
+                );
+                merged.insert(field.clone(), rule.merge(&values, &options.separator));
+            }
+
+            let merged_from: Vec<Value> = members
+                .iter()
+                .map(|&i| match rows[i].get(&options.id_field) {
+                    Some(id) if !id.is_null() => id.clone(),
+                    _ => serde_json::json!(i),
+                })
+                .collect();
+            merged.insert("_merged_from".into(), Value::Array(merged_from));
+            Value::Object(merged)
+        })
+        .collect();
+
+    Value::Array(output)
+}
+
+/// Score used by [`op_neighbors`] to re-rank LSH candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NeighborMetric {
+    /// TF-IDF cosine similarity; candidates from a weighted SimHash index.
+    #[default]
+    Cosine,
+    /// Exact Jaccard similarity of character shingles; candidates from MinHash.
+    Jaccard,
+}
+
+impl NeighborMetric {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "cosine" | "tfidf" => Some(Self::Cosine),
+            "jaccard" => Some(Self::Jaccard),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a metric name for [`NeighborsOptions::metric`].
+pub fn parse_neighbor_metric(s: &str) -> Result<NeighborMetric, String> {
+    NeighborMetric::from_str(s).ok_or_else(|| format!("Unknown neighbor metric '{s}'. Use: cosine, jaccard"))
+}
+
+/// Tunables for [`op_neighbors`].
+#[derive(Debug, Clone)]
+pub struct NeighborsOptions {
+    /// Neighbours kept per row.
+    pub k: usize,
+    pub metric: NeighborMetric,
+    /// Minimum score of an edge; also sets how wide the LSH net is cast.
+    pub min_score: f64,
+    /// Field used as `source` / `target`; row indices when missing.
+    pub id_field: String,
+    /// Character shingle width for the Jaccard metric.
+    pub shingle_size: usize,
+    /// Target LSH recall for pairs scoring exactly `min_score`.
+    pub recall: f64,
+}
+
+impl Default for NeighborsOptions {
+    fn default() -> Self {
+        Self {
+            k: 5,
+            metric: NeighborMetric::Cosine,
+            min_score: 0.2,
+            id_field: "id".into(),
+            shingle_size: 3,
+            recall: 0.95,
+        }
+    }
+}
+
+/// Top-k most similar rows for every row, as an edge list.
+///
+/// Candidate pairs come from LSH and are re-ranked by the exact score. Each
+/// row keeps only the `k` bucket-mates nearest by fingerprint (SimHash
+/// Hamming distance, or differing MinHash values), so at most n·k pairs are
+/// scored even when loose bands at a low `min_score` put most rows in every
+/// bucket. For cosine, the SimHash banding covers the Hamming distance matching
+/// `min_score` (angle = π·d/64). Returns `[{source, target, score}]` ordered
+/// by source row, then descending score; rows with empty text get no edges.
+pub fn op_neighbors(rows: &[Value], field: &str, options: &NeighborsOptions) -> Value {
+    let node_id = |i: usize| match rows[i].get(&options.id_field) {
+        Some(id) if !id.is_null() => id.clone(),
+        _ => serde_json::json!(i),
+    };
+    let mut edges: Vec<Value> = Vec::new();
+    for (i, neighbors) in neighbor_lists(rows, field, options).into_iter().enumerate() {
+        for (j, score) in neighbors {
+            edges.push(serde_json::json!({
+                "source": node_id(i),
+                "target": node_id(j),
+                "score": score,
+            }));
+        }
+    }
+    Value::Array(edges)
+}
+
+/// Top-k `(row, score)` neighbours of every row, best first.
+fn neighbor_lists(rows: &[Value], field: &str, options: &NeighborsOptions) -> Vec<Vec<(usize, f64)>> {
+    let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
+    let min_score = options.min_score.clamp(0.0, 1.0);
+
+    let scored: Vec<(usize, usize, f64)> = match options.metric {
+        NeighborMetric::Cosine => {
+            let token_lists: Vec<Vec<String>> = texts.par_iter().map(|t| tokenizer::tokenize(t)).collect();
+            let mut corpus = tfidf::Corpus::new();
+            for tokens in &token_lists {
+                corpus.add_document(tokens);
+            }
+            let fingerprints = weighted_fingerprints(&corpus, &token_lists);
+            let max_distance = (64.0 * min_score.acos() / std::f64::consts::PI).ceil() as u32;
+            let mut index = lsh::Banding::simhash(max_distance, options.recall).simhash_index();
+            for (i, &fp) in fingerprints.iter().enumerate() {
+                if !token_lists[i].is_empty() {
+                    index.insert(i, fp);
+                }
+            }
+            let vectors: Vec<SparseVector> = (0..rows.len()).map(|i| corpus.tfidf_vector(i)).collect();
+            let norms: Vec<f64> = vectors.iter().map(SparseVector::norm).collect();
+            let hamming = |i: usize, j: usize| (fingerprints[i] ^ fingerprints[j]).count_ones();
+            bounded_candidate_pairs(index.top_candidates(options.k, hamming))
+                .into_par_iter()
+                .map(|(i, j)| (i, j, clustering::cosine_similarity(&vectors[i], norms[i], &vectors[j], norms[j])))
+                .collect()
+        }
+        NeighborMetric::Jaccard => {
+            let hasher = minhash::MinHasher::with_default_perm();
+            let signatures = minhash_signatures(&hasher, &texts, options.shingle_size);
+            let mut index = lsh::Banding::minhash(min_score, options.recall, hasher.num_perm()).minhash_index();
+            for (i, sig) in signatures.iter().enumerate() {
+                if let Some(sig) = sig {
+                    index.insert(i, sig);
+                }
+            }
+            let shingle_sets: Vec<HashSet<String>> = texts
+                .par_iter()
+                .map(|t| {
+                    let normalized = t.split_whitespace().collect::<Vec<&str>>().join(" ");
+                    tokenizer::shingles(&normalized, options.shingle_size.max(1)).into_iter().collect()
+                })
+                .collect();
+            let differing = |i: usize, j: usize| match (&signatures[i], &signatures[j]) {
+                (Some(a), Some(b)) => a.iter().zip(b).filter(|(x, y)| x != y).count(),
+                _ => usize::MAX,
+            };
+            bounded_candidate_pairs(index.top_candidates(options.k, differing))
+                .into_par_iter()
+                .map(|(i, j)| {
+                    let (a, b) = (&shingle_sets[i], &shingle_sets[j]);
+                    let union = a.union(b).count();
+                    let score = if union == 0 { 0.0 } else { a.intersection(b).count() as f64 / union as f64 };
+                    (i, j, score)
+                })
+                .collect()
+        }
+    };
+
+    let mut adjacency: Vec<Vec<(usize, f64)>> = vec![Vec::new(); rows.len()];
+    for (i, j, score) in scored {
+        if score >= min_score && score > 0.0 {
+            adjacency[i].push((j, score));
+            adjacency[j].push((i, score));
+        }
+    }
+
+    for neighbors in &mut adjacency {
+        neighbors.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        neighbors.truncate(options.k);
+    }
+    adjacency
+}
+
+/// Unordered `(i, j)` pairs, `i < j`, from per-row candidate lists.
+fn bounded_candidate_pairs(lists: Vec<(usize, Vec<usize>)>) -> Vec<(usize, usize)> {
+    let pairs: HashSet<(usize, usize)> = lists
+        .into_iter()
+        .flat_map(|(i, others)| others.into_iter().map(move |j| (i.min(j), i.max(j))))
+        .collect();
+    pairs.into_iter().collect()
+}
+
+/// What [`op_graph`] draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphKind {
+    /// Top-k similarity edges between rows (see [`op_neighbors`]).
+    #[default]
+    Neighbors,
+    /// Duplicate groups from `dedup`: each member linked to its primary.
+    Dedup,
+    /// Category tree from `_hierarchy`, with rows hanging off their category.
+    Taxonomy,
+    /// HAC dendrogram over TF-IDF cosine distance, rows as leaves.
+    Dendrogram,
+}
+
+impl GraphKind {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "neighbors" | "neighbours" => Some(Self::Neighbors),
+            "dedup" | "duplicates" => Some(Self::Dedup),
+            "taxonomy" => Some(Self::Taxonomy),
+            "dendrogram" => Some(Self::Dendrogram),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a graph kind for [`GraphOptions::kind`].
+pub fn parse_graph_kind(s: &str) -> Result<GraphKind, String> {
+    GraphKind::from_str(s)
+        .ok_or_else(|| format!("Unknown graph kind '{s}'. Use: neighbors, dedup, taxonomy, dendrogram"))
+}
+
+/// Parse a linkage name for [`GraphOptions::linkage`].
+pub fn parse_linkage(s: &str) -> Result<clustering::Linkage, String> {
+    clustering::Linkage::from_str(s)
+        .ok_or_else(|| format!("Unknown linkage '{s}'. Use: ward, complete, average, single"))
+}
+
+/// Parse an output format for [`GraphOptions::format`].
+pub fn parse_graph_format(s: &str) -> Result<graph::GraphFormat, String> {
+    graph::GraphFormat::from_str(s).ok_or_else(|| format!("Unknown graph format '{s}'. Use: graphml, dot, gexf"))
+}
+
+/// Tunables for [`op_graph`].
+#[derive(Debug, Clone)]
+pub struct GraphOptions {
+    pub kind: GraphKind,
+    pub format: graph::GraphFormat,
+    /// Field used as node label; falls back to the text, then the id.
+    pub label_field: String,
+    /// Field stored as the `id` node attribute.
+    pub id_field: String,
+    /// Field holding the duplicate group for [`GraphKind::Dedup`].
+    pub group_field: String,
+    /// Linkage for [`GraphKind::Dendrogram`].
+    pub linkage: clustering::Linkage,
+    /// Taxonomy JSON file drawn in full for [`GraphKind::Taxonomy`],
+    /// including categories no row was assigned to.
+    pub taxonomy_path: Option<String>,
+    /// Edge settings for [`GraphKind::Neighbors`].
+    pub neighbors: NeighborsOptions,
+}
+
+impl Default for GraphOptions {
+    fn default() -> Self {
+        Self {
+            kind: GraphKind::Neighbors,
+            format: graph::GraphFormat::GraphMl,
+            label_field: "title".into(),
+            id_field: "id".into(),
+            group_field: "_dup_group".into(),
+            linkage: clustering::Linkage::Ward,
+            taxonomy_path: None,
+            neighbors: NeighborsOptions::default(),
+        }
+    }
+}
+
+const GRAPH_LABEL_CHARS: usize = 60;
+
+/// Render rows as a GraphML, DOT or GEXF document.
+///
+/// Row `i` becomes node `n{i}` carrying `id`, `url`, `category`,
+/// `hierarchy`, `tags`, `fingerprint` and `group` attributes where the row
+/// has them (`_category`, `_hierarchy`, `_tags`, `_dup_group` from earlier
+/// commands). `fingerprint` is the row's `_fingerprint`, or the SimHash of
+/// `field` when missing. Rows without a duplicate group or category are
+/// drawn as isolated nodes.
+pub fn op_graph(rows: &[Value], field: &str, options: &GraphOptions) -> Result<String, String> {
+    let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
+    let fingerprints = compute_fingerprints(&texts, false);
+    let items: Vec<graph::Node> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| graph_item_node(i, row, &texts[i], fingerprints[i], options))
+        .collect();
+
+    let graph = match options.kind {
+        GraphKind::Neighbors => {
+            let mut g = graph::Graph::new(false);
+            g.nodes = items;
+            // Both ends usually list the pair; draw each pair once.
+            let mut drawn: HashSet<(usize, usize)> = HashSet::new();
+            for (i, neighbors) in neighbor_lists(rows, field, &options.neighbors).into_iter().enumerate() {
+                for (j, score) in neighbors {
+                    if drawn.insert((i.min(j), i.max(j))) {
+                        g.add_edge(&format!("n{i}"), &format!("n{j}"), Some(score));
+                    }
+                }
+            }
+            g
+        }
+        GraphKind::Dedup => {
+            let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+            for (i, row) in rows.iter().enumerate() {
+                if let Some(group) = row.get(&options.group_field).filter(|g| !g.is_null()) {
+                    groups.entry(group.to_string()).or_default().push(i);
+                }
+            }
+            if groups.is_empty() && !rows.is_empty() {
+                return Err(format!(
+                    "No '{}' field on any row. Pipe through dedup first",
+                    options.group_field
+                ));
+            }
+            let mut g = graph::Graph::new(true);
+            g.nodes = items;
+            for members in groups.values() {
+                let primary = members
+                    .iter()
+                    .copied()
+                    .find(|&i| rows[i].get("_is_primary") == Some(&Value::Bool(true)))
+                    .unwrap_or(members[0]);
+                for &i in members.iter().filter(|&&i| i != primary) {
+                    g.add_edge(&format!("n{primary}"), &format!("n{i}"), None);
+                }
+            }
+            g
+        }
+        GraphKind::Taxonomy => {
+            let mut g = match &options.taxonomy_path {
+                Some(path) => graph::Graph::from_taxonomy(&taxonomy::load_taxonomy(path)?),
+                None => graph::Graph::new(true),
+            };
+            for (i, item) in items.into_iter().enumerate() {
+                let path = rows[i]
+                    .get("_hierarchy")
+                    .or_else(|| rows[i].get("_category"))
+                    .and_then(|v| v.as_str())
+                    .filter(|p| !p.is_empty());
+                let id = item.id.clone();
+                g.nodes.push(item);
+                if let Some(path) = path {
+                    let category = g.add_category_path(path);
+                    g.add_edge(&category, &id, None);
+                }
+            }
+            g
+        }
+        GraphKind::Dendrogram => {
+            let n = rows.len();
+            let token_lists: Vec<Vec<String>> = texts.iter().map(|t| tokenizer::tokenize(t)).collect();
+            let mut corpus = tfidf::Corpus::new();
+            for tokens in &token_lists {
+                corpus.add_document(tokens);
+            }
+            let vectors: Vec<SparseVector> = (0..n).map(|i| corpus.tfidf_vector(i)).collect();
+            let distances = clustering::cosine_distance_matrix(&vectors);
+            let dendrogram = clustering::hac(distances, n, options.linkage);
+            graph::Graph::from_dendrogram(&dendrogram, items)
+        }
+    };
+    Ok(graph.render(options.format))
+}
+
+fn graph_item_node(i: usize, row: &Value, text: &str, fingerprint: u64, options: &GraphOptions) -> graph::Node {
+    let as_string = |v: &Value| match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let field = |name: &str| row.get(name).filter(|v| !v.is_null()).map(as_string);
+    let id = field(&options.id_field);
+
+    let label = field(&options.label_field)
+        .filter(|l| !l.is_empty())
+        .or_else(|| {
+            let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+            (!text.is_empty()).then(|| match text.char_indices().nth(GRAPH_LABEL_CHARS) {
+                Some((cut, _)) => format!("{}…", &text[..cut]),
+                None => text,
+            })
+        })
+        .or_else(|| id.clone())
+        .unwrap_or_else(|| format!("item {i}"));
+
+    let mut node = graph::Node::new(format!("n{i}"), label).with("kind", "item");
+    let tags = row.get("_tags").and_then(|v| v.as_array()).map(|tags| {
+        tags.iter().map(as_string).collect::<Vec<String>>().join(", ")
+    });
+    let fingerprint = field("_fingerprint").or_else(|| {
+        (!text.trim().is_empty()).then(|| simhash::fingerprint_to_hex(fingerprint))
+    });
+    for (key, value) in [
+        ("id", id),
+        ("url", field("url")),
+        ("category", field("_category")),
+        ("hierarchy", field("_hierarchy")),
+        ("tags", tags),
+        ("fingerprint", fingerprint),
+        ("group", field(&options.group_field)),
+    ] {
+        if let Some(value) = value {
+            node = node.with(key, value);
+        }
+    }
+    node
+}
+
+pub fn op_similarity(
+    a: &str,
+    b: &str,
+    metric_name: &str,
+    all: bool,
+) -> Result<Value, String> {
+    if all {
+        let mut results = serde_json::Map::new();
+        results.insert("a".into(), Value::String(a.into()));
+        results.insert("b".into(), Value::String(b.into()));
+        for name in string_distance::Metric::all_names() {
+            let metric = string_distance::Metric::from_str(name).unwrap();
+            let score = string_distance::similarity(a, b, metric);
+            results.insert((*name).into(), serde_json::json!(score));
+        }
+        Ok(Value::Object(results))
+    } else {
+        let metric = string_distance::Metric::from_str(metric_name).ok_or_else(|| {
             format!(
                 "Unknown metric '{metric_name}'. Use: {}",
                 string_distance::Metric::all_names().join(", ")
@@ -507,6 +1897,31 @@ pub fn op_normalize_url(url: &str) -> Result<Value, String> {
     }
 }
 
+/// Extra generate tunables.
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// Pick the cluster count by silhouette, trying 2 up to `depth`.
+    pub auto_k: bool,
+    /// Cut where merges exceed this cosine distance instead of at `depth`
+    /// clusters. Takes precedence over `auto_k`.
+    pub cut_distance: Option<f64>,
+    /// Include the dendrogram merges and its Newick rendering in the result.
+    pub dendrogram: bool,
+    /// Field labelling Newick leaves; row indices when missing.
+    pub id_field: String,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            auto_k: false,
+            cut_distance: None,
+            dendrogram: false,
+            id_field: "id".into(),
+        }
+    }
+}
+
 pub fn op_generate(
     rows: &[Value],
     field: &str,
@@ -514,14 +1929,41 @@ pub fn op_generate(
     linkage_str: &str,
     top_n: usize,
 ) -> Result<Value, String> {
+    op_generate_with_options(rows, field, depth, linkage_str, top_n, &GenerateOptions::default())
+}
+
+/// [`op_generate`] with explicit [`GenerateOptions`].
+pub fn op_generate_with_options(
+    rows: &[Value],
+    field: &str,
+    depth: usize,
+    linkage_str: &str,
+    top_n: usize,
+    options: &GenerateOptions,
+) -> Result<Value, String> {
+    generate_taxonomy(rows, field, depth, linkage_str, top_n, options, None).map(|(result, _)| result)
+}
+
+/// Shared body of [`op_generate_with_options`] and [`op_generate_cached`].
+///
+/// A `cached` dendrogram skips the O(n²) distance matrix unless `auto_k`
+/// needs it to score cuts by silhouette. Returns the dendrogram used so it
+/// can be stored.
+fn generate_taxonomy(
+    rows: &[Value],
+    field: &str,
+    depth: usize,
+    linkage_str: &str,
+    top_n: usize,
+    options: &GenerateOptions,
+    cached: Option<clustering::Dendrogram>,
+) -> Result<(Value, clustering::Dendrogram), String> {
     let n = rows.len();
     if n < 2 {
         return Err("Need at least 2 items to generate a taxonomy".into());
     }
 
-    let linkage = clustering::Linkage::from_str(linkage_str).ok_or_else(|| {
-        format!("Unknown linkage '{linkage_str}'. Use: ward, complete, average, single")
-    })?;
+    let linkage = parse_linkage(linkage_str)?;
 
     let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
     let token_lists: Vec<Vec<String>> = texts.iter().map(|t| tokenizer::tokenize(t)).collect();
@@ -531,11 +1973,21 @@ pub fn op_generate(
         corpus.add_document(tokens);
     }
 
-    let vectors: Vec<HashMap<String, f64>> = (0..n).map(|i| corpus.tfidf_vector(i)).collect();
-    let distances = clustering::cosine_distance_matrix(&vectors);
-    let k = depth.min(n);
-    let dendrogram = clustering::hac(&distances, n, linkage);
-    let labels = clustering::cut_tree(&dendrogram, k);
+    let vectors: Vec<SparseVector> = (0..n).map(|i| corpus.tfidf_vector(i)).collect();
+    let cached = cached.filter(|d| d.n == n);
+    let auto_k = options.auto_k && options.cut_distance.is_none();
+    // HAC overwrites its matrix, so silhouette scoring recomputes the
+    // distances rather than keeping a second copy.
+    let dendrogram = cached
+        .unwrap_or_else(|| clustering::hac(clustering::cosine_distance_matrix(&vectors), n, linkage));
+    let selection = auto_k.then(|| {
+        let distances = clustering::cosine_distance_matrix(&vectors);
+        clustering::select_k(&dendrogram, &distances, 2, depth)
+    });
+    let labels = match options.cut_distance {
+        Some(max_distance) => clustering::cut_tree_at(&dendrogram, max_distance),
+        None => clustering::cut_tree(&dendrogram, selection.as_ref().map_or(depth, |s| s.k).min(n)),
+    };
 
     let actual_k = labels.iter().max().map(|m| m + 1).unwrap_or(0);
     let mut categories: Vec<Value> = Vec::with_capacity(actual_k);
@@ -552,22 +2004,19 @@ pub fn op_generate(
             continue;
         }
 
-        let mut merged: HashMap<String, f64> = HashMap::new();
+        let mut merged = SparseVector::new();
         for &i in &member_indices {
-            for (term, weight) in &vectors[i] {
-                *merged.entry(term.clone()).or_insert(0.0) += weight;
-            }
+            merged.add_scaled(&vectors[i], 1.0);
         }
 
-        let mut sorted_terms: Vec<(String, f64)> = merged.into_iter().collect();
-        sorted_terms
-            .sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        let mut sorted_terms: Vec<(&str, f64)> = merged.to_terms(corpus.vocabulary()).collect();
+        sorted_terms.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
         sorted_terms.truncate(top_n);
 
         let label = sorted_terms
             .iter()
             .take(3)
-            .map(|(t, _)| t.as_str())
+            .map(|(t, _)| *t)
             .collect::<Vec<&str>>()
             .join(", ");
 
@@ -587,13 +2036,103 @@ pub fn op_generate(
         }));
     }
 
-    Ok(serde_json::json!({
+    let mut result = serde_json::json!({
         "name": "generated",
         "num_clusters": actual_k,
         "num_items": n,
         "linkage": linkage_str,
         "categories": categories,
-    }))
+    });
+    if let Some(max_distance) = options.cut_distance {
+        result["cut_distance"] = serde_json::json!(max_distance);
+    }
+    if let Some(selection) = selection {
+        result["k_selection"] = serde_json::json!(selection);
+    }
+    if options.dendrogram {
+        let leaf_labels: Vec<String> = rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| match row.get(&options.id_field) {
+                Some(Value::String(id)) => id.clone(),
+                Some(id) if !id.is_null() => id.to_string(),
+                _ => i.to_string(),
+            })
+            .collect();
+        result["dendrogram"] = serde_json::json!({
+            "n": dendrogram.n,
+            "merges": dendrogram.merges,
+            "newick": dendrogram.to_newick(&leaf_labels),
+        });
+    }
+    Ok((result, dendrogram))
+}
+
+/// Topic model behind [`op_topics_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TopicModel {
+    /// Non-negative matrix factorization of the TF-IDF matrix.
+    #[default]
+    Nmf,
+    /// Latent Dirichlet Allocation on raw term counts, via collapsed Gibbs sampling.
+    Lda,
+}
+
+/// Parse a model name for [`TopicsOptions::model`].
+pub fn parse_topic_model(s: &str) -> Result<TopicModel, String> {
+    match s.to_lowercase().as_str() {
+        "nmf" => Ok(TopicModel::Nmf),
+        "lda" => Ok(TopicModel::Lda),
+        _ => Err(format!("Unknown topic model '{s}'. Use: nmf, lda")),
+    }
+}
+
+/// Tunables for [`op_topics_with_options`] beyond the iteration cap and
+/// vocabulary size. `tol`, `init`, `l1` and `l2` apply to NMF; `alpha`,
+/// `beta` and `seed` to LDA.
+#[derive(Debug, Clone)]
+pub struct TopicsOptions {
+    pub model: TopicModel,
+    /// Return the input rows with a `_topics` column holding each row's
+    /// top this-many topics and normalized weights, instead of the report.
+    /// Ignored by [`op_topics_sweep`].
+    pub mixture: Option<usize>,
+    /// Stop once the relative drop in the NMF objective falls below this.
+    pub tol: f64,
+    pub init: nmf::NmfInit,
+    /// L1 penalty on both factors.
+    pub l1: f64,
+    /// L2 penalty on both factors.
+    pub l2: f64,
+    /// Dirichlet prior on document-topic mixtures.
+    pub alpha: f64,
+    /// Dirichlet prior on topic-term distributions.
+    pub beta: f64,
+    /// Gibbs sampling seed.
+    pub seed: u64,
+}
+
+impl Default for TopicsOptions {
+    fn default() -> Self {
+        let nmf = nmf::NmfConfig::default();
+        let lda = lda::LdaConfig::default();
+        Self {
+            model: TopicModel::Nmf,
+            mixture: None,
+            tol: nmf.tol,
+            init: nmf.init,
+            l1: nmf.l1,
+            l2: nmf.l2,
+            alpha: lda.alpha,
+            beta: lda.beta,
+            seed: lda.seed,
+        }
+    }
+}
+
+/// Parse an initialisation name for [`TopicsOptions::init`].
+pub fn parse_nmf_init(s: &str) -> Result<nmf::NmfInit, String> {
+    nmf::NmfInit::from_str(s).ok_or_else(|| format!("Unknown NMF init '{s}'. Use: nndsvd, uniform"))
 }
 
 pub fn op_topics(
@@ -604,71 +2143,310 @@ pub fn op_topics(
     max_iter: usize,
     vocab_limit: usize,
 ) -> Result<Value, String> {
+    op_topics_with_options(rows, field, k, top_n, max_iter, vocab_limit, &TopicsOptions::default())
+}
+
+/// [`op_topics`] with explicit [`TopicsOptions`].
+///
+/// Both models return the same shape: `model`, topics with terms and
+/// members, and per-item assignments. `max_iter` caps NMF updates or sets
+/// the number of LDA Gibbs sweeps. Fit statistics depend on the model:
+///
+/// * NMF reports the final `reconstruction_error` (‖V − WH‖),
+///   `relative_error` (that over ‖V‖), the `iterations` run and whether the
+///   objective `converged` before `max_iter`. A relative error that barely
+///   moves as k grows means the extra topics are not explaining more of
+///   the corpus.
+/// * LDA reports `iterations` and the corpus `perplexity`. Runs with the
+///   same seed are identical.
+///
+/// Every topic also carries its UMass and NPMI `coherence` over its top
+/// terms, and the result reports their mean as `coherence` plus the
+/// `diversity` of top terms across topics (see [`coherence`]).
+///
+/// With [`TopicsOptions::mixture`] set, the rows pass through instead, each
+/// with `_topics`: its strongest topics as `{topic, label, weight}`,
+/// heaviest first, weights normalized over all k topics.
+pub fn op_topics_with_options(
+    rows: &[Value],
+    field: &str,
+    k: usize,
+    top_n: usize,
+    max_iter: usize,
+    vocab_limit: usize,
+    options: &TopicsOptions,
+) -> Result<Value, String> {
+    validate_topics_options(options)?;
     if rows.is_empty() {
         return Err("Need at least 1 item for topic modeling".into());
     }
 
-    let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
-    let token_lists: Vec<Vec<String>> = texts.iter().map(|t| tokenizer::tokenize(t)).collect();
-
-    let mut corpus = tfidf::Corpus::new();
-    for tokens in &token_lists {
-        corpus.add_document(tokens);
+    let corpus = topics_corpus(rows, field);
+    let fit = fit_topics(&corpus, rows.len(), k, top_n, max_iter, vocab_limit, options);
+    if let Some(count) = options.mixture {
+        return Ok(attach_topic_mixtures(rows, &fit, count));
     }
+    let quality = TopicQuality::of(&corpus, &fit.top_terms);
 
-    let vectors: Vec<HashMap<String, f64>> = (0..rows.len())
-        .map(|i| corpus.tfidf_vector(i))
-        .collect();
-
-    let result = nmf::nmf(&vectors, k, max_iter, vocab_limit);
-    let dominant = result.dominant_topics();
-
-    let topics: Vec<Value> = (0..k)
-        .map(|t| {
-            let top = result.top_terms(t, top_n);
+    let topics: Vec<Value> = fit
+        .top_terms
+        .iter()
+        .zip(&quality.per_topic)
+        .enumerate()
+        .map(|(t, (top, coherence))| {
             let terms: Vec<Value> = top
                 .iter()
                 .map(|(term, weight)| serde_json::json!({"term": term, "weight": weight}))
                 .collect();
 
-            let members: Vec<Value> = dominant
+            let members: Vec<Value> = fit
+                .dominant
                 .iter()
                 .enumerate()
                 .filter(|(_, &topic)| topic == t)
                 .map(|(i, _)| serde_json::json!(i))
                 .collect();
 
-            let label = top
-                .iter()
-                .take(3)
-                .map(|(t, _)| t.as_str())
-                .collect::<Vec<&str>>()
-                .join(", ");
-
             serde_json::json!({
                 "id": t,
-                "label": label,
+                "label": topic_label(top),
                 "size": members.len(),
+                "coherence": coherence_value(coherence),
                 "terms": terms,
                 "members": members,
             })
         })
         .collect();
 
-    let assignments: Vec<Value> = dominant
+    let assignments: Vec<Value> = fit
+        .dominant
         .iter()
         .enumerate()
         .map(|(i, &topic)| serde_json::json!({"item": i, "topic": topic}))
         .collect();
 
+    let mut result = fit.stats;
+    result.insert("model".into(), serde_json::json!(fit.model));
+    result.insert("num_topics".into(), serde_json::json!(k));
+    result.insert("num_items".into(), serde_json::json!(rows.len()));
+    result.insert("coherence".into(), coherence_value(&quality.mean));
+    result.insert("diversity".into(), serde_json::json!(quality.diversity));
+    result.insert("topics".into(), Value::Array(topics));
+    result.insert("assignments".into(), Value::Array(assignments));
+    Ok(Value::Object(result))
+}
+
+/// Fit the topic model once per value in `ks` and report quality side by
+/// side, to choose k from evidence rather than by guesswork.
+///
+/// Each `sweep` entry has `k`, mean `coherence` (UMass and NPMI),
+/// `diversity` and the model's fit statistics as in
+/// [`op_topics_with_options`]. `best_k` is the k with the highest mean
+/// NPMI; the smaller k wins ties. The corpus is tokenized once and the
+/// fits run in parallel.
+pub fn op_topics_sweep(
+    rows: &[Value],
+    field: &str,
+    ks: &[usize],
+    top_n: usize,
+    max_iter: usize,
+    vocab_limit: usize,
+    options: &TopicsOptions,
+) -> Result<Value, String> {
+    validate_topics_options(options)?;
+    if rows.is_empty() {
+        return Err("Need at least 1 item for topic modeling".into());
+    }
+    let mut ks = ks.to_vec();
+    ks.sort_unstable();
+    ks.dedup();
+    if ks.is_empty() || ks[0] == 0 {
+        return Err("Sweep needs at least one k, each at least 1".into());
+    }
+
+    let corpus = topics_corpus(rows, field);
+    let runs: Vec<(usize, &'static str, TopicQuality, serde_json::Map<String, Value>)> = ks
+        .par_iter()
+        .map(|&k| {
+            let fit = fit_topics(&corpus, rows.len(), k, top_n, max_iter, vocab_limit, options);
+            (k, fit.model, TopicQuality::of(&corpus, &fit.top_terms), fit.stats)
+        })
+        .collect();
+
+    let best_k = runs
+        .iter()
+        .fold(None::<(usize, f64)>, |best, (k, _, quality, _)| match best {
+            Some((_, npmi)) if npmi >= quality.mean.npmi => best,
+            _ => Some((*k, quality.mean.npmi)),
+        })
+        .map(|(k, _)| k);
+    let model = runs[0].1;
+
+    let sweep: Vec<Value> = runs
+        .into_iter()
+        .map(|(k, _, quality, mut entry)| {
+            entry.insert("k".into(), serde_json::json!(k));
+            entry.insert("coherence".into(), coherence_value(&quality.mean));
+            entry.insert("diversity".into(), serde_json::json!(quality.diversity));
+            Value::Object(entry)
+        })
+        .collect();
+
     Ok(serde_json::json!({
-        "num_topics": k,
+        "model": model,
         "num_items": rows.len(),
-        "topics": topics,
-        "assignments": assignments,
+        "best_k": best_k,
+        "sweep": sweep,
     }))
 }
 
+fn validate_topics_options(options: &TopicsOptions) -> Result<(), String> {
+    if options.tol < 0.0 || options.l1 < 0.0 || options.l2 < 0.0 {
+        return Err("tol, l1 and l2 must be non-negative".into());
+    }
+    if options.alpha <= 0.0 || options.beta <= 0.0 {
+        return Err("alpha and beta must be positive".into());
+    }
+    Ok(())
+}
+
+fn topics_corpus(rows: &[Value], field: &str) -> tfidf::Corpus {
+    let mut corpus = tfidf::Corpus::new();
+    for row in rows {
+        corpus.add_document(&tokenizer::tokenize(&get_text(row, field)));
+    }
+    corpus
+}
+
+/// One topic model fit, reduced to what the outputs need.
+struct TopicFit {
+    model: &'static str,
+    /// Top `(term, weight)` pairs per topic.
+    top_terms: Vec<Vec<(String, f64)>>,
+    dominant: Vec<usize>,
+    /// Per-document topic weights summing to 1 (or all zero).
+    mixtures: Vec<Vec<f64>>,
+    /// Model-specific fit statistics.
+    stats: serde_json::Map<String, Value>,
+}
+
+fn fit_topics(
+    corpus: &tfidf::Corpus,
+    n: usize,
+    k: usize,
+    top_n: usize,
+    max_iter: usize,
+    vocab_limit: usize,
+    options: &TopicsOptions,
+) -> TopicFit {
+    let (model, top_terms, dominant, mixtures, stats) = match options.model {
+        TopicModel::Nmf => {
+            let vectors: Vec<SparseVector> = (0..n).map(|i| corpus.tfidf_vector(i)).collect();
+            let config = nmf::NmfConfig {
+                max_iter,
+                tol: options.tol,
+                vocab_limit,
+                init: options.init,
+                l1: options.l1,
+                l2: options.l2,
+            };
+            let result = nmf::nmf(&vectors, corpus.vocabulary(), k, &config);
+            let stats = serde_json::json!({
+                "reconstruction_error": result.reconstruction_error,
+                "relative_error": result.relative_error,
+                "iterations": result.iterations,
+                "converged": result.converged,
+            });
+            let top: Vec<Vec<(String, f64)>> = (0..k).map(|t| result.top_terms(t, top_n)).collect();
+            ("nmf", top, result.dominant_topics(), result.topic_mixtures(), stats)
+        }
+        TopicModel::Lda => {
+            let counts: Vec<&[(TermId, u32)]> = (0..n).map(|i| corpus.term_counts(i)).collect();
+            let config = lda::LdaConfig {
+                iterations: max_iter,
+                alpha: options.alpha,
+                beta: options.beta,
+                seed: options.seed,
+                vocab_limit,
+            };
+            let result = lda::lda(&counts, corpus.vocabulary(), k, &config);
+            let stats = serde_json::json!({
+                "iterations": result.iterations,
+                "perplexity": result.perplexity,
+            });
+            let top: Vec<Vec<(String, f64)>> = (0..k).map(|t| result.top_terms(t, top_n)).collect();
+            let dominant = result.dominant_topics();
+            ("lda", top, dominant, result.doc_topics, stats)
+        }
+    };
+    let stats = match stats {
+        Value::Object(map) => map,
+        _ => serde_json::Map::new(),
+    };
+    TopicFit { model, top_terms, dominant, mixtures, stats }
+}
+
+/// The first three top terms, comma-separated.
+fn topic_label(top: &[(String, f64)]) -> String {
+    top.iter()
+        .take(3)
+        .map(|(t, _)| t.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+fn attach_topic_mixtures(rows: &[Value], fit: &TopicFit, count: usize) -> Value {
+    let labels: Vec<String> = fit.top_terms.iter().map(|top| topic_label(top)).collect();
+    let output: Vec<Value> = rows
+        .iter()
+        .cloned()
+        .zip(&fit.mixtures)
+        .map(|(mut row, mixture)| {
+            let mut ranked: Vec<(usize, f64)> =
+                mixture.iter().copied().enumerate().filter(|&(_, w)| w > 0.0).collect();
+            ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            let topics: Vec<Value> = ranked
+                .into_iter()
+                .take(count)
+                .map(|(t, w)| serde_json::json!({"topic": t, "label": labels[t], "weight": w}))
+                .collect();
+            if let Some(obj) = row.as_object_mut() {
+                obj.insert("_topics".into(), Value::Array(topics));
+            }
+            row
+        })
+        .collect();
+    Value::Array(output)
+}
+
+/// Coherence per topic, its mean, and diversity across topics.
+struct TopicQuality {
+    per_topic: Vec<coherence::Coherence>,
+    mean: coherence::Coherence,
+    diversity: f64,
+}
+
+impl TopicQuality {
+    fn of(corpus: &tfidf::Corpus, top_terms: &[Vec<(String, f64)>]) -> Self {
+        let lists: Vec<Vec<&str>> = top_terms
+            .iter()
+            .map(|top| top.iter().map(|(t, _)| t.as_str()).collect())
+            .collect();
+        let per_topic: Vec<coherence::Coherence> =
+            lists.iter().map(|terms| coherence::topic_coherence(corpus, terms)).collect();
+        let count = per_topic.len().max(1) as f64;
+        let mean = coherence::Coherence {
+            umass: per_topic.iter().map(|c| c.umass).sum::<f64>() / count,
+            npmi: per_topic.iter().map(|c| c.npmi).sum::<f64>() / count,
+        };
+        Self { per_topic, mean, diversity: coherence::diversity(&lists) }
+    }
+}
+
+fn coherence_value(c: &coherence::Coherence) -> Value {
+    serde_json::json!({"umass": c.umass, "npmi": c.npmi})
+}
+
 pub fn op_organize(
     rows: &[Value],
     format: &str,
@@ -709,7 +2487,7 @@ pub fn op_organize(
                         .to_string();
                     let path = hierarchy
                         .split(" > ")
-                        .map(|p| url_normalize::slugify(p))
+                        .map(url_normalize::slugify)
                         .collect::<Vec<String>>()
                         .join("/");
                     std::format!("{output_dir}/{path}/{slug_name}")
@@ -739,12 +2517,36 @@ struct ClassifyArgs {
     clusters: usize,
     sample_size: usize,
     seed: u64,
+    depth: usize,
+    branching: usize,
+    auto_k: bool,
+    assign: discover::Assignment,
+    linkage: clustering::Linkage,
+}
+
+/// Args struct for corpus cache keying. Row-keyed artifacts are looked up by
+/// args alone, so everything that shapes them besides row text goes here.
+#[cfg_attr(not(feature = "cache"), allow(dead_code))]
+#[derive(Serialize, Deserialize)]
+struct CorpusArgs<'a> {
+    field: &'a str,
+    tokenizer: tokenizer::TokenizerConfig,
+}
+
+/// Args struct for dendrogram cache keying. The tree depends only on the
+/// rows and the linkage; `depth`, `auto_k` and `cut_distance` just cut it.
+#[cfg_attr(not(feature = "cache"), allow(dead_code))]
+#[derive(Serialize, Deserialize)]
+struct DendrogramArgs {
+    linkage: clustering::Linkage,
 }
 
 /// Args struct for fingerprint cache keying.
 #[cfg_attr(not(feature = "cache"), allow(dead_code))]
 #[derive(Serialize, Deserialize)]
-struct FingerprintArgs {
+struct FingerprintArgs<'a> {
+    field: &'a str,
+    tokenizer: tokenizer::TokenizerConfig,
     weighted: bool,
 }
 
@@ -765,32 +2567,8 @@ pub fn op_analyze_cached(
             let content_field = field.unwrap_or("content");
             if let Ok(db) = storage::CacheDb::open_or_create(path) {
                 let texts: Vec<String> = rows.iter().map(|r| get_text(r, content_field)).collect();
-                let c_hash = cache::content_hash(&texts);
-
-                // Cache the corpus (no args dependency — always same structure)
-                let a_hash = cache::args_hash(&());
-                let token_lists: Vec<Vec<String>> =
-                    texts.iter().map(|t| tokenizer::tokenize(t)).collect();
-                let mut corpus = tfidf::Corpus::new();
-                for tokens in &token_lists {
-                    corpus.add_document(tokens);
-                }
-                if let Ok(payload) = serde_json::to_vec(&corpus) {
-                    let meta = cache::CacheMeta::new(c_hash, rows.len(), a_hash);
-                    let _ = db.put(cache::ArtifactKind::Corpus, &meta, &payload);
-                }
-
-                // Cache fingerprints (unweighted)
-                let fingerprints: Vec<u64> = token_lists
-                    .par_iter()
-                    .map(|t| simhash::simhash_uniform(t))
-                    .collect();
-                if let Ok(payload) = serde_json::to_vec(&fingerprints) {
-                    let fp_args = FingerprintArgs { weighted: false };
-                    let fp_a_hash = cache::args_hash(&fp_args);
-                    let meta = cache::CacheMeta::new(c_hash, rows.len(), fp_a_hash);
-                    let _ = db.put(cache::ArtifactKind::Fingerprints, &meta, &payload);
-                }
+                cached_corpus(&db, content_field, &texts);
+                cached_fingerprints(&db, content_field, &texts, false);
 
                 // Add _cache info to result
                 if let Some(obj) = result.as_object_mut() {
@@ -822,7 +2600,16 @@ pub fn op_analyze_cached(
     result
 }
 
+/// Classified rows, plus the per-k silhouette scores when `auto_k` chose
+/// the number of discovered categories.
+#[derive(Debug)]
+pub struct ClassifyOutput {
+    pub rows: Value,
+    pub k_selection: Option<clustering::KSelection>,
+}
+
 /// Classify with optional cache. Caches the discovered taxonomy.
+#[allow(clippy::too_many_arguments)]
 pub fn op_classify_cached(
     rows: &[Value],
     field: &str,
@@ -831,43 +2618,30 @@ pub fn op_classify_cached(
     sample_size: usize,
     threshold: f64,
     seed: u64,
+    options: &ClassifyOptions,
     cache_path: Option<&str>,
-) -> Result<Value, String> {
+) -> Result<ClassifyOutput, String> {
     if rows.is_empty() {
-        return Ok(Value::Array(vec![]));
+        return Ok(ClassifyOutput { rows: Value::Array(vec![]), k_selection: None });
     }
 
     let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
 
-    let tax = match taxonomy_json {
+    let (tax, k_selection) = match taxonomy_json {
         Some(v) => {
             let json_str = serde_json::to_string(v)
                 .map_err(|e| format!("Failed to serialize taxonomy: {e}"))?;
-            taxonomy::parse_taxonomy(&json_str)?
+            (taxonomy::parse_taxonomy(&json_str)?, None)
         }
-        None => resolve_taxonomy(&texts, clusters, sample_size, seed, cache_path)?,
+        None => resolve_taxonomy(&texts, field, clusters, sample_size, seed, options, cache_path)?,
     };
 
-    let classifications = discover::classify_against_taxonomy(&texts, &tax, threshold);
-
-    let output: Vec<Value> = rows
-        .iter()
-        .cloned()
-        .zip(classifications)
-        .map(|(mut row, (cat, hier, conf))| {
-            if let Some(obj) = row.as_object_mut() {
-                obj.insert("_category".into(), Value::String(cat));
-                obj.insert("_hierarchy".into(), Value::String(hier));
-                obj.insert("_confidence".into(), serde_json::json!(conf));
-            }
-            row
-        })
-        .collect();
-
-    Ok(Value::Array(output))
+    let rows = classify_rows(rows, field, &texts, &tax, threshold, options, cache_path)?;
+    Ok(ClassifyOutput { rows, k_selection })
 }
 
 /// Classify from file path with optional cache.
+#[allow(clippy::too_many_arguments)]
 pub fn op_classify_from_file_cached(
     rows: &[Value],
     field: &str,
@@ -876,60 +2650,62 @@ pub fn op_classify_from_file_cached(
     sample_size: usize,
     threshold: f64,
     seed: u64,
+    options: &ClassifyOptions,
     cache_path: Option<&str>,
-) -> Result<Value, String> {
+) -> Result<ClassifyOutput, String> {
     if rows.is_empty() {
-        return Ok(Value::Array(vec![]));
+        return Ok(ClassifyOutput { rows: Value::Array(vec![]), k_selection: None });
     }
 
     let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
 
-    let tax = match taxonomy_path {
-        Some(path) => taxonomy::load_taxonomy(path)?,
-        None => resolve_taxonomy(&texts, clusters, sample_size, seed, cache_path)?,
+    let (tax, k_selection) = match taxonomy_path {
+        Some(path) => (taxonomy::load_taxonomy(path)?, None),
+        None => resolve_taxonomy(&texts, field, clusters, sample_size, seed, options, cache_path)?,
     };
 
-    let classifications = discover::classify_against_taxonomy(&texts, &tax, threshold);
-
-    let output: Vec<Value> = rows
-        .iter()
-        .cloned()
-        .zip(classifications)
-        .map(|(mut row, (cat, hier, conf))| {
-            if let Some(obj) = row.as_object_mut() {
-                obj.insert("_category".into(), Value::String(cat));
-                obj.insert("_hierarchy".into(), Value::String(hier));
-                obj.insert("_confidence".into(), serde_json::json!(conf));
-            }
-            row
-        })
-        .collect();
+    let rows = classify_rows(rows, field, &texts, &tax, threshold, options, cache_path)?;
+    Ok(ClassifyOutput { rows, k_selection })
+}
 
-    Ok(Value::Array(output))
+/// Cached discovery result. The taxonomy is flattened so entries written
+/// before `k_selection` was stored still load.
+#[cfg_attr(not(feature = "cache"), allow(dead_code))]
+#[derive(Serialize, Deserialize)]
+struct DiscoveredTaxonomy {
+    #[serde(flatten)]
+    taxonomy: taxonomy::Taxonomy,
+    #[serde(default)]
+    k_selection: Option<clustering::KSelection>,
 }
 
 /// Resolve taxonomy: try cache first, then discover from scratch.
 fn resolve_taxonomy(
     texts: &[String],
+    #[cfg_attr(not(feature = "cache"), allow(unused))]
+    field: &str,
     clusters: usize,
     sample_size: usize,
     seed: u64,
+    options: &ClassifyOptions,
     cache_path: Option<&str>,
-) -> Result<taxonomy::Taxonomy, String> {
-    let config = discover::DiscoverConfig {
-        k: clusters,
-        sample_size,
-        label_terms: 3,
-        keywords_per_cluster: 20,
-        linkage: clustering::Linkage::Ward,
-        seed,
-    };
+) -> Result<(taxonomy::Taxonomy, Option<clustering::KSelection>), String> {
+    let config = discover_config(clusters, sample_size, seed, options);
 
     #[cfg(feature = "cache")]
     if let Some(path) = cache_path {
         if let Ok(db) = storage::CacheDb::open_or_create(path) {
-            let c_hash = cache::content_hash(texts);
-            let args = ClassifyArgs { clusters, sample_size, seed };
+            let c_hash = cache::content_hash(texts, field, &tokenizer::TokenizerConfig::default());
+            let args = ClassifyArgs {
+                clusters,
+                sample_size,
+                seed,
+                depth: options.depth,
+                branching: options.branching,
+                auto_k: options.auto_k,
+                assign: options.assign,
+                linkage: options.linkage,
+            };
             let a_hash = cache::args_hash(&args);
 
             // Try cache hit
@@ -937,24 +2713,29 @@ fn resolve_taxonomy(
                 db.get(cache::ArtifactKind::Taxonomy, c_hash, a_hash)
             {
                 if cache::is_valid(&meta, c_hash, a_hash) {
-                    if let Ok(tax) = serde_json::from_slice::<taxonomy::Taxonomy>(&payload) {
-                        return Ok(tax);
+                    if let Ok(found) = serde_json::from_slice::<DiscoveredTaxonomy>(&payload) {
+                        // Entries from before k_selection was stored are
+                        // rediscovered so --auto-k can report its scores.
+                        if !options.auto_k || found.k_selection.is_some() {
+                            return Ok((found.taxonomy, found.k_selection));
+                        }
                     }
                 }
             }
 
             // Cache miss: discover and store
-            let tax = discover::discover_taxonomy(texts, &config);
-            if let Ok(payload) = serde_json::to_vec(&tax) {
+            let (taxonomy, k_selection) = discover::discover_taxonomy_with_selection(texts, &config);
+            let found = DiscoveredTaxonomy { taxonomy, k_selection };
+            if let Ok(payload) = serde_json::to_vec(&found) {
                 let meta = cache::CacheMeta::new(c_hash, texts.len(), a_hash);
                 let _ = db.put(cache::ArtifactKind::Taxonomy, &meta, &payload);
             }
-            return Ok(tax);
+            return Ok((found.taxonomy, found.k_selection));
         }
     }
 
     let _ = cache_path;
-    Ok(discover::discover_taxonomy(texts, &config))
+    Ok(discover::discover_taxonomy_with_selection(texts, &config))
 }
 
 /// Tags with optional cache (caches the corpus).
@@ -969,9 +2750,7 @@ pub fn op_tags_cached(
     }
 
     let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
-    let token_lists: Vec<Vec<String>> = texts.iter().map(|t| tokenizer::tokenize(t)).collect();
-
-    let corpus = resolve_corpus(&token_lists, &texts, cache_path);
+    let corpus = resolve_corpus(field, &texts, cache_path);
 
     let output: Vec<Value> = rows
         .iter()
@@ -993,50 +2772,174 @@ pub fn op_tags_cached(
     Value::Array(output)
 }
 
-/// Resolve corpus: try cache first, then build from scratch.
+/// Resolve corpus: reuse the cached one (patched for added/removed rows),
+/// otherwise build from scratch.
 fn resolve_corpus(
-    token_lists: &[Vec<String>],
     #[cfg_attr(not(feature = "cache"), allow(unused))]
+    field: &str,
     texts: &[String],
     cache_path: Option<&str>,
 ) -> tfidf::Corpus {
     #[cfg(feature = "cache")]
     if let Some(path) = cache_path {
         if let Ok(db) = storage::CacheDb::open_or_create(path) {
-            let c_hash = cache::content_hash(texts);
-            let a_hash = cache::args_hash(&());
-
-            if let Ok(Some((meta, payload))) =
-                db.get(cache::ArtifactKind::Corpus, c_hash, a_hash)
-            {
-                if cache::is_valid(&meta, c_hash, a_hash) {
-                    if let Ok(corpus) = serde_json::from_slice::<tfidf::Corpus>(&payload) {
-                        return corpus;
-                    }
-                }
-            }
-
-            // Build and cache
-            let mut corpus = tfidf::Corpus::new();
-            for tokens in token_lists {
-                corpus.add_document(tokens);
-            }
-            if let Ok(payload) = serde_json::to_vec(&corpus) {
-                let meta = cache::CacheMeta::new(c_hash, texts.len(), a_hash);
-                let _ = db.put(cache::ArtifactKind::Corpus, &meta, &payload);
-            }
-            return corpus;
+            return cached_corpus(&db, field, texts);
         }
     }
 
     let _ = cache_path;
+    build_corpus(texts)
+}
+
+fn build_corpus(texts: &[String]) -> tfidf::Corpus {
+    let token_lists: Vec<Vec<String>> = texts.par_iter().map(|t| tokenizer::tokenize(t)).collect();
     let mut corpus = tfidf::Corpus::new();
-    for tokens in token_lists {
+    for tokens in &token_lists {
+        corpus.add_document(tokens);
+    }
+    corpus
+}
+
+/// Load the most recent row-keyed artifact of `kind` stored under `a_hash`.
+#[cfg(feature = "cache")]
+fn load_row_keyed<T: serde::de::DeserializeOwned>(
+    db: &storage::CacheDb,
+    kind: cache::ArtifactKind,
+    a_hash: u64,
+) -> Option<cache::RowKeyed<T>> {
+    let (meta, payload) = db.get_latest(kind, a_hash).ok().flatten()?;
+    if !cache::is_reusable(&meta, a_hash) {
+        return None;
+    }
+    // Payloads written before per-row keying fail here and count as a miss.
+    serde_json::from_slice(&payload).ok()
+}
+
+/// Store a row-keyed artifact and drop older snapshots under the same args.
+#[cfg(feature = "cache")]
+fn store_row_keyed<T: Serialize>(
+    db: &storage::CacheDb,
+    kind: cache::ArtifactKind,
+    field: &str,
+    texts: &[String],
+    a_hash: u64,
+    row_hashes: Vec<u64>,
+    data: &T,
+) {
+    let c_hash = cache::content_hash(texts, field, &tokenizer::TokenizerConfig::default());
+    let keyed = cache::RowKeyed { row_hashes, data };
+    if let Ok(payload) = serde_json::to_vec(&keyed) {
+        let meta = cache::CacheMeta::new(c_hash, texts.len(), a_hash);
+        if db.put(kind, &meta, &payload).is_ok() {
+            let _ = db.prune(kind, a_hash, c_hash);
+        }
+    }
+}
+
+/// Corpus for `texts`, reusing the latest cached corpus. Only rows that were
+/// not seen before are tokenized; rows that disappeared are subtracted.
+#[cfg(feature = "cache")]
+fn cached_corpus(db: &storage::CacheDb, field: &str, texts: &[String]) -> tfidf::Corpus {
+    let kind = cache::ArtifactKind::Corpus;
+    let a_hash = cache::args_hash(&CorpusArgs {
+        field,
+        tokenizer: tokenizer::TokenizerConfig::default(),
+    });
+    let row_hashes: Vec<u64> = texts.iter().map(|t| cache::row_hash(t)).collect();
+
+    let corpus = match load_row_keyed::<tfidf::Corpus>(db, kind, a_hash) {
+        Some(prev) if prev.row_hashes == row_hashes => return prev.data,
+        Some(prev) => patch_corpus(prev, texts, &row_hashes),
+        None => build_corpus(texts),
+    };
+    store_row_keyed(db, kind, field, texts, a_hash, row_hashes, &corpus);
+    corpus
+}
+
+/// Bring a cached corpus in line with the current rows: remove unmatched
+/// documents, append new ones, then restore the current row order.
+#[cfg(feature = "cache")]
+fn patch_corpus(
+    prev: cache::RowKeyed<tfidf::Corpus>,
+    texts: &[String],
+    row_hashes: &[u64],
+) -> tfidf::Corpus {
+    let (matched, removed) = cache::match_rows(&prev.row_hashes, row_hashes);
+    let mut corpus = prev.data;
+    for &idx in removed.iter().rev() {
+        corpus.remove_document(idx);
+    }
+    let kept = prev.row_hashes.len() - removed.len();
+
+    let new_rows: Vec<usize> = (0..texts.len()).filter(|&i| matched[i].is_none()).collect();
+    let new_tokens: Vec<Vec<String>> = new_rows
+        .par_iter()
+        .map(|&i| tokenizer::tokenize(&texts[i]))
+        .collect();
+    for tokens in &new_tokens {
         corpus.add_document(tokens);
     }
+
+    // Surviving old docs shift down past the removed ones; new docs follow them.
+    let mut next_new = kept;
+    let order: Vec<usize> = matched
+        .iter()
+        .map(|m| match m {
+            Some(old) => old - removed.partition_point(|&r| r < *old),
+            None => {
+                next_new += 1;
+                next_new - 1
+            }
+        })
+        .collect();
+    corpus.reorder(&order);
     corpus
 }
 
+/// Fingerprints for `texts`, reusing cached values. Unweighted fingerprints
+/// are per-row, so only new rows are hashed; weighted ones depend on corpus
+/// IDF and are recomputed from the (incrementally cached) corpus.
+#[cfg(feature = "cache")]
+fn cached_fingerprints(
+    db: &storage::CacheDb,
+    field: &str,
+    texts: &[String],
+    weighted: bool,
+) -> Vec<u64> {
+    let kind = cache::ArtifactKind::Fingerprints;
+    let a_hash = cache::args_hash(&FingerprintArgs {
+        field,
+        tokenizer: tokenizer::TokenizerConfig::default(),
+        weighted,
+    });
+    let row_hashes: Vec<u64> = texts.iter().map(|t| cache::row_hash(t)).collect();
+
+    let fingerprints = match load_row_keyed::<Vec<u64>>(db, kind, a_hash) {
+        Some(prev) if prev.row_hashes == row_hashes => return prev.data,
+        _ if weighted => {
+            let corpus = cached_corpus(db, field, texts);
+            let token_lists: Vec<Vec<String>> =
+                texts.par_iter().map(|t| tokenizer::tokenize(t)).collect();
+            weighted_fingerprints(&corpus, &token_lists)
+        }
+        Some(prev) => {
+            let known: HashMap<u64, u64> =
+                prev.row_hashes.into_iter().zip(prev.data).collect();
+            texts
+                .par_iter()
+                .zip(&row_hashes)
+                .map(|(text, h)| match known.get(h) {
+                    Some(&fp) => fp,
+                    None => simhash::simhash_uniform(&tokenizer::tokenize(text)),
+                })
+                .collect()
+        }
+        None => compute_fingerprints(texts, false),
+    };
+    store_row_keyed(db, kind, field, texts, a_hash, row_hashes, &fingerprints);
+    fingerprints
+}
+
 /// Fingerprint with optional cache.
 pub fn op_fingerprint_cached(
     rows: &[Value],
@@ -1044,64 +2947,115 @@ pub fn op_fingerprint_cached(
     weighted: bool,
     cache_path: Option<&str>,
 ) -> Value {
-    let result = op_fingerprint(rows, field, weighted);
-
     #[cfg(feature = "cache")]
     if let Some(path) = cache_path {
         if !rows.is_empty() {
             if let Ok(db) = storage::CacheDb::open_or_create(path) {
                 let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
-                let c_hash = cache::content_hash(&texts);
-                let fp_args = FingerprintArgs { weighted };
-                let a_hash = cache::args_hash(&fp_args);
-
-                // Extract fingerprints from result for caching
-                if let Some(arr) = result.as_array() {
-                    let fps: Vec<String> = arr
-                        .iter()
-                        .filter_map(|r| {
-                            r.get("_fingerprint")
-                                .and_then(|v| v.as_str())
-                                .map(|s| s.to_string())
-                        })
-                        .collect();
-                    if let Ok(payload) = serde_json::to_vec(&fps) {
-                        let meta = cache::CacheMeta::new(c_hash, rows.len(), a_hash);
-                        let _ = db.put(cache::ArtifactKind::Fingerprints, &meta, &payload);
-                    }
-                }
+                return attach_fingerprints(rows, &cached_fingerprints(&db, field, &texts, weighted));
             }
         }
     }
 
     let _ = cache_path;
-    result
+    op_fingerprint(rows, field, weighted)
 }
 
-/// Dedup with optional cache (pass-through — dedup always recomputes).
+/// Dedup with optional cache. The SimHash strategies reuse cached
+/// fingerprints; grouping itself is always recomputed.
 pub fn op_dedup_cached(
     rows: &[Value],
     field: &str,
     url_field: &str,
     strategy: &str,
     threshold: u32,
+    options: &DedupOptions,
+    cache_path: Option<&str>,
+) -> Value {
+    #[cfg(feature = "cache")]
+    if let Some(path) = cache_path {
+        if let Ok(db) = storage::CacheDb::open_or_create(path) {
+            return dedup_rows(rows, field, url_field, strategy, threshold, options, |texts| {
+                cached_fingerprints(&db, field, texts, false)
+            });
+        }
+    }
+
+    let _ = cache_path;
+    op_dedup_with_options(rows, field, url_field, strategy, threshold, options)
+}
+
+/// [`op_dedup_against`] with optional cache: the reference set's SimHash
+/// fingerprints are reused from the cache, so only new or changed reference
+/// rows are hashed.
+#[allow(clippy::too_many_arguments)]
+pub fn op_dedup_against_cached(
+    rows: &[Value],
+    reference: &[Value],
+    field: &str,
+    url_field: &str,
+    strategy: &str,
+    threshold: u32,
+    options: &DedupOptions,
     cache_path: Option<&str>,
 ) -> Value {
+    #[cfg(feature = "cache")]
+    if let Some(path) = cache_path {
+        if let Ok(db) = storage::CacheDb::open_or_create(path) {
+            return match_reference(
+                rows, reference, field, url_field, strategy, threshold, options,
+                |texts| cached_fingerprints(&db, field, texts, false),
+            );
+        }
+    }
+
     let _ = cache_path;
-    op_dedup(rows, field, url_field, strategy, threshold)
+    op_dedup_against(rows, reference, field, url_field, strategy, threshold, options)
 }
 
-/// Generate taxonomy with optional cache (pass-through for now).
+/// Generate taxonomy with optional cache. The dendrogram is stored per rows
+/// and linkage, so re-cutting at another `depth` or `cut_distance` skips the
+/// distance matrix and HAC.
 pub fn op_generate_cached(
     rows: &[Value],
     field: &str,
     depth: usize,
     linkage_str: &str,
     top_n: usize,
+    options: &GenerateOptions,
     cache_path: Option<&str>,
 ) -> Result<Value, String> {
+    #[cfg(feature = "cache")]
+    if let Some(path) = cache_path {
+        if let (Ok(db), Some(linkage)) = (
+            storage::CacheDb::open_or_create(path),
+            clustering::Linkage::from_str(linkage_str),
+        ) {
+            let texts: Vec<String> = rows.iter().map(|r| get_text(r, field)).collect();
+            let c_hash = cache::content_hash(&texts, field, &tokenizer::TokenizerConfig::default());
+            let a_hash = cache::args_hash(&DendrogramArgs { linkage });
+
+            let cached = match db.get(cache::ArtifactKind::Dendrogram, c_hash, a_hash) {
+                Ok(Some((meta, payload))) if cache::is_valid(&meta, c_hash, a_hash) => {
+                    serde_json::from_slice::<clustering::Dendrogram>(&payload).ok()
+                }
+                _ => None,
+            };
+            let hit = cached.is_some();
+            let (result, dendrogram) =
+                generate_taxonomy(rows, field, depth, linkage_str, top_n, options, cached)?;
+            if !hit {
+                if let Ok(payload) = serde_json::to_vec(&dendrogram) {
+                    let meta = cache::CacheMeta::new(c_hash, rows.len(), a_hash);
+                    let _ = db.put(cache::ArtifactKind::Dendrogram, &meta, &payload);
+                }
+            }
+            return Ok(result);
+        }
+    }
+
     let _ = cache_path;
-    op_generate(rows, field, depth, linkage_str, top_n)
+    op_generate_with_options(rows, field, depth, linkage_str, top_n, options)
 }
 
 // ── Cache management operations ─────────────────────────────────────────────
@@ -1119,7 +3073,7 @@ pub fn op_cache_info(#[cfg_attr(not(feature = "cache"), allow(unused))] cache_pa
             .map(|a| {
                 serde_json::json!({
                     "kind": a.kind,
-                    "content_hash": a.content_hash,
+                    "content_hash": cache::content_hash_hex(a.content_hash),
                     "args_hash": a.args_hash,
                     "row_count": a.row_count,
                     "version": a.version,
@@ -1129,12 +3083,13 @@ pub fn op_cache_info(#[cfg_attr(not(feature = "cache"), allow(unused))] cache_pa
             })
             .collect();
 
-        return Ok(serde_json::json!({
+        Ok(serde_json::json!({
             "path": cache_path,
             "size_bytes": size,
             "total": artifacts.len(),
             "artifacts": artifact_list,
-        }));
+            "indexed": db.index_len()?,
+        }))
     }
 
     #[cfg(not(feature = "cache"))]
@@ -1157,11 +3112,11 @@ pub fn op_cache_clear(
             None => None,
         };
         let deleted = db.invalidate(artifact_kind)?;
-        return Ok(serde_json::json!({
+        Ok(serde_json::json!({
             "path": cache_path,
             "deleted": deleted,
             "kind": kind.unwrap_or("all"),
-        }));
+        }))
     }
 
     #[cfg(not(feature = "cache"))]
@@ -1170,3 +3125,288 @@ pub fn op_cache_clear(
         Err("Cache feature not enabled. Build with --features cache".into())
     }
 }
+
+// ── Persistent near-duplicate index ─────────────────────────────────────────
+
+/// Tunables for [`op_lookup`].
+#[derive(Debug, Clone)]
+pub struct LookupOptions {
+    /// Max Hamming distance between SimHash fingerprints.
+    pub threshold: u32,
+    /// Min estimated Jaccard similarity between MinHash signatures.
+    pub jaccard: f64,
+    /// Max number of neighbours returned.
+    pub limit: usize,
+}
+
+impl Default for LookupOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 3,
+            jaccard: 0.5,
+            limit: 10,
+        }
+    }
+}
+
+/// What [`op_lookup`] searches for. A fingerprint or signature given directly
+/// replaces the one computed from `text`.
+#[derive(Debug, Clone, Default)]
+pub struct LookupQuery {
+    pub text: String,
+    /// Matches records with the same canonical URL.
+    pub url: Option<String>,
+    /// Unweighted SimHash, as in `_fingerprint` from `fingerprint`.
+    pub fingerprint: Option<u64>,
+    /// MinHash signature of 128 values over 3-character shingles.
+    pub signature: Option<Vec<u64>>,
+}
+
+/// Parse a hex SimHash fingerprint (optionally `0x`-prefixed) for
+/// [`LookupQuery::fingerprint`].
+pub fn parse_fingerprint(s: &str) -> Result<u64, String> {
+    let hex = s.trim();
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    u64::from_str_radix(hex, 16).map_err(|e| format!("Invalid fingerprint '{s}': expected up to 16 hex digits ({e})"))
+}
+
+/// Parse a MinHash signature of hex values separated by commas or
+/// whitespace for [`LookupQuery::signature`].
+pub fn parse_signature(s: &str) -> Result<Vec<u64>, String> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty())
+        .map(parse_fingerprint)
+        .collect()
+}
+
+/// SimHash and MinHash fingerprints of one text for the persistent index,
+/// `None` when the text is empty.
+#[cfg(feature = "cache")]
+fn index_fingerprints(text: &str) -> (Option<u64>, Option<Vec<u64>>) {
+    let tokens = tokenizer::tokenize(text);
+    let simhash = (!tokens.is_empty()).then(|| simhash::simhash_uniform(&tokens));
+    let hasher = minhash::MinHasher::with_default_perm();
+    let signature = minhash_signatures(&hasher, &[text.to_string()], INDEX_SHINGLE_SIZE)
+        .pop()
+        .flatten();
+    (simhash, signature)
+}
+
+/// MinHash bands of the persistent index (4 rows each over 128 values).
+#[cfg(feature = "cache")]
+const INDEX_MINHASH_BANDS: usize = 32;
+
+/// Largest SimHash distance the index's 16 bands of 4 bits find every pair
+/// within.
+#[cfg(feature = "cache")]
+const INDEX_MAX_DISTANCE: u32 = 15;
+
+/// Character shingle width of the persistent index's MinHash signatures.
+#[cfg(feature = "cache")]
+const INDEX_SHINGLE_SIZE: usize = 3;
+
+/// Version of the index band layout produced by [`index_band_keys`]. Bump it
+/// whenever that layout changes; [`open_index`] then re-keys stored entries.
+#[cfg(feature = "cache")]
+const INDEX_LAYOUT: u32 = 1;
+
+/// Open the cache database for the near-duplicate index, re-keying entries
+/// written under another band layout from their stored fingerprints.
+#[cfg(feature = "cache")]
+fn open_index(path: &str) -> Result<storage::CacheDb, String> {
+    let db = storage::CacheDb::open_or_create(path)?;
+    if db.index_layout()? != Some(INDEX_LAYOUT) {
+        db.index_rekey(INDEX_LAYOUT, |entry| index_band_keys(entry.simhash, entry.signature.as_deref()))?;
+    }
+    Ok(db)
+}
+
+/// LSH bucket keys of an index entry. SimHash bands come first, MinHash
+/// bands are numbered after them so both share one table.
+///
+/// The layout is fixed when entries are written, before lookup thresholds
+/// are known: 16 SimHash bands find every pair within 15 bits, and MinHash
+/// uses [`INDEX_MINHASH_BANDS`] bands over signatures of
+/// [`INDEX_SHINGLE_SIZE`]-character shingles. Both are pinned rather than
+/// derived from [`DedupOptions`], so changing a default cannot silently
+/// re-key an index already on disk. Changing the bands requires an
+/// [`INDEX_LAYOUT`] bump; stored signatures cannot follow a new shingle size.
+#[cfg(feature = "cache")]
+fn index_band_keys(simhash: Option<u64>, signature: Option<&[u64]>) -> Vec<(u32, u64)> {
+    let simhash_index = lsh::SimHashLshIndex::default_64();
+    let mut keys: Vec<(u32, u64)> = Vec::new();
+    if let Some(fp) = simhash {
+        keys.extend(simhash_index.band_keys(fp).into_iter().enumerate().map(|(b, k)| (b as u32, k)));
+    }
+    if let Some(sig) = signature {
+        let offset = simhash_index.band_keys(0).len() as u32;
+        let rows = sig.len() / INDEX_MINHASH_BANDS;
+        let minhash_keys = lsh::LshIndex::new(INDEX_MINHASH_BANDS, rows).band_keys(sig);
+        keys.extend(minhash_keys.into_iter().enumerate().map(|(b, k)| (offset + b as u32, k)));
+    }
+    keys
+}
+
+/// Add records to the near-duplicate index stored in the cache database.
+///
+/// Each record is keyed by `id_field`, falling back to its URL and then to a
+/// hash of its text; re-adding an id replaces the entry. Records with neither
+/// text nor URL are skipped. The batch is written in one transaction, so a
+/// failure leaves the index unchanged. Returns `{path, added, skipped, total}`.
+pub fn op_index_add(
+    #[cfg_attr(not(feature = "cache"), allow(unused))] rows: &[Value],
+    #[cfg_attr(not(feature = "cache"), allow(unused))] field: &str,
+    #[cfg_attr(not(feature = "cache"), allow(unused))] url_field: &str,
+    #[cfg_attr(not(feature = "cache"), allow(unused))] id_field: &str,
+    #[cfg_attr(not(feature = "cache"), allow(unused))] cache_path: &str,
+) -> Result<Value, String> {
+    #[cfg(feature = "cache")]
+    {
+        let db = open_index(cache_path)?;
+        let mut entries: Vec<(storage::IndexEntry, Vec<(u32, u64)>)> = Vec::new();
+        for row in rows {
+            let text = get_text(row, field);
+            let url = row.get(url_field).and_then(scalar_text);
+            let (simhash, signature) = index_fingerprints(&text);
+            if simhash.is_none() && signature.is_none() && url.is_none() {
+                continue;
+            }
+            let id = match row.get(id_field) {
+                Some(Value::String(id)) => id.clone(),
+                Some(id) if !id.is_null() => id.to_string(),
+                _ => match &url {
+                    Some(url) => url.clone(),
+                    None => format!("{:016x}", cache::row_hash(&text)),
+                },
+            };
+            let entry = storage::IndexEntry {
+                id,
+                url_key: url.as_deref().and_then(url_normalize::canonical_key),
+                simhash,
+                signature,
+                record: row.to_string(),
+            };
+            let band_keys = index_band_keys(entry.simhash, entry.signature.as_deref());
+            entries.push((entry, band_keys));
+        }
+        db.index_insert_all(entries.iter().map(|(entry, keys)| (entry, keys.as_slice())))?;
+        let added = entries.len();
+        Ok(serde_json::json!({
+            "path": cache_path,
+            "added": added,
+            "skipped": rows.len() - added,
+            "total": db.index_len()?,
+        }))
+    }
+
+    #[cfg(not(feature = "cache"))]
+    Err("Cache feature not enabled. Build with --features cache".into())
+}
+
+/// Remove records from the near-duplicate index by id.
+/// Returns `{path, removed, total}`.
+pub fn op_index_remove(
+    #[cfg_attr(not(feature = "cache"), allow(unused))] ids: &[String],
+    #[cfg_attr(not(feature = "cache"), allow(unused))] cache_path: &str,
+) -> Result<Value, String> {
+    #[cfg(feature = "cache")]
+    {
+        let db = open_index(cache_path)?;
+        let mut removed = 0;
+        for id in ids {
+            if db.index_remove(id)? {
+                removed += 1;
+            }
+        }
+        Ok(serde_json::json!({
+            "path": cache_path,
+            "removed": removed,
+            "total": db.index_len()?,
+        }))
+    }
+
+    #[cfg(not(feature = "cache"))]
+    Err("Cache feature not enabled. Build with --features cache".into())
+}
+
+/// Nearest indexed records to a text, URL, fingerprint and/or signature.
+///
+/// Only the LSH buckets of the query are read, so the cost depends on the
+/// number of candidates rather than the size of the index. A candidate
+/// matches on the same canonical URL, a SimHash distance within
+/// `threshold` or a MinHash Jaccard of at least `jaccard`. The index's 16
+/// SimHash bands only guarantee pairs within 15 bits, so larger thresholds
+/// are rejected. Results are `{id, reason, distance, jaccard, record}`, URL
+/// matches first, then by distance and Jaccard.
+pub fn op_lookup(
+    #[cfg_attr(not(feature = "cache"), allow(unused))] query: &LookupQuery,
+    #[cfg_attr(not(feature = "cache"), allow(unused))] options: &LookupOptions,
+    #[cfg_attr(not(feature = "cache"), allow(unused))] cache_path: &str,
+) -> Result<Value, String> {
+    #[cfg(feature = "cache")]
+    {
+        if query.text.trim().is_empty() && query.url.is_none() && query.fingerprint.is_none() && query.signature.is_none() {
+            return Err("Nothing to look up: provide a text, URL, fingerprint or signature".into());
+        }
+        if options.threshold > INDEX_MAX_DISTANCE {
+            return Err(format!(
+                "Lookup threshold {} is above {INDEX_MAX_DISTANCE}, the largest distance the index finds every pair within",
+                options.threshold
+            ));
+        }
+        let hasher = minhash::MinHasher::with_default_perm();
+        if let Some(sig) = &query.signature {
+            if sig.len() != hasher.num_perm() {
+                return Err(format!("Signature has {} values; the index uses {}", sig.len(), hasher.num_perm()));
+            }
+        }
+        let db = open_index(cache_path)?;
+        let (text_simhash, text_signature) = index_fingerprints(&query.text);
+        let simhash = query.fingerprint.or(text_simhash);
+        let signature = query.signature.clone().or(text_signature);
+        let url_key = query.url.as_deref().and_then(url_normalize::canonical_key);
+        let candidates = db.index_candidates(
+            &index_band_keys(simhash, signature.as_deref()),
+            url_key.as_deref(),
+        )?;
+
+        let mut found: Vec<(bool, Option<u32>, Option<f64>, storage::IndexEntry)> = candidates
+            .into_iter()
+            .filter_map(|entry| {
+                let same_url = url_key.is_some() && entry.url_key == url_key;
+                let distance = simhash.zip(entry.simhash).map(|(a, b)| simhash::hamming_distance(a, b));
+                let jaccard = signature
+                    .as_deref()
+                    .zip(entry.signature.as_deref())
+                    .map(|(a, b)| hasher.jaccard(a, b));
+                let close = distance.is_some_and(|d| d <= options.threshold)
+                    || jaccard.is_some_and(|j| j >= options.jaccard);
+                (same_url || close).then_some((same_url, distance, jaccard, entry))
+            })
+            .collect();
+        found.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(a.1.unwrap_or(u32::MAX).cmp(&b.1.unwrap_or(u32::MAX)))
+                .then(b.2.unwrap_or(0.0).total_cmp(&a.2.unwrap_or(0.0)))
+                .then(a.3.id.cmp(&b.3.id))
+        });
+        found.truncate(options.limit);
+
+        let results: Vec<Value> = found
+            .into_iter()
+            .map(|(same_url, distance, jaccard, entry)| {
+                serde_json::json!({
+                    "id": entry.id,
+                    "reason": if same_url { "url" } else { "content" },
+                    "distance": distance,
+                    "jaccard": jaccard,
+                    "record": serde_json::from_str::<Value>(&entry.record).unwrap_or(Value::Null),
+                })
+            })
+            .collect();
+        Ok(Value::Array(results))
+    }
+
+    #[cfg(not(feature = "cache"))]
+    Err("Cache feature not enabled. Build with --features cache".into())
+}
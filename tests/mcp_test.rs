@@ -127,8 +127,9 @@ fn mcp_tools_list_has_all_tools() {
         .collect();
 
     let expected = [
-        "fingerprint", "sample", "analyze", "classify", "tags", "dedup",
-        "similarity", "normalize_url", "generate", "topics", "organize",
+        "fingerprint", "sample", "analyze", "classify", "train", "tags", "dedup",
+        "merge_duplicates", "similarity", "normalize_url", "generate", "topics", "organize",
+        "neighbors", "graph", "index", "lookup", "cache_info", "cache_clear",
     ];
 
     for name in &expected {
@@ -137,7 +138,7 @@ fn mcp_tools_list_has_all_tools() {
             "Missing tool: {name}. Found: {tool_names:?}"
         );
     }
-    assert_eq!(tool_names.len(), 11, "Expected 11 tools, got {}", tool_names.len());
+    assert_eq!(tool_names.len(), expected.len(), "Expected {} tools, got {}", expected.len(), tool_names.len());
 }
 
 #[test]
@@ -61,7 +61,7 @@ fn ops_analyze_returns_stats() {
     let rows = sample_records();
     let result = ops::op_analyze(&rows, None);
     assert_eq!(result["total_rows"], 5);
-    assert!(result["columns"].as_array().unwrap().len() > 0);
+    assert!(!result["columns"].as_array().unwrap().is_empty());
     assert!(result["fields"].is_object());
 }
 
@@ -88,6 +88,172 @@ fn ops_classify_auto_discovers() {
     }
 }
 
+#[test]
+fn ops_classify_nested_hierarchy() {
+    let mut rows = Vec::new();
+    for text in [
+        "rust async tokio futures runtime",
+        "rust borrow checker lifetimes ownership",
+        "cooking baking bread flour oven",
+        "cooking pasta sauce tomato basil",
+    ] {
+        rows.push(json!({"content": text}));
+        rows.push(json!({"content": format!("{text} again")}));
+    }
+    let options = ops::ClassifyOptions { depth: 2, branching: 2, ..Default::default() };
+    let result = ops::op_classify_with_options(&rows, "content", None, 2, 500, 0.1, 42, &options).unwrap();
+    for row in result.as_array().unwrap() {
+        let path = row["_hierarchy"].as_str().unwrap();
+        assert!(path.contains(" > "), "expected a nested path, got {path}");
+    }
+}
+
+#[test]
+fn ops_classify_centroid_assignment() {
+    let rows = sample_records();
+    let options = ops::ClassifyOptions {
+        assign: ops::parse_assignment("centroid").unwrap(),
+        ..Default::default()
+    };
+    let result = ops::op_classify_with_options(&rows, "content", None, 2, 3, 0.0, 42, &options).unwrap();
+    for row in result.as_array().unwrap() {
+        assert_ne!(row["_category"], "Uncategorized");
+        assert!(row["_runner_up"].is_string());
+        assert!(row["_margin"].as_f64().unwrap() >= 0.0);
+    }
+
+    // The threshold applies to the cosine similarity on the raw scale...
+    let strict = ops::op_classify_with_options(&rows, "content", None, 2, 3, 1.01, 42, &options).unwrap();
+    assert!(strict.as_array().unwrap().iter().all(|row| row["_category"] == "Uncategorized"));
+
+    // ...and to the scaled confidence otherwise.
+    for scale in ["margin", "softmax"] {
+        let scaled = ops::ClassifyOptions { scale: ops::parse_scale(scale).unwrap(), ..options.clone() };
+        let result = ops::op_classify_with_options(&rows, "content", None, 2, 3, 0.3, 42, &scaled).unwrap();
+        for row in result.as_array().unwrap() {
+            let confidence = row["_confidence"].as_f64().unwrap();
+            assert!((0.0..=1.0).contains(&confidence), "{scale}: {confidence}");
+            if row["_category"] != "Uncategorized" {
+                assert!(confidence >= 0.3, "{scale}: {confidence}");
+            }
+        }
+    }
+
+    let taxonomy = json!({"name": "t", "version": "1", "categories": [{"name": "Rust", "keywords": ["rust"]}]});
+    let err = ops::op_classify_with_options(&rows, "content", Some(&taxonomy), 2, 3, 0.5, 42, &options).unwrap_err();
+    assert!(err.contains("centroid"), "{err}");
+    assert!(ops::parse_assignment("nearest").is_err());
+}
+
+#[test]
+fn ops_classify_samples_large_inputs() {
+    // More rows than the default sample: discovery clusters 2000 of them and
+    // every row is still classified.
+    let themes = [
+        ["rust", "cargo", "borrow", "compiler", "crate"],
+        ["garden", "tomato", "soil", "compost", "seeds"],
+        ["guitar", "chord", "melody", "rhythm", "amplifier"],
+    ];
+    let rows: Vec<Value> = (0..3000)
+        .map(|i| {
+            let words = &themes[i % 3];
+            json!({"content": format!("{} {} {} note {i}", words[i % 5], words[(i / 3 + 1) % 5], words[(i / 7 + 2) % 5])})
+        })
+        .collect();
+    let result = ops::op_classify(&rows, "content", None, 3, 2000, 0.0, 42).unwrap();
+    let arr = result.as_array().unwrap();
+    assert_eq!(arr.len(), 3000);
+    let categories: std::collections::HashSet<&str> = arr.iter().map(|row| row["_category"].as_str().unwrap()).collect();
+    assert!(categories.len() >= 3, "{categories:?}");
+    // Rows from one theme land together.
+    for theme in 0..3 {
+        let first = &arr[theme]["_category"];
+        let same = arr.iter().skip(theme).step_by(3).filter(|row| &row["_category"] == first).count();
+        assert!(same >= 900, "theme {theme}: {same} of 1000");
+    }
+}
+
+#[test]
+fn ops_classify_multi_label_lists_categories() {
+    let rows = vec![
+        json!({"content": "rust command line tool for terminal use"}),
+        json!({"content": "bread baking recipe"}),
+    ];
+    let taxonomy = json!({"name": "t", "version": "1", "categories": [
+        {"name": "Rust", "keywords": ["rust", "cargo", "crate"]},
+        {"name": "CLI tools", "keywords": ["command", "line", "terminal", "tool"]},
+        {"name": "Cooking", "keywords": ["bread", "recipe", "baking"]},
+    ]});
+    let options = ops::ClassifyOptions { multi_label: true, ..Default::default() };
+    let result = ops::op_classify_with_options(&rows, "content", Some(&taxonomy), 2, 500, 0.1, 42, &options).unwrap();
+    let arr = result.as_array().unwrap();
+
+    let first = arr[0]["_categories"].as_array().unwrap();
+    let names: Vec<&str> = first.iter().map(|c| c["category"].as_str().unwrap()).collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"Rust") && names.contains(&"CLI tools"));
+    assert_eq!(first[0]["category"], arr[0]["_category"]);
+    let total: f64 = first.iter().map(|c| c["probability"].as_f64().unwrap()).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+    assert!(first[0]["score"].as_f64().unwrap() >= first[1]["score"].as_f64().unwrap());
+
+    let second = arr[1]["_categories"].as_array().unwrap();
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0]["path"], "Cooking");
+    assert_eq!(second[0]["probability"], 1.0);
+
+    let single = ops::op_classify(&rows, "content", Some(&taxonomy), 2, 500, 0.1, 42).unwrap();
+    assert!(single[0].get("_categories").is_none());
+}
+
+#[test]
+fn ops_classify_scaled_confidence_is_bounded() {
+    let rows = vec![
+        json!({"content": "rust cargo crate rust cargo crate"}),
+        json!({"content": "rust command line tool for terminal use"}),
+    ];
+    let taxonomy = json!({"name": "t", "version": "1", "categories": [
+        {"name": "Rust", "keywords": ["rust", "cargo", "crate"]},
+        {"name": "CLI tools", "keywords": ["command", "line", "terminal", "tool"]},
+    ]});
+    let classify = |scale: &str, threshold: f64| {
+        let options = ops::ClassifyOptions { scale: ops::parse_scale(scale).unwrap(), ..Default::default() };
+        ops::op_classify_with_options(&rows, "content", Some(&taxonomy), 2, 500, threshold, 42, &options).unwrap()
+    };
+
+    let raw = classify("raw", 0.0);
+    assert!(raw[0]["_confidence"].as_f64().unwrap() > 1.0);
+    for scale in ["softmax", "margin"] {
+        let scaled = classify(scale, 0.0);
+        for (row, raw_row) in scaled.as_array().unwrap().iter().zip(raw.as_array().unwrap()) {
+            let confidence = row["_confidence"].as_f64().unwrap();
+            assert!((0.0..=1.0).contains(&confidence), "{scale}: {confidence}");
+            assert_eq!(row["_category"], raw_row["_category"]);
+        }
+    }
+
+    // Only the first row has a single category: the margin threshold drops the ambiguous one.
+    let margin = classify("margin", 0.9);
+    assert_eq!(margin[0]["_category"], "Rust");
+    assert_eq!(margin[0]["_confidence"], 1.0);
+    assert_eq!(margin[1]["_category"], "Uncategorized");
+    assert!(ops::parse_scale("sigmoid").is_err());
+
+    // Multi-label filtering uses the same scale as `_confidence`.
+    let multi = |scale: &str, threshold: f64| {
+        let options =
+            ops::ClassifyOptions { scale: ops::parse_scale(scale).unwrap(), multi_label: true, ..Default::default() };
+        ops::op_classify_with_options(&rows, "content", Some(&taxonomy), 2, 500, threshold, 42, &options).unwrap()
+    };
+    let softmax = multi("softmax", 0.9);
+    let categories = softmax[0]["_categories"].as_array().unwrap();
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0]["confidence"], softmax[0]["_confidence"]);
+    let loose = multi("softmax", 0.0);
+    assert_eq!(loose[1]["_categories"].as_array().unwrap().len(), 2);
+    assert!(multi("margin", 0.0)[1]["_categories"].as_array().unwrap().len() <= 1);
+}
+
 #[test]
 fn ops_tags_extracts_keywords() {
     let rows = sample_records();
@@ -130,6 +296,452 @@ fn ops_dedup_fuzzy_identical_content() {
     assert_eq!(arr[0]["_dup_group"], arr[1]["_dup_group"]);
 }
 
+#[test]
+fn ops_dedup_minhash_groups_near_duplicate_titles() {
+    let rows = vec![
+        json!({"content": "Asynchronous Programming in Rust"}),
+        json!({"content": "Asynchronous programming in Rust!"}),
+        json!({"content": "Sourdough bread baking for beginners"}),
+    ];
+    let result = ops::op_dedup(&rows, "content", "url", "minhash", 3);
+    let arr = result.as_array().unwrap();
+    assert_eq!(arr[0]["_dup_group"], arr[1]["_dup_group"]);
+    assert_ne!(arr[0]["_dup_group"], arr[2]["_dup_group"]);
+}
+
+#[test]
+fn ops_dedup_minhash_respects_jaccard_threshold() {
+    let rows = vec![
+        json!({"content": "rust web framework"}),
+        json!({"content": "rust web frameworks list"}),
+    ];
+    let strict = ops::DedupOptions { jaccard: 0.99, ..Default::default() };
+    let result = ops::op_dedup_with_options(&rows, "content", "url", "minhash", 3, &strict);
+    let arr = result.as_array().unwrap();
+    assert_ne!(arr[0]["_dup_group"], arr[1]["_dup_group"]);
+}
+
+#[test]
+fn ops_dedup_minhash_skips_empty_content() {
+    let rows = vec![json!({"url": "https://a.com"}), json!({"url": "https://b.com"})];
+    let result = ops::op_dedup(&rows, "content", "url", "minhash", 3);
+    let arr = result.as_array().unwrap();
+    assert_ne!(arr[0]["_dup_group"], arr[1]["_dup_group"]);
+}
+
+#[test]
+fn ops_dedup_combined_minhash_uses_urls() {
+    let rows = vec![
+        json!({"content": "Alpha", "url": "https://example.com/a?utm_source=x"}),
+        json!({"content": "Completely different", "url": "https://example.com/a"}),
+    ];
+    let result = ops::op_dedup(&rows, "content", "url", "combined-minhash", 3);
+    let arr = result.as_array().unwrap();
+    assert_eq!(arr[0]["_dup_group"], arr[1]["_dup_group"]);
+}
+
+#[test]
+fn ops_dedup_group_ids_follow_row_order() {
+    let rows = vec![
+        json!({"content": "Unique first row about gardening"}),
+        json!({"content": "The quick brown fox jumps over the lazy dog"}),
+        json!({"content": "Another unique row about astronomy"}),
+        json!({"content": "The quick brown fox jumps over the lazy dog"}),
+    ];
+    let result = ops::op_dedup(&rows, "content", "url", "fuzzy", 3);
+    let groups: Vec<&Value> = result.as_array().unwrap().iter().map(|r| &r["_dup_group"]).collect();
+    assert_eq!(groups, vec![&json!(0), &json!(1), &json!(2), &json!(1)]);
+    assert_eq!(result, ops::op_dedup(&rows, "content", "url", "fuzzy", 3));
+    assert_eq!(result[1]["_is_primary"], true);
+    assert_eq!(result[3]["_is_primary"], false);
+}
+
+#[test]
+fn ops_dedup_primary_policies() {
+    let rows = vec![
+        json!({"content": "Rust book", "url": "https://example.com/rust?utm_source=a", "date_added": "2023-05-01T00:00:00Z", "stars": 10}),
+        json!({"content": "The Rust book, second edition", "url": "https://example.com/rust", "date_added": "2021-01-15T00:00:00Z", "stars": "250"}),
+        json!({"content": "Rust", "url": "https://www.example.com/rust/", "stars": 99}),
+    ];
+    let primary = |policy: &str| {
+        let options = ops::DedupOptions {
+            primary: ops::parse_primary(policy).unwrap(),
+            ..Default::default()
+        };
+        let result = ops::op_dedup_with_options(&rows, "content", "url", "url", 3, &options);
+        let arr = result.as_array().unwrap();
+        assert!(arr.iter().all(|r| r["_dup_group"] == 0));
+        let keepers: Vec<usize> = (0..arr.len()).filter(|&i| arr[i]["_is_primary"] == true).collect();
+        assert_eq!(keepers.len(), 1, "{policy}");
+        keepers[0]
+    };
+
+    assert_eq!(primary("first"), 0);
+    assert_eq!(primary("oldest"), 1);
+    // The row without a date never wins.
+    assert_eq!(primary("newest:date_added"), 0);
+    assert_eq!(primary("longest"), 1);
+    assert_eq!(primary("most-starred"), 1);
+    assert_eq!(primary("shortest-url"), 1);
+    assert!(ops::parse_primary("loudest").is_err());
+    assert!(ops::parse_primary("first:date").is_err());
+}
+
+#[test]
+fn ops_dedup_explain_reports_reasons_and_groups() {
+    let rows = vec![
+        json!({"content": "Hello world", "url": "https://example.com/page?utm_source=twitter"}),
+        json!({"content": "Different text", "url": "https://example.com/page"}),
+        json!({"content": "The quick brown fox jumps over the lazy dog", "url": "https://a.com"}),
+        json!({"content": "The quick brown fox jumps over the lazy dog", "url": "https://b.com"}),
+        json!({"content": "Something completely unrelated", "url": "https://c.com"}),
+    ];
+    let options = ops::DedupOptions { explain: true, ..Default::default() };
+    let result = ops::op_dedup_with_options(&rows, "content", "url", "combined", 3, &options);
+    let arr = result["rows"].as_array().unwrap();
+
+    assert_eq!(arr[0]["_dup_reason"], "url");
+    assert_eq!(arr[0]["_dup_partner"], 1);
+    assert_eq!(arr[1]["_canonical_key"], arr[0]["_canonical_key"]);
+    assert!(arr[0]["_dup_distance"].is_null());
+
+    assert_eq!(arr[2]["_dup_reason"], "content");
+    assert_eq!(arr[3]["_dup_partner"], 2);
+    assert_eq!(arr[3]["_dup_distance"], 0);
+    assert!(arr[3]["_dup_jaccard"].is_null());
+
+    assert!(arr[4]["_dup_reason"].is_null());
+    assert!(arr[4]["_dup_partner"].is_null());
+    assert!(arr[4]["_canonical_key"].is_string());
+
+    // Singletons are left out of the summary.
+    let groups = result["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 2);
+    let content_group = groups
+        .iter()
+        .find(|g| g["group"] == arr[2]["_dup_group"])
+        .unwrap();
+    assert_eq!(content_group["size"], 2);
+    assert_eq!(content_group["reasons"], json!(["content"]));
+    assert_eq!(content_group["max_distance"], 0);
+    assert_eq!(content_group["members"], json!([2, 3]));
+
+    // Partners and members are named by id_field where rows have one.
+    let mut with_ids = rows.clone();
+    with_ids[2]["slug"] = json!("fox-a");
+    let options = ops::DedupOptions { id_field: "slug".into(), ..options };
+    let result = ops::op_dedup_with_options(&with_ids, "content", "url", "combined", 3, &options);
+    assert_eq!(result["rows"][3]["_dup_partner"], "fox-a");
+    assert_eq!(result["rows"][2]["_dup_partner"], 3);
+    let groups = result["groups"].as_array().unwrap();
+    assert!(groups.iter().any(|g| g["members"] == json!(["fox-a", 3])));
+}
+
+#[test]
+fn ops_dedup_explain_minhash_reports_jaccard() {
+    let rows = vec![
+        json!({"content": "Asynchronous Programming in Rust"}),
+        json!({"content": "Asynchronous programming in Rust!"}),
+    ];
+    let options = ops::DedupOptions { explain: true, ..Default::default() };
+    let result = ops::op_dedup_with_options(&rows, "content", "url", "minhash", 3, &options);
+    let jaccard = result["rows"][0]["_dup_jaccard"].as_f64().unwrap();
+    assert!((0.5..=1.0).contains(&jaccard));
+    assert!(result["rows"][0]["_canonical_key"].is_null());
+    assert_eq!(result["groups"][0]["min_jaccard"].as_f64().unwrap(), jaccard);
+
+    let empty = ops::op_dedup_with_options(&[], "content", "url", "minhash", 3, &options);
+    assert_eq!(empty, json!({"rows": [], "groups": [], "banding": {}}));
+}
+
+#[test]
+fn ops_dedup_banding_follows_threshold_and_recall() {
+    let rows = vec![json!({"content": "hello world"}), json!({"content": "hello world"})];
+    let options = ops::DedupOptions { explain: true, ..Default::default() };
+    let result = ops::op_dedup_with_options(&rows, "content", "url", "fuzzy", 3, &options);
+    assert_eq!(result["banding"], json!({"simhash": {"bands": 4, "rows": 16, "false_negative_rate": 0.0}}));
+
+    let wide = ops::op_dedup_with_options(&rows, "content", "url", "fuzzy", 24, &options);
+    assert!(wide["banding"]["simhash"]["false_negative_rate"].as_f64().unwrap() <= 0.05);
+    assert_eq!(wide["groups"][0]["size"], 2);
+
+    // A fixed band count reports the recall it gives up.
+    let fixed = ops::DedupOptions { bands: Some(2), ..options.clone() };
+    let result = ops::op_dedup_with_options(&rows, "content", "url", "combined-minhash", 3, &fixed);
+    let minhash = &result["banding"]["minhash"];
+    assert_eq!(minhash["bands"], 2);
+    assert_eq!(minhash["rows"], 64);
+    assert!(minhash["false_negative_rate"].as_f64().unwrap() > 0.99);
+    assert!(result["banding"].get("simhash").is_none());
+
+    let strict = ops::DedupOptions { recall: 0.999, ..options };
+    let result = ops::op_dedup_with_options(&rows, "content", "url", "minhash", 3, &strict);
+    assert!(result["banding"]["minhash"]["false_negative_rate"].as_f64().unwrap() <= 0.001);
+
+    // Plain runs report the same layout through dedup_banding.
+    assert_eq!(ops::dedup_banding("minhash", 3, &strict), result["banding"]);
+    assert_eq!(ops::dedup_banding("url", 3, &strict), json!({}));
+}
+
+fn reference_records() -> Vec<Value> {
+    vec![
+        json!({"id": "r1", "content": "The quick brown fox jumps over the lazy dog", "url": "https://a.com/fox"}),
+        json!({"id": "r2", "content": "Asynchronous Programming in Rust", "url": "https://example.com/async"}),
+        json!({"content": "Sourdough bread baking for beginners", "url": "https://bread.com"}),
+    ]
+}
+
+#[test]
+fn ops_dedup_against_reports_existing_matches() {
+    let rows = vec![
+        json!({"content": "Totally new words", "url": "https://example.com/async?utm_source=feed"}),
+        json!({"content": "The quick brown fox jumps over the lazy dog", "url": "https://b.com"}),
+        json!({"content": "Nothing like the reference", "url": "https://c.com"}),
+        json!({"content": "Nothing like the reference", "url": "https://d.com"}),
+    ];
+    let reference = reference_records();
+    let options = ops::DedupOptions::default();
+    let result = ops::op_dedup_against(&rows, &reference, "content", "url", "combined", 3, &options);
+    let arr = result.as_array().unwrap();
+
+    assert_eq!(arr[0]["_matches_existing"], json!([{"id": "r2", "reason": "url", "distance": null, "jaccard": null}]));
+    assert_eq!(arr[1]["_matches_existing"][0]["id"], "r1");
+    assert_eq!(arr[1]["_matches_existing"][0]["distance"], 0);
+    // Duplicates within the new batch are not reported, only reference matches.
+    assert_eq!(arr[2]["_matches_existing"], json!([]));
+    assert!(arr[3].get("_dup_group").is_none());
+
+    // MinHash matches report Jaccard; reference rows without an id use their index.
+    let rows = vec![json!({"content": "sourdough bread baking for beginners!"})];
+    let result = ops::op_dedup_against(&rows, &reference, "content", "url", "minhash", 3, &options);
+    let found = &result[0]["_matches_existing"][0];
+    assert_eq!(found["id"], 2);
+    assert!(found["jaccard"].as_f64().unwrap() >= 0.5);
+    assert_eq!(reference, reference_records());
+}
+
+#[test]
+fn ops_load_records_requires_array() {
+    let dir = std::env::temp_dir().join(format!("topology-records-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("reference.json");
+    std::fs::write(&path, r#"{"id": 1}"#).unwrap();
+    assert!(ops::load_records(path.to_str().unwrap()).is_err());
+    std::fs::write(&path, r#"[{"id": 1}]"#).unwrap();
+    assert_eq!(ops::load_records(path.to_str().unwrap()).unwrap(), vec![json!({"id": 1})]);
+    assert!(ops::load_records("/nonexistent/reference.json").is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "cache")]
+#[test]
+fn ops_dedup_against_cached_matches_uncached() {
+    let dir = std::env::temp_dir().join(format!("topology-against-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("cache.db");
+    let path = db.to_str().unwrap();
+
+    let rows = vec![json!({"content": "The quick brown fox jumps over the lazy dog"})];
+    let reference = reference_records();
+    let options = ops::DedupOptions::default();
+    let fresh = ops::op_dedup_against(&rows, &reference, "content", "url", "fuzzy", 3, &options);
+    for _ in 0..2 {
+        let cached = ops::op_dedup_against_cached(&rows, &reference, "content", "url", "fuzzy", 3, &options, Some(path));
+        assert_eq!(cached, fresh);
+    }
+    let info = ops::op_cache_info(path).unwrap();
+    assert!(info["artifacts"].as_array().unwrap().iter().any(|a| a["kind"] == "fingerprints"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "cache")]
+fn query(text: &str, url: Option<&str>) -> ops::LookupQuery {
+    ops::LookupQuery { text: text.into(), url: url.map(String::from), ..Default::default() }
+}
+
+#[cfg(feature = "cache")]
+#[test]
+fn ops_index_lookup_across_runs() {
+    let dir = std::env::temp_dir().join(format!("topology-index-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("index.db");
+    let path = db.to_str().unwrap();
+
+    let added = ops::op_index_add(&reference_records(), "content", "url", "id", path).unwrap();
+    assert_eq!(added["added"], 3);
+    assert_eq!(added["total"], 3);
+    let options = ops::LookupOptions::default();
+
+    // Same canonical URL, different text.
+    let found = ops::op_lookup(&query("unrelated words", Some("https://www.example.com/async?utm_source=x")), &options, path).unwrap();
+    assert_eq!(found[0]["id"], "r2");
+    assert_eq!(found[0]["reason"], "url");
+    assert_eq!(found[0]["record"]["url"], "https://example.com/async");
+
+    // Near-duplicate text; the record without an id is keyed by its URL.
+    let found = ops::op_lookup(&query("Sourdough bread baking for beginners!", None), &options, path).unwrap();
+    assert_eq!(found[0]["id"], "https://bread.com");
+    assert_eq!(found[0]["reason"], "content");
+    assert!(found[0]["distance"].as_u64().unwrap() <= 3);
+    assert!(ops::op_lookup(&query("Cooking pasta sauce at home", None), &options, path).unwrap().as_array().unwrap().is_empty());
+
+    // The same record by its SimHash fingerprint alone.
+    let fingerprint = ops::op_fingerprint(&[json!({"content": "Sourdough bread baking for beginners"})], "content", false)
+        [0]["_fingerprint"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let by_fingerprint = ops::LookupQuery {
+        fingerprint: Some(ops::parse_fingerprint(&fingerprint).unwrap()),
+        ..Default::default()
+    };
+    let found = ops::op_lookup(&by_fingerprint, &options, path).unwrap();
+    assert_eq!(found[0]["id"], "https://bread.com");
+    assert_eq!(found[0]["distance"], 0);
+
+    let wide = ops::LookupOptions { threshold: 16, ..options.clone() };
+    assert!(ops::op_lookup(&by_fingerprint, &wide, path).unwrap_err().contains("15"));
+    assert!(ops::op_lookup(&ops::LookupQuery::default(), &options, path).is_err());
+    let short = ops::LookupQuery { signature: Some(ops::parse_signature("1, 2 ff").unwrap()), ..Default::default() };
+    assert_eq!(short.signature, Some(vec![1, 2, 255]));
+    assert!(ops::op_lookup(&short, &options, path).unwrap_err().contains("128"));
+    assert!(ops::parse_fingerprint("xyz").is_err());
+
+    let removed = ops::op_index_remove(&["r2".into(), "missing".into()], path).unwrap();
+    assert_eq!(removed["removed"], 1);
+    assert_eq!(removed["total"], 2);
+    let found = ops::op_lookup(&query("", Some("https://example.com/async")), &options, path).unwrap();
+    assert!(found.as_array().unwrap().is_empty());
+    assert_eq!(ops::op_cache_info(path).unwrap()["indexed"], 2);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn neighbor_rows() -> Vec<Value> {
+    vec![
+        json!({"id": "a", "content": "rust ownership borrow checker memory safety"}),
+        json!({"id": "b", "content": "rust borrow checker ownership rules explained"}),
+        json!({"id": "c", "content": "italian pasta sauce recipe with fresh tomatoes"}),
+        json!({"id": "d", "content": "fresh tomato pasta sauce italian recipe"}),
+        json!({"id": "e", "content": ""}),
+    ]
+}
+
+#[test]
+fn ops_neighbors_returns_top_k_edges() {
+    let options = ops::NeighborsOptions { k: 1, ..Default::default() };
+    let edges = ops::op_neighbors(&neighbor_rows(), "content", &options);
+    let pairs: Vec<(&str, &str)> = edges
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["source"].as_str().unwrap(), e["target"].as_str().unwrap()))
+        .collect();
+    assert_eq!(pairs, [("a", "b"), ("b", "a"), ("c", "d"), ("d", "c")]);
+    let score = edges[0]["score"].as_f64().unwrap();
+    assert!(score > 0.2 && score <= 1.0);
+    assert_eq!(edges[0]["score"], edges[1]["score"]);
+
+    let options = ops::NeighborsOptions {
+        metric: ops::parse_neighbor_metric("jaccard").unwrap(),
+        min_score: 0.1,
+        id_field: "missing".into(),
+        ..Default::default()
+    };
+    let edges = ops::op_neighbors(&neighbor_rows(), "content", &options);
+    assert!(edges.as_array().unwrap().iter().any(|e| e["source"] == 2 && e["target"] == 3));
+    assert!(edges.as_array().unwrap().iter().all(|e| e["source"] != 4 && e["target"] != 4));
+    assert!(ops::parse_neighbor_metric("euclid").is_err());
+}
+
+#[test]
+fn ops_graph_exports_each_kind() {
+    let options = ops::GraphOptions { format: ops::parse_graph_format("dot").unwrap(), ..Default::default() };
+    let dot = ops::op_graph(&neighbor_rows(), "content", &options).unwrap();
+    assert!(dot.starts_with("graph topology {"));
+    assert_eq!(dot.matches(" -- ").count(), 2, "{dot}");
+    assert!(dot.contains(r#""n0" -- "n1""#));
+    assert!(dot.contains(r#""fingerprint"="#));
+
+    let mut rows = ops::op_tags(&neighbor_rows(), "content", 2).as_array().unwrap().clone();
+    rows[0]["_hierarchy"] = json!("Tech > Rust");
+    rows[2]["_hierarchy"] = json!("Food");
+    let options = ops::GraphOptions {
+        kind: ops::parse_graph_kind("taxonomy").unwrap(),
+        format: ops::parse_graph_format("gexf").unwrap(),
+        ..Default::default()
+    };
+    let gexf = ops::op_graph(&rows, "content", &options).unwrap();
+    assert!(gexf.contains(r#"<node id="cat:Tech &gt; Rust" label="Rust">"#));
+    assert!(gexf.contains(r#"source="cat:Tech" target="cat:Tech &gt; Rust""#));
+    assert!(gexf.contains(r#"source="cat:Food" target="n2""#));
+    assert!(gexf.contains(r#"title="tags""#));
+
+    let deduped = ops::op_dedup(&neighbor_rows(), "content", "url", "fuzzy", 40);
+    let options = ops::GraphOptions { kind: ops::GraphKind::Dedup, ..Default::default() };
+    let graphml = ops::op_graph(deduped.as_array().unwrap(), "content", &options).unwrap();
+    assert!(graphml.contains(r#"attr.name="group""#));
+    assert!(ops::op_graph(&neighbor_rows(), "content", &options).is_err());
+
+    let options = ops::GraphOptions { kind: ops::GraphKind::Dendrogram, ..Default::default() };
+    let graphml = ops::op_graph(&neighbor_rows(), "content", &options).unwrap();
+    assert!(graphml.contains(r#"<node id="cluster8">"#));
+    assert_eq!(graphml.matches("<edge ").count(), 8);
+
+    assert!(ops::parse_graph_kind("tree").is_err());
+    assert!(ops::parse_graph_format("svg").is_err());
+}
+
+#[test]
+fn ops_merge_duplicates_applies_field_rules() {
+    let rows = vec![
+        json!({"id": "a", "url": "https://example.com/x?utm_source=1", "tags": ["rust"], "date_added": "2023-02-01", "folder": "Dev", "note": null}),
+        json!({"id": "b", "url": "https://other.com", "tags": ["misc"], "date_added": "2020-01-01", "folder": "Misc"}),
+        json!({"id": "c", "url": "https://example.com/x", "tags": ["rust", "async"], "date_added": "2021-06-01", "folder": "Reading", "note": "great"}),
+    ];
+    let deduped = ops::op_dedup(&rows, "content", "url", "url", 3);
+    let options = ops::MergeOptions {
+        rules: ops::parse_merge_rules("date_added=min, folder=concat").unwrap(),
+        ..Default::default()
+    };
+    let merged = ops::op_merge_duplicates(deduped.as_array().unwrap(), &options);
+    let arr = merged.as_array().unwrap();
+    assert_eq!(arr.len(), 2);
+
+    let group = &arr[0];
+    assert_eq!(group["_merged_from"], json!(["a", "c"]));
+    assert_eq!(group["id"], "a");
+    assert_eq!(group["tags"], json!(["rust", "async"]));
+    assert_eq!(group["date_added"], "2021-06-01");
+    assert_eq!(group["folder"], "Dev; Reading");
+    // First non-null skips the primary's null.
+    assert_eq!(group["note"], "great");
+    assert_eq!(arr[1]["_merged_from"], json!(["b"]));
+}
+
+#[test]
+fn ops_merge_duplicates_starts_from_primary() {
+    let rows = vec![
+        json!({"title": "old", "stars": 3, "_dup_group": 0, "_is_primary": false}),
+        json!({"title": "new", "stars": 10, "_dup_group": 0, "_is_primary": true}),
+        json!({"title": "solo"}),
+    ];
+    let options = ops::MergeOptions {
+        rules: ops::parse_merge_rules("stars=min").unwrap(),
+        ..Default::default()
+    };
+    let merged = ops::op_merge_duplicates(&rows, &options);
+    assert_eq!(merged[0]["title"], "new");
+    assert_eq!(merged[0]["stars"], 3);
+    // Without an id field, members are listed by row index.
+    assert_eq!(merged[0]["_merged_from"], json!([1, 0]));
+    assert_eq!(merged[1]["_merged_from"], json!([2]));
+
+    assert!(ops::parse_merge_rules("stars").is_err());
+    assert!(ops::parse_merge_rules("stars=sum").is_err());
+}
+
 #[test]
 fn ops_similarity_levenshtein() {
     let result = ops::op_similarity("kitten", "sitting", "levenshtein", false).unwrap();
@@ -190,6 +802,139 @@ fn ops_generate_taxonomy_returns_clusters() {
     }
 }
 
+#[test]
+fn ops_generate_auto_k_reports_scores() {
+    let rows: Vec<Value> = [
+        "rust borrow checker ownership",
+        "rust ownership lifetimes borrow",
+        "baking bread flour oven",
+        "bread dough flour baking",
+        "guitar chords melody song",
+        "song melody guitar strings",
+    ]
+    .iter()
+    .map(|t| json!({"content": t}))
+    .collect();
+    let options = ops::GenerateOptions { auto_k: true, ..Default::default() };
+    let result = ops::op_generate_with_options(&rows, "content", 5, "average", 5, &options).unwrap();
+    assert_eq!(result["num_clusters"], 3);
+    let selection = &result["k_selection"];
+    assert_eq!(selection["k"], 3);
+    let ks: Vec<u64> = selection["scores"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["k"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ks, vec![2, 3, 4, 5]);
+
+    let fixed = ops::op_generate(&rows, "content", 5, "average", 5).unwrap();
+    assert!(fixed.get("k_selection").is_none());
+}
+
+fn six_topic_rows() -> Vec<Value> {
+    [
+        "rust borrow checker ownership",
+        "rust ownership lifetimes borrow",
+        "baking bread flour oven",
+        "bread dough flour baking",
+        "guitar chords melody song",
+        "song melody guitar strings",
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, t)| json!({"id": format!("r{i}"), "content": t}))
+    .collect()
+}
+
+#[test]
+fn ops_generate_returns_dendrogram_and_cuts_by_distance() {
+    let rows = six_topic_rows();
+    let options = ops::GenerateOptions { dendrogram: true, ..Default::default() };
+    let result = ops::op_generate_with_options(&rows, "content", 3, "average", 5, &options).unwrap();
+    let dendrogram = &result["dendrogram"];
+    assert_eq!(dendrogram["n"], 6);
+    let merges = dendrogram["merges"].as_array().unwrap();
+    assert_eq!(merges.len(), 5);
+    assert_eq!(merges[4]["size"], 6);
+    let newick = dendrogram["newick"].as_str().unwrap();
+    assert!(newick.ends_with(");"));
+    for i in 0..6 {
+        assert!(newick.contains(&format!("r{i}:")), "{newick}");
+    }
+
+    // Cutting just below the third-from-last merge leaves three clusters.
+    let threshold = merges[2]["distance"].as_f64().unwrap();
+    let options = ops::GenerateOptions { cut_distance: Some(threshold), ..Default::default() };
+    let cut = ops::op_generate_with_options(&rows, "content", 10, "average", 5, &options).unwrap();
+    assert_eq!(cut["num_clusters"], 3);
+    assert_eq!(cut["cut_distance"], threshold);
+    assert!(cut.get("dendrogram").is_none());
+    let options = ops::GenerateOptions { cut_distance: Some(-1.0), ..Default::default() };
+    let cut = ops::op_generate_with_options(&rows, "content", 2, "average", 5, &options).unwrap();
+    assert_eq!(cut["num_clusters"], 6);
+}
+
+#[test]
+fn ops_classify_auto_k_reports_scores() {
+    let rows = six_topic_rows();
+    let options = ops::ClassifyOptions { auto_k: true, ..Default::default() };
+    let output = ops::op_classify_cached(&rows, "content", None, 5, 500, 0.0, 42, &options, None).unwrap();
+    assert_eq!(output.rows.as_array().unwrap().len(), rows.len());
+    let selection = output.k_selection.unwrap();
+    let ks: Vec<usize> = selection.scores.iter().map(|s| s.k).collect();
+    assert_eq!(ks, vec![2, 3, 4, 5]);
+    assert!(ks.contains(&selection.k));
+
+    let fixed = ops::op_classify_cached(&rows, "content", None, 5, 500, 0.0, 42, &ops::ClassifyOptions::default(), None).unwrap();
+    assert!(fixed.k_selection.is_none());
+}
+
+#[cfg(feature = "cache")]
+#[test]
+fn ops_classify_cached_keeps_k_selection() {
+    let dir = std::env::temp_dir().join(format!("topology-k-selection-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("cache.db");
+    let path = db.to_str().unwrap();
+
+    let rows = six_topic_rows();
+    let options = ops::ClassifyOptions { auto_k: true, ..Default::default() };
+    let fresh = ops::op_classify_cached(&rows, "content", None, 5, 500, 0.0, 42, &options, Some(path)).unwrap();
+    let cached = ops::op_classify_cached(&rows, "content", None, 5, 500, 0.0, 42, &options, Some(path)).unwrap();
+    assert_eq!(cached.rows, fresh.rows);
+    assert_eq!(cached.k_selection.unwrap().k, fresh.k_selection.unwrap().k);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "cache")]
+#[test]
+fn ops_generate_cached_reuses_dendrogram() {
+    let dir = std::env::temp_dir().join(format!("topology-dendrogram-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("cache.db");
+    let path = db.to_str().unwrap();
+
+    let rows = six_topic_rows();
+    for depth in [2, 3, 4] {
+        let options = ops::GenerateOptions { dendrogram: true, ..Default::default() };
+        let fresh = ops::op_generate_with_options(&rows, "content", depth, "ward", 5, &options).unwrap();
+        let cached = ops::op_generate_cached(&rows, "content", depth, "ward", 5, &options, Some(path)).unwrap();
+        assert_eq!(cached, fresh);
+    }
+    let info = ops::op_cache_info(path).unwrap();
+    let dendrograms = info["artifacts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|a| a["kind"] == "dendrogram")
+        .count();
+    assert_eq!(dendrograms, 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
 #[test]
 fn ops_generate_taxonomy_too_few() {
     let rows = vec![json!({"content": "only one"})];
@@ -201,6 +946,7 @@ fn ops_generate_taxonomy_too_few() {
 fn ops_topics_nmf_returns_terms() {
     let rows = sample_records();
     let result = ops::op_topics(&rows, "content", 2, 5, 50, 1000).unwrap();
+    assert_eq!(result["model"], "nmf");
     assert_eq!(result["num_topics"], 2);
     assert_eq!(result["num_items"], 5);
     let topics = result["topics"].as_array().unwrap();
@@ -213,6 +959,136 @@ fn ops_topics_nmf_returns_terms() {
     assert_eq!(assignments.len(), 5);
 }
 
+#[test]
+fn ops_topics_reports_fit_and_convergence() {
+    let rows = sample_records();
+    let result = ops::op_topics(&rows, "content", 2, 5, 500, 1000).unwrap();
+    let iterations = result["iterations"].as_u64().unwrap();
+    assert!(iterations > 0 && iterations <= 500);
+    assert_eq!(result["converged"], iterations < 500);
+    let relative = result["relative_error"].as_f64().unwrap();
+    assert!((0.0..=1.0).contains(&relative));
+    assert!(result["reconstruction_error"].as_f64().unwrap() >= 0.0);
+
+    let options = ops::TopicsOptions {
+        tol: 0.0,
+        init: ops::parse_nmf_init("uniform").unwrap(),
+        l1: 0.1,
+        ..Default::default()
+    };
+    let capped = ops::op_topics_with_options(&rows, "content", 2, 5, 7, 1000, &options).unwrap();
+    assert_eq!(capped["iterations"], 7);
+    assert_eq!(capped["converged"], false);
+
+    let negative = ops::TopicsOptions { l2: -1.0, ..Default::default() };
+    assert!(ops::op_topics_with_options(&rows, "content", 2, 5, 7, 1000, &negative).is_err());
+    assert!(ops::parse_nmf_init("random").is_err());
+}
+
+#[test]
+fn ops_topics_lda_matches_shape_and_is_seeded() {
+    let rows = sample_records();
+    let options = ops::TopicsOptions { model: ops::parse_topic_model("LDA").unwrap(), ..Default::default() };
+    let result = ops::op_topics_with_options(&rows, "content", 2, 5, 100, 1000, &options).unwrap();
+    assert_eq!(result["model"], "lda");
+    assert_eq!(result["num_topics"], 2);
+    assert_eq!(result["iterations"], 100);
+    assert!(result["perplexity"].as_f64().unwrap() > 1.0);
+    assert!(result.get("reconstruction_error").is_none());
+    let topics = result["topics"].as_array().unwrap();
+    assert_eq!(topics.len(), 2);
+    let members: usize = topics.iter().map(|t| t["members"].as_array().unwrap().len()).sum();
+    assert_eq!(members, 5);
+    assert_eq!(result["assignments"].as_array().unwrap().len(), 5);
+
+    let again = ops::op_topics_with_options(&rows, "content", 2, 5, 100, 1000, &options).unwrap();
+    assert_eq!(result, again);
+
+    let bad_prior = ops::TopicsOptions { alpha: 0.0, ..options };
+    assert!(ops::op_topics_with_options(&rows, "content", 2, 5, 100, 1000, &bad_prior).is_err());
+    assert!(ops::parse_topic_model("plsa").is_err());
+}
+
+#[test]
+fn ops_topics_reports_coherence_and_diversity() {
+    let result = ops::op_topics(&sample_records(), "content", 2, 5, 200, 1000).unwrap();
+    let diversity = result["diversity"].as_f64().unwrap();
+    assert!(diversity > 0.0 && diversity <= 1.0);
+    let mean_npmi = result["coherence"]["npmi"].as_f64().unwrap();
+    assert!((-1.0..=1.0).contains(&mean_npmi));
+    let topics = result["topics"].as_array().unwrap();
+    let npmi_sum: f64 = topics.iter().map(|t| t["coherence"]["npmi"].as_f64().unwrap()).sum();
+    assert!((npmi_sum / topics.len() as f64 - mean_npmi).abs() < 1e-9);
+    assert!(topics.iter().all(|t| t["coherence"]["umass"].is_number()));
+}
+
+#[test]
+fn ops_topics_sweep_compares_k() {
+    let rows = sample_records();
+    let options = ops::TopicsOptions::default();
+    let result = ops::op_topics_sweep(&rows, "content", &[3, 1, 2, 3], 5, 200, 1000, &options).unwrap();
+    assert_eq!(result["model"], "nmf");
+    let sweep = result["sweep"].as_array().unwrap();
+    let ks: Vec<u64> = sweep.iter().map(|e| e["k"].as_u64().unwrap()).collect();
+    assert_eq!(ks, [1, 2, 3]);
+    for entry in sweep {
+        assert!(entry["coherence"]["npmi"].is_number());
+        assert!(entry["diversity"].is_number());
+        assert!(entry["relative_error"].is_number());
+    }
+    // Each sweep entry matches a standalone run at that k.
+    let single = ops::op_topics(&rows, "content", 2, 5, 200, 1000).unwrap();
+    assert_eq!(sweep[1]["coherence"], single["coherence"]);
+    assert_eq!(sweep[1]["diversity"], single["diversity"]);
+
+    let best = result["best_k"].as_u64().unwrap();
+    let best_npmi = sweep
+        .iter()
+        .map(|e| e["coherence"]["npmi"].as_f64().unwrap())
+        .fold(f64::NEG_INFINITY, f64::max);
+    assert_eq!(sweep[best as usize - 1]["coherence"]["npmi"].as_f64().unwrap(), best_npmi);
+
+    let lda = ops::TopicsOptions { model: ops::TopicModel::Lda, ..Default::default() };
+    let result = ops::op_topics_sweep(&rows, "content", &[2], 5, 50, 1000, &lda).unwrap();
+    assert!(result["sweep"][0]["perplexity"].is_number());
+
+    assert!(ops::op_topics_sweep(&rows, "content", &[], 5, 50, 1000, &options).is_err());
+    assert!(ops::op_topics_sweep(&rows, "content", &[0, 2], 5, 50, 1000, &options).is_err());
+}
+
+#[test]
+fn ops_topics_mixture_appends_to_rows() {
+    let rows = sample_records();
+    let options = ops::TopicsOptions { mixture: Some(1), ..Default::default() };
+    let result = ops::op_topics_with_options(&rows, "content", 2, 5, 200, 1000, &options).unwrap();
+    let out = result.as_array().unwrap();
+    assert_eq!(out.len(), rows.len());
+    let report = ops::op_topics(&rows, "content", 2, 5, 200, 1000).unwrap();
+    for (i, row) in out.iter().enumerate() {
+        assert_eq!(row["content"], rows[i]["content"]);
+        let topics = row["_topics"].as_array().unwrap();
+        assert_eq!(topics.len(), 1);
+        // The single strongest topic is the dominant-topic assignment.
+        assert_eq!(topics[0]["topic"], report["assignments"][i]["topic"]);
+        let t = topics[0]["topic"].as_u64().unwrap() as usize;
+        assert_eq!(topics[0]["label"], report["topics"][t]["label"]);
+    }
+
+    let options = ops::TopicsOptions { mixture: Some(5), model: ops::TopicModel::Lda, ..Default::default() };
+    let result = ops::op_topics_with_options(&rows, "content", 3, 5, 50, 1000, &options).unwrap();
+    for row in result.as_array().unwrap() {
+        let weights: Vec<f64> = row["_topics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["weight"].as_f64().unwrap())
+            .collect();
+        assert_eq!(weights.len(), 3);
+        assert!(weights.windows(2).all(|w| w[0] >= w[1]));
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
+
 #[test]
 fn ops_topics_empty() {
     let result = ops::op_topics(&[], "content", 2, 5, 50, 1000);
@@ -249,3 +1125,80 @@ fn ops_organize_empty() {
     let result = ops::op_organize(&[], "folders", "./out", "_category", "id");
     assert_eq!(result, json!([]));
 }
+
+#[cfg(feature = "cache")]
+#[test]
+fn ops_cached_results_track_row_changes() {
+    let dir = std::env::temp_dir().join(format!("topology-incr-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("cache.db");
+    let path = db.to_str().unwrap();
+
+    let mut rows = sample_records();
+    ops::op_tags_cached(&rows, "content", 3, Some(path));
+    ops::op_fingerprint_cached(&rows, "content", false, Some(path));
+
+    // Drop one row, reorder, and add a new one.
+    rows.remove(1);
+    rows.swap(0, 2);
+    rows.push(json!({"content": "Zig is a general-purpose language and toolchain", "id": "zig"}));
+
+    assert_eq!(
+        ops::op_tags_cached(&rows, "content", 3, Some(path)),
+        ops::op_tags(&rows, "content", 3)
+    );
+    assert_eq!(
+        ops::op_fingerprint_cached(&rows, "content", false, Some(path)),
+        ops::op_fingerprint(&rows, "content", false)
+    );
+    assert_eq!(
+        ops::op_fingerprint_cached(&rows, "content", true, Some(path)),
+        ops::op_fingerprint(&rows, "content", true)
+    );
+
+    // Dedup reuses the cached fingerprints.
+    rows.push(rows[0].clone());
+    let options = ops::DedupOptions::default();
+    let cached = ops::op_dedup_cached(&rows, "content", "url", "fuzzy", 3, &options, Some(path));
+    assert_eq!(cached, ops::op_dedup_with_options(&rows, "content", "url", "fuzzy", 3, &options));
+    assert_eq!(cached[0]["_dup_group"], cached[rows.len() - 1]["_dup_group"]);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn ops_train_then_classify_with_model() {
+    let dir = std::env::temp_dir().join(format!("topology-model-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("model.json");
+    let path = path.to_str().unwrap();
+
+    let summary = ops::op_train(
+        &sample_records(),
+        "content",
+        2,
+        500,
+        42,
+        &ops::ClassifyOptions::default(),
+        Some(path),
+    ).unwrap();
+    assert_eq!(summary["trained_rows"], 5);
+    assert!(!summary["categories"].as_array().unwrap().is_empty());
+
+    // A later batch is labelled against the frozen taxonomy, not a new one.
+    let new_rows = vec![json!({"content": "Rust and Go are compiled systems languages"})];
+    let options = ops::ClassifyOptions { multi_label: true, ..Default::default() };
+    let result = ops::op_classify_with_model(&new_rows, "content", path, 0.0, &options).unwrap();
+    let row = &result.as_array().unwrap()[0];
+    let categories = summary["categories"].as_array().unwrap();
+    assert!(categories.contains(&row["_hierarchy"]));
+    assert_eq!(row["_categories"][0]["path"], row["_hierarchy"]);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn ops_classify_with_missing_model_errors() {
+    let result = ops::op_classify_with_model(&sample_records(), "content", "/nonexistent/model.json", 0.5, &ops::ClassifyOptions::default());
+    assert!(result.is_err());
+}
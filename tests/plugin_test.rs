@@ -0,0 +1,46 @@
+#![cfg(feature = "plugin")]
+
+use nu_plugin_test_support::PluginTest;
+use nu_plugin_topology::TopologyPlugin;
+use nu_protocol::{Span, Value};
+
+/// `_fingerprint` of every row the pipeline returns.
+fn fingerprints(test: &mut PluginTest, source: &str) -> Vec<Value> {
+    let value = test.eval(source).unwrap().into_value(Span::test_data()).unwrap();
+    value
+        .into_list()
+        .unwrap()
+        .iter()
+        .map(|row| row.as_record().unwrap().get("_fingerprint").cloned().unwrap_or_default())
+        .collect()
+}
+
+#[test]
+fn plugin_fingerprint_reads_numeric_text_column() {
+    let mut test = PluginTest::new("topology", TopologyPlugin.into()).unwrap();
+    let numeric = fingerprints(&mut test, "[[content]; [12345] [1.5] [true]] | topology fingerprint");
+    let strings = fingerprints(&mut test, "[[content]; ['12345'] ['1.5'] ['true']] | topology fingerprint");
+    let empty = fingerprints(&mut test, "[[content]; ['']] | topology fingerprint");
+    assert_eq!(numeric, strings);
+    assert!(numeric.iter().all(|fp| fp != &empty[0]));
+}
+
+#[test]
+fn plugin_dedup_reports_banding_in_metadata() {
+    let mut test = PluginTest::new("topology", TopologyPlugin.into()).unwrap();
+    let output = test.eval("[[content]; ['hello world'] ['hello world']] | topology dedup --strategy fuzzy").unwrap();
+    let metadata = output.metadata().unwrap();
+    let banding = metadata.custom.get("topology_banding").unwrap().as_record().unwrap();
+    let simhash = banding.get("simhash").unwrap().as_record().unwrap();
+    assert_eq!(simhash.get("bands").unwrap().as_int().unwrap(), 4);
+}
+
+#[test]
+fn plugin_classify_auto_k_reports_scores_in_metadata() {
+    let mut test = PluginTest::new("topology", TopologyPlugin.into()).unwrap();
+    let source = "['rust borrow checker' 'rust ownership borrow' 'bread flour oven' 'bread dough flour' 'guitar chords song' 'song guitar strings'] | topology classify --clusters 4 --auto-k";
+    let output = test.eval(source).unwrap();
+    let selection = output.metadata().unwrap().custom.get("topology_k_selection").cloned().unwrap();
+    let scores = selection.as_record().unwrap().get("scores").unwrap().as_list().unwrap().len();
+    assert_eq!(scores, 3);
+}